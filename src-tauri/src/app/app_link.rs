@@ -0,0 +1,71 @@
+//! `app://open?path=...#anchor` hrefs emitted for wikilinks: decoding and vault confinement,
+//! so the UI never has to take the path apart itself.
+
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+use crate::text::percent_decode;
+
+use super::types::AppResult;
+
+/// Decoded (path, anchor) of an `app://open` href; `None` for other hrefs or a missing path.
+pub fn parse_app_link(href: &str) -> Option<(String, Option<String>)> {
+    let rest = href.strip_prefix("app://open")?;
+    let (rest, fragment) = match rest.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (rest, None),
+    };
+    let query = rest.strip_prefix('?').or_else(|| rest.strip_prefix("/?"))?;
+    let path = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("path="))
+        .map(percent_decode)
+        .filter(|p| !p.is_empty())?;
+    let anchor = fragment.map(percent_decode).filter(|a| !a.is_empty());
+    Some((path, anchor))
+}
+
+/// Canonical form of a link target (symlinks resolved); rejected when it lands outside `vault_root`.
+pub fn resolve_link_path(path: &str, vault_root: Option<&Path>) -> AppResult<PathBuf> {
+    let canonical = paths::canonicalize(Path::new(path))?;
+    if let Some(root) = vault_root {
+        if !canonical.starts_with(root) {
+            return Err(format!("{} is outside the vault", path));
+        }
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parse_app_link_decodes_path_and_anchor() {
+        let parsed = parse_app_link("app://open?path=/v/Caf%C3%A9%20Notes/50%25%2B.md#%C3%A9t%C3%A9");
+        assert_eq!(
+            parsed,
+            Some(("/v/Café Notes/50%+.md".to_string(), Some("été".to_string())))
+        );
+        assert_eq!(parse_app_link("app://open?path="), None);
+        assert_eq!(parse_app_link("https://example.com"), None);
+    }
+
+    #[test]
+    fn resolve_link_path_stays_in_vault() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = dir.path().join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        fs::write(vault.join("in.md"), "").unwrap();
+        fs::write(dir.path().join("out.md"), "").unwrap();
+        let root = paths::canonicalize(&vault).unwrap();
+
+        let inside = vault.join("in.md");
+        assert!(resolve_link_path(inside.to_str().unwrap(), Some(&root)).is_ok());
+        let escape = vault.join("../out.md");
+        assert!(resolve_link_path(escape.to_str().unwrap(), Some(&root)).is_err());
+        assert!(resolve_link_path(escape.to_str().unwrap(), None).is_ok(), "no vault, no confinement");
+    }
+}
@@ -1,81 +1,636 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
-use crate::markdown::render_markdown_safe;
-use crate::obsidian_embed::{RenderCache, RenderContext, VaultIndex};
-use crate::wiki;
+use crate::export::inline_local_images;
+use crate::frontmatter::{is_published, parse_frontmatter};
+use crate::git::{note_at_revision, note_history, NoteCommit};
+use crate::image_size::add_image_dimensions;
+use crate::markdown::{
+    annotate_source_lines, block_source_map, note_stats, render_markdown, BlockMapping, RenderOptions,
+};
+use crate::obsidian_embed::{
+    add_block_id as insert_block_id, block_source_map_of_string, block_source_map_with_embeds,
+    check_vault_links,
+    complete_wikilink as complete_index_wikilink, find_backlinks, find_duplicates,
+    find_embedding_notes, find_outgoing_links, flatten_markdown_with_embeds, folder_move_edits, link_counts,
+    lint_note as lint_markdown, map_of_content, note_aliases, note_tags, preview_link as render_link_preview,
+    query_frontmatter as query_index_frontmatter, render_markdown_string_with_embeds,
+    render_markdown_with_embeds, suggest_targets, Backlink, DuplicateReport, FrontmatterMatch, LinkCompletion,
+    LinkCounts, LinkPreview, LintIssue, NoteAlias, NoteEdits, OutgoingLink, QueryOp, RenderCache,
+    RenderContext, RenderGeneration, VaultIndex,
+};
+use crate::calendar::{calendar_month, CalendarDay};
+use crate::diagrams::render_diagrams;
+use crate::diff::{diff_lines, NoteDiff};
+use crate::epub::{self, Chapter};
+use crate::pandoc;
+use crate::paths::{changed_on_disk, content_hash, slash_path, write_atomic, write_atomic_with};
+use crate::remote_images::block_remote_images;
+use crate::search::{search_notes, search_notes_with, SearchHit};
+use crate::tasks::{apply_task_offsets, mark_tasks, toggle_task_at};
+use crate::template_vars::RenderedTemplate;
+use crate::templates::{apply_template_file, find_template};
+use crate::text::{truncate_snippet, MAX_SNIPPET_CHARS};
+use crate::thumbnails::thumbnail;
+use crate::wiki::{self, epoch_ms, TreeOptions};
 
-use super::state::{canonicalize_path, parent_dir_string, path_to_string, VaultState};
-use super::types::{AppResult, InitialPath, OpenMarkdownFileResult, OpenWikiFolderResult};
+use super::app_link::{parse_app_link, resolve_link_path};
+use super::editor::{editor_launch, launch};
+use super::fulltext::FulltextState;
+use super::operations::{spawn_operation, OperationId, OperationRegistry};
+use super::pagination::{paginate, Page};
+use super::profiling::PhaseTimer;
+use super::render_queue::{RenderPriority, RenderQueue};
+use super::serve::PreviewServer;
+use super::settings::{OutsideEmbeds, SavedSearch, Settings, SettingsState};
+use super::site;
+use super::state::{
+    breadcrumbs_for, canonicalize_path, parent_dir_string, path_to_string, RenderGenerations, VaultState,
+    Vaults, RENDER_SUPERSEDED,
+};
+use super::theme::{current_theme, ThemeChanged};
+use super::types::{
+    AppLink, AppResult, DiffBase, FileMetadata, FolderMove, InitialPath, NodeKind, NotePanelData,
+    OpenMarkdownFileResult, OpenWikiFolderResult, RecentNote, RenderedMarkdown, SaveResult, TreeNode,
+    VaultOpenProgress,
+};
+use super::workspace::{Workspace, WorkspaceState};
+
+const OPEN_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Most recently modified notes rendered into the cache once a vault is indexed.
+const WARM_NOTES: usize = 10;
 
 #[tauri::command]
 pub fn get_initial_file(state: State<super::state::InitialFile>) -> Option<InitialPath> {
     state.take()
 }
 
+/// Async so a newer navigation can run while an older render is still expanding embeds;
+/// the older one then fails with `RENDER_SUPERSEDED`. Rendered on the render queue, ahead of
+/// prefetches.
 #[tauri::command]
-pub fn open_markdown_file(
+pub async fn open_markdown_file(
     path: String,
     vault_root: Option<String>,
+    state: State<'_, VaultState>,
+    settings: State<'_, SettingsState>,
+    renders: State<'_, RenderGenerations>,
+    queue: State<'_, RenderQueue>,
+) -> AppResult<OpenMarkdownFileResult> {
+    let generation = renders.start();
+    // A note opened on its own still gets wikilinks when it lives in an open vault.
+    let vault_root = match vault_root {
+        Some(root) => Some(root),
+        None => {
+            let canonical = canonicalize_path(&path)?;
+            let vaults = state.0.read().unwrap();
+            vaults.containing(&canonical).map(|(root, _, _)| path_to_string(root)).transpose()?
+        }
+    };
+    let render = settings.get().render_options(vault_root.as_deref());
+    queue.render_visible(path, vault_root, render, generation).await
+}
+
+/// Renders unsaved `markdown` for a live editor preview, with wikilinks and embeds resolved in
+/// the vault of `base_path` (the note being edited, or a folder) or else the active vault.
+/// Top-level elements carry `data-source-line` for scroll sync with the editor.
+#[tauri::command]
+pub fn render_markdown_string(
+    markdown: String,
+    base_path: Option<String>,
     state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<RenderedMarkdown> {
+    let base = base_path.as_deref().map(canonicalize_path).transpose()?;
+    let note = base.as_deref().filter(|path| path.is_file());
+    let dir = match &base {
+        Some(path) if path.is_file() => path.parent().ok_or("No parent dir")?.to_path_buf(),
+        Some(path) => path.clone(),
+        None => state.0.read().unwrap().active_root().unwrap_or_default(),
+    };
+    with_render_context(&dir, &dir, &state, &settings.get(), |ctx| {
+        let html = render_markdown_string_with_embeds(&markdown, note, ctx);
+        let source_map = block_source_map_of_string(&markdown, note, ctx);
+        RenderedMarkdown {
+            html: annotate_source_lines(&html, &source_map),
+            source_map,
+        }
+    })
+}
+
+/// Renders `paths` into the render cache in the background, behind the note being opened, so
+/// following a link to one of them is instant.
+#[tauri::command]
+pub fn prefetch_notes(paths: Vec<String>, queue: State<RenderQueue>) {
+    let notes = paths.iter().filter_map(|path| canonicalize_path(path).ok()).collect();
+    queue.enqueue(notes, RenderPriority::Prefetch);
+}
+
+/// Reads and renders a note; embeds and wikilinks are expanded when `vault_root` is the open vault.
+/// Top-level elements carry `data-source-line` (see `source_map`).
+/// Fails with `RENDER_SUPERSEDED` once `generation` is superseded.
+pub(super) fn open_markdown(
+    path: &str,
+    vault_root: Option<&str>,
+    state: &VaultState,
+    render: &RenderOptions,
+    generation: RenderGeneration,
 ) -> AppResult<OpenMarkdownFileResult> {
-    let canonical_path = canonicalize_path(&path)?;
+    let canonical_path = canonicalize_path(path)?;
     let path_str = path_to_string(&canonical_path)?;
     let base_dir = parent_dir_string(&canonical_path)?;
     let raw_md = std::fs::read_to_string(&path_str).map_err(|e| e.to_string())?;
 
-    let html = if let Some(vault_str) = vault_root {
-        let vault_canon = canonicalize_path(&vault_str)?;
+    let vault_canon = vault_root.map(canonicalize_path).transpose()?;
+    let breadcrumbs = vault_canon
+        .as_deref()
+        .map(|root| breadcrumbs_for(root, &canonical_path))
+        .unwrap_or_default();
+
+    let mut rendered = None;
+    if let Some(vault_canon) = vault_canon {
         let mut guard = state.0.write().unwrap();
-        if let Some((root, index, cache)) = guard.as_mut() {
-            if *root == vault_canon {
-                let mut ctx = RenderContext {
-                    vault_root: root.clone(),
-                    index,
-                    cache,
-                    visited: HashSet::new(),
-                    depth: 0,
-                    max_depth: 5,
-                };
-                crate::obsidian_embed::render_markdown_with_embeds(&canonical_path, &mut ctx)
-            } else {
-                render_markdown_safe(&raw_md)
-            }
-        } else {
-            render_markdown_safe(&raw_md)
+        if let Some((root, index, cache)) = guard.get_mut(&vault_canon) {
+            let mut ctx = RenderContext {
+                vault_root: root.clone(),
+                index,
+                cache,
+                visited: HashSet::new(),
+                depth: 0,
+                max_depth: 5,
+                render: render.clone(),
+                generation: generation.clone(),
+            };
+            let html = crate::obsidian_embed::render_markdown_with_embeds(&canonical_path, &mut ctx);
+            let source_map = block_source_map_with_embeds(&canonical_path, &mut ctx);
+            rendered = Some((html, source_map));
+        }
+    }
+    let (html, source_map) = match rendered {
+        Some(rendered) => rendered,
+        None => {
+            state.0.write().unwrap().remember_file(canonical_path.clone());
+            render_standalone(&raw_md, &canonical_path, render)
         }
-    } else {
-        render_markdown_safe(&raw_md)
     };
 
+    if generation.is_superseded() {
+        return Err(RENDER_SUPERSEDED.to_string());
+    }
+
+    let stats = note_stats(&raw_md);
     Ok(OpenMarkdownFileResult {
+        content_hash: content_hash(&raw_md),
         raw_md,
-        html,
+        html: annotate_source_lines(&html, &source_map),
         base_dir,
+        source_map,
+        breadcrumbs,
+        stats,
     })
 }
 
+/// HTML and source map of the note at `path` opened outside every vault: plain markdown with
+/// task offsets, image sizes and, in privacy mode, remote images blocked, as in vaults.
+fn render_standalone(raw_md: &str, path: &Path, render: &RenderOptions) -> (String, Vec<BlockMapping>) {
+    let html = apply_task_offsets(&render_markdown(&mark_tasks(raw_md), render));
+    let html = add_image_dimensions(&html, path.parent().unwrap_or(path), &mut RenderCache::default());
+    let html = if render.block_remote_images { block_remote_images(&html) } else { html };
+    (html, block_source_map(raw_md))
+}
+
 #[tauri::command]
-pub fn open_wiki_folder(path: String, state: State<VaultState>) -> AppResult<OpenWikiFolderResult> {
-    let root = canonicalize_path(&path)?;
+pub fn open_wiki_folder(
+    path: String,
+    app: tauri::AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OpenWikiFolderResult> {
+    open_vault(&path, &app, &state, &settings)
+}
+
+/// Builds the tree for `path`, renders the initial note and makes it the active vault, then
+/// indexes it in the background; `vault-open-progress` reports each phase and `ready` marks
+/// the index as installed.
+pub(super) fn open_vault(
+    path: &str,
+    app: &AppHandle,
+    state: &VaultState,
+    settings: &SettingsState,
+) -> AppResult<OpenWikiFolderResult> {
+    let mut timer = PhaseTimer::start();
+    let current = settings.get();
+    let root = canonicalize_path(path)?;
     let root_str = path_to_string(&root)?;
-    let tree = wiki::build_tree(&root_str)?;
+    let mut progress = VaultOpenProgress {
+        vault_root: root_str.clone(),
+        phase: "tree".to_string(),
+        files_walked: 0,
+        indexed: 0,
+        total: 0,
+        error: None,
+    };
+    let _ = app.emit("vault-open-progress", progress.clone());
+    let tree = wiki::build_tree_with(&root_str, &current.tree_options(&root))?;
+    progress.files_walked = count_nodes(&tree);
+    let _ = app.emit("vault-open-progress", progress.clone());
+    timer.finish_phase("tree");
+    timer.record_tree(&tree);
 
-    let index = VaultIndex::build_index(&root)?;
-    let mut cache = RenderCache::default();
-    let (initial_note_path, initial_html) =
-        wiki::initial_note_with_embeds(&root_str, &index, &mut cache)?;
+    // The index is built in the background, so the first render can't resolve links yet: it
+    // stays out of the vault's cache, and the note is rendered again once the index is in place.
+    let render = current.render_options(Some(&root_str));
+    let (initial_note_path, initial_html) = wiki::initial_note_with_embeds(
+        &root_str,
+        &current.initial_notes(),
+        &current.excluded_folders(&root),
+        &VaultIndex::default(),
+        &mut RenderCache::default(),
+        &render,
+    )?;
+    let initial_content_hash = initial_note_path
+        .as_deref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|raw_md| content_hash(&raw_md));
+    timer.finish_phase("render");
 
-    *state.0.write().unwrap() = Some((root, index, cache));
+    state.0.write().unwrap().insert(root.clone(), VaultIndex::default(), RenderCache::default());
+    let initial_note = initial_note_path.as_ref().map(PathBuf::from);
+    let _ = settings.remember_vault(&root_str);
+
+    let options = current.index_options(&root);
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let still_open = |app: &AppHandle| {
+            app.state::<VaultState>().0.read().unwrap().contains(&root)
+        };
+        let mut last_emit: Option<Instant> = None;
+        progress.phase = "index".to_string();
+        let built = VaultIndex::build_index_with(&root, &options, |done, total| {
+            if last_emit.map(|at| at.elapsed() < OPEN_PROGRESS_INTERVAL).unwrap_or(false) && done < total {
+                return Ok(());
+            }
+            if !still_open(&app) {
+                return Err("superseded".to_string());
+            }
+            last_emit = Some(Instant::now());
+            progress.indexed = done;
+            progress.total = total;
+            let _ = app.emit("vault-open-progress", progress.clone());
+            Ok(())
+        });
+        timer.finish_phase("index");
+        match built {
+            Ok(index) => {
+                progress.indexed = index.notes().len();
+                progress.total = progress.indexed;
+                let vaults = app.state::<VaultState>();
+                let mut guard = vaults.0.write().unwrap();
+                let Some((_, open_index, cache)) = guard.get_mut(&root) else {
+                    return;
+                };
+                let notes = index.notes();
+                let recent = index.notes_by_mtime(WARM_NOTES).into_iter().map(|(path, _)| path).collect();
+                *open_index = index;
+                cache.clear();
+                drop(guard);
+                app.state::<FulltextState>().open_vault(&app, &root, notes);
+                let queue = app.state::<RenderQueue>();
+                queue.enqueue(initial_note.into_iter().collect(), RenderPriority::Prefetch);
+                queue.enqueue(recent, RenderPriority::CacheWarm);
+                progress.phase = "ready".to_string();
+                let _ = app.emit("startup-report", timer.into_report(&root));
+            }
+            Err(_) if !still_open(&app) => return,
+            Err(error) => {
+                progress.phase = "error".to_string();
+                progress.error = Some(error);
+            }
+        }
+        let _ = app.emit("vault-open-progress", progress);
+    });
 
     Ok(OpenWikiFolderResult {
         tree,
         initial_note_path,
         initial_html,
+        initial_content_hash,
     })
 }
 
+fn count_nodes(nodes: &[TreeNode]) -> usize {
+    nodes.iter().map(|node| 1 + count_nodes(&node.children)).sum()
+}
+
+/// Copies the rendered note (embeds expanded, local images inlined) to the clipboard as HTML,
+/// with the markdown source as the plain-text flavor.
+#[tauri::command]
+pub fn copy_note_as_html(
+    path: String,
+    vault_root: Option<String>,
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<()> {
+    let settings = settings.get();
+    let render = settings.render_options(vault_root.as_deref());
+    let note = open_markdown(&path, vault_root.as_deref(), &state, &render, RenderGeneration::default())?;
+    let base_dir = Path::new(&note.base_dir);
+    let html = with_render_context(&canonicalize_path(&path)?, base_dir, &state, &settings, |ctx| {
+        render_diagrams(&note.html, &settings.diagram_tools(), ctx.cache)
+    })?;
+    let html = inline_local_images(&html, base_dir);
+    app.clipboard()
+        .write_html(html, Some(note.raw_md))
+        .map_err(|e| e.to_string())
+}
+
+/// `path` as one standard markdown document: embeds inlined, wikilinks turned into relative
+/// links. Also written to `destination` when given, with links relative to it. Runs as an
+/// "export" operation whose result is the markdown.
+#[tauri::command]
+pub fn export_flattened_markdown(
+    path: String,
+    destination: Option<String>,
+    app: AppHandle,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let canonical = canonicalize_path(&path)?;
+    let base_dir = match &destination {
+        Some(destination) => {
+            let parent = Path::new(destination).parent().ok_or("No parent dir")?;
+            canonicalize_path(&path_to_string(parent)?)?
+        }
+        None => canonical.parent().ok_or("No parent dir")?.to_path_buf(),
+    };
+    let settings = settings.get();
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "export", settings.operation_timeout("export"), move |_| {
+        let markdown = flattened_markdown(&canonical, &base_dir, true, &handle.state(), &settings)?;
+        if let Some(destination) = destination {
+            write_atomic(Path::new(&destination), &markdown)?;
+        }
+        Ok(markdown)
+    }))
+}
+
+/// Converts the flattened note to `format` ("docx", "odt" or "latex") with pandoc. Runs as an
+/// "export" operation whose result is the written file: `destination`, or the note's path with
+/// the format's extension.
+#[tauri::command]
+pub fn export_via_pandoc(
+    path: String,
+    format: String,
+    destination: Option<String>,
+    app: AppHandle,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let canonical = canonicalize_path(&path)?;
+    let (_, extension) = pandoc::output_format(&format)?;
+    let output = match destination {
+        Some(destination) => PathBuf::from(destination),
+        None => canonical.with_extension(extension),
+    };
+    let output_dir = output.parent().ok_or("No parent dir")?;
+    let base_dir = canonicalize_path(&path_to_string(output_dir)?)?;
+    let settings = settings.get();
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "export", settings.operation_timeout("export"), move |_| {
+        let state = handle.state::<VaultState>();
+        let markdown = flattened_markdown(&canonical, &base_dir, true, &state, &settings)?;
+        let vault_root = state.0.read().unwrap().containing(&canonical).map(|(root, _, _)| root.clone());
+        let bibliography = settings.render_options(vault_root.as_deref().and_then(Path::to_str)).bibliography;
+        let pandoc = settings.pandoc_path.as_deref().unwrap_or("pandoc");
+        pandoc::convert(pandoc, &markdown, &format, &base_dir, bibliography.as_deref(), &output)?;
+        path_to_string(&output)
+    }))
+}
+
+/// Copies the note as markdown with wikilinks turned into relative links; embeds are inlined
+/// unless `inline_embeds` is false, in which case they become links too.
+#[tauri::command]
+pub fn copy_note_as_markdown(
+    path: String,
+    inline_embeds: Option<bool>,
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<()> {
+    let canonical = canonicalize_path(&path)?;
+    let base_dir = canonical.parent().ok_or("No parent dir")?;
+    let inline_embeds = inline_embeds.unwrap_or(true);
+    let markdown = flattened_markdown(&canonical, base_dir, inline_embeds, &state, &settings.get())?;
+    app.clipboard().write_text(markdown).map_err(|e| e.to_string())
+}
+
+/// Flattens `canonical` with the open vault containing it, or with links resolved relative to
+/// the note when no open vault does.
+fn flattened_markdown(
+    canonical: &Path,
+    base_dir: &Path,
+    inline_embeds: bool,
+    state: &VaultState,
+    settings: &Settings,
+) -> AppResult<String> {
+    let fallback_root = canonical.parent().ok_or("No parent dir")?;
+    with_render_context(canonical, fallback_root, state, settings, |ctx| {
+        flatten_markdown_with_embeds(canonical, base_dir, inline_embeds, ctx)
+    })
+}
+
+/// Writes an EPUB of the notes under `folder`, or of just `notes`, to `destination`: one chapter
+/// per note in tree order (or the order of `notes`), with local images and links between the
+/// exported notes kept inside the book. Unpublished notes (`publish: false` or `draft: true`)
+/// are left out and their embeds replaced by the configured placeholder; embeds of other notes
+/// outside the book follow `export_outside_embeds`. Runs as an "export" operation, one step per
+/// chapter, whose result is `destination`.
+#[tauri::command]
+pub fn export_epub(
+    folder: String,
+    notes: Option<Vec<String>>,
+    destination: String,
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let root = canonicalize_path(&folder)?;
+    let settings = settings.get();
+    let mut notes = match notes {
+        Some(notes) => notes.iter().map(|note| canonicalize_path(note)).collect::<AppResult<Vec<_>>>()?,
+        None => {
+            let vault_root = state.0.read().unwrap().containing(&root).map(|(root, _, _)| root.clone());
+            let tree = wiki::build_tree_with(
+                &path_to_string(&root)?,
+                &settings.tree_options(vault_root.as_deref().unwrap_or(&root)),
+            )?;
+            let mut notes = Vec::new();
+            tree_notes(&tree, &mut notes);
+            notes
+        }
+    };
+    notes.retain(|note| std::fs::read_to_string(note).map(|md| is_published(&md)).unwrap_or(true));
+    if notes.is_empty() {
+        return Err("No notes to export".to_string());
+    }
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "export", settings.operation_timeout("export"), move |op| {
+        let tools = settings.diagram_tools();
+        let total = notes.len();
+        let chapters = with_render_context(&root, &root, &handle.state(), &settings, |ctx| {
+            ctx.render.unpublished_embed = Some(settings.unpublished_embed_placeholder());
+            if settings.export_outside_embeds == OutsideEmbeds::Link {
+                ctx.render.embed_scope = Some(notes.iter().cloned().collect());
+            }
+            notes
+                .into_iter()
+                .enumerate()
+                .map(|(done, path)| {
+                    op.tick(done, total)?;
+                    Ok(Chapter {
+                        title: path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+                        html: render_diagrams(&render_markdown_with_embeds(&path, ctx), &tools, ctx.cache),
+                        path,
+                    })
+                })
+                .collect::<AppResult<Vec<_>>>()
+        })??;
+        let title = root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        epub::write_epub(&title, &chapters, Path::new(&destination))?;
+        Ok(destination)
+    }))
+}
+
+/// Writes the published notes under `folder` to `destination` as a static HTML site with an
+/// `index.html` landing page and a `sitemap.xml` whose URLs start with `base_url`. Runs as an
+/// "export" operation whose result is `destination`.
+#[tauri::command]
+pub fn export_site(
+    folder: String,
+    destination: String,
+    base_url: Option<String>,
+    app: AppHandle,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let root = canonicalize_path(&folder)?;
+    let base_url = base_url.unwrap_or_default();
+    let settings = settings.get();
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "export", settings.operation_timeout("export"), move |_| {
+        site::write_site(&root, Path::new(&destination), &base_url, &handle.state(), &settings)?;
+        Ok(destination)
+    }))
+}
+
+/// Notes of `nodes` and their descendants, in tree order.
+fn tree_notes(nodes: &[TreeNode], out: &mut Vec<PathBuf>) {
+    for node in nodes {
+        if node.kind == NodeKind::Note {
+            out.push(PathBuf::from(&node.path));
+        }
+        tree_notes(&node.children, out);
+    }
+}
+
+/// Runs `render` with a context for the open vault containing `path`; outside every open vault
+/// an empty index rooted at `fallback_root` is used, so only relative paths resolve.
+pub(super) fn with_render_context<T>(
+    path: &Path,
+    fallback_root: &Path,
+    state: &VaultState,
+    settings: &Settings,
+    render: impl FnOnce(&mut RenderContext<'_>) -> T,
+) -> AppResult<T> {
+    let mut vaults = state.0.write().unwrap();
+    let vault_root = vaults.containing(path).map(|(root, _, _)| root.clone());
+    let options = settings.render_options(vault_root.as_deref().and_then(Path::to_str));
+    let (empty_index, mut empty_cache) = (VaultIndex::default(), RenderCache::default());
+    let (root, index, cache) = match vault_root.as_deref().and_then(|root| vaults.get_mut(root)) {
+        Some((root, index, cache)) => (root.clone(), &*index, cache),
+        None => (fallback_root.to_path_buf(), &empty_index, &mut empty_cache),
+    };
+    let mut ctx = RenderContext {
+        vault_root: root,
+        index,
+        cache,
+        visited: HashSet::new(),
+        depth: 0,
+        max_depth: 5,
+        render: options,
+        generation: RenderGeneration::default(),
+    };
+    Ok(render(&mut ctx))
+}
+
+/// Serves the active vault read-only over HTTP on `port` (0 picks a free port) of localhost,
+/// or with `lan` to other devices on the LAN behind an access token; returns the local URL,
+/// token included. Replaces a running server.
+#[tauri::command]
+pub fn serve_vault(
+    port: u16,
+    lan: Option<bool>,
+    app: AppHandle,
+    state: State<VaultState>,
+    server: State<PreviewServer>,
+) -> AppResult<String> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    let (port, token) = server.start(app, root, port, lan.unwrap_or(false))?;
+    match token {
+        Some(token) => Ok(format!("http://localhost:{}/?token={}", port, token)),
+        None => Ok(format!("http://localhost:{}/", port)),
+    }
+}
+
+#[tauri::command]
+pub fn stop_serving(server: State<PreviewServer>) {
+    server.stop();
+}
+
+/// Opens the webview print dialog; the print stylesheet hides the chrome and paginates on H1.
+#[tauri::command]
+pub fn print_note(window: tauri::WebviewWindow) -> AppResult<()> {
+    window.print().map_err(|e| e.to_string())
+}
+
+/// Rebuilds the tree for `path`; `options` defaults to the saved tree settings. The vault's
+/// excluded folders apply either way. Inside an open vault, notes carry their link counts.
+#[tauri::command]
+pub fn get_tree(
+    path: String,
+    options: Option<TreeOptions>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<Vec<TreeNode>> {
+    let root = canonicalize_path(&path)?;
+    let settings = settings.get();
+    let options = match options {
+        Some(options) => TreeOptions {
+            excluded: settings.excluded_folders(&root),
+            ..options
+        },
+        None => settings.tree_options(&root),
+    };
+    let mut tree = wiki::build_tree_with(&path_to_string(&root)?, &options)?;
+    if let Some((vault_root, index, _)) = state.0.read().unwrap().containing(&root) {
+        set_link_counts(&mut tree, &link_counts(index, vault_root));
+    }
+    Ok(tree)
+}
+
+fn set_link_counts(nodes: &mut [TreeNode], counts: &HashMap<PathBuf, LinkCounts>) {
+    for node in nodes {
+        if node.kind == NodeKind::Note {
+            let found = counts.get(Path::new(&node.path)).copied().unwrap_or_default();
+            (node.inbound_links, node.outbound_links) = (found.inbound, found.outbound);
+        }
+        set_link_counts(&mut node.children, counts);
+    }
+}
+
 #[tauri::command]
 pub fn watch_paths(
     state: State<super::state::WatchService>,
@@ -83,3 +638,892 @@ pub fn watch_paths(
 ) -> AppResult<()> {
     state.watch(paths)
 }
+
+#[tauri::command]
+pub fn get_settings(state: State<SettingsState>) -> Settings {
+    state.get()
+}
+
+/// Saved tree and preview state for `vault_root` (empty when none was saved).
+#[tauri::command]
+pub fn load_workspace(vault_root: String, workspaces: State<WorkspaceState>) -> AppResult<Workspace> {
+    let root = path_to_string(&canonicalize_path(&vault_root)?)?;
+    Ok(workspaces.get(&root))
+}
+
+#[tauri::command]
+pub fn save_workspace(
+    vault_root: String,
+    workspace: Workspace,
+    workspaces: State<WorkspaceState>,
+) -> AppResult<()> {
+    let root = path_to_string(&canonicalize_path(&vault_root)?)?;
+    workspaces.save(&root, workspace)
+}
+
+#[tauri::command]
+pub fn update_settings(
+    settings: Settings,
+    window: tauri::Window,
+    state: State<SettingsState>,
+    vaults: State<VaultState>,
+) -> AppResult<()> {
+    let previous = state.get();
+    for (root, _, cache) in vaults.0.write().unwrap().iter_mut() {
+        let root = root.to_str();
+        if settings.render_options(root) != previous.render_options(root) {
+            cache.clear();
+        }
+    }
+    state.update(settings)?;
+    let _ = window.emit("theme-changed", current_theme(&window));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_theme(window: tauri::Window) -> ThemeChanged {
+    current_theme(&window)
+}
+
+/// Searches the active vault, or only the notes under `folder` (see `vault_notes`).
+#[tauri::command]
+pub fn search_vault(
+    query: String,
+    folder: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<VaultState>,
+    fulltext: State<FulltextState>,
+) -> AppResult<Page<SearchHit>> {
+    let notes = search_candidates(&state, &fulltext, &query, folder.as_deref())?;
+    Ok(search_page(search_notes(&notes, &query), offset, limit))
+}
+
+/// Async variant of `search_vault`: returns an operation id; the first page arrives with
+/// `operation-finished`.
+#[tauri::command]
+pub fn start_search(
+    query: String,
+    folder: Option<String>,
+    limit: Option<usize>,
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+    fulltext: State<FulltextState>,
+) -> AppResult<OperationId> {
+    let notes = search_candidates(&state, &fulltext, &query, folder.as_deref())?;
+    let timeout = settings.get().operation_timeout("search");
+    Ok(spawn_operation(&app, "search", timeout, move |op| {
+        let hits = search_notes_with(&notes, &query, |done, total| op.tick(done, total))?;
+        Ok(search_page(hits, None, limit))
+    }))
+}
+
+/// Rebuilds the open vault's index from disk and clears its render cache, for changes the
+/// watcher missed. Runs as an "index" operation; the result is the number of notes indexed.
+#[tauri::command]
+pub fn rebuild_index(
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    let timeout = settings.get().operation_timeout("index");
+    let options = settings.get().index_options(&root);
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "index", timeout, move |op| {
+        let index = VaultIndex::build_index_with(&root, &options, |done, total| op.tick(done, total))?;
+        let notes = index.notes();
+        let vaults = handle.state::<VaultState>();
+        let mut guard = vaults.0.write().unwrap();
+        match guard.get_mut(&root) {
+            Some((_, open_index, cache)) => {
+                *open_index = index;
+                cache.clear();
+                drop(guard);
+                let count = notes.len();
+                handle.state::<FulltextState>().open_vault(&handle, &root, notes);
+                Ok(count)
+            }
+            None => Err("The vault was closed while indexing".to_string()),
+        }
+    }))
+}
+
+/// Moves (or renames) vault folder `old` to `new`, rewriting every path-style wikilink and
+/// embed into it across the vault. Without `write` only the planned edits are returned, as a
+/// preview; with it the folder is renamed, the notes rewritten and the index rebuilt.
+#[tauri::command]
+pub fn move_folder(
+    old: String,
+    new: String,
+    write: Option<bool>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<FolderMove> {
+    let from = canonicalize_path(&old)?;
+    if !from.is_dir() {
+        return Err(format!("{} is not a folder", old));
+    }
+    let to = PathBuf::from(&new);
+    if to.exists() {
+        return Err(format!("{} already exists", new));
+    }
+    let to_parent = to.parent().ok_or("No parent dir")?;
+    let mut vaults = state.0.write().unwrap();
+    let (root, index, cache) = vaults
+        .iter_mut()
+        .filter(|(root, _, _)| from.starts_with(root) && from != **root)
+        .max_by_key(|(root, _, _)| root.components().count())
+        .ok_or("The folder is not inside an open vault")?;
+    let root = root.clone();
+    let to = match canonicalize_path(&path_to_string(to_parent)?) {
+        Ok(parent) if parent.starts_with(&root) => parent.join(to.file_name().ok_or("Invalid path")?),
+        _ => return Err(format!("{} is not inside the vault", new)),
+    };
+    if to.starts_with(&from) {
+        return Err("Cannot move a folder into itself".to_string());
+    }
+    let rel = |path: &Path| path.strip_prefix(&root).map(|p| p.to_string_lossy().into_owned());
+    let notes = folder_move_edits(
+        index,
+        &rel(&from).map_err(|e| e.to_string())?,
+        &rel(&to).map_err(|e| e.to_string())?,
+    );
+    let applied = write.unwrap_or(false);
+    if applied {
+        let settings = settings.get();
+        let rewrites = plan_rewrites(&notes, &from, &to)?;
+        apply_folder_move(&from, &to, &rewrites, settings.backup_on_save)?;
+        *index = VaultIndex::build_index_with(&root, &settings.index_options(&root), |_, _| Ok(()))?;
+        cache.clear();
+    }
+    Ok(FolderMove {
+        from: path_to_string(&from)?,
+        to: path_to_string(&to)?,
+        notes,
+        applied,
+    })
+}
+
+/// A note rewrite of a folder move: the note's path once the folder moved, its new text and the
+/// text it had, to restore on failure.
+struct Rewrite<'a> {
+    path: PathBuf,
+    content: &'a str,
+    original: String,
+}
+
+/// The rewrites of `notes` for moving `from` to `to`, checked up front: every note must be
+/// readable and writable before anything is touched.
+fn plan_rewrites<'a>(notes: &'a [NoteEdits], from: &Path, to: &Path) -> AppResult<Vec<Rewrite<'a>>> {
+    notes
+        .iter()
+        .map(|note| {
+            let cannot =
+                |e: &dyn std::fmt::Display| format!("Cannot rewrite {}: {}", note.path.display(), e);
+            let metadata = std::fs::metadata(&note.path).map_err(|e| cannot(&e))?;
+            if metadata.permissions().readonly() {
+                return Err(cannot(&"the note is read-only"));
+            }
+            let original = std::fs::read_to_string(&note.path).map_err(|e| cannot(&e))?;
+            let path = match note.path.strip_prefix(from) {
+                Ok(inside) => to.join(inside),
+                Err(_) => note.path.clone(),
+            };
+            Ok(Rewrite {
+                path,
+                content: &note.content,
+                original,
+            })
+        })
+        .collect()
+}
+
+/// Renames `from` to `to` and applies `rewrites`. When a rewrite fails, the notes already
+/// rewritten get their text back and the folder its name, and the error says so.
+fn apply_folder_move(from: &Path, to: &Path, rewrites: &[Rewrite<'_>], backup: bool) -> AppResult<()> {
+    std::fs::rename(from, to).map_err(|e| e.to_string())?;
+    for (done, rewrite) in rewrites.iter().enumerate() {
+        let written = write_atomic_with(&rewrite.path, backup, |file| {
+            file.write_all(rewrite.content.as_bytes())
+        });
+        let Err(error) = written else {
+            continue;
+        };
+        let restored = rewrites[..done]
+            .iter()
+            .try_for_each(|rewrite| write_atomic(&rewrite.path, &rewrite.original))
+            .and_then(|()| std::fs::rename(to, from).map_err(|e| e.to_string()));
+        return Err(match restored {
+            Ok(()) => format!("Cannot rewrite {}: {}; the move was undone", rewrite.path.display(), error),
+            Err(undo) => format!(
+                "Cannot rewrite {}: {}; undoing the move failed too: {}",
+                rewrite.path.display(),
+                error,
+                undo
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_operation(id: OperationId, registry: State<OperationRegistry>) -> bool {
+    registry.cancel(id)
+}
+
+/// Notes of the active vault, or only those under `folder` (vault-relative like `Projects/`, or
+/// absolute) when given.
+fn vault_notes(state: &VaultState, folder: Option<&str>) -> AppResult<Vec<std::path::PathBuf>> {
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.active().ok_or("No vault open")?;
+    let Some(folder) = folder.map(str::trim).filter(|f| !f.is_empty()) else {
+        return Ok(index.notes());
+    };
+    let dir = crate::paths::canonicalize(&root.join(folder))?;
+    if !dir.starts_with(root) {
+        return Err(format!("{} is not in the open vault", folder));
+    }
+    Ok(index.notes_under(&dir))
+}
+
+/// `vault_notes` narrowed to those the active vault's full-text index says can match `query`.
+fn search_candidates(
+    state: &VaultState,
+    fulltext: &FulltextState,
+    query: &str,
+    folder: Option<&str>,
+) -> AppResult<Vec<PathBuf>> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    Ok(fulltext.narrow(&root, query, vault_notes(state, folder)?))
+}
+
+fn search_page(hits: Vec<SearchHit>, offset: Option<usize>, limit: Option<usize>) -> Page<SearchHit> {
+    let mut page = paginate(hits, offset, limit);
+    for hit in &mut page.items {
+        hit.snippet = truncate_snippet(&hit.snippet, MAX_SNIPPET_CHARS);
+    }
+    page
+}
+
+/// Opens Explorer / Finder / the default Linux file manager with `path` selected.
+#[tauri::command]
+pub fn reveal_in_file_manager(path: String) -> AppResult<()> {
+    let canonical_path = canonicalize_path(&path)?;
+    tauri_plugin_opener::reveal_item_in_dir(&canonical_path).map_err(|e| e.to_string())
+}
+
+/// Opens `path` in the editor configured in settings, at `line` when the editor supports it.
+#[tauri::command]
+pub fn open_in_editor(
+    path: String,
+    line: Option<u32>,
+    settings: State<SettingsState>,
+) -> AppResult<()> {
+    let canonical_path = canonicalize_path(&path)?;
+    let path_str = path_to_string(&canonical_path)?;
+    let editor = settings.get().editor.ok_or("No editor configured")?;
+    launch(editor_launch(&editor, &path_str, line)?)
+}
+
+#[tauri::command]
+pub fn get_file_metadata(path: String, state: State<VaultState>) -> AppResult<FileMetadata> {
+    file_metadata(&canonicalize_path(&path)?, &state)
+}
+
+fn file_metadata(canonical_path: &Path, state: &VaultState) -> AppResult<FileMetadata> {
+    let metadata = std::fs::metadata(canonical_path).map_err(|e| e.to_string())?;
+    let relative_path = state.0.read().unwrap().containing(canonical_path).and_then(|(root, _, _)| {
+        canonical_path
+            .strip_prefix(root)
+            .ok()
+            .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+    });
+    let is_markdown = canonical_path.extension().map(|e| e == "md").unwrap_or(false);
+    let frontmatter = if is_markdown && metadata.is_file() {
+        std::fs::read_to_string(canonical_path)
+            .map(|raw| parse_frontmatter(&raw))
+            .unwrap_or_default()
+    } else {
+        Default::default()
+    };
+    Ok(FileMetadata {
+        path: path_to_string(canonical_path)?,
+        relative_path,
+        size: metadata.len(),
+        created_ms: epoch_ms(metadata.created()),
+        modified_ms: epoch_ms(metadata.modified()),
+        frontmatter,
+    })
+}
+
+/// Frontmatter, tags, link counts, word counts and file stats of a note, for the info panel.
+#[tauri::command]
+pub fn get_note_panel_data(path: String, state: State<VaultState>) -> AppResult<NotePanelData> {
+    let canonical_path = canonicalize_path(&path)?;
+    let metadata = file_metadata(&canonical_path, &state)?;
+    let raw_md = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+    let guard = state.0.read().unwrap();
+    let vault = guard.containing(&canonical_path);
+    let backlink_count = vault.map(|(root, index, _)| find_backlinks(&canonical_path, index, root).len());
+    let outgoing_link_count = match vault {
+        Some((root, index, _)) => Some(find_outgoing_links(&canonical_path, index, root)?.len()),
+        None => None,
+    };
+    Ok(NotePanelData {
+        metadata,
+        tags: note_tags(&raw_md),
+        backlink_count,
+        outgoing_link_count,
+        stats: note_stats(&raw_md),
+    })
+}
+
+/// Path of a cached thumbnail of the image at `path`, at most `size` pixels on its longest side.
+/// Only images inside an open vault get thumbnails.
+#[tauri::command]
+pub fn get_thumbnail(
+    path: String,
+    size: u32,
+    state: State<'_, VaultState>,
+    app: AppHandle,
+) -> AppResult<String> {
+    let canonical_path = canonicalize_path(&path)?;
+    if state.0.read().unwrap().containing(&canonical_path).is_none() {
+        return Err(format!("{} is not inside an open vault", path));
+    }
+    let cache_dir = app.path().app_cache_dir().map_err(|e| e.to_string())?.join("thumbnails");
+    path_to_string(&thumbnail(&canonical_path, size, &cache_dir)?)
+}
+
+#[tauri::command]
+pub fn get_note_history(path: String) -> AppResult<Vec<NoteCommit>> {
+    note_history(&canonicalize_path(&path)?)
+}
+
+/// Line diff from `base` to the note on disk.
+#[tauri::command]
+pub fn diff_note(path: String, base: DiffBase) -> AppResult<NoteDiff> {
+    let canonical_path = canonicalize_path(&path)?;
+    let current = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+    let old = match base {
+        DiffBase::Revision(rev) => note_at_revision(&canonical_path, &rev)?,
+        DiffBase::Content(content) => content,
+    };
+    Ok(diff_lines(&old, &current))
+}
+
+/// Renders the note as it was at `rev`, for the history viewer.
+#[tauri::command]
+pub fn get_note_at_revision(
+    path: String,
+    rev: String,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OpenMarkdownFileResult> {
+    let canonical_path = canonicalize_path(&path)?;
+    let raw_md = note_at_revision(&canonical_path, &rev)?;
+    let vault_root = state.0.read().unwrap().containing(&canonical_path).map(|(root, _, _)| root.clone());
+    let render = settings.get().render_options(vault_root.as_deref().and_then(Path::to_str));
+    Ok(OpenMarkdownFileResult {
+        html: render_markdown(&raw_md, &render),
+        base_dir: parent_dir_string(&canonical_path)?,
+        source_map: block_source_map(&raw_md),
+        breadcrumbs: Vec::new(),
+        stats: note_stats(&raw_md),
+        content_hash: content_hash(&raw_md),
+        raw_md,
+    })
+}
+
+/// Canonical path of the note a save to `path` writes: a `.md` file inside an open vault, or one
+/// opened on its own. A note that doesn't exist yet is placed in its canonical parent folder.
+fn save_target(path: &str, vaults: &Vaults) -> AppResult<PathBuf> {
+    let path = Path::new(path);
+    let canonical = match crate::paths::canonicalize(path) {
+        Ok(canonical) => canonical,
+        Err(_) => {
+            let name = path.file_name().ok_or("Invalid path")?;
+            let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).ok_or("No parent dir")?;
+            crate::paths::canonicalize(parent)?.join(name)
+        }
+    };
+    if !canonical.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md")) {
+        return Err(format!("{} is not a markdown note", path.display()));
+    }
+    if !vaults.may_write(&canonical) {
+        return Err(format!("{} is not inside an open vault", path.display()));
+    }
+    Ok(canonical)
+}
+
+/// Writes `content` to the note unless it changed on disk since the frontend loaded the version
+/// hashing to `expected_hash`; then both versions come back as a conflict. `force` overwrites.
+/// Only notes inside an open vault, or opened on their own, are written.
+#[tauri::command]
+pub fn save_markdown_file(
+    path: String,
+    content: String,
+    expected_hash: String,
+    force: Option<bool>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<SaveResult> {
+    let canonical_path = save_target(&path, &state.0.read().unwrap())?;
+    let modified_ms = || epoch_ms(std::fs::metadata(&canonical_path).and_then(|m| m.modified()));
+    if !force.unwrap_or(false) {
+        if let Some(theirs) = changed_on_disk(&canonical_path, &expected_hash)? {
+            return Ok(SaveResult::Conflict {
+                ours: content,
+                theirs,
+                theirs_modified_ms: modified_ms(),
+            });
+        }
+    }
+    let backup = settings.get().backup_on_save;
+    write_atomic_with(&canonical_path, backup, |file| file.write_all(content.as_bytes()))?;
+    Ok(SaveResult::Saved {
+        content_hash: content_hash(&content),
+        modified_ms: modified_ms(),
+    })
+}
+
+/// Ends the block at 1-based `line` of the note with a new `^id` marker (or returns the one it
+/// already has) and records it in the vault's block index.
+#[tauri::command]
+pub fn add_block_id(
+    path: String,
+    line: usize,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<String> {
+    let canonical_path = canonicalize_path(&path)?;
+    let raw_md = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+    let (updated, id) = insert_block_id(&raw_md, line)?;
+    if updated != raw_md {
+        write_atomic_with(&canonical_path, settings.get().backup_on_save, |file| {
+            file.write_all(updated.as_bytes())
+        })?;
+    }
+    let mut guard = state.0.write().unwrap();
+    if let Some((_, index, _)) = guard.containing_mut(&canonical_path) {
+        index.refresh_block_ids(&canonical_path, &updated);
+    }
+    Ok(id)
+}
+
+/// Flips the task checkbox whose `[` is at byte `offset` (the rendered `data-task-offset`),
+/// then returns the re-rendered note. Like `save_markdown_file`, refuses when the note changed
+/// on disk since the frontend loaded the version hashing to `expected_hash`: the offset may
+/// point elsewhere now.
+#[tauri::command]
+pub fn toggle_task(
+    path: String,
+    offset: usize,
+    expected_hash: String,
+    vault_root: Option<String>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OpenMarkdownFileResult> {
+    let canonical_path = canonicalize_path(&path)?;
+    let raw_md = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+    if content_hash(&raw_md) != expected_hash {
+        return Err(format!("{} changed on disk; reload it and try again", path));
+    }
+    let toggled = toggle_task_at(&raw_md, offset)?;
+    let settings = settings.get();
+    write_atomic_with(&canonical_path, settings.backup_on_save, |file| {
+        file.write_all(toggled.as_bytes())
+    })?;
+    let render = settings.render_options(vault_root.as_deref());
+    open_markdown(&path, vault_root.as_deref(), &state, &render, RenderGeneration::default())
+}
+
+/// Renders template `template_name` for a note at `target_path`; with `write`, also creates
+/// the note (never overwriting an existing file), which must be inside an open vault. The
+/// result says where `{{cursor}}` was.
+#[tauri::command]
+pub fn apply_template(
+    template_name: String,
+    target_path: String,
+    write: Option<bool>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<RenderedTemplate> {
+    let folder = settings.get().templates_folder.ok_or("No templates folder configured")?;
+    let vault_root = state.0.read().unwrap().active_root();
+    let folder = match &vault_root {
+        Some(root) if std::path::Path::new(&folder).is_relative() => root.join(&folder),
+        _ => std::path::PathBuf::from(&folder),
+    };
+    let template = find_template(&folder, &template_name)?;
+    let target = std::path::Path::new(&target_path);
+    let rendered = apply_template_file(&template, target, chrono::Local::now())?;
+    if write.unwrap_or(false) {
+        let outside = || format!("{} is not inside an open vault", target_path);
+        if target.components().any(|c| c == std::path::Component::ParentDir) {
+            return Err(outside());
+        }
+        let parent = target.parent().ok_or("No parent dir")?;
+        let existing = parent.ancestors().find(|dir| dir.is_dir()).ok_or_else(outside)?;
+        let existing = canonicalize_path(&path_to_string(existing)?)?;
+        if state.0.read().unwrap().containing(&existing).is_none() {
+            return Err(outside());
+        }
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(target)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => format!("{} already exists", target_path),
+                _ => e.to_string(),
+            })?;
+        file.write_all(rendered.markdown.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    Ok(rendered)
+}
+
+/// Map of content of `folder`: a wikilink to each of its notes, grouped by subfolder. With
+/// `write`, also saves it as the folder note (`<folder>/<folder name>.md`); an existing folder
+/// note is only replaced with `force`.
+#[tauri::command]
+pub fn generate_moc(
+    folder: String,
+    write: Option<bool>,
+    force: Option<bool>,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<String> {
+    let folder = canonicalize_path(&folder)?;
+    let name = folder.file_name().ok_or("Invalid path")?.to_string_lossy().into_owned();
+    let note = folder.join(format!("{}.md", name));
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.containing(&folder).ok_or("The folder is not inside an open vault")?;
+    let markdown = map_of_content(&folder, Some(&note), index, root);
+    drop(guard);
+    if !write.unwrap_or(false) {
+        return Ok(markdown);
+    }
+    if force.unwrap_or(false) {
+        write_atomic_with(&note, settings.get().backup_on_save, |file| file.write_all(markdown.as_bytes()))?;
+    } else {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&note)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => format!("{} already exists", note.display()),
+                _ => e.to_string(),
+            })?;
+        file.write_all(markdown.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    Ok(markdown)
+}
+
+/// Decodes an `app://open` href; with a vault open, targets outside every open vault are rejected.
+#[tauri::command]
+pub fn resolve_app_link(href: String, state: State<VaultState>) -> AppResult<AppLink> {
+    let (path, anchor) = parse_app_link(&href).ok_or("Invalid app link")?;
+    let vault_root = {
+        let vaults = state.0.read().unwrap();
+        canonicalize_path(&path)
+            .ok()
+            .and_then(|target| vaults.containing(&target).map(|(root, _, _)| root.clone()))
+            .or_else(|| vaults.active_root())
+    };
+    let canonical = resolve_link_path(&path, vault_root.as_deref())?;
+    Ok(AppLink {
+        path: path_to_string(&canonical)?,
+        anchor,
+    })
+}
+
+/// Excerpt of a wikilink target for hover previews (see `obsidian_embed::preview_link`).
+#[tauri::command]
+pub fn preview_link(
+    target: String,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<LinkPreview> {
+    let mut guard = state.0.write().unwrap();
+    let (root, index, cache) = guard.active_mut().ok_or("No vault open")?;
+    let mut ctx = RenderContext {
+        vault_root: root.clone(),
+        index,
+        cache,
+        visited: HashSet::new(),
+        depth: 0,
+        max_depth: 5,
+        render: settings.get().render_options(root.to_str()),
+        generation: RenderGeneration::default(),
+    };
+    render_link_preview(&target, &mut ctx)
+}
+
+#[tauri::command]
+pub fn get_outgoing_links(path: String, state: State<VaultState>) -> AppResult<Vec<OutgoingLink>> {
+    let path = canonicalize_path(&path)?;
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.containing(&path).ok_or("Note is not in an open vault")?;
+    find_outgoing_links(&path, index, root)
+}
+
+/// Structure problems in one note (skipped heading levels, duplicate headings, unclosed fences,
+/// malformed wikilinks), sorted by line.
+#[tauri::command]
+pub fn lint_note(path: String) -> AppResult<Vec<LintIssue>> {
+    let canonical_path = canonicalize_path(&path)?;
+    let raw_md = std::fs::read_to_string(&canonical_path).map_err(|e| e.to_string())?;
+    Ok(lint_markdown(&raw_md))
+}
+
+/// Checks every link in the active vault; with `destination`, the report is also written there
+/// as markdown. Runs as a "link_check" operation whose result is the report.
+#[tauri::command]
+pub fn check_links(
+    destination: Option<String>,
+    app: AppHandle,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<OperationId> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    let timeout = settings.get().operation_timeout("link_check");
+    let handle = app.clone();
+    Ok(spawn_operation(&app, "link_check", timeout, move |_| {
+        let vaults = handle.state::<VaultState>();
+        let guard = vaults.0.read().unwrap();
+        let (root, index, _) = guard.containing(&root).ok_or("The vault was closed")?;
+        let report = check_vault_links(index, root);
+        if let Some(destination) = destination {
+            write_atomic(Path::new(&destination), report.to_markdown(root))?;
+        }
+        Ok(report)
+    }))
+}
+
+/// Notes of the active vault sharing a basename (wikilinks resolve to only one of them) and
+/// notes with identical or nearly identical content.
+#[tauri::command]
+pub fn find_duplicate_notes(state: State<VaultState>) -> AppResult<DuplicateReport> {
+    let guard = state.0.read().unwrap();
+    let (_, index, _) = guard.active().ok_or("No vault open")?;
+    Ok(find_duplicates(index))
+}
+
+/// Saves `query` as `name` in the active vault's saved searches; returns them all.
+#[tauri::command]
+pub fn save_search(
+    name: String,
+    query: String,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<Vec<SavedSearch>> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    settings.save_search(&path_to_string(&root)?, &name, &query)
+}
+
+/// The active vault's saved searches, in the order they were saved.
+#[tauri::command]
+pub fn list_saved_searches(
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<Vec<SavedSearch>> {
+    let root = state.0.read().unwrap().active_root().ok_or("No vault open")?;
+    let root = path_to_string(&root)?;
+    Ok(settings.get().vaults.get(&root).map(|vault| vault.saved_searches.clone()).unwrap_or_default())
+}
+
+/// Notes of the active vault dated in `year`-`month`, by day: daily notes by their name, other
+/// notes by creation date.
+#[tauri::command]
+pub fn get_calendar_data(
+    year: i32,
+    month: u32,
+    state: State<VaultState>,
+    settings: State<SettingsState>,
+) -> AppResult<Vec<CalendarDay>> {
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.active().ok_or("No vault open")?;
+    let format = settings.get().daily_note_format(root);
+    calendar_month(&index.notes(), root, year, month, &format)
+}
+
+/// Notes of the active vault whose frontmatter `field` matches `value` under `op` (equality by
+/// default).
+#[tauri::command]
+pub fn query_frontmatter(
+    field: String,
+    value: Option<String>,
+    op: Option<QueryOp>,
+    state: State<VaultState>,
+) -> AppResult<Vec<FrontmatterMatch>> {
+    let guard = state.0.read().unwrap();
+    let (_, index, _) = guard.active().ok_or("No vault open")?;
+    let value = value.unwrap_or_default();
+    Ok(query_index_frontmatter(index, &field, op.unwrap_or_default(), &value))
+}
+
+/// Notes and aliases of the active vault matching `prefix`, best first (at most `limit`, 20 by
+/// default), for `[[` autocompletion; after `Note#`, that note's headings.
+#[tauri::command]
+pub fn complete_wikilink(
+    prefix: String,
+    limit: Option<usize>,
+    state: State<VaultState>,
+) -> AppResult<Vec<LinkCompletion>> {
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.active().ok_or("No vault open")?;
+    Ok(complete_index_wikilink(index, root, &prefix, limit.unwrap_or(20)))
+}
+
+/// Names of notes in the active vault close to the unresolved wikilink target `target`, nearest
+/// first, to offer as fixes for a typo.
+#[tauri::command]
+pub fn suggest_link_targets(target: String, state: State<VaultState>) -> AppResult<Vec<String>> {
+    let guard = state.0.read().unwrap();
+    let (_, index, _) = guard.active().ok_or("No vault open")?;
+    Ok(suggest_targets(&target, index))
+}
+
+/// Frontmatter aliases of every note in the active vault, for the quick switcher and link
+/// completion.
+#[tauri::command]
+pub fn get_aliases(state: State<VaultState>) -> AppResult<Vec<NoteAlias>> {
+    let guard = state.0.read().unwrap();
+    let (_, index, _) = guard.active().ok_or("No vault open")?;
+    Ok(note_aliases(index))
+}
+
+/// The `limit` most recently modified notes of the active vault, newest first.
+#[tauri::command]
+pub fn get_notes_by_mtime(limit: usize, state: State<VaultState>) -> AppResult<Vec<RecentNote>> {
+    let guard = state.0.read().unwrap();
+    let (root, index, _) = guard.active().ok_or("No vault open")?;
+    index
+        .notes_by_mtime(limit)
+        .into_iter()
+        .map(|(path, modified)| {
+            Ok(RecentNote {
+                relative_path: slash_path(path.strip_prefix(root).unwrap_or(&path)),
+                modified_ms: epoch_ms(Ok(modified)).unwrap_or(0),
+                path: path_to_string(&path)?,
+            })
+        })
+        .collect()
+}
+
+/// Notes whose rendered output includes one of the changed `paths` through embeds, so only
+/// those are re-rendered. Deleted files count too.
+#[tauri::command]
+pub fn get_embedding_notes(paths: Vec<String>, state: State<VaultState>) -> AppResult<Vec<String>> {
+    let changed: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| canonicalize_path(path).unwrap_or_else(|_| PathBuf::from(path)))
+        .collect();
+    let guard = state.0.read().unwrap();
+    let mut notes: Vec<String> = Vec::new();
+    for (root, index, _) in guard.iter() {
+        let inside: Vec<PathBuf> = changed.iter().filter(|path| path.starts_with(root)).cloned().collect();
+        if inside.is_empty() {
+            continue;
+        }
+        for note in find_embedding_notes(&inside, index, root) {
+            notes.push(path_to_string(&note)?);
+        }
+    }
+    notes.sort();
+    notes.dedup();
+    Ok(notes)
+}
+
+#[tauri::command]
+pub fn get_backlinks(
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<VaultState>,
+) -> AppResult<Page<Backlink>> {
+    let backlinks = {
+        let path = canonicalize_path(&path)?;
+        let guard = state.0.read().unwrap();
+        let (root, index, _) = guard.containing(&path).ok_or("Note is not in an open vault")?;
+        find_backlinks(&path, index, root)
+    };
+    let mut page = paginate(backlinks, offset, limit);
+    for backlink in &mut page.items {
+        backlink.snippet = truncate_snippet(&backlink.snippet, MAX_SNIPPET_CHARS);
+    }
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standalone_notes_block_remote_images_in_privacy_mode() {
+        let md = "![cat](https://example.org/cat.png)";
+        let path = Path::new("/elsewhere/Note.md");
+        let (html, _) = render_standalone(md, path, &RenderOptions::default());
+        assert!(html.contains("<img src=\"https://example.org/cat.png\""), "{}", html);
+        let private = RenderOptions {
+            block_remote_images: true,
+            ..Default::default()
+        };
+        let (html, _) = render_standalone(md, path, &private);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(html.contains("remote-image"), "{}", html);
+    }
+    #[test]
+    fn failed_folder_move_rewrites_are_undone() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("old")).unwrap();
+        std::fs::write(root.join("old/a.md"), "[[old/b]]").unwrap();
+        std::fs::write(root.join("index.md"), "[[old/a]]").unwrap();
+        let edits = |path: PathBuf, content: &str| NoteEdits {
+            path,
+            edits: Vec::new(),
+            content: content.to_string(),
+        };
+        let notes = [
+            edits(root.join("old/a.md"), "[[new/b]]"),
+            edits(root.join("index.md"), "[[new/a]]"),
+        ];
+        let (from, to) = (root.join("old"), root.join("new"));
+        let mut rewrites = plan_rewrites(&notes, &from, &to).unwrap();
+        assert_eq!(rewrites[0].path, root.join("new/a.md"));
+        rewrites[1].path = root.join("missing/index.md");
+        let err = apply_folder_move(&from, &to, &rewrites, false).unwrap_err();
+        assert!(err.contains("the move was undone"), "{}", err);
+        assert!(!to.exists());
+        assert_eq!(std::fs::read_to_string(root.join("old/a.md")).unwrap(), "[[old/b]]");
+
+        let mut readonly = std::fs::metadata(root.join("index.md")).unwrap().permissions();
+        readonly.set_readonly(true);
+        std::fs::set_permissions(root.join("index.md"), readonly).unwrap();
+        assert!(plan_rewrites(&notes, &from, &to).is_err_and(|err| err.contains("read-only")));
+    }
+
+    #[test]
+    fn saves_only_target_notes_in_open_vaults() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = crate::paths::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir(root.join("vault")).unwrap();
+        std::fs::write(root.join("vault/a.md"), "").unwrap();
+        std::fs::write(root.join("vault/a.txt"), "").unwrap();
+        std::fs::write(root.join("loose.md"), "").unwrap();
+        let mut vaults = Vaults::default();
+        vaults.insert(root.join("vault"), VaultIndex::default(), RenderCache::default());
+        let target = |path: PathBuf, vaults: &Vaults| save_target(path.to_str().unwrap(), vaults);
+        assert_eq!(target(root.join("vault/a.md"), &vaults), Ok(root.join("vault/a.md")));
+        assert_eq!(target(root.join("vault/new.md"), &vaults), Ok(root.join("vault/new.md")), "new notes");
+        assert!(target(root.join("vault/../new.md"), &vaults).is_err());
+        assert!(target(root.join("vault/a.txt"), &vaults).is_err());
+        assert!(target(root.join("vault/../loose.md"), &vaults).is_err());
+        vaults.remember_file(root.join("loose.md"));
+        assert_eq!(target(root.join("vault/../loose.md"), &vaults), Ok(root.join("loose.md")));
+    }
+}
@@ -0,0 +1,151 @@
+//! Obsidian-style deep links (`mdglasses://open?vault=...&file=...`) mapped onto vault and note opens.
+//! The app registers only its own scheme; `obsidian://` URIs work when passed to it explicitly
+//! (e.g. on the command line), so Obsidian keeps handling its links system-wide.
+
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::paths;
+use crate::text::percent_decode;
+
+use super::open_events::{initial_path_for, route_open_target};
+use super::settings::SettingsState;
+use super::types::InitialPath;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ObsidianUri {
+    pub vault: Option<String>,
+    pub file: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Parses `mdglasses://open?...` or `obsidian://open?...`; other actions and schemes return `None`.
+pub fn parse_obsidian_uri(uri: &str) -> Option<ObsidianUri> {
+    let rest = uri.strip_prefix("mdglasses://").or_else(|| uri.strip_prefix("obsidian://"))?;
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if action.trim_end_matches('/') != "open" {
+        return None;
+    }
+    let mut parsed = ObsidianUri::default();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match key {
+            "vault" => parsed.vault = Some(value),
+            "file" => parsed.file = Some(value),
+            "path" => parsed.path = Some(value),
+            _ => {}
+        }
+    }
+    Some(parsed)
+}
+
+/// Maps a parsed URI onto what the UI should open. `vault` is a vault folder path or the folder
+/// name of a recently opened vault; `file` is relative to the vault, with `.md` optional.
+pub fn resolve_obsidian_uri(uri: &ObsidianUri, recent_vaults: &[String]) -> Option<InitialPath> {
+    if let Some(path) = &uri.path {
+        return initial_path_for(Path::new(path));
+    }
+    let root = find_vault_root(uri.vault.as_deref()?, recent_vaults)?;
+    let root_str = root.to_str()?.to_string();
+    let note = uri.file.as_deref().and_then(|file| resolve_vault_file(&root, file));
+    Some(match note {
+        Some(path) => InitialPath {
+            path,
+            is_dir: false,
+            vault_root: Some(root_str),
+        },
+        None => InitialPath {
+            path: root_str,
+            is_dir: true,
+            vault_root: None,
+        },
+    })
+}
+
+fn find_vault_root(vault: &str, recent_vaults: &[String]) -> Option<PathBuf> {
+    let as_path = Path::new(vault);
+    if as_path.is_absolute() && as_path.is_dir() {
+        return paths::canonicalize(as_path).ok();
+    }
+    recent_vaults
+        .iter()
+        .map(Path::new)
+        .find(|root| root.file_name().and_then(|n| n.to_str()) == Some(vault) && root.is_dir())
+        .and_then(|root| paths::canonicalize(root).ok())
+}
+
+fn resolve_vault_file(root: &Path, file: &str) -> Option<String> {
+    let rel = file.replace('\\', "/");
+    let rel = rel.trim_start_matches('/');
+    [root.join(rel), root.join(format!("{}.md", rel))]
+        .iter()
+        .filter(|p| p.is_file())
+        .find_map(|p| paths::canonicalize(p).ok())
+        .filter(|p| p.starts_with(root))
+        .and_then(|p| p.to_str().map(String::from))
+}
+
+pub fn handle_deep_link(app: &AppHandle, uri: &str) {
+    let Some(parsed) = parse_obsidian_uri(uri) else {
+        return;
+    };
+    let recent_vaults = app.state::<SettingsState>().get().recent_vaults;
+    let Some(target) = resolve_obsidian_uri(&parsed, &recent_vaults) else {
+        let _ = app.emit("open-file-error", format!("Cannot open {}", uri));
+        return;
+    };
+    route_open_target(app, target);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parses_vault_and_file() {
+        let uri = parse_obsidian_uri("obsidian://open?vault=My%20Vault&file=Projects%2FPlan").unwrap();
+        assert_eq!(uri.vault.as_deref(), Some("My Vault"));
+        assert_eq!(uri.file.as_deref(), Some("Projects/Plan"));
+        assert!(uri.path.is_none());
+    }
+
+    #[test]
+    fn rejects_other_actions() {
+        assert!(parse_obsidian_uri("obsidian://new?vault=x").is_none());
+        assert!(parse_obsidian_uri("mdglasses://open?vault=x").is_some());
+        assert!(parse_obsidian_uri("https://open?vault=x").is_none());
+    }
+
+    #[test]
+    fn resolves_recent_vault_by_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = dir.path().join("Notes");
+        fs::create_dir_all(vault.join("sub")).unwrap();
+        fs::write(vault.join("sub").join("Plan.md"), "# Plan").unwrap();
+        let recent = vec![vault.to_str().unwrap().to_string()];
+
+        let uri = parse_obsidian_uri("obsidian://open?vault=Notes&file=sub/Plan").unwrap();
+        let target = resolve_obsidian_uri(&uri, &recent).unwrap();
+        assert!(!target.is_dir);
+        assert!(target.path.ends_with("Plan.md"), "{}", target.path);
+        assert!(target.vault_root.is_some());
+    }
+
+    #[test]
+    fn file_outside_vault_falls_back_to_vault() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = dir.path().join("Notes");
+        fs::create_dir_all(&vault).unwrap();
+        fs::write(dir.path().join("secret.md"), "x").unwrap();
+        let recent = vec![vault.to_str().unwrap().to_string()];
+
+        let uri = parse_obsidian_uri("obsidian://open?vault=Notes&file=../secret").unwrap();
+        let target = resolve_obsidian_uri(&uri, &recent).unwrap();
+        assert!(target.is_dir);
+        assert!(target.vault_root.is_none());
+    }
+}
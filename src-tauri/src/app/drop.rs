@@ -0,0 +1,106 @@
+//! Files and folders dropped on the window: folders open as vaults, `.md` files render directly.
+
+use std::path::PathBuf;
+
+use tauri::{Emitter, Manager, Window};
+
+use crate::obsidian_embed::RenderGeneration;
+
+use super::commands::{open_markdown, open_vault};
+use super::settings::SettingsState;
+use super::state::{path_to_string, VaultState};
+use super::types::{AppResult, OpenMarkdownFileResult, OpenWikiFolderResult};
+
+#[derive(Clone, serde::Serialize)]
+pub struct DroppedFile {
+    pub path: String,
+    /// Set when the file lives inside the open vault and was rendered with it.
+    pub vault_root: Option<String>,
+    pub result: OpenMarkdownFileResult,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct DroppedFolder {
+    pub path: String,
+    pub result: OpenWikiFolderResult,
+}
+
+/// Picks the first dropped folder, else the first markdown file; other files are ignored.
+fn pick_drop_target(paths: &[PathBuf]) -> Option<PathBuf> {
+    paths
+        .iter()
+        .find(|p| p.is_dir())
+        .or_else(|| {
+            paths
+                .iter()
+                .find(|p| p.is_file() && p.extension().map(|e| e == "md").unwrap_or(false))
+        })
+        .cloned()
+}
+
+pub fn handle_drop(window: &Window, paths: Vec<PathBuf>) {
+    let Some(target) = pick_drop_target(&paths) else {
+        let _ = window.emit("drop-error", "Only folders and .md files can be opened");
+        return;
+    };
+    let window = window.clone();
+    std::thread::spawn(move || {
+        if let Err(error) = open_dropped(&window, &target) {
+            let _ = window.emit("drop-error", error);
+        }
+    });
+}
+
+fn open_dropped(window: &Window, target: &std::path::Path) -> AppResult<()> {
+    let path = path_to_string(&crate::paths::canonicalize(target)?)?;
+    let vaults = window.state::<VaultState>();
+    if target.is_dir() {
+        let settings = window.state::<SettingsState>();
+        let result = open_vault(&path, window.app_handle(), &vaults, &settings)?;
+        let _ = window.emit("folder-dropped", DroppedFolder { path, result });
+        return Ok(());
+    }
+    let vault_root = vaults
+        .0
+        .read()
+        .unwrap()
+        .containing(std::path::Path::new(&path))
+        .and_then(|(root, _, _)| root.to_str().map(String::from));
+    let render = window.state::<SettingsState>().get().render_options(vault_root.as_deref());
+    let result = open_markdown(&path, vault_root.as_deref(), &vaults, &render, RenderGeneration::default())?;
+    let _ = window.emit(
+        "file-dropped",
+        DroppedFile {
+            path,
+            vault_root,
+            result,
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn folder_wins_over_markdown_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let note = dir.path().join("a.md");
+        fs::write(&note, "# A").unwrap();
+        let folder = dir.path().join("vault");
+        fs::create_dir_all(&folder).unwrap();
+        assert_eq!(pick_drop_target(&[note.clone(), folder.clone()]), Some(folder));
+        assert_eq!(pick_drop_target(std::slice::from_ref(&note)), Some(note));
+    }
+
+    #[test]
+    fn non_markdown_files_are_ignored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let image = dir.path().join("a.png");
+        fs::write(&image, "x").unwrap();
+        assert_eq!(pick_drop_target(&[image]), None);
+    }
+}
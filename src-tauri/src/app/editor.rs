@@ -0,0 +1,144 @@
+//! External editor launching. `Settings::editor` is a preset ("vscode" | "neovim" | "obsidian")
+//! or a command template with `{path}` / `{line}` placeholders, e.g. `subl {path}:{line}`.
+
+use std::process::Command;
+
+use crate::text::percent_encode;
+
+use super::types::AppResult;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EditorLaunch {
+    Command { program: String, args: Vec<String> },
+    Url(String),
+}
+
+fn preset_template(editor: &str) -> Option<&'static str> {
+    match editor {
+        "vscode" => Some("code --goto {path}:{line}"),
+        "neovim" => Some("nvim +{line} {path}"),
+        _ => None,
+    }
+}
+
+/// Builds the launch for `path` (and `line`, 1-based). Template arguments are whitespace-separated;
+/// without a line, `:{line}` suffixes are stripped and other arguments using `{line}` are dropped.
+pub fn editor_launch(editor: &str, path: &str, line: Option<u32>) -> AppResult<EditorLaunch> {
+    let editor = editor.trim();
+    if editor.is_empty() {
+        return Err("No editor configured".to_string());
+    }
+    if editor == "obsidian" {
+        return Ok(EditorLaunch::Url(format!("obsidian://open?path={}", percent_encode(path))));
+    }
+    let template = preset_template(editor).unwrap_or(editor);
+    let mut parts = template.split_whitespace();
+    let program = parts.next().unwrap_or_default().to_string();
+    let mut args: Vec<String> = parts
+        .filter_map(|arg| match line {
+            Some(line) => Some(arg.replace("{line}", &line.to_string())),
+            None => {
+                let arg = arg.replace(":{line}", "");
+                (!arg.contains("{line}")).then_some(arg)
+            }
+        })
+        .map(|arg| arg.replace("{path}", path))
+        .collect();
+    if !template.contains("{path}") {
+        args.push(path.to_string());
+    }
+    Ok(EditorLaunch::Command { program, args })
+}
+
+pub fn launch(editor_launch: EditorLaunch) -> AppResult<()> {
+    match editor_launch {
+        EditorLaunch::Url(url) => {
+            tauri_plugin_opener::open_url(url, None::<&str>).map_err(|e| e.to_string())
+        }
+        EditorLaunch::Command { program, args } => {
+            #[cfg(windows)]
+            let mut command = Command::new(windows_program(&program));
+            #[cfg(not(windows))]
+            let mut command = Command::new(&program);
+            command
+                .args(&args)
+                .spawn()
+                .map(|_| ())
+                .map_err(|e| format!("Failed to launch {}: {}", program, e))
+        }
+    }
+}
+
+/// `program` looked up in `PATH` with the `PATHEXT` extensions, as cmd would. Editors are often
+/// `.cmd` shims on Windows (`code.cmd`); running the shim itself, rather than through `cmd /C`,
+/// lets Rust escape the arguments for batch files (or refuse ones it cannot escape).
+#[cfg(windows)]
+fn windows_program(program: &str) -> std::path::PathBuf {
+    let program_path = std::path::Path::new(program);
+    if program_path.extension().is_some() || program_path.components().count() > 1 {
+        return program_path.to_path_buf();
+    }
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    let dirs = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&dirs)
+        .flat_map(|dir| {
+            extensions
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(move |ext| dir.join(format!("{}{}", program, ext)))
+        })
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| program_path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(program: &str, args: &[&str]) -> EditorLaunch {
+        EditorLaunch::Command {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn presets_place_line_number() {
+        assert_eq!(
+            editor_launch("vscode", "/v/a.md", Some(12)).unwrap(),
+            command("code", &["--goto", "/v/a.md:12"])
+        );
+        assert_eq!(
+            editor_launch("neovim", "/v/a.md", Some(3)).unwrap(),
+            command("nvim", &["+3", "/v/a.md"])
+        );
+    }
+
+    #[test]
+    fn missing_line_drops_line_arguments() {
+        assert_eq!(
+            editor_launch("vscode", "/v/a.md", None).unwrap(),
+            command("code", &["--goto", "/v/a.md"])
+        );
+        assert_eq!(
+            editor_launch("neovim", "/v/a.md", None).unwrap(),
+            command("nvim", &["/v/a.md"])
+        );
+    }
+
+    #[test]
+    fn custom_template_without_path_appends_it() {
+        assert_eq!(
+            editor_launch("gedit", "/v/a.md", Some(1)).unwrap(),
+            command("gedit", &["/v/a.md"])
+        );
+    }
+
+    #[test]
+    fn obsidian_uses_uri() {
+        assert_eq!(
+            editor_launch("obsidian", "/v/my note.md", None).unwrap(),
+            EditorLaunch::Url("obsidian://open?path=%2Fv%2Fmy%20note.md".to_string())
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Full-text indexes of the open vaults, kept under the app data dir and updated from watcher
+//! events. Search narrows the notes it scans with them when the `fulltext_index` setting is on;
+//! builds without the `fulltext` feature keep none and always scan.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "fulltext")]
+use std::collections::HashMap;
+#[cfg(feature = "fulltext")]
+use std::sync::{Arc, RwLock};
+
+use tauri::AppHandle;
+#[cfg(feature = "fulltext")]
+use tauri::{Emitter, Manager};
+
+#[cfg(feature = "fulltext")]
+use crate::fulltext::FulltextIndex;
+
+#[cfg(feature = "fulltext")]
+use super::settings::SettingsState;
+
+#[derive(Default)]
+pub struct FulltextState {
+    /// Canonical vault root → its index, once synced.
+    #[cfg(feature = "fulltext")]
+    indexes: RwLock<HashMap<PathBuf, Arc<FulltextIndex>>>,
+}
+
+#[cfg(feature = "fulltext")]
+impl FulltextState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the index of the vault at `root` and syncs it with `notes` in the background; search
+    /// scans every note until that is done. Does nothing when the setting is off.
+    pub fn open_vault(&self, app: &AppHandle, root: &Path, notes: Vec<PathBuf>) {
+        if !app.state::<SettingsState>().get().fulltext_index {
+            self.indexes.write().unwrap().remove(root);
+            return;
+        }
+        let existing = self.indexes.read().unwrap().get(root).cloned();
+        let Ok(data_dir) = app.path().app_data_dir() else {
+            return;
+        };
+        let dir = data_dir.join("fulltext").join(crate::paths::content_hash(&root.to_string_lossy()));
+        let (app, root) = (app.clone(), root.to_path_buf());
+        std::thread::spawn(move || {
+            let synced = existing
+                .map_or_else(|| FulltextIndex::open(&dir).map(Arc::new), Ok)
+                .and_then(|index| index.sync(&notes).map(|_| index));
+            match synced {
+                Ok(index) => {
+                    app.state::<FulltextState>().indexes.write().unwrap().insert(root, index);
+                }
+                Err(error) => {
+                    let _ = app.emit("fulltext-error", error);
+                }
+            }
+        });
+    }
+
+    /// Reindexes changed files in the vaults whose index is loaded.
+    pub fn notes_changed(&self, app: &AppHandle, paths: &[PathBuf]) {
+        let indexes = self.indexes.read().unwrap();
+        for (root, index) in indexes.iter() {
+            let changed: Vec<PathBuf> = paths.iter().filter(|path| path.starts_with(root)).cloned().collect();
+            if let Err(error) = index.update(&changed) {
+                let _ = app.emit("fulltext-error", error);
+            }
+        }
+    }
+
+    /// The notes of `notes` (from the vault at `root`) that can match `query`, in their order;
+    /// all of them when the vault has no index or the index cannot narrow the query.
+    pub fn narrow(&self, root: &Path, query: &str, notes: Vec<PathBuf>) -> Vec<PathBuf> {
+        let index = self.indexes.read().unwrap().get(root).cloned();
+        match index.map(|index| index.candidates(query)) {
+            Some(Ok(Some(found))) => notes.into_iter().filter(|note| found.contains(note)).collect(),
+            _ => notes,
+        }
+    }
+}
+
+#[cfg(not(feature = "fulltext"))]
+impl FulltextState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open_vault(&self, _app: &AppHandle, _root: &Path, _notes: Vec<PathBuf>) {}
+
+    pub fn notes_changed(&self, _app: &AppHandle, _paths: &[PathBuf]) {}
+
+    pub fn narrow(&self, _root: &Path, _query: &str, notes: Vec<PathBuf>) -> Vec<PathBuf> {
+        notes
+    }
+}
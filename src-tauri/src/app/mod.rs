@@ -1,9 +1,49 @@
+mod app_link;
 mod commands;
+mod deep_link;
+mod drop;
+mod editor;
+mod fulltext;
+mod open_events;
+mod operations;
+mod pagination;
+mod profiling;
+mod render_queue;
+mod serve;
+mod settings;
+mod site;
 mod state;
+mod theme;
+mod tree_diff;
 mod types;
 mod watch;
+mod window_events;
+mod workspace;
 
-pub use commands::{get_initial_file, open_markdown_file, open_wiki_folder, watch_paths};
-pub use state::{InitialFile, VaultState, WatchService};
-pub use types::{InitialPath, TreeNode};
+pub use commands::{
+    add_block_id, apply_template, cancel_operation, check_links, complete_wikilink,
+    copy_note_as_html, copy_note_as_markdown, diff_note, export_epub, export_flattened_markdown,
+    export_site, export_via_pandoc, find_duplicate_notes, generate_moc, get_aliases, get_backlinks,
+    get_calendar_data, get_embedding_notes, get_file_metadata, get_initial_file,
+    get_note_at_revision, get_note_history, get_note_panel_data, get_notes_by_mtime,
+    get_outgoing_links, get_settings, get_theme, get_thumbnail, get_tree, lint_note,
+    list_saved_searches, load_workspace, move_folder, open_in_editor, open_markdown_file,
+    open_wiki_folder, prefetch_notes, preview_link, print_note, query_frontmatter, rebuild_index,
+    render_markdown_string, resolve_app_link, reveal_in_file_manager, save_markdown_file,
+    save_search, save_workspace, search_vault, serve_vault, start_search, stop_serving,
+    suggest_link_targets, toggle_task, update_settings, watch_paths,
+};
+pub use deep_link::handle_deep_link;
+pub use fulltext::FulltextState;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub use open_events::handle_opened_path;
+pub use open_events::initial_path_for;
+pub use operations::OperationRegistry;
+pub use render_queue::RenderQueue;
+pub use serve::PreviewServer;
+pub use settings::SettingsState;
+pub use state::{InitialFile, RenderGenerations, VaultState, WatchService};
+pub use window_events::handle_window_event;
+pub use workspace::WorkspaceState;
+pub use types::{InitialPath, NodeKind, TreeNode};
 pub use watch::spawn_watch_service;
@@ -0,0 +1,33 @@
+//! Paths handed to the app from outside (argv, deep links, OS open-file events).
+
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::state::InitialFile;
+use super::types::InitialPath;
+
+pub fn initial_path_for(path: &Path) -> Option<InitialPath> {
+    let canonical_path = crate::paths::canonicalize(path).ok()?;
+    let path_str = canonical_path.to_str()?.to_string();
+    let is_dir = canonical_path.is_dir();
+    Some(InitialPath {
+        path: path_str,
+        is_dir,
+        vault_root: None,
+    })
+}
+
+/// Stores the target as the initial file (for a UI that has not asked yet) and emits
+/// `open-file` (for one that is already running).
+pub fn route_open_target(app: &AppHandle, target: InitialPath) {
+    app.state::<InitialFile>().set(target.clone());
+    let _ = app.emit("open-file", target);
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn handle_opened_path(app: &AppHandle, path: &Path) {
+    if let Some(target) = initial_path_for(path) {
+        route_open_target(app, target);
+    }
+}
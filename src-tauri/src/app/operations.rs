@@ -0,0 +1,204 @@
+//! Long-running operations: started commands return an id at once, report through
+//! `operation-progress` / `operation-finished` events, and stop cooperatively on cancel or timeout.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::types::AppResult;
+
+pub type OperationId = u64;
+
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct OperationRegistry {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<OperationId, CancelToken>>,
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        OperationRegistry {
+            next_id: AtomicU64::new(1),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn register(&self) -> (OperationId, CancelToken) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancelToken::default();
+        self.running.lock().unwrap().insert(id, token.clone());
+        (id, token)
+    }
+
+    fn finish(&self, id: OperationId) {
+        self.running.lock().unwrap().remove(&id);
+    }
+
+    /// Returns false when no operation with `id` is running.
+    pub fn cancel(&self, id: OperationId) -> bool {
+        match self.running.lock().unwrap().get(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct OperationProgress {
+    pub id: OperationId,
+    pub kind: String,
+    pub done: usize,
+    pub total: usize,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct OperationFinished {
+    pub id: OperationId,
+    pub kind: String,
+    /// "ok" | "error" | "cancelled" | "timeout"
+    pub status: String,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// Handed to a running job for progress reporting and cooperative cancellation checks.
+pub struct OperationHandle {
+    pub id: OperationId,
+    kind: String,
+    token: CancelToken,
+    deadline: Option<Instant>,
+    last_progress: Cell<Option<Instant>>,
+    app: AppHandle,
+}
+
+const CANCELLED: &str = "cancelled";
+const TIMED_OUT: &str = "timeout";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+impl OperationHandle {
+    /// Err once the operation was cancelled or ran past its timeout; jobs should return it as is.
+    pub fn check(&self) -> AppResult<()> {
+        if self.token.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        if self.deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+            return Err(TIMED_OUT.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn progress(&self, done: usize, total: usize, message: Option<String>) {
+        let _ = self.app.emit(
+            "operation-progress",
+            OperationProgress {
+                id: self.id,
+                kind: self.kind.clone(),
+                done,
+                total,
+                message,
+            },
+        );
+    }
+
+    /// `check` plus a progress event, throttled so tight loops can call it per item.
+    pub fn tick(&self, done: usize, total: usize) -> AppResult<()> {
+        self.check()?;
+        let due = self
+            .last_progress
+            .get()
+            .map(|at| at.elapsed() >= PROGRESS_INTERVAL)
+            .unwrap_or(true);
+        if due || done == total {
+            self.last_progress.set(Some(Instant::now()));
+            self.progress(done, total, None);
+        }
+        Ok(())
+    }
+}
+
+/// Runs `job` on a worker thread and returns its id immediately. `kind` names the operation
+/// ("search", "index", ...) in events and selects its timeout.
+pub fn spawn_operation<T, F>(
+    app: &AppHandle,
+    kind: &str,
+    timeout: Option<Duration>,
+    job: F,
+) -> OperationId
+where
+    T: serde::Serialize,
+    F: FnOnce(&OperationHandle) -> AppResult<T> + Send + 'static,
+{
+    let (id, token) = app.state::<OperationRegistry>().register();
+    let handle = OperationHandle {
+        id,
+        kind: kind.to_string(),
+        token,
+        deadline: timeout.map(|t| Instant::now() + t),
+        last_progress: Cell::new(None),
+        app: app.clone(),
+    };
+    std::thread::spawn(move || {
+        let outcome = job(&handle).and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string()));
+        handle.app.state::<OperationRegistry>().finish(id);
+        let (status, result, error) = match outcome {
+            Ok(value) => ("ok".to_string(), Some(value), None),
+            Err(e) if e == CANCELLED || e == TIMED_OUT => (e, None, None),
+            Err(e) => ("error".to_string(), None, Some(e)),
+        };
+        let _ = handle.app.emit(
+            "operation-finished",
+            OperationFinished {
+                id,
+                kind: handle.kind.clone(),
+                status,
+                result,
+                error,
+            },
+        );
+    });
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_marks_running_token() {
+        let registry = OperationRegistry::new();
+        let (id, token) = registry.register();
+        assert!(!token.is_cancelled());
+        assert!(registry.cancel(id));
+        assert!(token.is_cancelled());
+        registry.finish(id);
+        assert!(!registry.cancel(id), "finished operations cannot be cancelled");
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        let registry = OperationRegistry::new();
+        let (a, _) = registry.register();
+        let (b, _) = registry.register();
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,51 @@
+//! Offset/limit pagination for list-returning commands.
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Clone, serde::Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    /// Offset to pass for the next page; `None` on the last page.
+    pub next_offset: Option<usize>,
+}
+
+pub fn paginate<T>(items: Vec<T>, offset: Option<usize>, limit: Option<usize>) -> Page<T> {
+    let total = items.len();
+    let offset = offset.unwrap_or(0).min(total);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let items: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+    let end = offset + items.len();
+    Page {
+        items,
+        total,
+        next_offset: (end < total).then_some(end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_through_items() {
+        let first = paginate((0..120).collect(), None, None);
+        assert_eq!(first.items.len(), DEFAULT_PAGE_SIZE);
+        assert_eq!(first.total, 120);
+        assert_eq!(first.next_offset, Some(50));
+
+        let last = paginate((0..120).collect::<Vec<_>>(), Some(100), Some(50));
+        assert_eq!(last.items, (100..120).collect::<Vec<_>>());
+        assert_eq!(last.next_offset, None);
+    }
+
+    #[test]
+    fn limit_is_clamped() {
+        let page = paginate((0..1000).collect::<Vec<_>>(), None, Some(10_000));
+        assert_eq!(page.items.len(), MAX_PAGE_SIZE);
+        let page = paginate((0..10).collect::<Vec<_>>(), Some(50), Some(0));
+        assert!(page.items.is_empty());
+        assert_eq!(page.next_offset, None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Vault-open phase timings and slow-vault hints, reported via the `startup-report` event.
+
+use std::path::Path;
+use std::time::Instant;
+
+use super::types::{NodeKind, TreeNode};
+
+const SLOW_PHASE_MILLIS: u64 = 1000;
+const HEAVY_FOLDER_FILES: usize = 2000;
+
+#[derive(Clone, serde::Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub millis: u64,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct StartupReport {
+    pub vault_root: String,
+    pub phases: Vec<PhaseTiming>,
+    pub total_millis: u64,
+    pub hints: Vec<String>,
+}
+
+pub struct PhaseTimer {
+    started: Instant,
+    phase_started: Instant,
+    phases: Vec<PhaseTiming>,
+    /// Files the tree walk listed under each top-level folder.
+    folder_files: Vec<(String, usize)>,
+}
+
+impl PhaseTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        PhaseTimer {
+            started: now,
+            phase_started: now,
+            phases: Vec::new(),
+            folder_files: Vec::new(),
+        }
+    }
+
+    /// Keeps the per-folder file counts of `tree` for the slow-vault hints, so they need no
+    /// second walk of the vault.
+    pub fn record_tree(&mut self, tree: &[TreeNode]) {
+        self.folder_files = tree
+            .iter()
+            .filter(|node| node.kind == NodeKind::Dir)
+            .map(|node| (node.name.clone(), count_files(&node.children)))
+            .collect();
+    }
+
+    /// Records the time since the previous phase ended (or since start) under `phase`.
+    pub fn finish_phase(&mut self, phase: &str) {
+        let now = Instant::now();
+        self.phases.push(PhaseTiming {
+            phase: phase.to_string(),
+            millis: now.duration_since(self.phase_started).as_millis() as u64,
+        });
+        self.phase_started = now;
+    }
+
+    pub fn into_report(self, vault_root: &Path) -> StartupReport {
+        let hints = slow_vault_hints(&self.phases, &self.folder_files);
+        StartupReport {
+            vault_root: vault_root.to_string_lossy().into_owned(),
+            total_millis: self.started.elapsed().as_millis() as u64,
+            phases: self.phases,
+            hints,
+        }
+    }
+}
+
+fn slow_vault_hints(phases: &[PhaseTiming], folder_files: &[(String, usize)]) -> Vec<String> {
+    let slow: Vec<&PhaseTiming> = phases.iter().filter(|p| p.millis >= SLOW_PHASE_MILLIS).collect();
+    if slow.is_empty() {
+        return Vec::new();
+    }
+    let mut hints: Vec<String> = slow
+        .iter()
+        .map(|p| match p.phase.as_str() {
+            "render" => format!(
+                "Initial note took {:.1} s to render — it may embed many or very large notes",
+                p.millis as f64 / 1000.0
+            ),
+            "tree" => format!("Tree walk took {:.1} s", p.millis as f64 / 1000.0),
+            "index" => format!("Index build took {:.1} s", p.millis as f64 / 1000.0),
+            phase => format!("{} took {:.1} s", phase, p.millis as f64 / 1000.0),
+        })
+        .collect();
+    if slow.iter().any(|p| p.phase != "render") {
+        hints.extend(heavy_folder_hints(folder_files, HEAVY_FOLDER_FILES));
+    }
+    hints
+}
+
+/// One hint per top-level folder of `folder_files` holding at least `min_files` files,
+/// largest first.
+pub(crate) fn heavy_folder_hints(folder_files: &[(String, usize)], min_files: usize) -> Vec<String> {
+    let mut counts: Vec<(String, usize)> =
+        folder_files.iter().filter(|(_, count)| *count >= min_files).cloned().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .map(|(name, count)| {
+            format!("{} files under {} — consider excluding it", format_thousands(count), name)
+        })
+        .collect()
+}
+
+/// Files (not folders) anywhere in `nodes`.
+fn count_files(nodes: &[TreeNode]) -> usize {
+    nodes
+        .iter()
+        .map(|node| if node.kind == NodeKind::Dir { count_files(&node.children) } else { 1 })
+        .sum()
+}
+
+fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let head = digits.len() % 3;
+    let mut groups: Vec<&str> = Vec::new();
+    if head > 0 {
+        groups.push(&digits[..head]);
+    }
+    let mut rest = &digits[head..];
+    while !rest.is_empty() {
+        groups.push(&rest[..3]);
+        rest = &rest[3..];
+    }
+    groups.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_thousands() {
+        assert_eq!(format_thousands(7), "7");
+        assert_eq!(format_thousands(12000), "12,000");
+        assert_eq!(format_thousands(1234567), "1,234,567");
+    }
+
+    fn node(name: &str, kind: NodeKind, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            name: name.to_string(),
+            path: name.to_string(),
+            kind,
+            children,
+            size: 0,
+            modified_ms: None,
+            created_ms: None,
+            note_count: 0,
+            folder_note: None,
+            inbound_links: 0,
+            outbound_links: 0,
+        }
+    }
+
+    #[test]
+    fn heavy_folder_hint_names_large_folders_only() {
+        let notes = (0..5).map(|i| node(&format!("{}.md", i), NodeKind::Note, Vec::new())).collect();
+        let tree = vec![
+            node("archive", NodeKind::Dir, vec![node("deep", NodeKind::Dir, notes)]),
+            node("small", NodeKind::Dir, vec![node("a.md", NodeKind::Note, Vec::new())]),
+            node("top.md", NodeKind::Note, Vec::new()),
+        ];
+        let mut timer = PhaseTimer::start();
+        timer.record_tree(&tree);
+        assert_eq!(timer.folder_files, vec![("archive".to_string(), 5), ("small".to_string(), 1)]);
+
+        let hints = heavy_folder_hints(&timer.folder_files, 3);
+        assert_eq!(hints, vec!["5 files under archive — consider excluding it".to_string()]);
+    }
+
+    #[test]
+    fn fast_phases_produce_no_hints() {
+        let phases = vec![PhaseTiming {
+            phase: "tree".to_string(),
+            millis: 10,
+        }];
+        assert!(slow_vault_hints(&phases, &[("big".to_string(), 5000)]).is_empty());
+    }
+}
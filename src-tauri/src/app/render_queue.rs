@@ -0,0 +1,235 @@
+//! Background render worker. One thread renders notes in priority order — the note being
+//! opened, then prefetches, then cache warming — and opening a note supersedes a background
+//! render already running, so speculative embed expansion never delays it. A render that
+//! panics fails on its own; the worker goes on with the next one.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use crate::markdown::RenderOptions;
+use crate::obsidian_embed::{render_markdown_with_embeds, RenderGeneration};
+
+use super::commands::{open_markdown, with_render_context};
+use super::settings::SettingsState;
+use super::state::{VaultState, RENDER_SUPERSEDED};
+use super::types::{AppResult, OpenMarkdownFileResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderPriority {
+    /// Notes likely to be opened soon (recently modified), rendered when nothing else is queued.
+    CacheWarm,
+    /// Notes the user is about to open (e.g. a hovered link).
+    Prefetch,
+    /// The note being opened.
+    Visible,
+}
+
+enum RenderJob {
+    Visible {
+        path: String,
+        vault_root: Option<String>,
+        render: RenderOptions,
+        generation: RenderGeneration,
+        reply: oneshot::Sender<AppResult<OpenMarkdownFileResult>>,
+    },
+    /// Renders into the note's vault render cache; the HTML itself is dropped.
+    Background(PathBuf),
+}
+
+struct QueuedJob {
+    priority: RenderPriority,
+    seq: u64,
+    job: RenderJob,
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Higher priority first, then first queued first.
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+#[derive(Default)]
+pub struct RenderQueue {
+    jobs: Mutex<BinaryHeap<QueuedJob>>,
+    ready: Condvar,
+    next_seq: AtomicU64,
+    /// Generation counter of background renders; bumped to supersede the one running.
+    background: Arc<AtomicU64>,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the worker thread.
+    pub fn spawn_worker(&self, app: AppHandle) {
+        std::thread::spawn(move || loop {
+            let job = app.state::<RenderQueue>().next_job();
+            run_catching_panics(job, |job| run_job(&app, job));
+        });
+    }
+
+    /// Renders the note at `path` ahead of every queued background render and waits for it
+    /// without holding up the async runtime.
+    pub async fn render_visible(
+        &self,
+        path: String,
+        vault_root: Option<String>,
+        render: RenderOptions,
+        generation: RenderGeneration,
+    ) -> AppResult<OpenMarkdownFileResult> {
+        let (reply, result) = oneshot::channel();
+        self.push(
+            RenderPriority::Visible,
+            RenderJob::Visible {
+                path,
+                vault_root,
+                render,
+                generation,
+                reply,
+            },
+        );
+        self.background.fetch_add(1, Ordering::Relaxed);
+        result.await.map_err(|_| "The render failed".to_string())?
+    }
+
+    /// Queues background renders of `notes` at `priority`; notes already queued at that priority
+    /// or higher are skipped, and a higher priority moves a queued note up (to the back of its
+    /// new priority).
+    pub fn enqueue(&self, notes: Vec<PathBuf>, priority: RenderPriority) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut queued = std::mem::take(&mut *jobs).into_vec();
+        for note in notes {
+            let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+            let existing = queued
+                .iter()
+                .position(|q| matches!(&q.job, RenderJob::Background(path) if *path == note));
+            match existing {
+                Some(i) if queued[i].priority >= priority => {}
+                Some(i) => {
+                    queued[i].priority = priority;
+                    queued[i].seq = seq;
+                }
+                None => queued.push(QueuedJob {
+                    priority,
+                    seq,
+                    job: RenderJob::Background(note),
+                }),
+            }
+        }
+        *jobs = queued.into();
+        self.ready.notify_one();
+    }
+
+    fn push(&self, priority: RenderPriority, job: RenderJob) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.jobs.lock().unwrap().push(QueuedJob { priority, seq, job });
+        self.ready.notify_one();
+    }
+
+    fn next_job(&self) -> RenderJob {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            if let Some(queued) = jobs.pop() {
+                return queued.job;
+            }
+            jobs = self.ready.wait(jobs).unwrap();
+        }
+    }
+}
+
+/// Runs `job` with `run`, surviving a panic; the job's reply is then dropped, so the caller
+/// waiting for it gets an error instead of waiting forever.
+fn run_catching_panics(job: RenderJob, run: impl FnOnce(RenderJob)) {
+    let _ = catch_unwind(AssertUnwindSafe(|| run(job)));
+}
+
+fn run_job(app: &AppHandle, job: RenderJob) {
+    match job {
+        RenderJob::Visible {
+            path,
+            vault_root,
+            render,
+            generation,
+            reply,
+        } => {
+            let result = if generation.is_superseded() {
+                Err(RENDER_SUPERSEDED.to_string())
+            } else {
+                open_markdown(&path, vault_root.as_deref(), &app.state::<VaultState>(), &render, generation)
+            };
+            let _ = reply.send(result);
+        }
+        RenderJob::Background(path) => {
+            let state = app.state::<VaultState>();
+            if state.0.read().unwrap().containing(&path).is_none() {
+                return;
+            }
+            let generation = RenderGeneration::next(&app.state::<RenderQueue>().background);
+            let settings = app.state::<SettingsState>().get();
+            let _ = with_render_context(&path, &path, &state, &settings, |ctx| {
+                ctx.generation = generation;
+                render_markdown_with_embeds(&path, ctx)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_run_by_priority_then_queue_order() {
+        let queue = RenderQueue::new();
+        queue.enqueue(vec![PathBuf::from("/warm.md"), PathBuf::from("/b.md")], RenderPriority::CacheWarm);
+        queue.enqueue(vec![PathBuf::from("/a.md")], RenderPriority::Prefetch);
+        queue.enqueue(vec![PathBuf::from("/b.md")], RenderPriority::Prefetch);
+        queue.enqueue(vec![PathBuf::from("/a.md")], RenderPriority::CacheWarm);
+        let order: Vec<PathBuf> = (0..3)
+            .map(|_| match queue.next_job() {
+                RenderJob::Background(path) => path,
+                RenderJob::Visible { .. } => unreachable!(),
+            })
+            .collect();
+        assert_eq!(order, [PathBuf::from("/a.md"), PathBuf::from("/b.md"), PathBuf::from("/warm.md")]);
+        assert!(queue.jobs.lock().unwrap().is_empty(), "no duplicate jobs");
+    }
+
+    #[test]
+    fn panicking_render_answers_its_caller() {
+        let (reply, result) = oneshot::channel();
+        let job = RenderJob::Visible {
+            path: "/a.md".to_string(),
+            vault_root: None,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+            reply,
+        };
+        run_catching_panics(job, |_| panic!("render bug"));
+        assert!(result.blocking_recv().is_err());
+    }
+}
@@ -0,0 +1,470 @@
+//! Read-only HTTP preview of a vault: notes are rendered with embeds on request, wikilinks
+//! point at the other served notes and vault files (images, PDFs) are served as is. Hidden and
+//! excluded folders are never served, and pages carry no absolute paths. The server listens on
+//! localhost; LAN access is opt-in and requires a random token. Note pages keep a server-sent
+//! events stream open and reload when the watcher reports a change to the note or anything it
+//! embeds.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::diagrams::render_diagrams;
+use crate::export::image_mime;
+use crate::obsidian_embed::{embed_dependencies, portable_href, render_markdown_with_embeds};
+use crate::paths;
+use crate::text::percent_decode;
+use crate::wiki;
+
+use super::commands::with_render_context;
+use super::settings::SettingsState;
+use super::state::VaultState;
+use super::types::{AppResult, NodeKind, TreeNode};
+
+const PAGE_STYLE: &str = "body{font-family:system-ui,sans-serif;line-height:1.6;margin:0;color:#1f2328}\
+nav{padding:.5rem 1rem;border-bottom:1px solid #d0d7de;background:#f6f8fa}\
+main{max-width:50rem;margin:0 auto;padding:1rem 1.5rem}\
+img{max-width:100%}pre{overflow:auto;background:#f6f8fa;padding:1rem}\
+.obs-link.broken{color:#cf222e}";
+
+/// Path of the live-reload event stream; `?note=` names the page's note.
+const EVENTS_PATH: &str = "/__events";
+/// Comment sent on idle event streams so closed pages are noticed.
+const EVENTS_PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Threads answering requests; event streams run on threads of their own.
+const WORKER_THREADS: usize = 4;
+/// Event streams open at once; further pages get no live reload.
+const MAX_RELOAD_CLIENTS: usize = 32;
+/// Cookie holding the access token of a LAN server, set when a URL carries `?token=`.
+const TOKEN_COOKIE: &str = "mdglasses_token";
+
+pub struct PreviewServer {
+    server: RwLock<Option<Arc<Server>>>,
+    /// Open live-reload streams, each with the files its page was rendered from.
+    reload_clients: Mutex<Vec<ReloadClient>>,
+    next_client_id: AtomicU64,
+}
+
+struct ReloadClient {
+    id: u64,
+    files: HashSet<PathBuf>,
+    reload: Sender<()>,
+}
+
+impl PreviewServer {
+    pub fn new() -> Self {
+        PreviewServer {
+            server: RwLock::new(None),
+            reload_clients: Mutex::new(Vec::new()),
+            next_client_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Serves `root` on `port` (0 picks a free port) of localhost, or with `lan` of every
+    /// interface behind a random access token, replacing the server already running. Returns
+    /// the bound port and the token, if any.
+    pub fn start(
+        &self,
+        app: AppHandle,
+        root: PathBuf,
+        port: u16,
+        lan: bool,
+    ) -> AppResult<(u16, Option<String>)> {
+        self.stop();
+        let token = lan.then(random_token).transpose()?;
+        let host = if lan { "0.0.0.0" } else { "127.0.0.1" };
+        let server = Server::http((host, port))
+            .map_err(|e| format!("Could not listen on port {}: {}", port, e))?;
+        let port = server.server_addr().to_ip().map(|addr| addr.port()).unwrap_or(port);
+        let server = Arc::new(server);
+        for _ in 0..WORKER_THREADS {
+            let (server, app, root, token) = (server.clone(), app.clone(), root.clone(), token.clone());
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle_request(&app, &root, token.as_deref(), request);
+                }
+            });
+        }
+        *self.server.write().unwrap() = Some(server);
+        Ok((port, token))
+    }
+
+    pub fn stop(&self) {
+        if let Some(server) = self.server.write().unwrap().take() {
+            for _ in 0..WORKER_THREADS {
+                server.unblock();
+            }
+        }
+        self.reload_clients.lock().unwrap().clear();
+    }
+
+    /// Registers a live-reload stream of a page rendered from `files`; returns its id and the
+    /// receiver of its reloads, or `None` once `MAX_RELOAD_CLIENTS` streams are open.
+    fn add_reload_client(&self, files: HashSet<PathBuf>) -> Option<(u64, Receiver<()>)> {
+        let mut clients = self.reload_clients.lock().unwrap();
+        if clients.len() >= MAX_RELOAD_CLIENTS {
+            return None;
+        }
+        let id = self.next_client_id.fetch_add(1, Ordering::Relaxed);
+        let (reload, events) = mpsc::channel();
+        clients.push(ReloadClient { id, files, reload });
+        Some((id, events))
+    }
+
+    /// Forgets the stream `id`, once its page is gone.
+    fn remove_reload_client(&self, id: u64) {
+        self.reload_clients.lock().unwrap().retain(|client| client.id != id);
+    }
+
+    /// Tells the pages rendered from any of the `changed` files to reload. Each stream gets a
+    /// single reload; the reloaded page opens a new one.
+    pub fn notify_changed(&self, changed: &[PathBuf]) {
+        let changed: Vec<PathBuf> = changed
+            .iter()
+            .map(|path| paths::canonicalize(path).unwrap_or_else(|_| path.clone()))
+            .collect();
+        self.reload_clients.lock().unwrap().retain(|client| {
+            if changed.iter().any(|path| client.files.contains(path)) {
+                let _ = client.reload.send(());
+                return false;
+            }
+            true
+        });
+    }
+}
+
+/// 128 random bits, hex-encoded.
+fn random_token() -> AppResult<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Whether `request` carries `token` in its `?token=` query or the token cookie.
+fn authorized(request: &Request, token: &str) -> bool {
+    let query = request.url().split_once('?').map_or("", |(_, query)| query);
+    let in_query = query.split('&').any(|pair| pair.strip_prefix("token=") == Some(token));
+    let in_cookie = request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Cookie"))
+        .flat_map(|header| header.value.as_str().split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(TOKEN_COOKIE)?.strip_prefix('='))
+        .any(|value| value == token);
+    in_query || in_cookie
+}
+
+fn handle_request(app: &AppHandle, root: &Path, token: Option<&str>, request: Request) {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        let _ = request.respond(Response::from_string("Read-only preview").with_status_code(405));
+        return;
+    }
+    if token.is_some_and(|token| !authorized(&request, token)) {
+        let _ = request.respond(Response::from_string("Forbidden").with_status_code(403));
+        return;
+    }
+    // Links between pages carry no token, so the one from the URL is kept in a cookie.
+    let token_cookie = token.map(|token| {
+        let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", TOKEN_COOKIE, token);
+        Header::from_bytes("Set-Cookie", cookie).expect("valid header")
+    });
+    let url_path = request.url().split(['?', '#']).next().unwrap_or("/");
+    let relative = percent_decode(url_path.trim_start_matches('/'));
+    let settings = app.state::<SettingsState>().get();
+    if url_path == EVENTS_PATH {
+        let note = request
+            .url()
+            .split_once("?note=")
+            .map(|(_, note)| percent_decode(note.trim_start_matches('/')))
+            .and_then(|note| servable_path(root, &note, &settings.excluded_folders(root)));
+        let Some(note) = note else {
+            let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            return;
+        };
+        let files = match app.state::<VaultState>().0.read().unwrap().containing(&note) {
+            Some((vault_root, index, _)) => embed_dependencies(&note, index, vault_root),
+            None => HashSet::from([note]),
+        };
+        match app.state::<PreviewServer>().add_reload_client(files) {
+            Some((id, events)) => {
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    stream_reload_events(events, request);
+                    app.state::<PreviewServer>().remove_reload_client(id);
+                });
+            }
+            None => {
+                let _ = request.respond(Response::from_string("Too many open pages").with_status_code(503));
+            }
+        }
+        return;
+    }
+    if relative.is_empty() {
+        let tree = wiki::build_tree_with(&root.to_string_lossy(), &settings.tree_options(root));
+        let body = match tree {
+            Ok(tree) => format!("<h1>{}</h1>{}", escape_html(&vault_name(root)), note_list(&tree, root)),
+            Err(error) => format!("<p>{}</p>", escape_html(&error)),
+        };
+        let _ = request.respond(html_response(page(root, "/", &vault_name(root), &body), token_cookie));
+        return;
+    }
+    let Some(path) = servable_path(root, &relative, &settings.excluded_folders(root)) else {
+        let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+        return;
+    };
+    if path.extension().map(|e| e == "md").unwrap_or(false) {
+        let state = app.state::<VaultState>();
+        // Served pages carry no diagram scripts, so diagrams are rendered here.
+        let html = with_render_context(&path, root, &state, &settings, |ctx| {
+            render_diagrams(&render_markdown_with_embeds(&path, ctx), &settings.diagram_tools(), ctx.cache)
+        });
+        let title = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let _ = match html {
+            Ok(html) => {
+                let body = format!("{}{}", served_links(&html, root), RELOAD_SCRIPT);
+                request.respond(html_response(page(root, "/", &title, &body), token_cookie))
+            }
+            Err(error) => request.respond(Response::from_string(error).with_status_code(500)),
+        };
+        return;
+    }
+    let _ = match fs::read(&path) {
+        Ok(bytes) => request.respond(Response::from_data(bytes).with_header(content_type(file_mime(&path)))),
+        Err(error) => request.respond(Response::from_string(error.to_string()).with_status_code(500)),
+    };
+}
+
+/// Reloads the page once the note changes (see `stream_reload_events`).
+const RELOAD_SCRIPT: &str =
+    "<script>new EventSource(\"/__events?note=\" + location.pathname).onmessage = () => location.reload();</script>";
+
+/// Keeps an event stream open until `events` brings a reload, which is sent on, or a ping
+/// finds the page closed.
+fn stream_reload_events(events: Receiver<()>, request: Request) {
+    let response = Response::empty(200)
+        .with_header(content_type("text/event-stream"))
+        .with_header(Header::from_bytes("Cache-Control", "no-cache").expect("valid header"));
+    // tiny_http buffers response bodies; taking over the connection lets every event be flushed
+    // as it is written. The stream is only ever written to.
+    let mut stream = request.upgrade("text/event-stream", response);
+    loop {
+        let message: &[u8] = match events.recv_timeout(EVENTS_PING_INTERVAL) {
+            Ok(()) => b"data: reload\n\n",
+            Err(RecvTimeoutError::Timeout) => b": ping\n\n",
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        if stream.write_all(message).and_then(|_| stream.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+/// The file `relative` names inside `root`, if it exists and may be served: not outside the
+/// vault, not hidden (`.git`, `.obsidian`, ...) and not in an excluded folder.
+fn servable_path(root: &Path, relative: &str, excluded: &[PathBuf]) -> Option<PathBuf> {
+    let path = paths::canonicalize(&root.join(relative)).ok()?;
+    let inside = path.strip_prefix(root).ok()?;
+    let hidden = inside.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    if hidden || !path.is_file() || excluded.iter().any(|folder| path.starts_with(folder)) {
+        return None;
+    }
+    Some(path)
+}
+
+/// Points rendered `app://open` and `file:///` links at their served URLs; links leaving the
+/// vault lose their href. Absolute paths are dropped or made relative (see `vault_links`).
+fn served_links(html: &str, root: &Path) -> String {
+    vault_links(html, root, |inside| Some(format!("/{}", portable_href(inside))))
+}
+
+/// Points rendered `app://open` and `file:///` links to files in `root` at `url(path inside
+/// root)`; other links, and those `url` gives no URL for, lose their href. Attributes holding
+/// absolute paths are dropped and paths in text (embed errors) are made relative to `root`.
+pub(super) fn vault_links(html: &str, root: &Path, url: impl Fn(&Path) -> Option<String>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some((start, prefix)) = [" href=\"app://open?path=", " href=\"file:///"]
+        .into_iter()
+        .filter_map(|prefix| rest.find(prefix).map(|at| (at, prefix)))
+        .min()
+    {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + prefix.len()..];
+        let end = after.find('"').unwrap_or(after.len());
+        let (target, anchor) = match after[..end].split_once('#') {
+            Some((target, anchor)) => (target, Some(anchor)),
+            None => (&after[..end], None),
+        };
+        let target = PathBuf::from(percent_decode(target));
+        if let Some(url) = target.strip_prefix(root).ok().and_then(&url) {
+            out.push_str(" href=\"");
+            out.push_str(&url);
+            if let Some(anchor) = anchor {
+                out.push('#');
+                out.push_str(anchor);
+            }
+            out.push('"');
+        }
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    out.push_str(rest);
+    let root_prefix = escape_html(&root.join("").to_string_lossy());
+    PATH_ATTRIBUTES
+        .into_iter()
+        .fold(out, |html, attr| strip_attribute(&html, attr))
+        .replace(&root_prefix, "")
+}
+
+/// Attributes of rendered notes whose values are absolute paths.
+const PATH_ATTRIBUTES: [&str; 5] =
+    ["data-obs-path", "data-obs-candidates", "data-source", "data-embed-path", "data-path"];
+
+fn strip_attribute(html: &str, name: &str) -> String {
+    let needle = format!(" {}=\"", name);
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(&needle) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + needle.len()..];
+        rest = after.find('"').map(|end| &after[end + 1..]).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Nested list linking every note of `tree`.
+fn note_list(tree: &[TreeNode], root: &Path) -> String {
+    let items: Vec<String> = tree
+        .iter()
+        .filter_map(|node| match node.kind {
+            NodeKind::Dir if node.note_count > 0 => Some(format!(
+                "<li>{}{}</li>",
+                escape_html(&node.name),
+                note_list(&node.children, root)
+            )),
+            NodeKind::Note => {
+                let inside = Path::new(&node.path).strip_prefix(root).ok()?;
+                let name = node.name.strip_suffix(".md").unwrap_or(&node.name);
+                Some(format!("<li><a href=\"/{}\">{}</a></li>", portable_href(inside), escape_html(name)))
+            }
+            _ => None,
+        })
+        .collect();
+    format!("<ul>{}</ul>", items.concat())
+}
+
+/// A page of the vault at `root`, whose nav links to `home`.
+pub(super) fn page(root: &Path, home: &str, title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\"><title>{}</title><style>{}</style></head><body><nav><a href=\"{}\">{}</a></nav><main class=\"markdown-body\">{}</main></body></html>",
+        escape_html(title),
+        PAGE_STYLE,
+        home,
+        escape_html(&vault_name(root)),
+        body
+    )
+}
+
+pub(super) fn vault_name(root: &Path) -> String {
+    root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn file_mime(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    image_mime(path).unwrap_or(match ext.as_str() {
+        "pdf" => "application/pdf",
+        "txt" | "csv" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    })
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime).expect("valid header")
+}
+
+fn html_response(html: String, cookie: Option<Header>) -> Response<std::io::Cursor<Vec<u8>>> {
+    let response = Response::from_string(html).with_header(content_type("text/html; charset=utf-8"));
+    match cookie {
+        Some(cookie) => response.with_header(cookie),
+        None => response,
+    }
+}
+
+pub(super) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_visible_vault_files_are_served() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("Archive")).unwrap();
+        fs::write(root.join("Note.md"), "x").unwrap();
+        fs::write(root.join(".git/config"), "x").unwrap();
+        fs::write(root.join("Archive/Old.md"), "x").unwrap();
+        let excluded = [root.join("Archive")];
+        assert_eq!(servable_path(&root, "Note.md", &excluded), Some(root.join("Note.md")));
+        assert_eq!(servable_path(&root, ".git/config", &excluded), None);
+        assert_eq!(servable_path(&root, "Archive/Old.md", &excluded), None);
+        assert_eq!(servable_path(&root, "../", &excluded), None);
+    }
+
+    #[test]
+    fn changes_reload_pages_rendered_from_them_once() {
+        let server = PreviewServer::new();
+        let files = HashSet::from([PathBuf::from("/v/A.md"), PathBuf::from("/v/B.md")]);
+        let (_, events) = server.add_reload_client(files).unwrap();
+
+        server.notify_changed(&[PathBuf::from("/v/C.md")]);
+        assert!(events.try_recv().is_err());
+        server.notify_changed(&[PathBuf::from("/v/B.md")]);
+        assert!(events.try_recv().is_ok());
+        assert!(server.reload_clients.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reload_streams_are_capped_and_forgotten_when_closed() {
+        let server = PreviewServer::new();
+        let ids: Vec<u64> = (0..MAX_RELOAD_CLIENTS)
+            .map(|_| server.add_reload_client(HashSet::new()).unwrap().0)
+            .collect();
+        assert!(server.add_reload_client(HashSet::new()).is_none());
+        server.remove_reload_client(ids[0]);
+        assert!(server.add_reload_client(HashSet::new()).is_some());
+        assert_eq!(server.reload_clients.lock().unwrap().len(), MAX_RELOAD_CLIENTS);
+    }
+
+    #[test]
+    fn links_point_at_served_urls() {
+        let html = "<a class=\"obs-link\" href=\"app://open?path=/v/My%20Note.md#part\" data-obs-path=\"/v/My Note.md\">A</a> \
+                    <a href=\"app://open?path=/elsewhere.md\">B</a>";
+        let out = served_links(html, Path::new("/v"));
+        assert_eq!(out, "<a class=\"obs-link\" href=\"/My%20Note.md#part\">A</a> <a>B</a>");
+    }
+
+    #[test]
+    fn served_pages_carry_no_absolute_paths() {
+        let html = "<div class=\"obs-embed\" data-source=\"/v/Sub/B.md\"></div>\
+                    <span class=\"embed-error\" data-embed-path=\"/v/Gone.md\"><code>/v/Gone.md</code></span>\
+                    <a class=\"csv-open\" href=\"file:////v/t.csv\" data-path=\"/v/t.csv\">Open</a>";
+        let out = served_links(html, Path::new("/v"));
+        assert_eq!(
+            out,
+            "<div class=\"obs-embed\"></div><span class=\"embed-error\"><code>Gone.md</code></span>\
+             <a class=\"csv-open\" href=\"/t.csv\">Open</a>"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! User settings persisted as JSON in the app config dir.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::calendar::DEFAULT_DAILY_NOTE_FORMAT;
+use crate::diagrams::DiagramTools;
+use crate::markdown::{EmbedPlaceholder, RenderOptions};
+use crate::obsidian_embed::{IndexOptions, SymlinkPolicy};
+use crate::paths::{self, write_atomic};
+use crate::wiki::{TreeOptions, DEFAULT_INITIAL_NOTES};
+
+use super::types::AppResult;
+
+const SETTINGS_FILE: &str = "settings.json";
+const MAX_RECENT_VAULTS: usize = 10;
+const DEFAULT_MAX_EMBED_BYTES: u64 = 1024 * 1024;
+const DEFAULT_UNPUBLISHED_EMBED: &str = "*[Unpublished: {name}]*";
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Fixed theme id ("light" | "sepia" | "dark"); `None` follows the OS theme.
+    pub theme_override: Option<String>,
+    /// Canonical roots of recently opened vaults, most recent first.
+    pub recent_vaults: Vec<String>,
+    /// Per-operation timeouts in seconds, keyed by operation kind ("search", "index", "export",
+    /// "link_check").
+    pub operation_timeouts_secs: HashMap<String, u64>,
+    /// External editor: "vscode" | "neovim" | "obsidian" or a command template (see `editor`).
+    pub editor: Option<String>,
+    /// Sidebar tree ordering.
+    pub tree: TreeOptions,
+    /// Folder holding note templates; relative paths are resolved against the open vault.
+    pub templates_folder: Option<String>,
+    /// Keep raw HTML in notes (sanitized against an allowlist) instead of stripping it.
+    pub allow_html: bool,
+    /// Render single newlines as line breaks (Obsidian's default); vaults can override it.
+    pub hardbreaks: bool,
+    /// Embeds larger than this are shown as a link; `None` means 1 MB, 0 disables the guard.
+    pub max_embed_bytes: Option<u64>,
+    /// Mark wikilinks whose basename matches several notes instead of silently picking one.
+    pub report_ambiguous_links: bool,
+    /// Notes tried, in order, when a vault opens; `None` means index.md, README.md, Home.md.
+    pub initial_notes: Option<Vec<String>>,
+    /// Whether indexing follows symlinked notes and folders.
+    pub symlinks: SymlinkPolicy,
+    /// Pandoc binary used by `export_via_pandoc`; `None` looks up `pandoc` on the PATH.
+    pub pandoc_path: Option<String>,
+    /// mermaid-cli (`mmdc`) binary rendering mermaid blocks in exports; `None` uses the PATH.
+    pub mermaid_cli_path: Option<String>,
+    /// Graphviz `dot` binary rendering `dot` blocks in exports; `None` uses the PATH.
+    pub graphviz_dot_path: Option<String>,
+    /// Markdown shown in exports in place of embeds of unpublished notes (`publish: false` or
+    /// `draft: true`); `{name}` is the note name. `None` means `*[Unpublished: {name}]*`.
+    pub unpublished_embed_placeholder: Option<String>,
+    /// In exports of part of a vault, what embeds of notes outside the export become.
+    pub export_outside_embeds: OutsideEmbeds,
+    /// Texts of the placeholders shown for embeds that cannot be shown, keyed by
+    /// `EmbedPlaceholder::key` (`cycle`, `not_found`, `too_large`, `retry`, ...); `{name}` is
+    /// the embed target. Missing keys keep the English defaults; unknown keys are ignored.
+    pub embed_placeholders: BTreeMap<String, String>,
+    /// Keep the previous version of a note saved from the app as `<name>.bak`.
+    pub backup_on_save: bool,
+    /// End rendered notes (and exports) with the notes linking to them.
+    pub backlinks_footer: bool,
+    /// Privacy mode: remote images are shown as placeholders that load them on click.
+    pub block_remote_images: bool,
+    /// Render frontmatter as a properties table at the top of the note.
+    pub properties_table: bool,
+    /// Keep a full-text index of each vault in the app data dir to speed up search (builds
+    /// with the `fulltext` feature only).
+    pub fulltext_index: bool,
+    /// Per-vault overrides, keyed by canonical vault root.
+    pub vaults: HashMap<String, VaultSettings>,
+}
+
+/// Embeds of notes left out of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutsideEmbeds {
+    /// Inline a copy of the note, as in the app.
+    #[default]
+    Inline,
+    /// Link to the note (plain text in EPUBs, whose links only reach exported notes).
+    Link,
+}
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    pub hardbreaks: Option<bool>,
+    /// Vault-relative folders (e.g. "Archive") hidden from the tree, index and search.
+    /// Notes inside them can still be opened directly.
+    pub excluded_folders: Vec<String>,
+    /// BibTeX (`.bib`) or CSL JSON file for `[@key]` citations, relative to the vault root.
+    pub bibliography: Option<String>,
+    /// moment.js format of daily-note names, as in Obsidian (`DD.MM.YYYY`); may contain `/` for
+    /// dated folders. `None` means `YYYY-MM-DD`.
+    pub daily_note_format: Option<String>,
+    /// Named search queries, in the order they were first saved.
+    pub saved_searches: Vec<SavedSearch>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+impl Settings {
+    /// Absolute paths of the folders excluded in `vault_root`.
+    pub fn excluded_folders(&self, vault_root: &Path) -> Vec<PathBuf> {
+        let Some(vault) = vault_root.to_str().and_then(|root| self.vaults.get(root)) else {
+            return Vec::new();
+        };
+        vault
+            .excluded_folders
+            .iter()
+            .map(|folder| folder.trim_matches(|c| c == '/' || c == '\\'))
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| vault_root.join(folder))
+            .collect()
+    }
+
+    pub fn daily_note_format(&self, vault_root: &Path) -> String {
+        vault_root
+            .to_str()
+            .and_then(|root| self.vaults.get(root))
+            .and_then(|vault| vault.daily_note_format.clone())
+            .unwrap_or_else(|| DEFAULT_DAILY_NOTE_FORMAT.to_string())
+    }
+
+    pub fn initial_notes(&self) -> Vec<String> {
+        self.initial_notes
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INITIAL_NOTES.iter().map(|n| n.to_string()).collect())
+    }
+
+    pub fn index_options(&self, vault_root: &Path) -> IndexOptions {
+        IndexOptions {
+            symlinks: self.symlinks,
+            excluded: self.excluded_folders(vault_root),
+        }
+    }
+
+    /// The saved tree settings plus `vault_root`'s excluded folders.
+    pub fn tree_options(&self, vault_root: &Path) -> TreeOptions {
+        TreeOptions {
+            excluded: self.excluded_folders(vault_root),
+            ..self.tree.clone()
+        }
+    }
+
+    pub fn unpublished_embed_placeholder(&self) -> String {
+        self.unpublished_embed_placeholder
+            .clone()
+            .unwrap_or_else(|| DEFAULT_UNPUBLISHED_EMBED.to_string())
+    }
+
+    pub fn diagram_tools(&self) -> DiagramTools {
+        DiagramTools {
+            mermaid: self.mermaid_cli_path.clone().unwrap_or_else(|| "mmdc".to_string()),
+            dot: self.graphviz_dot_path.clone().unwrap_or_else(|| "dot".to_string()),
+        }
+    }
+
+    /// Render options for notes in `vault_root` (`None` outside a vault), canonicalized first
+    /// so any spelling of the root finds its per-vault overrides.
+    pub fn render_options(&self, vault_root: Option<&str>) -> RenderOptions {
+        let vault_root = vault_root.map(|root| match paths::canonicalize(Path::new(root)) {
+            Ok(canonical) => canonical.to_string_lossy().into_owned(),
+            Err(_) => root.to_string(),
+        });
+        let vault_root = vault_root.as_deref();
+        let vault = vault_root.and_then(|root| self.vaults.get(root));
+        RenderOptions {
+            allow_html: self.allow_html,
+            hardbreaks: vault.and_then(|v| v.hardbreaks).unwrap_or(self.hardbreaks),
+            report_ambiguous: self.report_ambiguous_links,
+            max_embed_bytes: Some(self.max_embed_bytes.unwrap_or(DEFAULT_MAX_EMBED_BYTES)).filter(|b| *b > 0),
+            bibliography: vault_root
+                .zip(vault.and_then(|v| v.bibliography.as_deref()))
+                .map(|(root, file)| Path::new(root).join(file)),
+            unpublished_embed: None,
+            embed_scope: None,
+            backlinks_footer: self.backlinks_footer,
+            block_remote_images: self.block_remote_images,
+            properties_table: self.properties_table,
+            embed_placeholders: self
+                .embed_placeholders
+                .iter()
+                .filter_map(|(key, text)| Some((EmbedPlaceholder::from_key(key)?, text.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn operation_timeout(&self, kind: &str) -> Option<Duration> {
+        self.operation_timeouts_secs
+            .get(kind)
+            .filter(|secs| **secs > 0)
+            .map(|secs| Duration::from_secs(*secs))
+    }
+}
+
+pub struct SettingsState {
+    file: RwLock<Option<PathBuf>>,
+    settings: RwLock<Settings>,
+}
+
+impl SettingsState {
+    pub fn new() -> Self {
+        SettingsState {
+            file: RwLock::new(None),
+            settings: RwLock::new(Settings::default()),
+        }
+    }
+
+    /// Points the store at `config_dir` and loads any saved settings (missing or invalid file → defaults).
+    pub fn load_from(&self, config_dir: PathBuf) {
+        let file = config_dir.join(SETTINGS_FILE);
+        let settings = fs::read_to_string(&file)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        *self.settings.write().unwrap() = settings;
+        *self.file.write().unwrap() = Some(file);
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn update(&self, settings: Settings) -> AppResult<()> {
+        if let Some(file) = self.file.read().unwrap().as_ref() {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+            write_atomic(file, json)?;
+        }
+        *self.settings.write().unwrap() = settings;
+        Ok(())
+    }
+
+    /// Saves `query` under `name` for the vault at `root`, replacing a search of the same name
+    /// (case-insensitive) in place. Returns the vault's saved searches.
+    pub fn save_search(&self, root: &str, name: &str, query: &str) -> AppResult<Vec<SavedSearch>> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Saved search needs a name".to_string());
+        }
+        let mut settings = self.get();
+        let searches = &mut settings.vaults.entry(root.to_string()).or_default().saved_searches;
+        let search = SavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+        };
+        match searches.iter_mut().find(|s| s.name.eq_ignore_ascii_case(name)) {
+            Some(existing) => *existing = search,
+            None => searches.push(search),
+        }
+        let saved = searches.clone();
+        self.update(settings)?;
+        Ok(saved)
+    }
+
+    pub fn remember_vault(&self, root: &str) -> AppResult<()> {
+        let mut settings = self.get();
+        settings.recent_vaults.retain(|v| v != root);
+        settings.recent_vaults.insert(0, root.to_string());
+        settings.recent_vaults.truncate(MAX_RECENT_VAULTS);
+        self.update(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_embed_placeholder_keys_are_ignored() {
+        let raw = r#"{"backup_on_save": true, "embed_placeholders": {"not-found": "Sumiu", "bogus": "x"}}"#;
+        let settings: Settings = serde_json::from_str(raw).unwrap();
+        assert!(settings.backup_on_save);
+        let render = settings.render_options(None);
+        assert_eq!(render.embed_placeholders.len(), 1);
+        assert_eq!(render.embed_placeholders[&EmbedPlaceholder::NotFound], "Sumiu");
+    }
+
+    #[test]
+    fn vault_overrides_apply_to_any_spelling_of_the_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        let mut settings = Settings::default();
+        let vault = VaultSettings {
+            hardbreaks: Some(true),
+            ..Default::default()
+        };
+        settings.vaults.insert(root.to_string_lossy().into_owned(), vault);
+        assert!(settings.render_options(root.join("sub").join("..").to_str()).hardbreaks);
+        assert!(!settings.render_options(None).hardbreaks);
+    }
+}
@@ -0,0 +1,289 @@
+//! Static HTML export of a vault folder: a page per published note with the links between
+//! exported files kept, the files the pages reference copied alongside, an `index.html` landing
+//! page listing the notes by title in collapsible folders, and a `sitemap.xml`.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diagrams::render_diagrams;
+use crate::frontmatter::{is_published, parse_frontmatter, FrontmatterValue};
+use crate::obsidian_embed::{portable_href, render_markdown_with_embeds};
+use crate::paths;
+use crate::text::percent_decode;
+use crate::wiki::{self, TreeOptions};
+
+use super::commands::with_render_context;
+use super::serve::{escape_html, page, vault_links, vault_name};
+use super::settings::{OutsideEmbeds, Settings};
+use super::state::VaultState;
+use super::types::{AppResult, NodeKind, TreeNode};
+
+struct SiteNote {
+    path: PathBuf,
+    /// Frontmatter `title`, else the file name.
+    title: String,
+    modified_ms: Option<u64>,
+}
+
+/// Writes the site of the notes under `root` into `destination`, with `base_url` prefixing the
+/// sitemap's URLs (which stay relative without it); returns the number of note pages. Embeds of
+/// notes left out of the site follow `export_outside_embeds`.
+pub fn write_site(
+    root: &Path,
+    destination: &Path,
+    base_url: &str,
+    state: &VaultState,
+    settings: &Settings,
+) -> AppResult<usize> {
+    fs::create_dir_all(destination).map_err(|e| e.to_string())?;
+    let destination = paths::canonicalize(destination)?;
+    if destination.starts_with(root) {
+        return Err("The export folder cannot be inside the exported folder".to_string());
+    }
+    let vault_root = state.0.read().unwrap().containing(root).map(|(root, _, _)| root.clone());
+    let options = site_tree_options(settings, vault_root.as_deref(), root);
+    let tree = wiki::build_tree_with(&root.to_string_lossy(), &options)?;
+    let mut notes = Vec::new();
+    collect(&tree, &mut notes);
+    if notes.is_empty() {
+        return Err("No notes to export".to_string());
+    }
+
+    let tools = settings.diagram_tools();
+    let pages = with_render_context(root, root, state, settings, |ctx| {
+        ctx.render.unpublished_embed = Some(settings.unpublished_embed_placeholder());
+        if settings.export_outside_embeds == OutsideEmbeds::Link {
+            ctx.render.embed_scope = Some(notes.iter().map(|note| note.path.clone()).collect());
+        }
+        notes
+            .iter()
+            .map(|note| render_diagrams(&render_markdown_with_embeds(&note.path, ctx), &tools, ctx.cache))
+            .collect::<Vec<_>>()
+    })?;
+    let files: BTreeSet<PathBuf> = notes
+        .iter()
+        .zip(&pages)
+        .flat_map(|(note, html)| referenced_files(html, note.path.parent().unwrap_or(root)))
+        .filter_map(|file| publishable_asset(&file, root, &options.excluded))
+        .collect();
+    for file in &files {
+        let target = destination.join(file.strip_prefix(root).map_err(|e| e.to_string())?);
+        fs::create_dir_all(target.parent().unwrap_or(&destination)).map_err(|e| e.to_string())?;
+        fs::copy(file, &target).map_err(|e| e.to_string())?;
+    }
+    let exported: HashSet<&Path> =
+        notes.iter().map(|n| n.path.as_path()).chain(files.iter().map(PathBuf::as_path)).collect();
+    for (note, html) in notes.iter().zip(pages) {
+        let inside = note.path.strip_prefix(root).map_err(|e| e.to_string())?;
+        let up = "../".repeat(inside.components().count() - 1);
+        let html = vault_links(&html, root, |target| {
+            exported
+                .contains(root.join(target).as_path())
+                .then(|| format!("{}{}", up, portable_href(&site_path(target))))
+        });
+        let home = format!("{}index.html", up);
+        write_file(&destination.join(site_path(inside)), &page(root, &home, &note.title, &html))?;
+    }
+
+    let titles: HashMap<&Path, &str> = notes.iter().map(|n| (n.path.as_path(), n.title.as_str())).collect();
+    let body = format!("<h1>{}</h1>{}", escape_html(&vault_name(root)), index_list(&tree, root, &titles));
+    write_file(&destination.join("index.html"), &page(root, "index.html", &vault_name(root), &body))?;
+    write_file(&destination.join("sitemap.xml"), &sitemap(&notes, root, base_url))?;
+    Ok(notes.len())
+}
+
+/// The sidebar tree settings, listing every note whatever its age (excluded folders still apply).
+fn site_tree_options(settings: &Settings, vault_root: Option<&Path>, root: &Path) -> TreeOptions {
+    TreeOptions {
+        modified_within_days: None,
+        ..settings.tree_options(vault_root.unwrap_or(root))
+    }
+}
+
+/// Published notes of `nodes` and their descendants, in tree order.
+fn collect(nodes: &[TreeNode], notes: &mut Vec<SiteNote>) {
+    for node in nodes {
+        let path = PathBuf::from(&node.path);
+        match node.kind {
+            NodeKind::Dir => collect(&node.children, notes),
+            NodeKind::Note => {
+                let md = fs::read_to_string(&path).unwrap_or_default();
+                if !is_published(&md) {
+                    continue;
+                }
+                let title = match parse_frontmatter(&md).get("title") {
+                    Some(FrontmatterValue::Text(title)) if !title.trim().is_empty() => {
+                        title.trim().to_string()
+                    }
+                    _ => path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+                };
+                notes.push(SiteNote {
+                    path,
+                    title,
+                    modified_ms: node.modified_ms,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Local files the `src` and `href` attributes of a page rendered from a note in `note_dir`
+/// point at: `app://open` and `file:///` links, and relative URLs. The files may not exist.
+fn referenced_files(html: &str, note_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for attr in [" src=\"", " href=\""] {
+        let mut rest = html;
+        while let Some(start) = rest.find(attr) {
+            let after = &rest[start + attr.len()..];
+            let end = after.find('"').unwrap_or(after.len());
+            let value = after[..end].replace("&amp;", "&");
+            let value = value.split('#').next().unwrap_or_default();
+            let absolute = value.strip_prefix("app://open?path=").or_else(|| value.strip_prefix("file:///"));
+            match absolute {
+                Some(path) => files.push(PathBuf::from(percent_decode(path))),
+                None if !value.is_empty() && !value.contains(':') && !value.starts_with('/') => {
+                    let relative = value.split('?').next().unwrap_or_default();
+                    files.push(note_dir.join(percent_decode(relative)));
+                }
+                None => {}
+            }
+            rest = &after[end..];
+        }
+    }
+    files
+}
+
+/// The canonical path of `file` when it may be copied into the site: an existing file under
+/// `root` that is not a note, a hidden file, a `.bak` backup or in a hidden or excluded folder.
+fn publishable_asset(file: &Path, root: &Path, excluded: &[PathBuf]) -> Option<PathBuf> {
+    let file = paths::canonicalize(file).ok()?;
+    let inside = file.strip_prefix(root).ok()?;
+    let hidden = inside.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let excluded = excluded.iter().any(|folder| file.starts_with(folder));
+    let skipped = hidden || excluded || ext == "md" || ext == "bak";
+    (!skipped && file.is_file()).then_some(file)
+}
+
+/// Where the file at `inside` (relative to the exported folder) lands in the site.
+fn site_path(inside: &Path) -> PathBuf {
+    if inside.extension().is_some_and(|ext| ext == "md") {
+        inside.with_extension("html")
+    } else {
+        inside.to_path_buf()
+    }
+}
+
+/// Nested list linking the exported notes of `tree` by title; folders collapse.
+fn index_list(tree: &[TreeNode], root: &Path, titles: &HashMap<&Path, &str>) -> String {
+    let items: Vec<String> = tree
+        .iter()
+        .filter_map(|node| match node.kind {
+            NodeKind::Dir => {
+                let list = index_list(&node.children, root, titles);
+                let name = escape_html(&node.name);
+                (!list.is_empty())
+                    .then(|| format!("<li><details open><summary>{}</summary>{}</details></li>", name, list))
+            }
+            NodeKind::Note => {
+                let path = Path::new(&node.path);
+                let title = titles.get(path)?;
+                let href = portable_href(&site_path(path.strip_prefix(root).ok()?));
+                Some(format!("<li><a href=\"{}\">{}</a></li>", href, escape_html(title)))
+            }
+            _ => None,
+        })
+        .collect();
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("<ul>{}</ul>", items.concat())
+    }
+}
+
+fn sitemap(notes: &[SiteNote], root: &Path, base_url: &str) -> String {
+    let base = match base_url.trim() {
+        "" => String::new(),
+        url => format!("{}/", url.trim_end_matches('/')),
+    };
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    xml.push_str(&format!("<url><loc>{}index.html</loc></url>\n", escape_html(&base)));
+    for note in notes {
+        let Ok(inside) = note.path.strip_prefix(root) else {
+            continue;
+        };
+        let loc = format!("{}{}", base, portable_href(&site_path(inside)));
+        let lastmod = note
+            .modified_ms
+            .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+            .map(|date| format!("<lastmod>{}</lastmod>", date.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        xml.push_str(&format!("<url><loc>{}</loc>{}</url>\n", escape_html(&loc), lastmod));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn write_file(path: &Path, contents: &str) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    paths::write_atomic(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_lists_published_notes_by_title_with_sitemap() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("Sub Dir")).unwrap();
+        fs::write(root.join("Home.md"), "---\ntitle: Welcome\n---\nHi").unwrap();
+        fs::write(root.join("Sub Dir/Page.md"), "Body").unwrap();
+        fs::write(root.join("Sub Dir/Draft.md"), "---\ndraft: true\n---\nx").unwrap();
+        fs::write(root.join("Sub Dir/pic.png"), "png").unwrap();
+
+        let options = site_tree_options(&Settings::default(), None, &root);
+        let tree = wiki::build_tree_with(&root.to_string_lossy(), &options).unwrap();
+        let mut notes = Vec::new();
+        collect(&tree, &mut notes);
+        let titles: HashMap<&Path, &str> =
+            notes.iter().map(|n| (n.path.as_path(), n.title.as_str())).collect();
+        assert_eq!(
+            index_list(&tree, &root, &titles),
+            "<ul><li><details open><summary>Sub Dir</summary><ul><li><a href=\"Sub%20Dir/Page.html\">Page</a>\
+             </li></ul></details></li><li><a href=\"Home.html\">Welcome</a></li></ul>"
+        );
+
+        let xml = sitemap(&notes, &root, "https://example.org/notes");
+        assert!(xml.contains("<loc>https://example.org/notes/index.html</loc>"), "{}", xml);
+        assert!(xml.contains("<loc>https://example.org/notes/Sub%20Dir/Page.html</loc><lastmod>"), "{}", xml);
+        assert!(!xml.contains("Draft"), "{}", xml);
+    }
+
+    #[test]
+    fn only_referenced_visible_assets_are_copied() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join(".obsidian")).unwrap();
+        fs::create_dir_all(root.join("Sub")).unwrap();
+        for file in ["Sub/pic.png", "Sub/Draft.md.bak", ".obsidian/app.json", "data.csv", "unused.png"] {
+            fs::write(root.join(file), "x").unwrap();
+        }
+        let html = format!(
+            "<img src=\"pic.png\"><a href=\"Draft.md.bak\">b</a><a href=\"../.obsidian/app.json\">c</a>\
+             <a href=\"file:///{}\">d</a><a href=\"https://example.org/x.png\">e</a><a href=\"#top\">f</a>",
+            root.join("data.csv").display()
+        );
+        let files: BTreeSet<PathBuf> = referenced_files(&html, &root.join("Sub"))
+            .iter()
+            .filter_map(|file| publishable_asset(file, &root, &[]))
+            .collect();
+        assert_eq!(files, BTreeSet::from([root.join("Sub/pic.png"), root.join("data.csv")]));
+    }
+}
@@ -1,10 +1,13 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
-use std::sync::RwLock;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, RwLock};
 
-use crate::obsidian_embed::{RenderCache, VaultIndex};
+use crate::obsidian_embed::{RenderCache, RenderGeneration, VaultIndex};
+use crate::paths;
 
-use super::types::{AppResult, InitialPath};
+use super::types::{AppResult, Breadcrumb, InitialPath};
 
 pub struct InitialFile(RwLock<Option<InitialPath>>);
 
@@ -16,6 +19,10 @@ impl InitialFile {
     pub fn take(&self) -> Option<InitialPath> {
         self.0.write().unwrap().take()
     }
+
+    pub fn set(&self, initial: InitialPath) {
+        *self.0.write().unwrap() = Some(initial);
+    }
 }
 
 pub struct WatchService(RwLock<Option<Sender<Vec<String>>>>);
@@ -41,17 +48,122 @@ impl WatchService {
     }
 }
 
-/// Per-vault state: canonical root, index, and render cache for embed expansion.
-pub struct VaultState(pub RwLock<Option<(PathBuf, VaultIndex, RenderCache)>>);
+const MAX_OPEN_VAULTS: usize = 5;
+
+/// Open vaults: canonical root → index and render cache for embed expansion.
+pub struct VaultState(pub RwLock<Vaults>);
 
 impl VaultState {
     pub fn new() -> Self {
-        VaultState(RwLock::new(None))
+        VaultState(RwLock::new(Vaults::default()))
+    }
+}
+
+/// Every vault opened this session (up to `MAX_OPEN_VAULTS`, oldest dropped first), so notes
+/// from any of them keep their wikilinks. The active vault is the one in the tree; commands
+/// without a note path (search, index rebuild, ...) act on it.
+#[derive(Default)]
+pub struct Vaults {
+    active: Option<PathBuf>,
+    /// Roots in the order they were opened.
+    order: Vec<PathBuf>,
+    open: HashMap<PathBuf, (VaultIndex, RenderCache)>,
+    /// Canonical paths of notes opened outside every vault, which may be saved too.
+    opened_files: HashSet<PathBuf>,
+}
+
+impl Vaults {
+    /// Adds (or replaces) the vault at `root` and makes it active.
+    pub fn insert(&mut self, root: PathBuf, index: VaultIndex, cache: RenderCache) {
+        self.order.retain(|r| *r != root);
+        self.order.push(root.clone());
+        while self.order.len() > MAX_OPEN_VAULTS {
+            let oldest = self.order.remove(0);
+            self.open.remove(&oldest);
+        }
+        self.open.insert(root.clone(), (index, cache));
+        self.active = Some(root);
+    }
+
+    pub fn contains(&self, root: &Path) -> bool {
+        self.open.contains_key(root)
+    }
+
+    pub fn active(&self) -> Option<(&PathBuf, &VaultIndex, &RenderCache)> {
+        let root = self.active.as_ref()?;
+        self.open.get(root).map(|(index, cache)| (root, index, cache))
+    }
+
+    pub fn active_root(&self) -> Option<PathBuf> {
+        self.active().map(|(root, _, _)| root.clone())
+    }
+
+    pub fn get_mut(&mut self, root: &Path) -> Option<(&PathBuf, &mut VaultIndex, &mut RenderCache)> {
+        self.open
+            .iter_mut()
+            .find(|(open_root, _)| *open_root == root)
+            .map(|(root, (index, cache))| (root, index, cache))
+    }
+
+    pub fn active_mut(&mut self) -> Option<(&PathBuf, &mut VaultIndex, &mut RenderCache)> {
+        let root = self.active.clone()?;
+        self.get_mut(&root)
+    }
+
+    /// The open vault whose root contains `path`; the innermost wins for nested vaults.
+    pub fn containing(&self, path: &Path) -> Option<(&PathBuf, &VaultIndex, &RenderCache)> {
+        self.open
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(root, (index, cache))| (root, index, cache))
+    }
+
+    pub fn containing_mut(&mut self, path: &Path) -> Option<(&PathBuf, &mut VaultIndex, &mut RenderCache)> {
+        self.open
+            .iter_mut()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(root, (index, cache))| (root, index, cache))
+    }
+
+    /// Records `note`, opened on its own, as one the user may save.
+    pub fn remember_file(&mut self, note: PathBuf) {
+        self.opened_files.insert(note);
+    }
+
+    /// Whether saves may write to `note` (canonical): it lies in an open vault or was opened.
+    pub fn may_write(&self, note: &Path) -> bool {
+        self.containing(note).is_some() || self.opened_files.contains(note)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PathBuf, &VaultIndex, &RenderCache)> {
+        self.open.iter().map(|(root, (index, cache))| (root, index, cache))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&PathBuf, &mut VaultIndex, &mut RenderCache)> {
+        self.open.iter_mut().map(|(root, (index, cache))| (root, index, cache))
+    }
+}
+
+/// Error returned by a note render that a newer navigation superseded.
+pub const RENDER_SUPERSEDED: &str = "superseded";
+
+/// Generations of note renders driven by navigation; each new one supersedes the rest.
+pub struct RenderGenerations(Arc<AtomicU64>);
+
+impl RenderGenerations {
+    pub fn new() -> Self {
+        RenderGenerations(Arc::new(AtomicU64::new(0)))
+    }
+
+    pub fn start(&self) -> RenderGeneration {
+        RenderGeneration::next(&self.0)
     }
 }
 
 pub fn canonicalize_path(path: &str) -> AppResult<PathBuf> {
-    Path::new(path).canonicalize().map_err(|e| e.to_string())
+    paths::canonicalize(Path::new(path))
 }
 
 pub fn path_to_string(path: &Path) -> AppResult<String> {
@@ -62,3 +174,70 @@ pub fn parent_dir_string(path: &Path) -> AppResult<String> {
     let parent = path.parent().ok_or("No parent dir")?;
     path_to_string(parent)
 }
+
+/// One crumb per path component of `path` below `root` (folders, then the note itself);
+/// empty when `path` is not inside `root`.
+pub fn breadcrumbs_for(root: &Path, path: &Path) -> Vec<Breadcrumb> {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return Vec::new();
+    };
+    let mut current = root.to_path_buf();
+    relative
+        .components()
+        .map(|component| {
+            current.push(component);
+            Breadcrumb {
+                name: component.as_os_str().to_string_lossy().into_owned(),
+                path: current.to_string_lossy().into_owned(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vaults_route_notes_to_their_vault() {
+        let mut vaults = Vaults::default();
+        vaults.insert(PathBuf::from("/a"), VaultIndex::default(), RenderCache::default());
+        vaults.insert(PathBuf::from("/a/nested"), VaultIndex::default(), RenderCache::default());
+        vaults.insert(PathBuf::from("/b"), VaultIndex::default(), RenderCache::default());
+        assert_eq!(vaults.active_root(), Some(PathBuf::from("/b")));
+        let root_of = |path: &str| vaults.containing(Path::new(path)).map(|(root, _, _)| root.clone());
+        assert_eq!(root_of("/a/note.md"), Some(PathBuf::from("/a")));
+        assert_eq!(root_of("/a/nested/note.md"), Some(PathBuf::from("/a/nested")));
+        assert_eq!(root_of("/c/note.md"), None);
+
+        for i in 0..MAX_OPEN_VAULTS {
+            vaults.insert(PathBuf::from(format!("/v{}", i)), VaultIndex::default(), RenderCache::default());
+        }
+        assert!(!vaults.contains(Path::new("/a")), "oldest vaults dropped");
+        assert!(vaults.contains(Path::new("/v0")));
+    }
+
+    #[test]
+    fn writes_stay_in_open_vaults_and_opened_files() {
+        let mut vaults = Vaults::default();
+        vaults.insert(PathBuf::from("/a"), VaultIndex::default(), RenderCache::default());
+        vaults.remember_file(PathBuf::from("/b/note.md"));
+        assert!(vaults.may_write(Path::new("/a/sub/new.md")));
+        assert!(vaults.may_write(Path::new("/b/note.md")));
+        assert!(!vaults.may_write(Path::new("/b/other.md")));
+        assert!(!vaults.may_write(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn breadcrumbs_cover_folders_and_note() {
+        let crumbs = breadcrumbs_for(Path::new("/vault"), Path::new("/vault/a/b/note.md"));
+        let names: Vec<&str> = crumbs.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "note.md"]);
+        assert_eq!(Path::new(&crumbs[1].path), Path::new("/vault/a/b"));
+    }
+
+    #[test]
+    fn breadcrumbs_empty_outside_vault() {
+        assert!(breadcrumbs_for(Path::new("/vault"), Path::new("/other/note.md")).is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+//! OS theme following: window theme events become `theme-changed` events for the UI.
+
+use tauri::{Emitter, Manager, Theme, Window};
+
+use super::settings::SettingsState;
+
+#[derive(Clone, serde::Serialize)]
+pub struct ThemeChanged {
+    /// Theme reported by the OS ("light" | "dark").
+    pub system_theme: String,
+    /// Theme the UI should apply: the settings override when set, else the OS theme.
+    pub theme: String,
+}
+
+pub fn resolve_theme(system: Theme, theme_override: Option<&str>) -> ThemeChanged {
+    let system_theme = match system {
+        Theme::Dark => "dark",
+        _ => "light",
+    }
+    .to_string();
+    let theme = theme_override
+        .filter(|t| matches!(*t, "light" | "sepia" | "dark"))
+        .map(String::from)
+        .unwrap_or_else(|| system_theme.clone());
+    ThemeChanged { system_theme, theme }
+}
+
+pub fn current_theme(window: &Window) -> ThemeChanged {
+    let system = window.theme().unwrap_or(Theme::Light);
+    let settings = window.state::<SettingsState>().get();
+    resolve_theme(system, settings.theme_override.as_deref())
+}
+
+pub fn handle_theme_changed(window: &Window, theme: Theme) {
+    let settings = window.state::<SettingsState>().get();
+    let payload = resolve_theme(theme, settings.theme_override.as_deref());
+    let _ = window.emit("theme-changed", payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_os_without_override() {
+        let t = resolve_theme(Theme::Dark, None);
+        assert_eq!(t.system_theme, "dark");
+        assert_eq!(t.theme, "dark");
+    }
+
+    #[test]
+    fn override_wins_over_os() {
+        let t = resolve_theme(Theme::Dark, Some("sepia"));
+        assert_eq!(t.system_theme, "dark");
+        assert_eq!(t.theme, "sepia");
+    }
+
+    #[test]
+    fn unknown_override_is_ignored() {
+        let t = resolve_theme(Theme::Light, Some("neon"));
+        assert_eq!(t.theme, "light");
+    }
+}
@@ -0,0 +1,233 @@
+//! Incremental sidebar updates: watcher events mapped onto added / removed / renamed tree nodes.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+
+use crate::wiki::{tree_node_for, TreeOptions, TreeSortKey};
+
+use super::types::TreeNode;
+
+/// Inserted nodes carry the tree's sort so the sidebar can place them among their siblings.
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TreeChange {
+    Added {
+        /// Parent folder; `None` for the vault root.
+        parent: Option<String>,
+        node: TreeNode,
+        sort_by: TreeSortKey,
+        descending: bool,
+    },
+    Removed {
+        path: String,
+    },
+    Renamed {
+        from: String,
+        parent: Option<String>,
+        node: TreeNode,
+        sort_by: TreeSortKey,
+        descending: bool,
+    },
+}
+
+/// Whether `path` is inside `root` and outside hidden (dot) folders.
+fn in_tree(root: &Path, path: &Path) -> bool {
+    match path.strip_prefix(root) {
+        Ok(rel) => !rel.components().any(|c| match c {
+            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            _ => false,
+        }),
+        Err(_) => false,
+    }
+}
+
+fn parent_of(root: &Path, path: &Path) -> Option<String> {
+    path.parent()
+        .filter(|parent| *parent != root)
+        .map(|parent| parent.to_string_lossy().into_owned())
+}
+
+fn added(root: &Path, path: &Path, options: &TreeOptions) -> Option<TreeChange> {
+    let node = tree_node_for(path, options).ok()??;
+    Some(TreeChange::Added {
+        parent: parent_of(root, path),
+        node,
+        sort_by: options.sort_by,
+        descending: options.descending,
+    })
+}
+
+fn removed(path: &Path) -> TreeChange {
+    TreeChange::Removed {
+        path: path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Folders of `vacated` (parents of removed entries) that no longer list anything, and the
+/// ancestors that emptied with them, innermost first.
+fn emptied_folders(root: &Path, vacated: BTreeSet<PathBuf>, options: &TreeOptions) -> Vec<TreeChange> {
+    let mut pruned = HashSet::new();
+    let mut changes = Vec::new();
+    for dir in vacated.iter().rev() {
+        let mut dir = dir.as_path();
+        while dir != root && in_tree(root, dir) && !pruned.contains(dir) {
+            if tree_node_for(dir, options).ok().flatten().is_some() {
+                break;
+            }
+            pruned.insert(dir.to_path_buf());
+            changes.push(removed(dir));
+            let Some(parent) = dir.parent() else {
+                break;
+            };
+            dir = parent;
+        }
+    }
+    changes
+}
+
+/// Tree changes for a batch of watcher events under `root`. Content-only modifications
+/// produce nothing; removals are reported for any path, since the entry is already gone.
+/// Unless `options.show_empty_dirs`, folders a removal leaves empty are removed too.
+pub fn tree_changes<'a>(
+    events: impl IntoIterator<Item = &'a Event>,
+    root: &Path,
+    options: &TreeOptions,
+) -> Vec<TreeChange> {
+    let mut changes = Vec::new();
+    let mut vacated = BTreeSet::new();
+    for event in events {
+        let paths: Vec<&Path> = event
+            .paths
+            .iter()
+            .map(|p| p.as_path())
+            .filter(|p| in_tree(root, p))
+            .collect();
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                changes.extend(paths.iter().filter_map(|p| added(root, p, options)));
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                changes.extend(paths.iter().map(|p| removed(p)));
+                vacated.extend(paths.iter().filter_map(|p| p.parent()).map(Path::to_path_buf));
+            }
+            EventKind::Modify(ModifyKind::Name(_)) if event.paths.len() == 2 => {
+                let (from, to) = (event.paths[0].as_path(), event.paths[1].as_path());
+                let node = in_tree(root, to).then(|| tree_node_for(to, options).ok().flatten()).flatten();
+                if in_tree(root, from) {
+                    vacated.extend(from.parent().map(Path::to_path_buf));
+                }
+                match node {
+                    Some(node) if in_tree(root, from) => changes.push(TreeChange::Renamed {
+                        from: from.to_string_lossy().into_owned(),
+                        parent: parent_of(root, to),
+                        node,
+                        sort_by: options.sort_by,
+                        descending: options.descending,
+                    }),
+                    Some(node) => changes.push(TreeChange::Added {
+                        parent: parent_of(root, to),
+                        node,
+                        sort_by: options.sort_by,
+                        descending: options.descending,
+                    }),
+                    None if in_tree(root, from) => changes.push(removed(from)),
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    if !options.show_empty_dirs {
+        changes.extend(emptied_folders(root, vacated, options));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use notify::event::{CreateKind, RemoveKind};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn event(kind: EventKind, paths: &[&Path]) -> Event {
+        paths.iter().fold(Event::new(kind), |e, p| e.add_path(p.to_path_buf()))
+    }
+
+    #[test]
+    fn create_remove_and_rename_map_to_changes() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/new.md"), "# New").unwrap();
+        fs::write(root.join("renamed.md"), "# R").unwrap();
+        fs::write(root.join("image.png"), [0u8]).unwrap();
+
+        let events = vec![
+            event(EventKind::Create(CreateKind::File), &[&root.join("sub/new.md")]),
+            event(EventKind::Create(CreateKind::File), &[&root.join("image.png")]),
+            event(EventKind::Remove(RemoveKind::File), &[&root.join("gone.md")]),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &[&root.join("old.md"), &root.join("renamed.md")],
+            ),
+            event(EventKind::Create(CreateKind::File), &[&root.join(".git/x.md")]),
+        ];
+        let changes = tree_changes(&events, &root, &TreeOptions::default());
+        assert_eq!(changes.len(), 3, "image (not listed) and .git are skipped");
+        match &changes[0] {
+            TreeChange::Added { parent, node, .. } => {
+                assert_eq!(parent.as_deref(), Some(root.join("sub").to_str().unwrap()));
+                assert_eq!(node.name, "new.md");
+            }
+            _ => panic!("expected added"),
+        }
+        assert!(matches!(&changes[1], TreeChange::Removed { path } if path.ends_with("gone.md")));
+        match &changes[2] {
+            TreeChange::Renamed { from, parent, node, .. } => {
+                assert!(from.ends_with("old.md"));
+                assert!(parent.is_none(), "vault root parent is None");
+                assert_eq!(node.name, "renamed.md");
+            }
+            _ => panic!("expected renamed"),
+        }
+    }
+
+    #[test]
+    fn removals_prune_folders_left_empty() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("kept")).unwrap();
+        fs::write(root.join("kept/x.md"), "x").unwrap();
+
+        let events = vec![
+            event(EventKind::Remove(RemoveKind::File), &[&root.join("a/b/gone.md")]),
+            event(EventKind::Remove(RemoveKind::File), &[&root.join("kept/y.md")]),
+        ];
+        let removed_paths = |options: &TreeOptions| -> Vec<String> {
+            tree_changes(&events, &root, options)
+                .into_iter()
+                .filter_map(|change| match change {
+                    TreeChange::Removed { path } => Some(path),
+                    _ => None,
+                })
+                .collect()
+        };
+        let path = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        assert_eq!(
+            removed_paths(&TreeOptions::default()),
+            vec![path("a/b/gone.md"), path("kept/y.md"), path("a/b"), path("a")]
+        );
+        let show_empty = TreeOptions {
+            show_empty_dirs: true,
+            ..TreeOptions::default()
+        };
+        assert_eq!(removed_paths(&show_empty), vec![path("a/b/gone.md"), path("kept/y.md")]);
+    }
+}
@@ -1,28 +1,174 @@
+use std::collections::BTreeMap;
+
+use crate::frontmatter::FrontmatterValue;
+use crate::markdown::{BlockMapping, NoteStats};
+use crate::obsidian_embed::NoteEdits;
+
 pub type AppResult<T> = Result<T, String>;
 
-#[derive(serde::Serialize)]
+#[derive(Clone, serde::Serialize)]
 pub struct OpenMarkdownFileResult {
     pub raw_md: String,
     pub html: String,
     pub base_dir: String,
+    /// Raw line ranges ↔ rendered top-level element indices, for split raw/preview sync.
+    pub source_map: Vec<BlockMapping>,
+    /// Path components below the vault root; empty outside a vault.
+    pub breadcrumbs: Vec<Breadcrumb>,
+    pub stats: NoteStats,
+    /// Fingerprint of `raw_md`; pass it back to `save_markdown_file` to detect external edits.
+    pub content_hash: String,
+}
+
+/// What `diff_note` compares the note on disk against.
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffBase {
+    /// The note at a git revision (see `get_note_history`).
+    Revision(String),
+    /// Earlier contents, e.g. the `raw_md` shown before a watch event.
+    Content(String),
+}
+
+/// Markdown rendered without a file behind it (see `render_markdown_string`).
+#[derive(Clone, serde::Serialize)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub source_map: Vec<BlockMapping>,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SaveResult {
+    Saved {
+        content_hash: String,
+        modified_ms: Option<u64>,
+    },
+    /// The file changed on disk since it was loaded; nothing was written.
+    Conflict {
+        ours: String,
+        theirs: String,
+        theirs_modified_ms: Option<u64>,
+    },
+}
+
+/// Target of an `app://open` link, decoded and confined to the open vault.
+#[derive(Clone, serde::Serialize)]
+pub struct AppLink {
+    pub path: String,
+    pub anchor: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct Breadcrumb {
+    pub name: String,
+    pub path: String,
 }
 
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Dir,
+    Note,
+    Image,
+    Pdf,
+    Other,
+}
+
+#[derive(Clone, serde::Serialize)]
 pub struct TreeNode {
     pub name: String,
     pub path: String,
+    pub kind: NodeKind,
     pub children: Vec<TreeNode>,
+    /// Bytes; for folders, the total of the listed children.
+    pub size: u64,
+    /// Unix epoch milliseconds; for folders, the newest of the folder and its children.
+    pub modified_ms: Option<u64>,
+    pub created_ms: Option<u64>,
+    /// Notes anywhere below a folder; 0 for files.
+    pub note_count: usize,
+    /// For folders, the note named after the folder (or its `index.md`), opened on click.
+    pub folder_note: Option<String>,
+    /// Wikilinks and embeds from other notes resolving to this file; 0 for folders and until
+    /// the vault is indexed.
+    pub inbound_links: usize,
+    /// Wikilinks and embeds in this note resolving to other files.
+    pub outbound_links: usize,
 }
 
-#[derive(serde::Serialize)]
+#[derive(Clone, serde::Serialize)]
+pub struct RecentNote {
+    pub path: String,
+    /// Relative to the vault root, with forward slashes.
+    pub relative_path: String,
+    pub modified_ms: u64,
+}
+
+#[derive(Clone, serde::Serialize)]
 pub struct OpenWikiFolderResult {
     pub tree: Vec<TreeNode>,
     pub initial_note_path: Option<String>,
+    /// Rendered before the index is ready, so wikilinks and embeds only resolve after the
+    /// `ready` progress event.
     pub initial_html: Option<String>,
+    /// Fingerprint of the initial note's source, as `OpenMarkdownFileResult::content_hash`.
+    pub initial_content_hash: Option<String>,
+}
+
+/// `vault-open-progress` payload.
+#[derive(Clone, serde::Serialize)]
+pub struct VaultOpenProgress {
+    pub vault_root: String,
+    /// "tree" | "index" | "ready" | "error"
+    pub phase: String,
+    /// Files and folders listed in the tree.
+    pub files_walked: usize,
+    pub indexed: usize,
+    pub total: usize,
+    pub error: Option<String>,
 }
 
 #[derive(Clone, serde::Serialize)]
 pub struct InitialPath {
     pub path: String,
     pub is_dir: bool,
+    /// Vault to open before showing `path` (set for deep links into a vault note).
+    pub vault_root: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct FileMetadata {
+    pub path: String,
+    /// Relative to the open vault root; `None` outside the vault.
+    pub relative_path: Option<String>,
+    pub size: u64,
+    /// Unix epoch milliseconds; `None` where the platform does not report it.
+    pub created_ms: Option<u64>,
+    pub modified_ms: Option<u64>,
+    /// Frontmatter fields of markdown notes; empty for other files.
+    pub frontmatter: BTreeMap<String, FrontmatterValue>,
+}
+
+/// Everything the note info panel shows, gathered in one call.
+#[derive(Clone, serde::Serialize)]
+pub struct NotePanelData {
+    /// File stats and frontmatter.
+    pub metadata: FileMetadata,
+    /// Frontmatter and inline tags, without `#`.
+    pub tags: Vec<String>,
+    /// `None` outside an open vault.
+    pub backlink_count: Option<usize>,
+    pub outgoing_link_count: Option<usize>,
+    pub stats: NoteStats,
+}
+
+/// A folder move: its link edits, and whether they and the rename were carried out.
+#[derive(Clone, serde::Serialize)]
+pub struct FolderMove {
+    pub from: String,
+    pub to: String,
+    /// Notes whose links change, at their pre-move paths.
+    pub notes: Vec<NoteEdits>,
+    pub applied: bool,
 }
@@ -1,11 +1,18 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::Duration;
 
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
-use tauri::Emitter;
+use notify_debouncer_full::{
+    new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer, FileIdMap,
+};
+use tauri::{Emitter, Manager};
 
+use super::fulltext::FulltextState;
+use super::serve::PreviewServer;
+use super::settings::SettingsState;
+use super::state::VaultState;
+use super::tree_diff::tree_changes;
 use super::types::AppResult;
 
 type WatchDebouncer = Debouncer<RecommendedWatcher, FileIdMap>;
@@ -17,6 +24,12 @@ pub fn create_debouncer(app: tauri::AppHandle, paths: Vec<String>) -> AppResult<
         None,
         move |result: DebounceEventResult| {
             if let Ok(events) = result {
+                emit_tree_diff(&app_for_closure, &events);
+                let changed: Vec<PathBuf> = events.iter().flat_map(|event| event.paths.clone()).collect();
+                refresh_changed_notes(&app_for_closure, &changed);
+                evict_changed(&app_for_closure, &changed);
+                app_for_closure.state::<PreviewServer>().notify_changed(&changed);
+                app_for_closure.state::<FulltextState>().notes_changed(&app_for_closure, &changed);
                 let changed_paths: Vec<String> = events
                     .into_iter()
                     .flat_map(|event| event.paths.clone().into_iter())
@@ -37,12 +50,60 @@ pub fn create_debouncer(app: tauri::AppHandle, paths: Vec<String>) -> AppResult<
             let _ = app.emit("watch-error", error.to_string());
             continue;
         }
-        let _ = debouncer.cache().add_root(watch_path, RecursiveMode::Recursive);
+        debouncer.cache().add_root(watch_path, RecursiveMode::Recursive);
     }
 
     Ok(debouncer)
 }
 
+/// Updates what the vault indexes keep of each changed note (embeds, block ids, ...) from its
+/// new text, so lookups answered from the index see edits made since it was built.
+fn refresh_changed_notes(app: &tauri::AppHandle, changed: &[PathBuf]) {
+    let notes: Vec<(PathBuf, String)> = changed
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| {
+            let canonical = crate::paths::canonicalize(path).ok()?;
+            let text = std::fs::read_to_string(&canonical).ok()?;
+            Some((canonical, text))
+        })
+        .collect();
+    if notes.is_empty() {
+        return;
+    }
+    let state = app.state::<VaultState>();
+    let mut vaults = state.0.write().unwrap();
+    for (note, text) in &notes {
+        if let Some((_, index, _)) = vaults.containing_mut(note) {
+            index.refresh_note(note, text);
+        }
+    }
+}
+
+/// Drops cached renders of changed files, and of notes embedding them, in every open vault, so
+/// a note opened right after a save never shows the old HTML.
+fn evict_changed(app: &tauri::AppHandle, changed: &[PathBuf]) {
+    let changed: Vec<PathBuf> = changed
+        .iter()
+        .map(|path| crate::paths::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        .collect();
+    for (_, _, cache) in app.state::<VaultState>().0.write().unwrap().iter_mut() {
+        cache.evict_changed(&changed);
+    }
+}
+
+/// Emits `tree-diff` for structural changes inside the open vault.
+fn emit_tree_diff(app: &tauri::AppHandle, events: &[DebouncedEvent]) {
+    let Some(root) = app.state::<VaultState>().0.read().unwrap().active_root() else {
+        return;
+    };
+    let options = app.state::<SettingsState>().get().tree_options(&root);
+    let changes = tree_changes(events.iter().map(|e| &e.event), &root, &options);
+    if !changes.is_empty() {
+        let _ = app.emit("tree-diff", changes);
+    }
+}
+
 fn watch_loop(app: tauri::AppHandle, receiver: Receiver<Vec<String>>) {
     let mut _active_debouncer: Option<WatchDebouncer> = None;
 
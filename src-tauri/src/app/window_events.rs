@@ -0,0 +1,14 @@
+//! Window event dispatch (registered with `Builder::on_window_event`).
+
+use tauri::{DragDropEvent, Window, WindowEvent};
+
+use super::drop::handle_drop;
+use super::theme::handle_theme_changed;
+
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    match event {
+        WindowEvent::ThemeChanged(theme) => handle_theme_changed(window, *theme),
+        WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => handle_drop(window, paths.clone()),
+        _ => {}
+    }
+}
@@ -0,0 +1,137 @@
+//! Per-vault UI state (expanded folders, last note, scroll offsets) persisted as JSON in the
+//! app config dir, next to the settings.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::paths::write_atomic;
+
+use super::types::AppResult;
+
+const WORKSPACE_FILE: &str = "workspace.json";
+/// Scroll offsets kept per vault; the rest are dropped on save, in no particular order.
+const MAX_SCROLL_OFFSETS: usize = 500;
+
+#[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    /// Tree folders left expanded, as absolute paths.
+    pub expanded_folders: Vec<String>,
+    pub last_note: Option<String>,
+    /// Preview scroll position in pixels, keyed by note path.
+    #[serde(deserialize_with = "lenient_offsets")]
+    pub scroll_offsets: HashMap<String, f64>,
+}
+
+/// Scroll offsets with `null` (how JSON stores a NaN or infinite offset) read as 0, so one bad
+/// value doesn't discard every saved workspace.
+fn lenient_offsets<'de, D>(deserializer: D) -> Result<HashMap<String, f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let offsets: HashMap<String, Option<f64>> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(offsets.into_iter().map(|(path, offset)| (path, offset.unwrap_or(0.0))).collect())
+}
+
+pub struct WorkspaceState {
+    file: RwLock<Option<PathBuf>>,
+    /// Keyed by canonical vault root.
+    workspaces: RwLock<HashMap<String, Workspace>>,
+}
+
+impl WorkspaceState {
+    pub fn new() -> Self {
+        WorkspaceState {
+            file: RwLock::new(None),
+            workspaces: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Points the store at `config_dir` and loads saved workspaces (missing or invalid file → none).
+    pub fn load_from(&self, config_dir: PathBuf) {
+        let file = config_dir.join(WORKSPACE_FILE);
+        let workspaces = fs::read_to_string(&file)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        *self.workspaces.write().unwrap() = workspaces;
+        *self.file.write().unwrap() = Some(file);
+    }
+
+    pub fn get(&self, vault_root: &str) -> Workspace {
+        self.workspaces.read().unwrap().get(vault_root).cloned().unwrap_or_default()
+    }
+
+    pub fn save(&self, vault_root: &str, mut workspace: Workspace) -> AppResult<()> {
+        for offset in workspace.scroll_offsets.values_mut().filter(|offset| !offset.is_finite()) {
+            *offset = 0.0;
+        }
+        if workspace.scroll_offsets.len() > MAX_SCROLL_OFFSETS {
+            let keep: Vec<String> =
+                workspace.scroll_offsets.keys().take(MAX_SCROLL_OFFSETS).cloned().collect();
+            workspace.scroll_offsets.retain(|path, _| keep.contains(path));
+        }
+        let mut workspaces = self.workspaces.write().unwrap();
+        if workspaces.get(vault_root) == Some(&workspace) {
+            return Ok(());
+        }
+        workspaces.insert(vault_root.to_string(), workspace);
+        if let Some(file) = self.file.read().unwrap().as_ref() {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            let json = serde_json::to_string_pretty(&*workspaces).map_err(|e| e.to_string())?;
+            write_atomic(file, json)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspaces_round_trip_per_vault() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = WorkspaceState::new();
+        store.load_from(dir.path().to_path_buf());
+        let workspace = Workspace {
+            expanded_folders: vec!["/v/a".to_string()],
+            last_note: Some("/v/a/n.md".to_string()),
+            scroll_offsets: HashMap::from([("/v/a/n.md".to_string(), 120.0)]),
+        };
+        store.save("/v", workspace.clone()).unwrap();
+
+        let reloaded = WorkspaceState::new();
+        reloaded.load_from(dir.path().to_path_buf());
+        assert!(reloaded.get("/v") == workspace);
+        assert!(reloaded.get("/other") == Workspace::default());
+    }
+
+    #[test]
+    fn non_finite_scroll_offsets_are_saved_as_zero() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = WorkspaceState::new();
+        store.load_from(dir.path().to_path_buf());
+        let workspace = Workspace {
+            last_note: Some("/v/n.md".to_string()),
+            scroll_offsets: HashMap::from([("/v/n.md".to_string(), f64::NAN), ("/v/m.md".to_string(), 8.0)]),
+            ..Default::default()
+        };
+        store.save("/v", workspace).unwrap();
+
+        let reloaded = WorkspaceState::new();
+        reloaded.load_from(dir.path().to_path_buf());
+        let offsets = reloaded.get("/v").scroll_offsets;
+        assert_eq!(offsets.get("/v/n.md"), Some(&0.0));
+        assert_eq!(offsets.get("/v/m.md"), Some(&8.0));
+
+        let legacy = r#"{"/v": {"last_note": "/v/n.md", "scroll_offsets": {"/v/n.md": null}}}"#;
+        fs::write(dir.path().join(WORKSPACE_FILE), legacy).unwrap();
+        reloaded.load_from(dir.path().to_path_buf());
+        assert_eq!(reloaded.get("/v").last_note.as_deref(), Some("/v/n.md"));
+    }
+}
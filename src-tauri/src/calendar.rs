@@ -0,0 +1,117 @@
+//! Notes by calendar day, for a journaling calendar: daily notes are dated by their name (parsed
+//! with the vault's daily-note format), every other note by its creation date.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+
+use crate::template_vars::moment_to_chrono;
+
+/// Daily-note names when a vault configures none (`2024-05-31.md`), in moment.js syntax as
+/// Obsidian's daily-notes settings store it.
+pub const DEFAULT_DAILY_NOTE_FORMAT: &str = "YYYY-MM-DD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    /// The note's name matches the daily-note format.
+    DailyNote,
+    /// The file's creation time.
+    Created,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CalendarNote {
+    pub path: String,
+    pub name: String,
+    pub source: DateSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CalendarDay {
+    /// Day of the month, from 1.
+    pub day: u32,
+    /// Daily notes first, then by name.
+    pub notes: Vec<CalendarNote>,
+}
+
+/// The date of a daily note at vault-relative `rel` (extension included), when its name matches
+/// moment.js `format`. Formats with `/` (`YYYY/MM/DD`) match that many trailing path components.
+pub fn daily_note_date(rel: &Path, format: &str) -> Option<NaiveDate> {
+    let without_ext = rel.with_extension("");
+    let components: Vec<_> = without_ext.iter().filter_map(|c| c.to_str()).collect();
+    let depth = format.matches('/').count() + 1;
+    let name = components.get(components.len().checked_sub(depth)?..)?.join("/");
+    NaiveDate::parse_from_str(&name, &moment_to_chrono(format)).ok()
+}
+
+/// Days of `year`-`month` that have notes, in order; days without notes are left out.
+pub fn calendar_month(
+    notes: &[PathBuf],
+    vault_root: &Path,
+    year: i32,
+    month: u32,
+    format: &str,
+) -> Result<Vec<CalendarDay>, String> {
+    if !(1..=12).contains(&month) {
+        return Err(format!("Invalid month: {}", month));
+    }
+    let mut days: BTreeMap<u32, Vec<CalendarNote>> = BTreeMap::new();
+    for path in notes {
+        let rel = path.strip_prefix(vault_root).unwrap_or(path);
+        let dated = daily_note_date(rel, format).map(|date| (date, DateSource::DailyNote)).or_else(|| {
+            let created = fs::metadata(path).and_then(|m| m.created()).ok()?;
+            Some((DateTime::<Local>::from(created).date_naive(), DateSource::Created))
+        });
+        let Some((date, source)) = dated else {
+            continue;
+        };
+        if date.year() != year || date.month() != month {
+            continue;
+        }
+        days.entry(date.day()).or_default().push(CalendarNote {
+            path: path.to_string_lossy().into_owned(),
+            name: path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string(),
+            source,
+        });
+    }
+    Ok(days
+        .into_iter()
+        .map(|(day, mut notes)| {
+            notes.sort_by_key(|note| (note.source != DateSource::DailyNote, note.name.clone()));
+            CalendarDay { day, notes }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_note_names_follow_the_format() {
+        let date = |rel: &str, format: &str| daily_note_date(Path::new(rel), format);
+        let may_31 = NaiveDate::from_ymd_opt(2024, 5, 31);
+        assert_eq!(date("Journal/2024-05-31.md", DEFAULT_DAILY_NOTE_FORMAT), may_31);
+        assert_eq!(date("Journal/31.05.2024.md", "DD.MM.YYYY"), may_31);
+        assert_eq!(date("Journal/2024/05/31.md", "YYYY/MM/DD"), may_31);
+        assert_eq!(date("Journal/2024-05-31 Friday.md", "YYYY-MM-DD dddd"), may_31);
+        assert_eq!(date("31.md", "YYYY/MM/DD"), None);
+        assert_eq!(date("Meeting notes.md", DEFAULT_DAILY_NOTE_FORMAT), None);
+    }
+
+    #[test]
+    fn month_groups_daily_notes_by_day() {
+        let root = Path::new("/vault");
+        let notes: Vec<PathBuf> = ["2024-05-31.md", "Daily/2024-05-02.md", "2024-06-01.md"]
+            .iter()
+            .map(|rel| root.join(rel))
+            .collect();
+        let days = calendar_month(&notes, root, 2024, 5, DEFAULT_DAILY_NOTE_FORMAT).unwrap();
+        let summary: Vec<(u32, &str)> = days.iter().map(|d| (d.day, d.notes[0].name.as_str())).collect();
+        assert_eq!(summary, [(2, "2024-05-02"), (31, "2024-05-31")]);
+        assert!(calendar_month(&notes, root, 2024, 13, DEFAULT_DAILY_NOTE_FORMAT).is_err());
+    }
+}
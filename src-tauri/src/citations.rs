@@ -0,0 +1,491 @@
+//! Pandoc-style `[@key]` citations resolved against a vault's BibTeX (`.bib`) or CSL JSON
+//! bibliography. Citations render author–date, e.g. `(Doe 2020, p. 4; Roe and Poe 2019)`, and
+//! link to a generated reference list appended to the note.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// One bibliography entry, reduced to what the author–date style shows.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reference {
+    /// Authors (or editors) as (family, given) names.
+    pub authors: Vec<(String, String)>,
+    pub year: Option<String>,
+    pub title: Option<String>,
+    /// Journal, book or proceedings the work appeared in.
+    pub container: Option<String>,
+    pub publisher: Option<String>,
+    pub doi: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Bibliography(HashMap<String, Reference>);
+
+impl Bibliography {
+    /// Reads a CSL JSON (`.json`) or BibTeX (anything else) bibliography.
+    pub fn load(path: &Path) -> Result<Bibliography, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let is_json = path.extension().map(|e| e.eq_ignore_ascii_case("json")).unwrap_or(false);
+        if is_json {
+            Self::from_csl_json(&text)
+        } else {
+            Ok(Self::from_bibtex(&text))
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Reference> {
+        self.0.get(key)
+    }
+
+    pub fn from_csl_json(text: &str) -> Result<Bibliography, String> {
+        let items: Vec<serde_json::Value> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let field = |item: &serde_json::Value, name: &str| {
+            item.get(name).and_then(|v| v.as_str()).map(str::to_string)
+        };
+        let mut entries = HashMap::new();
+        for item in &items {
+            let Some(key) = item.get("id").and_then(|id| id.as_str()) else {
+                continue;
+            };
+            let names = item.get("author").or_else(|| item.get("editor"));
+            let authors = names
+                .and_then(|names| names.as_array())
+                .map(|names| {
+                    names
+                        .iter()
+                        .map(|name| {
+                            let family = field(name, "family").or_else(|| field(name, "literal"));
+                            (family.unwrap_or_default(), field(name, "given").unwrap_or_default())
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let year = item
+                .pointer("/issued/date-parts/0/0")
+                .map(|year| year.to_string().trim_matches('"').to_string());
+            let reference = Reference {
+                authors,
+                year,
+                title: field(item, "title"),
+                container: field(item, "container-title"),
+                publisher: field(item, "publisher"),
+                doi: field(item, "DOI"),
+                url: field(item, "URL"),
+            };
+            entries.insert(key.to_string(), reference);
+        }
+        Ok(Bibliography(entries))
+    }
+
+    /// Lenient BibTeX reader: `@type{key, field = {value} | "value" | bare, ...}` entries;
+    /// `@comment`, `@string` and `@preamble` are skipped and malformed entries ignored.
+    pub fn from_bibtex(text: &str) -> Bibliography {
+        let mut entries = HashMap::new();
+        let mut rest = text;
+        while let Some(at) = rest.find('@') {
+            rest = &rest[at + 1..];
+            let Some(open) = rest.find(['{', '(']) else {
+                break;
+            };
+            let kind = rest[..open].trim().to_ascii_lowercase();
+            let body_start = open + 1;
+            let body_end = balanced_end(rest, body_start).unwrap_or(rest.len());
+            let body = &rest[body_start..body_end];
+            rest = rest.get(body_end + 1..).unwrap_or("");
+            if matches!(kind.as_str(), "comment" | "string" | "preamble") || kind.contains(char::is_whitespace) {
+                continue;
+            }
+            let Some((key, fields)) = body.split_once(',') else {
+                continue;
+            };
+            let fields = bibtex_fields(fields);
+            let names = fields.get("author").or_else(|| fields.get("editor"));
+            let reference = Reference {
+                authors: names.map(String::as_str).map(bibtex_names).unwrap_or_default(),
+                year: fields.get("year").cloned().or_else(|| {
+                    fields.get("date").map(|date| date.chars().take(4).collect())
+                }),
+                title: fields.get("title").cloned(),
+                container: ["journal", "journaltitle", "booktitle"]
+                    .iter()
+                    .find_map(|name| fields.get(*name).cloned()),
+                publisher: fields.get("publisher").cloned(),
+                doi: fields.get("doi").cloned(),
+                url: fields.get("url").cloned(),
+            };
+            entries.insert(key.trim().to_string(), reference);
+        }
+        Bibliography(entries)
+    }
+}
+
+/// Index of the `}` or `)` closing the group opened just before `start`.
+fn balanced_end(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text[start..].char_indices() {
+        match c {
+            '{' | '(' => depth += 1,
+            '}' | ')' if depth == 0 => return Some(start + i),
+            '}' | ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `name = value` pairs of an entry body, names lowercased and values with TeX grouping removed.
+fn bibtex_fields(body: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut rest = body;
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let mut value = String::new();
+        let mut after = rest[eq + 1..].trim_start();
+        loop {
+            let (piece, remaining) = match after.chars().next() {
+                Some('{') => {
+                    let end = balanced_end(after, 1).unwrap_or(after.len());
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                Some('"') => {
+                    let end = after[1..].find('"').map_or(after.len(), |i| i + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find([',', '#']).unwrap_or(after.len());
+                    (after[..end].trim(), &after[end..])
+                }
+            };
+            value.push_str(piece);
+            after = remaining.trim_start();
+            match after.strip_prefix('#') {
+                Some(next) => after = next.trim_start(),
+                None => break,
+            }
+        }
+        fields.insert(name, detex(&value));
+        rest = after;
+    }
+    fields
+}
+
+/// Drops TeX braces and escapes, collapsing whitespace.
+fn detex(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => {}
+            '\\' if chars.peek().map(|c| !c.is_alphabetic()).unwrap_or(false) => {
+                out.extend(chars.next());
+            }
+            '~' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `Doe, Jane and John Roe` → [("Doe", "Jane"), ("Roe", "John")].
+fn bibtex_names(names: &str) -> Vec<(String, String)> {
+    names
+        .split(" and ")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.split_once(',') {
+            Some((family, given)) => (family.trim().to_string(), given.trim().to_string()),
+            None => match name.rsplit_once(' ') {
+                Some((given, family)) => (family.to_string(), given.trim().to_string()),
+                None => (name.to_string(), String::new()),
+            },
+        })
+        .collect()
+}
+
+/// One cited work inside a `[...]` group: `see @doe99, p. 33`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CitationItem {
+    pub prefix: String,
+    pub key: String,
+    pub locator: String,
+    /// `-@key`: show the year only.
+    pub suppress_author: bool,
+}
+
+/// A bracketed citation group at `start..end` of the markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    pub start: usize,
+    pub end: usize,
+    pub items: Vec<CitationItem>,
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.' | '/' | '+')
+}
+
+fn parse_item(text: &str) -> Option<CitationItem> {
+    let at = text.find('@')?;
+    let (prefix, suppress_author) = match text[..at].strip_suffix('-') {
+        Some(prefix) => (prefix, true),
+        None => (&text[..at], false),
+    };
+    if !(prefix.is_empty() || prefix.ends_with(char::is_whitespace)) {
+        return None;
+    }
+    let after = &text[at + 1..];
+    let key_len = after.find(|c: char| !is_key_char(c)).unwrap_or(after.len());
+    // Trailing punctuation ends a key (`@doe99.` or `@doe99:`).
+    let key = after[..key_len].trim_end_matches(['.', ':', '/', '-', '+']);
+    if key.is_empty() {
+        return None;
+    }
+    let locator = after[key.len()..].trim_start_matches(',').trim();
+    Some(CitationItem {
+        prefix: prefix.trim().to_string(),
+        key: key.to_string(),
+        locator: locator.to_string(),
+        suppress_author,
+    })
+}
+
+/// Every `[@key]`-style group in `markdown` (not at offsets where `in_code` holds). Brackets
+/// that open a link (`[@x](...)`, `[@x][ref]`) or belong to wikilinks are not citations.
+pub fn find_citations(markdown: &str, in_code: impl Fn(usize) -> bool) -> Vec<Citation> {
+    let bytes = markdown.as_bytes();
+    let mut citations = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = markdown[pos..].find('[') {
+        let start = pos + found;
+        pos = start + 1;
+        if in_code(start) || (start > 0 && matches!(bytes[start - 1], b'[' | b'!' | b'\\')) {
+            continue;
+        }
+        let Some(len) = markdown[pos..].find([']', '[', '\n']) else {
+            break;
+        };
+        let end = pos + len;
+        if bytes[end] != b']' || matches!(bytes.get(end + 1), Some(b'(') | Some(b'[')) {
+            continue;
+        }
+        let inner = &markdown[pos..end];
+        if !inner.contains('@') {
+            continue;
+        }
+        let items: Option<Vec<CitationItem>> = inner.split(';').map(parse_item).collect();
+        if let Some(items) = items {
+            citations.push(Citation {
+                start,
+                end: end + 1,
+                items,
+            });
+            pos = end + 1;
+        }
+    }
+    citations
+}
+
+/// Block id (see `split_block_id`) of the reference list entry for `key`.
+fn reference_id(key: &str) -> String {
+    let slug: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("ref-{}", slug)
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '`' | '<' | '>' | '#' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn author_label(reference: &Reference) -> String {
+    let families: Vec<&str> = reference.authors.iter().map(|(family, _)| family.as_str()).collect();
+    match families.as_slice() {
+        [] => reference.title.clone().unwrap_or_default(),
+        [one] => one.to_string(),
+        [one, two] => format!("{} and {}", one, two),
+        [one, ..] => format!("{} et al.", one),
+    }
+}
+
+fn year_label(reference: &Reference) -> &str {
+    reference.year.as_deref().unwrap_or("n.d.")
+}
+
+/// Replaces each citation group with its author–date markdown and appends a `References`
+/// section listing the cited works (sorted by author, then year), each entry carrying a block
+/// id the inline citations link to. Unknown keys render as `key?`.
+pub fn render_citations(markdown: &str, citations: &[Citation], bibliography: &Bibliography) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut last = 0;
+    let mut cited: Vec<(&str, &Reference)> = Vec::new();
+    for citation in citations {
+        out.push_str(&markdown[last..citation.start]);
+        let items: Vec<String> = citation
+            .items
+            .iter()
+            .map(|item| {
+                let mut text = String::new();
+                if !item.prefix.is_empty() {
+                    text.push_str(&escape_markdown(&item.prefix));
+                    text.push(' ');
+                }
+                match bibliography.get(&item.key) {
+                    Some(reference) => {
+                        let key = item.key.as_str();
+                        let label = if item.suppress_author {
+                            year_label(reference).to_string()
+                        } else {
+                            format!("{} {}", author_label(reference), year_label(reference))
+                        };
+                        text.push_str(&format!("[{}](#^{})", escape_markdown(&label), reference_id(key)));
+                        if !cited.iter().any(|(k, _)| *k == key) {
+                            cited.push((key, reference));
+                        }
+                    }
+                    None => text.push_str(&format!("**{}?**", escape_markdown(&item.key))),
+                }
+                if !item.locator.is_empty() {
+                    text.push_str(", ");
+                    text.push_str(&escape_markdown(&item.locator));
+                }
+                text
+            })
+            .collect();
+        out.push_str(&format!("({})", items.join("; ")));
+        last = citation.end;
+    }
+    out.push_str(&markdown[last..]);
+    if cited.is_empty() {
+        return out;
+    }
+    cited.sort_by(|(_, a), (_, b)| (author_label(a), &a.year).cmp(&(author_label(b), &b.year)));
+    out.push_str("\n\n## References\n\n");
+    for (key, reference) in cited {
+        out.push_str(&format!("- {} ^{}\n", reference_entry(reference), reference_id(key)));
+    }
+    out
+}
+
+/// `Doe, J., & Roe, R. (2020). Title. *Journal*. Publisher. https://doi.org/...`
+fn reference_entry(reference: &Reference) -> String {
+    let names: Vec<String> = reference
+        .authors
+        .iter()
+        .map(|(family, given)| {
+            let initials: Vec<String> = given
+                .split_whitespace()
+                .filter_map(|part| part.chars().next())
+                .map(|initial| format!("{}.", initial))
+                .collect();
+            if initials.is_empty() {
+                family.clone()
+            } else {
+                format!("{}, {}", family, initials.join(" "))
+            }
+        })
+        .collect();
+    let authors = match names.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{}, & {}", rest.join(", "), last),
+    };
+    let mut entry = escape_markdown(&authors);
+    if !entry.is_empty() {
+        entry.push(' ');
+    }
+    entry.push_str(&format!("({}).", year_label(reference)));
+    if let Some(title) = &reference.title {
+        entry.push_str(&format!(" {}.", escape_markdown(title.trim_end_matches('.'))));
+    }
+    if let Some(container) = &reference.container {
+        entry.push_str(&format!(" *{}*.", escape_markdown(container)));
+    }
+    if let Some(publisher) = &reference.publisher {
+        entry.push_str(&format!(" {}.", escape_markdown(publisher)));
+    }
+    match (&reference.doi, &reference.url) {
+        (Some(doi), _) => entry.push_str(&format!(" <https://doi.org/{}>", doi)),
+        (None, Some(url)) => entry.push_str(&format!(" <{}>", url)),
+        (None, None) => {}
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIB: &str = r#"
+@comment{ignored}
+@article{doe2020,
+  author = {Doe, Jane and Roe, Richard},
+  title = {On {Markdown} Notes},
+  journal = "Journal of Notes",
+  year = 2020,
+  doi = {10.1000/xyz}
+}
+@book{poe19, author = {Edgar Allan Poe}, title = {Tales}, publisher = {Penguin}, year = {2019}}
+"#;
+
+    #[test]
+    fn bibtex_entries_parse() {
+        let bib = Bibliography::from_bibtex(BIB);
+        let doe = bib.get("doe2020").unwrap();
+        assert_eq!(doe.authors, [("Doe".into(), "Jane".into()), ("Roe".into(), "Richard".into())]);
+        assert_eq!(doe.title.as_deref(), Some("On Markdown Notes"));
+        assert_eq!(doe.container.as_deref(), Some("Journal of Notes"));
+        assert_eq!(doe.year.as_deref(), Some("2020"));
+        assert_eq!(bib.get("poe19").unwrap().authors, [("Poe".into(), "Edgar Allan".into())]);
+    }
+
+    #[test]
+    fn csl_json_entries_parse() {
+        let json = r#"[{"id": "k1", "author": [{"family": "Doe", "given": "J"}],
+            "issued": {"date-parts": [[2021, 3]]}, "title": "T", "URL": "https://x.org"}]"#;
+        let bib = Bibliography::from_csl_json(json).unwrap();
+        let k1 = bib.get("k1").unwrap();
+        assert_eq!(k1.year.as_deref(), Some("2021"));
+        assert_eq!(k1.url.as_deref(), Some("https://x.org"));
+    }
+
+    #[test]
+    fn citation_groups_are_found_outside_links_and_code() {
+        let md = "As [see @doe2020, p. 4; -@poe19] said. [@x](url) [[@y]] `[@z]` mail [me @ home]";
+        let code_start = md.find('`').unwrap();
+        let citations = find_citations(md, |pos| pos == code_start + 1);
+        assert_eq!(citations.len(), 1, "{:?}", citations);
+        let items = &citations[0].items;
+        assert_eq!((items[0].prefix.as_str(), items[0].key.as_str()), ("see", "doe2020"));
+        assert_eq!(items[0].locator, "p. 4");
+        assert!(items[1].suppress_author);
+    }
+
+    #[test]
+    fn citations_render_author_date_with_reference_list() {
+        let bib = Bibliography::from_bibtex(BIB);
+        let md = "Claim [@poe19; @doe2020, p. 4; @missing].";
+        let out = render_citations(md, &find_citations(md, |_| false), &bib);
+        assert!(
+            out.starts_with("Claim ([Poe 2019](#^ref-poe19); [Doe and Roe 2020](#^ref-doe2020), p. 4; **missing?**)."),
+            "{}",
+            out
+        );
+        let references = out.split("## References").nth(1).unwrap();
+        assert!(
+            references.contains("- Doe, J., & Roe, R. (2020). On Markdown Notes. *Journal of Notes*. <https://doi.org/10.1000/xyz> ^ref-doe2020"),
+            "{}",
+            references
+        );
+        assert!(references.find("Doe,").unwrap() < references.find("Poe,").unwrap(), "sorted by author");
+    }
+}
@@ -0,0 +1,151 @@
+//! Fence info after the language, as docs sites use it: ```` ```rust title="main.rs" {1,3-5} ````
+//! gives the block a filename header and highlighted lines.
+
+const CODE_OPEN: &str = "<pre><code class=\"language-";
+const META_ATTR: &str = " data-meta=\"";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CodeMeta {
+    pub title: Option<String>,
+    /// 1-based inclusive line ranges, in the order written.
+    pub highlight: Vec<(usize, usize)>,
+}
+
+/// `1,3-5` as ranges; `None` when any part is not a line number or range.
+fn parse_line_ranges(spec: &str) -> Option<Vec<(usize, usize)>> {
+    spec.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (from, to) = part.split_once('-').unwrap_or((part, part));
+            let (from, to) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+            (from >= 1 && from <= to).then_some((from, to))
+        })
+        .collect()
+}
+
+/// `title="..."` (or `title='...'`, or an unquoted word) and a `{...}` line list from the info
+/// string after the language. Anything else is ignored.
+pub fn parse_code_meta(meta: &str) -> CodeMeta {
+    let mut out = CodeMeta::default();
+    let mut rest = meta.trim();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("title=") {
+            let (title, next) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+            };
+            out.title = Some(title.to_string()).filter(|t| !t.trim().is_empty());
+            rest = next.trim_start();
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after.find('}').unwrap_or(after.len());
+            out.highlight = parse_line_ranges(&after[..end]).unwrap_or_default();
+            rest = after.get(end + 1..).unwrap_or("").trim_start();
+        } else {
+            let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            rest = rest[word_end..].trim_start();
+        }
+    }
+    out
+}
+
+fn unescape_attr(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Rewrites code blocks rendered with their full info string (`data-meta`): a title becomes a
+/// `div.code-block-title` before the `<pre>`, highlighted lines a `data-highlight-lines`
+/// attribute on it (`1,3-5`, clamped to the block's lines), and `data-meta` is dropped.
+pub fn apply_code_meta(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(CODE_OPEN) {
+        let after = &rest[start + CODE_OPEN.len()..];
+        let tag_end = after.find('>').unwrap_or(after.len());
+        let Some(meta_at) = after[..tag_end].find(META_ATTR) else {
+            out.push_str(&rest[..start + CODE_OPEN.len()]);
+            rest = after;
+            continue;
+        };
+        let meta_start = meta_at + META_ATTR.len();
+        let meta_end = after[meta_start..tag_end].find('"').map_or(tag_end, |i| meta_start + i);
+        let meta = parse_code_meta(&unescape_attr(&after[meta_start..meta_end]));
+        out.push_str(&rest[..start]);
+        if let Some(title) = &meta.title {
+            out.push_str(&format!("<div class=\"code-block-title\">{}</div>", escape_html(title)));
+        }
+        let code = &after[(tag_end + 1).min(after.len())..];
+        let code = &code[..code.find("</code>").unwrap_or(code.len())];
+        let line_count = code.trim_end_matches('\n').split('\n').count();
+        let lines: Vec<String> = meta
+            .highlight
+            .iter()
+            .filter(|&&(from, _)| from <= line_count)
+            .map(|&(from, to)| (from, to.min(line_count)))
+            .map(|(from, to)| if from == to { from.to_string() } else { format!("{}-{}", from, to) })
+            .collect();
+        if lines.is_empty() {
+            out.push_str("<pre>");
+        } else {
+            out.push_str(&format!("<pre data-highlight-lines=\"{}\">", lines.join(",")));
+        }
+        out.push_str("<code class=\"language-");
+        out.push_str(&after[..meta_at]);
+        out.push_str(&after[(meta_end + 1).min(tag_end)..tag_end]);
+        rest = &after[tag_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_and_line_ranges_from_info_string() {
+        let meta = parse_code_meta("title=\"src/main.rs\" {1,3-5}");
+        assert_eq!(meta.title.as_deref(), Some("src/main.rs"));
+        assert_eq!(meta.highlight, [(1, 1), (3, 5)]);
+        assert_eq!(parse_code_meta("{2} title=lib.rs").title.as_deref(), Some("lib.rs"));
+        assert_eq!(parse_code_meta("{5-3}").highlight, []);
+        assert_eq!(parse_code_meta("linenos"), CodeMeta::default());
+    }
+
+    #[test]
+    fn meta_becomes_header_and_highlight_attribute() {
+        let html = "<pre><code class=\"language-rust\" data-meta=\"title=&quot;a&lt;b.rs&quot; {1,3-5}\">\
+                    use a;\n\nfn main() {\n    a();\n}\n</code></pre>\n\
+                    <pre><code class=\"language-sh\">ls\n</code></pre>\n";
+        assert_eq!(
+            apply_code_meta(html),
+            "<div class=\"code-block-title\">a&lt;b.rs</div><pre data-highlight-lines=\"1,3-5\">\
+             <code class=\"language-rust\">use a;\n\nfn main() {\n    a();\n}\n</code></pre>\n\
+             <pre><code class=\"language-sh\">ls\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn highlight_ranges_are_clamped_to_the_block() {
+        let html = "<pre><code class=\"language-sh\" data-meta=\"{2-999999999,7}\">a\nb\nc\n</code></pre>\n\
+                    <pre><code class=\"language-sh\" data-meta=\"{9}\">a\n</code></pre>\n";
+        assert_eq!(
+            apply_code_meta(html),
+            "<pre data-highlight-lines=\"2-3\"><code class=\"language-sh\">a\nb\nc\n</code></pre>\n\
+             <pre><code class=\"language-sh\">a\n</code></pre>\n"
+        );
+    }
+}
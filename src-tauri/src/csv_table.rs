@@ -0,0 +1,95 @@
+//! Minimal CSV reader for `![[data.csv]]` embeds: RFC 4180 quoting (quoted fields may hold
+//! delimiters, newlines and `""` escapes), with `,`, `;` or tab as the delimiter, whichever the
+//! header line uses most.
+
+/// The first rows of a CSV file; `total_rows` counts every record, shown or not.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CsvPreview {
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+}
+
+impl CsvPreview {
+    pub fn truncated(&self) -> bool {
+        self.total_rows > self.rows.len()
+    }
+}
+
+fn sniff_delimiter(text: &str) -> char {
+    let header = text.lines().next().unwrap_or("");
+    [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|&d| (header.matches(d).count(), d == ','))
+        .unwrap_or(',')
+}
+
+/// Parses `text`, keeping at most `max_rows` records (the header included). Blank lines are
+/// skipped; an unterminated quote runs to the end of the file.
+pub fn parse_csv(text: &str, max_rows: usize) -> CsvPreview {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let delimiter = sniff_delimiter(text);
+    let mut preview = CsvPreview::default();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    let mut end_row = |row: &mut Vec<String>, field: &mut String| {
+        row.push(std::mem::take(field));
+        let row = std::mem::take(row);
+        if row.len() == 1 && row[0].is_empty() {
+            return;
+        }
+        preview.total_rows += 1;
+        if preview.rows.len() < max_rows {
+            preview.rows.push(row);
+        }
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => end_row(&mut row, &mut field),
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if !row.is_empty() || !field.is_empty() {
+        end_row(&mut row, &mut field);
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_keep_delimiters_quotes_and_newlines() {
+        let preview = parse_csv("name,notes\r\n\"Doe, J\",\"said \"\"hi\"\"\nthen left\"\n\nb,2", 10);
+        assert_eq!(
+            preview.rows,
+            [
+                vec!["name", "notes"],
+                vec!["Doe, J", "said \"hi\"\nthen left"],
+                vec!["b", "2"],
+            ]
+        );
+        assert_eq!(preview.total_rows, 3);
+    }
+
+    #[test]
+    fn semicolon_files_and_row_limit() {
+        let preview = parse_csv("a;b\n1;2,5\n3;4\n5;6\n", 2);
+        assert_eq!(preview.rows, [vec!["a", "b"], vec!["1", "2,5"]]);
+        assert_eq!(preview.total_rows, 4);
+        assert!(preview.truncated());
+    }
+}
@@ -0,0 +1,312 @@
+//! Mermaid and Graphviz fenced blocks rendered to inline SVG for exports (HTML copy, EPUB,
+//! served pages), where no diagram script runs. Uses the `mmdc` (mermaid-cli) and `dot` binaries;
+//! results are sanitized, then cached by content hash in the vault's `RenderCache`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::obsidian_embed::RenderCache;
+
+/// Diagram renderers, as configured in settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramTools {
+    pub mermaid: String,
+    pub dot: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DiagramKind {
+    Mermaid,
+    Graphviz,
+}
+
+const CODE_OPEN: &str = "<pre><code class=\"language-";
+const CODE_CLOSE: &str = "</code></pre>";
+
+fn diagram_kind(language: &str) -> Option<DiagramKind> {
+    match language {
+        "mermaid" => Some(DiagramKind::Mermaid),
+        "dot" | "graphviz" => Some(DiagramKind::Graphviz),
+        _ => None,
+    }
+}
+
+fn diagram_key(kind: DiagramKind, source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (kind, source).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Replaces every mermaid and `dot`/`graphviz` code block of rendered `html` with its SVG.
+/// Blocks whose renderer is missing or fails stay as code.
+pub fn render_diagrams(html: &str, tools: &DiagramTools, cache: &mut RenderCache) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(CODE_OPEN) {
+        let after = &rest[start + CODE_OPEN.len()..];
+        let language_end = after.find('"').unwrap_or(0);
+        let code_start = after.find('>').map(|i| i + 1);
+        let code_end = after.find(CODE_CLOSE);
+        let (Some(kind), Some(code_start), Some(code_end)) =
+            (diagram_kind(&after[..language_end]), code_start, code_end)
+        else {
+            out.push_str(&rest[..start + CODE_OPEN.len()]);
+            rest = after;
+            continue;
+        };
+        out.push_str(&rest[..start]);
+        let source = unescape_html(&after[code_start..code_end]);
+        let key = diagram_key(kind, &source);
+        let svg = cache.diagram(key).or_else(|| {
+            let svg = run_renderer(kind, tools, &source).ok()?;
+            cache.insert_diagram(key, svg.clone());
+            Some(svg)
+        });
+        let block_end = start + CODE_OPEN.len() + code_end + CODE_CLOSE.len();
+        match svg {
+            Some(svg) => {
+                let class = match kind {
+                    DiagramKind::Mermaid => "mermaid",
+                    DiagramKind::Graphviz => "graphviz",
+                };
+                out.push_str(&format!("<div class=\"diagram diagram-{}\">{}</div>", class, svg));
+            }
+            None => out.push_str(&rest[start..block_end]),
+        }
+        rest = &rest[block_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn run_renderer(kind: DiagramKind, tools: &DiagramTools, source: &str) -> Result<String, String> {
+    let svg = match kind {
+        DiagramKind::Graphviz => run_with_stdin(Command::new(&tools.dot).arg("-Tsvg"), source)?,
+        DiagramKind::Mermaid => {
+            // mermaid-cli reads and writes files; a private directory keeps other users out.
+            let dir = tempfile::Builder::new().prefix("mdglasses-").tempdir().map_err(|e| e.to_string())?;
+            let (input, output) = (dir.path().join("diagram.mmd"), dir.path().join("diagram.svg"));
+            fs::write(&input, source).map_err(|e| e.to_string())?;
+            let result = Command::new(&tools.mermaid)
+                .arg("--quiet")
+                .arg("--input")
+                .arg(&input)
+                .arg("--output")
+                .arg(&output)
+                .output();
+            match result {
+                Ok(result) if result.status.success() => {
+                    fs::read_to_string(&output).map_err(|e| e.to_string())?
+                }
+                Ok(result) => return Err(String::from_utf8_lossy(&result.stderr).trim().to_string()),
+                Err(e) => return Err(format!("{} not available: {}", tools.mermaid, e)),
+            }
+        }
+    };
+    // Drop the XML prolog and doctype so the SVG can sit inside HTML.
+    let start = svg.find("<svg").ok_or("Renderer produced no SVG")?;
+    Ok(sanitize_svg(svg[start..].trim_end()))
+}
+
+/// Elements dropped from diagram SVG with their content (`embed` has none).
+const DROPPED_ELEMENTS: [&str; 4] = ["script", "iframe", "object", "embed"];
+
+/// `svg` without script-like elements, `on*` event attributes and links to anything but
+/// http(s), mailto or relative targets. Diagram sources come from notes, and labels and links
+/// in them pass through to the SVG that exports inline.
+fn sanitize_svg(svg: &str) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let markup = &rest[start..];
+        let end = markup_end(markup);
+        let (tag, after) = markup.split_at(end);
+        rest = after;
+        let name = tag_name(tag);
+        if !DROPPED_ELEMENTS.iter().any(|dropped| name.eq_ignore_ascii_case(dropped)) {
+            out.push_str(&clean_tag(tag));
+        } else if !tag.ends_with("/>") && !name.eq_ignore_ascii_case("embed") {
+            let close = format!("</{}", name.to_ascii_lowercase());
+            rest = match after.to_ascii_lowercase().find(&close) {
+                Some(at) => after[at..].find('>').map_or("", |gt| &after[at + gt + 1..]),
+                None => "",
+            };
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the tag, comment or CDATA section opening `markup`; `>` in quoted attribute values
+/// does not end a tag.
+fn markup_end(markup: &str) -> usize {
+    for (open, close) in [("<!--", "-->"), ("<![CDATA[", "]]>")] {
+        if markup.starts_with(open) {
+            return markup.find(close).map_or(markup.len(), |at| at + close.len());
+        }
+    }
+    let mut quote = None;
+    for (i, c) in markup.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    markup.len()
+}
+
+/// Element name of an opening tag (`<rect x="1">` → `rect`); empty for anything else.
+fn tag_name(tag: &str) -> &str {
+    let name = tag.strip_prefix('<').unwrap_or("");
+    let end = name.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(name.len());
+    &name[..end]
+}
+
+/// Opening tag `tag` without its unsafe attributes; other markup is returned unchanged.
+fn clean_tag(tag: &str) -> String {
+    let name = tag_name(tag);
+    if name.is_empty() || !tag.ends_with('>') {
+        return tag.to_string();
+    }
+    let self_closing = tag.ends_with("/>");
+    let mut out = format!("<{}", name);
+    let mut attrs = &tag[1 + name.len()..tag.len() - if self_closing { 2 } else { 1 }];
+    loop {
+        attrs = attrs.trim_start();
+        let name_end = attrs.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(attrs.len());
+        if name_end == 0 {
+            break;
+        }
+        let attr_name = &attrs[..name_end];
+        let after_name = attrs[name_end..].trim_start();
+        let (value, rest) = match after_name.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                match value.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = value[1..].find(q).map_or(value.len(), |at| at + 2);
+                        (&value[..end], &value[end..])
+                    }
+                    _ => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+                }
+            }
+            None => ("", after_name),
+        };
+        if safe_attribute(attr_name, value.trim_matches(|c| c == '"' || c == '\'')) {
+            out.push(' ');
+            out.push_str(attr_name);
+            if !value.is_empty() {
+                out.push('=');
+                out.push_str(value);
+            }
+        }
+        attrs = rest;
+    }
+    out.push_str(if self_closing { "/>" } else { ">" });
+    out
+}
+
+fn safe_attribute(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let value: String = value
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if name.starts_with("on") || value.contains("javascript:") {
+        return false;
+    }
+    if name != "href" && !name.ends_with(":href") {
+        return true;
+    }
+    // Entities could spell out a scheme the checks below would miss.
+    if value.contains("&#") || value.contains("&colon") {
+        return false;
+    }
+    let scheme_end = value.find(':').filter(|at| !value[..*at].contains(['/', '?', '#']));
+    match scheme_end {
+        Some(at) => ["http", "https", "mailto"].contains(&&value[..at]),
+        None => true,
+    }
+}
+
+fn run_with_stdin(command: &mut Command, input: &str) -> Result<String, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{:?} not available: {}", command.get_program(), e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reverses comrak's escaping of code block text.
+fn unescape_html(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing_tools() -> DiagramTools {
+        DiagramTools {
+            mermaid: "/nonexistent/mmdc".to_string(),
+            dot: "/nonexistent/dot".to_string(),
+        }
+    }
+
+    #[test]
+    fn cached_diagrams_replace_their_code_blocks() {
+        let mut cache = RenderCache::default();
+        cache.insert_diagram(diagram_key(DiagramKind::Graphviz, "a -> b\n"), "<svg>g</svg>".to_string());
+        let html = "<pre><code class=\"language-dot\">a -&gt; b\n</code></pre><p>x</p>";
+        assert_eq!(
+            render_diagrams(html, &missing_tools(), &mut cache),
+            "<div class=\"diagram diagram-graphviz\"><svg>g</svg></div><p>x</p>"
+        );
+    }
+
+    #[test]
+    fn sanitized_svg_loses_scripts_handlers_and_script_links() {
+        let svg = "<svg onload=\"alert(1)\"><!-- a > b --><script>alert(2)</script>\
+                   <SCRIPT type=\"x\">alert(3)</SCRIPT>\
+                   <a xlink:href=\"java&#115;cript:alert(4)\"><text>t</text></a>\
+                   <a href=\" JavaScript:alert(5)\" title='x > y'>u</a>\
+                   <a href=\"https://example.com/?a=1&amp;b=2\">v</a>\
+                   <animate attributeName=\"href\" to=\"javascript:alert(6)\"/>\
+                   <rect x=\"1\" onclick=alert(7) fill=\"red\"/>\
+                   <foreignObject><iframe src=x></iframe>w</foreignObject></svg>";
+        assert_eq!(
+            sanitize_svg(svg),
+            "<svg><!-- a > b --><a><text>t</text></a><a title='x > y'>u</a>\
+             <a href=\"https://example.com/?a=1&amp;b=2\">v</a><animate attributeName=\"href\"/>\
+             <rect x=\"1\" fill=\"red\"/><foreignObject>w</foreignObject></svg>"
+        );
+    }
+
+    #[test]
+    fn blocks_stay_code_without_renderer() {
+        let mut cache = RenderCache::default();
+        let html = "<pre><code class=\"language-mermaid\">graph TD\n</code></pre>\
+                    <pre><code class=\"language-rust\">fn f() {}\n</code></pre>";
+        assert_eq!(render_diagrams(html, &missing_tools(), &mut cache), html);
+    }
+}
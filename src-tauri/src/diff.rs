@@ -0,0 +1,158 @@
+//! Line diffs between two versions of a note (Myers' algorithm).
+
+/// Past this many inserted plus deleted lines the differing middle is reported as replaced
+/// wholesale, which keeps the search bounded on unrelated texts.
+const MAX_EDITS: isize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    /// 1-based line in the old text; `None` for inserted lines.
+    pub old_line: Option<usize>,
+    /// 1-based line in the new text; `None` for deleted lines.
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NoteDiff {
+    /// Every line of both texts in order, deletions before the insertions replacing them.
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+pub fn diff_lines(old: &str, new: &str) -> NoteDiff {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let middle = edit_script(old_mid, new_mid).unwrap_or_else(|| {
+        let mut ops = vec![DiffKind::Delete; old_mid.len()];
+        ops.resize(old_mid.len() + new_mid.len(), DiffKind::Insert);
+        ops
+    });
+
+    let mut diff = NoteDiff::default();
+    let (mut x, mut y) = (0, 0);
+    let mut ops = vec![DiffKind::Equal; prefix];
+    ops.extend(middle);
+    ops.resize(ops.len() + suffix, DiffKind::Equal);
+    for kind in ops {
+        let (old_line, new_line, text) = match kind {
+            DiffKind::Equal => (Some(x + 1), Some(y + 1), old[x]),
+            DiffKind::Delete => (Some(x + 1), None, old[x]),
+            DiffKind::Insert => (None, Some(y + 1), new[y]),
+        };
+        match kind {
+            DiffKind::Equal => (x, y) = (x + 1, y + 1),
+            DiffKind::Delete => (x, diff.removed) = (x + 1, diff.removed + 1),
+            DiffKind::Insert => (y, diff.added) = (y + 1, diff.added + 1),
+        }
+        diff.lines.push(DiffLine {
+            kind,
+            old_line,
+            new_line,
+            text: text.to_string(),
+        });
+    }
+    diff
+}
+
+/// Shortest edit script turning `old` into `new`, or `None` past `MAX_EDITS`. `trace[d]` keeps
+/// the furthest x reached on each diagonal k = x - y (indexed k + d) after d edits.
+fn edit_script(old: &[&str], new: &[&str]) -> Option<Vec<DiffKind>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max.min(MAX_EDITS) {
+        for k in (-d..=d).step_by(2) {
+            let i = (k + max) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                trace.push(v[(max - d) as usize..=(max + d) as usize].to_vec());
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+        trace.push(v[(max - d) as usize..=(max + d) as usize].to_vec());
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<DiffKind> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let at = |k: isize| trace[d as usize - 1][(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(DiffKind::Equal);
+            (x, y) = (x - 1, y - 1);
+        }
+        ops.push(if prev_k == k + 1 { DiffKind::Insert } else { DiffKind::Delete });
+        (x, y) = (prev_x, prev_y);
+    }
+    ops.resize(ops.len() + x as usize, DiffKind::Equal);
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &NoteDiff) -> String {
+        let sign = |kind| match kind {
+            DiffKind::Equal => ' ',
+            DiffKind::Insert => '+',
+            DiffKind::Delete => '-',
+        };
+        diff.lines.iter().map(|l| format!("{}{}\n", sign(l.kind), l.text)).collect()
+    }
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        let diff = diff_lines("a\nb\nc\nd\ne", "a\nc\nx\nd\ne\nf");
+        assert_eq!(render(&diff), " a\n-b\n c\n+x\n d\n e\n+f\n");
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        let x = &diff.lines[3];
+        assert_eq!((x.old_line, x.new_line), (None, Some(3)));
+        let d = &diff.lines[4];
+        assert_eq!((d.old_line, d.new_line), (Some(4), Some(4)));
+    }
+
+    #[test]
+    fn diff_of_unrelated_or_empty_texts() {
+        assert_eq!(render(&diff_lines("a\nb", "c")), "-a\n-b\n+c\n");
+        assert_eq!(render(&diff_lines("", "a")), "+a\n");
+        assert!(diff_lines("same\n", "same").lines.iter().all(|l| l.kind == DiffKind::Equal));
+    }
+}
@@ -0,0 +1,354 @@
+//! EPUB 3 export of a folder: one XHTML chapter per note, local images packaged in the book
+//! and wikilinks between exported notes pointing at their chapters.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use zip::result::ZipResult;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::export::{image_mime, local_image_path};
+use crate::paths::{self, write_atomic_with};
+use crate::text::percent_decode;
+
+pub struct Chapter {
+    pub title: String,
+    /// Canonical note path; wikilinks to it from other chapters point at this chapter.
+    pub path: PathBuf,
+    /// The note rendered with embeds.
+    pub html: String,
+}
+
+struct Image {
+    source: PathBuf,
+    /// Path inside the book, relative to the package document.
+    href: String,
+    mime: &'static str,
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const VOID_TAGS: [&str; 7] = ["br", "hr", "img", "input", "col", "wbr", "source"];
+
+fn chapter_href(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes `chapters`, in order, as an EPUB titled `title` at `output`.
+pub fn write_epub(title: &str, chapters: &[Chapter], output: &Path) -> Result<(), String> {
+    let chapter_hrefs: HashMap<&Path, String> = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| (chapter.path.as_path(), chapter_href(i)))
+        .collect();
+    let mut images = Vec::new();
+    let pages: Vec<String> = chapters
+        .iter()
+        .map(|chapter| {
+            let base_dir = chapter.path.parent().unwrap_or(Path::new(""));
+            let html = rewrite_note_links(&chapter.html, &chapter_hrefs);
+            let html = package_images(&html, base_dir, &mut images);
+            xhtml_page(&chapter.title, &to_xhtml(&html))
+        })
+        .collect();
+    write_atomic_with(output, false, |file| {
+        write_package(file, title, chapters, &pages, &images).map_err(io::Error::other)
+    })
+}
+
+fn write_package(
+    file: &mut File,
+    title: &str,
+    chapters: &[Chapter],
+    pages: &[String],
+    images: &[Image],
+) -> ZipResult<()> {
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(file);
+    // Readers identify the format from this first, uncompressed entry.
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package_document(title, chapters.len(), images).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav_document(title, chapters).as_bytes())?;
+    for (i, page) in pages.iter().enumerate() {
+        zip.start_file(format!("OEBPS/{}", chapter_href(i)), deflated)?;
+        zip.write_all(page.as_bytes())?;
+    }
+    for image in images {
+        zip.start_file(format!("OEBPS/{}", image.href), stored)?;
+        zip.write_all(&fs::read(&image.source)?)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn package_document(title: &str, chapter_count: usize, images: &[Image]) -> String {
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let mut hasher = DefaultHasher::new();
+    (title, &modified).hash(&mut hasher);
+    let mut manifest =
+        String::from(r#"    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#);
+    let mut spine = String::new();
+    for i in 0..chapter_count {
+        manifest.push_str(&format!(
+            "\n    <item id=\"chapter-{n}\" href=\"{href}\" media-type=\"application/xhtml+xml\"/>",
+            n = i + 1,
+            href = chapter_href(i)
+        ));
+        spine.push_str(&format!("\n    <itemref idref=\"chapter-{}\"/>", i + 1));
+    }
+    for (i, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "\n    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>",
+            i + 1,
+            image.href,
+            image.mime
+        ));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:mdglasses:{id:016x}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+{manifest}
+  </manifest>
+  <spine>{spine}
+  </spine>
+</package>
+"#,
+        id = hasher.finish(),
+        title = escape_xml(title),
+        modified = modified,
+        manifest = manifest,
+        spine = spine
+    )
+}
+
+fn nav_document(title: &str, chapters: &[Chapter]) -> String {
+    let items: Vec<String> = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| {
+            format!("      <li><a href=\"{}\">{}</a></li>", chapter_href(i), escape_xml(&chapter.title))
+        })
+        .collect();
+    let nav = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n    <h1>{}</h1>\n    <ol>\n{}\n    </ol>\n  </nav>",
+        escape_xml(title),
+        items.join("\n")
+    );
+    xhtml_page(title, &nav)
+}
+
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <meta charset="UTF-8"/>
+  <title>{}</title>
+</head>
+<body>
+  {}
+</body>
+</html>
+"#,
+        escape_xml(title),
+        body
+    )
+}
+
+/// Points `app://open` links at the chapter of their note; links to notes outside the book
+/// lose their href and stay as plain text.
+fn rewrite_note_links(html: &str, chapter_hrefs: &HashMap<&Path, String>) -> String {
+    const HREF: &str = " href=\"app://open?path=";
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(HREF) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + HREF.len()..];
+        let end = after.find('"').unwrap_or(after.len());
+        let (path, anchor) = match after[..end].split_once('#') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (&after[..end], None),
+        };
+        if let Some(chapter) = chapter_hrefs.get(Path::new(&percent_decode(path))) {
+            out.push_str(" href=\"");
+            out.push_str(chapter);
+            if let Some(anchor) = anchor {
+                out.push('#');
+                out.push_str(anchor);
+            }
+            out.push('"');
+        }
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Adds the local images of `<img>` tags and image asset links (`![[photo.png]]`) to `images`
+/// and points the chapter at the packaged copies.
+fn package_images(html: &str, base_dir: &Path, images: &mut Vec<Image>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = [rest.find("<img "), rest.find("<a href=\"file://")].into_iter().flatten().min() {
+        out.push_str(&rest[..start]);
+        let tag_end = rest[start..].find('>').map(|i| start + i + 1).unwrap_or(rest.len());
+        let tag = &rest[start..tag_end];
+        let is_img = tag.starts_with("<img");
+        let src = attr_value(tag, if is_img { "src" } else { "href" });
+        let packaged = src.and_then(|(_, src)| package_image(src, base_dir, images));
+        match (src, packaged) {
+            (Some((at, src)), Some(href)) if is_img => {
+                out.push_str(&tag[..at]);
+                out.push_str(&href);
+                out.push_str(&tag[at + src.len()..]);
+                rest = &rest[tag_end..];
+            }
+            (Some(_), Some(href)) => {
+                // Asset link: the whole `<a>...</a>` becomes the image.
+                let close = rest[tag_end..].find("</a>").map(|i| tag_end + i);
+                let alt = close.map(|c| &rest[tag_end..c]).unwrap_or("");
+                let alt = alt.strip_prefix("Asset: ").unwrap_or(alt);
+                out.push_str(&format!("<img src=\"{}\" alt=\"{}\" />", href, alt.replace('"', "&quot;")));
+                rest = close.map(|c| &rest[c + "</a>".len()..]).unwrap_or(&rest[tag_end..]);
+            }
+            _ => {
+                out.push_str(tag);
+                rest = &rest[tag_end..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset and value of attribute `name` in an HTML start tag.
+fn attr_value<'a>(tag: &'a str, name: &str) -> Option<(usize, &'a str)> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let len = tag[start..].find('"')?;
+    Some((start, &tag[start..start + len]))
+}
+
+/// Book path of the image `src` refers to, packaging it on first use; `None` when it is not a
+/// readable local image.
+fn package_image(src: &str, base_dir: &Path, images: &mut Vec<Image>) -> Option<String> {
+    let source = paths::canonicalize(&local_image_path(src, base_dir)?).ok()?;
+    if let Some(image) = images.iter().find(|image| image.source == source) {
+        return Some(image.href.clone());
+    }
+    let mime = image_mime(&source)?;
+    let extension = source.extension()?.to_str()?.to_ascii_lowercase();
+    let href = format!("images/{}.{}", images.len() + 1, extension);
+    images.push(Image {
+        source,
+        href: href.clone(),
+        mime,
+    });
+    Some(href)
+}
+
+/// HTML fragment made well-formed for XHTML: void elements self-close and `&nbsp;` (not an
+/// XML entity) becomes a character reference.
+fn to_xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('>').map(|i| start + i).unwrap_or(rest.len() - 1);
+        let tag = &rest[start..=end];
+        let name: String = tag[1..].chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        if VOID_TAGS.contains(&name.to_ascii_lowercase().as_str()) && !tag.ends_with("/>") {
+            out.push_str(&tag[..tag.len() - 1]);
+            out.push_str(" />");
+        } else {
+            out.push_str(tag);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out.replace("&nbsp;", "&#160;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn epub_has_chapters_images_and_chapter_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("pic.png"), [1u8, 2, 3]).unwrap();
+        let chapters = vec![
+            Chapter {
+                title: "A".to_string(),
+                path: root.join("A.md"),
+                html: format!(
+                    "<p><a href=\"app://open?path={}#part\">B</a><br><img src=\"pic.png\" alt=\"p\"></p>\
+                     <p><a href=\"app://open?path=/elsewhere.md\">Out</a></p>",
+                    root.join("B.md").to_string_lossy()
+                ),
+            },
+            Chapter {
+                title: "B".to_string(),
+                path: root.join("B.md"),
+                html: format!(
+                    "<p><a href=\"file:///{}\">Asset: pic.png</a></p>",
+                    root.join("pic.png").to_string_lossy()
+                ),
+            },
+        ];
+        let output = root.join("book.epub");
+        write_epub("Vault", &chapters, &output).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut text).unwrap();
+            text
+        };
+        let first = read("OEBPS/chapter-1.xhtml");
+        assert!(first.contains("<a href=\"chapter-2.xhtml#part\">B</a>"), "{}", first);
+        assert!(first.contains("<br />"), "{}", first);
+        assert!(first.contains("<img src=\"images/1.png\" alt=\"p\" />"), "{}", first);
+        assert!(first.contains("<a>Out</a>"), "outside links lose their href: {}", first);
+        let second = read("OEBPS/chapter-2.xhtml");
+        assert!(second.contains("<img src=\"images/1.png\" alt=\"pic.png\" />"), "{}", second);
+        let opf = read("OEBPS/content.opf");
+        assert!(opf.contains("href=\"images/1.png\" media-type=\"image/png\""), "{}", opf);
+        assert_eq!(opf.matches("<itemref").count(), 2);
+    }
+}
@@ -0,0 +1,100 @@
+//! Self-contained HTML for copy/export: local images are inlined as `data:` URIs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::text::percent_decode;
+
+pub(crate) fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        _ => return None,
+    })
+}
+
+fn is_remote_src(src: &str) -> bool {
+    src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:")
+}
+
+/// Image file a local `src` points at (relative to `base_dir`, or `file://`); `None` for remote
+/// sources and non-image files. The file may not exist.
+pub(crate) fn local_image_path(src: &str, base_dir: &Path) -> Option<PathBuf> {
+    if is_remote_src(src) {
+        return None;
+    }
+    let decoded = percent_decode(&src.replace("&amp;", "&"));
+    let local = decoded.strip_prefix("file://").unwrap_or(&decoded);
+    let path = base_dir.join(local);
+    image_mime(&path).map(|_| path)
+}
+
+/// Data URI for a local `src` (relative to `base_dir`, or `file://`); `None` keeps the original.
+fn data_uri_for(src: &str, base_dir: &Path) -> Option<String> {
+    let path = local_image_path(src, base_dir)?;
+    let mime = image_mime(&path)?;
+    let bytes = fs::read(&path).ok()?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Rewrites `src` of every `<img>` whose file can be read; unreadable or remote images are left as is.
+pub fn inline_local_images(html: &str, base_dir: &Path) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(tag_start) = rest.find("<img ") {
+        let tag_len = rest[tag_start..].find('>').map(|i| i + 1).unwrap_or(rest.len() - tag_start);
+        let tag = &rest[tag_start..tag_start + tag_len];
+        out.push_str(&rest[..tag_start]);
+        out.push_str(&inline_img_tag(tag, base_dir));
+        rest = &rest[tag_start + tag_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn inline_img_tag(tag: &str, base_dir: &Path) -> String {
+    let Some(attr_start) = tag.find("src=\"") else {
+        return tag.to_string();
+    };
+    let value_start = attr_start + "src=\"".len();
+    let Some(value_len) = tag[value_start..].find('"') else {
+        return tag.to_string();
+    };
+    let src = &tag[value_start..value_start + value_len];
+    match data_uri_for(src, base_dir) {
+        Some(uri) => format!("{}{}{}", &tag[..value_start], uri, &tag[value_start + value_len..]),
+        None => tag.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn inlines_relative_images_and_keeps_remote() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("img dir")).unwrap();
+        fs::write(dir.path().join("img dir/a.png"), [1u8, 2, 3]).unwrap();
+        let html = r#"<p><img src="img%20dir/a.png" alt="a" /> <img src="https://x/y.png" alt="y" /></p>"#;
+        let out = inline_local_images(html, dir.path());
+        assert!(out.contains(r#"<img src="data:image/png;base64,AQID" alt="a" />"#), "{}", out);
+        assert!(out.contains(r#"<img src="https://x/y.png""#), "{}", out);
+    }
+
+    #[test]
+    fn missing_images_are_left_alone() {
+        let dir = TempDir::new().unwrap();
+        let html = r#"<img src="missing.png" alt="" />"#;
+        assert_eq!(inline_local_images(html, dir.path()), html);
+    }
+}
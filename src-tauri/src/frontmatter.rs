@@ -0,0 +1,123 @@
+//! YAML frontmatter (`---` fenced block at the top of a note), read as flat key/value pairs.
+//! Only what note properties use is supported: scalars, `[a, b]` lists and `- item` lists;
+//! nested maps are skipped.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(untagged)]
+pub enum FrontmatterValue {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// Splits a leading frontmatter block off `md`: (yaml, body). `None` when there is none.
+pub fn split_frontmatter(md: &str) -> Option<(&str, &str)> {
+    let rest = md.strip_prefix("---")?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
+
+pub fn parse_frontmatter(md: &str) -> BTreeMap<String, FrontmatterValue> {
+    let mut fields = BTreeMap::new();
+    let Some((yaml, _)) = split_frontmatter(md) else {
+        return fields;
+    };
+    let mut current_list: Option<(String, Vec<String>)> = None;
+    for line in yaml.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some((_, items)) = current_list.as_mut() {
+                items.push(unquote(item));
+            }
+            continue;
+        }
+        if indented {
+            continue;
+        }
+        if let Some((key, items)) = current_list.take() {
+            fields.insert(key, FrontmatterValue::List(items));
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            current_list = Some((key, Vec::new()));
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items = inner
+                .split(',')
+                .map(unquote)
+                .filter(|item| !item.is_empty())
+                .collect();
+            fields.insert(key, FrontmatterValue::List(items));
+        } else {
+            fields.insert(key, FrontmatterValue::Text(unquote(value)));
+        }
+    }
+    if let Some((key, items)) = current_list {
+        fields.insert(key, FrontmatterValue::List(items));
+    }
+    fields
+}
+
+/// Whether exports include the note: not when its frontmatter says `publish: false` or
+/// `draft: true`.
+pub fn is_published(md: &str) -> bool {
+    let fields = parse_frontmatter(md);
+    let flag = |key: &str, value: &str| {
+        matches!(fields.get(key), Some(FrontmatterValue::Text(text)) if text.eq_ignore_ascii_case(value))
+    };
+    !flag("publish", "false") && !flag("draft", "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scalars_and_lists() {
+        let md = "---\ntitle: \"My Note\"\ntags: [a, b]\naliases:\n  - One\n  - 'Two'\ndraft: true\n---\n# Body";
+        let fields = parse_frontmatter(md);
+        assert_eq!(fields["title"], FrontmatterValue::Text("My Note".into()));
+        assert_eq!(fields["tags"], FrontmatterValue::List(vec!["a".into(), "b".into()]));
+        assert_eq!(fields["aliases"], FrontmatterValue::List(vec!["One".into(), "Two".into()]));
+        assert_eq!(fields["draft"], FrontmatterValue::Text("true".into()));
+    }
+
+    #[test]
+    fn publish_and_draft_flags_exclude_notes() {
+        assert!(is_published("# No frontmatter"));
+        assert!(is_published("---\npublish: true\ndraft: false\n---\n"));
+        assert!(!is_published("---\npublish: false\n---\n"));
+        assert!(!is_published("---\ndraft: True\n---\n"));
+    }
+
+    #[test]
+    fn split_requires_leading_fence() {
+        assert_eq!(split_frontmatter("---\na: 1\n---\nbody"), Some(("a: 1\n", "body")));
+        assert_eq!(split_frontmatter("text\n---\na: 1\n---\n"), None);
+        assert!(parse_frontmatter("# No frontmatter").is_empty());
+    }
+}
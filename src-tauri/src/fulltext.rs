@@ -0,0 +1,212 @@
+//! Persistent full-text index over a vault's notes (tantivy, behind the `fulltext` feature).
+//!
+//! Names and contents are indexed as lowercased character trigrams, so the notes the index
+//! returns for a query are exactly those the plain scan in `search` would match, for queries of
+//! three characters or more. Search still runs the scan over those notes for lines and snippets.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use tantivy::collector::DocSetCollector;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING,
+};
+use tantivy::tokenizer::{LowerCaser, NgramTokenizer, TextAnalyzer};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+const TRIGRAM: &str = "trigram";
+const WRITER_MEMORY_BYTES: usize = 20_000_000;
+
+fn err(e: impl std::fmt::Display) -> String {
+    e.to_string()
+}
+
+struct Fields {
+    path: Field,
+    mtime: Field,
+    name: Field,
+    body: Field,
+}
+
+fn schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let trigrams = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(TRIGRAM)
+            .set_index_option(IndexRecordOption::Basic),
+    );
+    let fields = Fields {
+        path: builder.add_text_field("path", STRING | STORED),
+        mtime: builder.add_u64_field("mtime", STORED),
+        name: builder.add_text_field("name", trigrams.clone()),
+        body: builder.add_text_field("body", trigrams),
+    };
+    (builder.build(), fields)
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+pub struct FulltextIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    fields: Fields,
+}
+
+impl FulltextIndex {
+    /// Opens the index stored in `dir`, creating it (or recreating it, when it was written with
+    /// another schema) as needed.
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(err)?;
+        let (schema, fields) = schema();
+        let open = |dir: &Path| -> tantivy::Result<Index> {
+            Index::open_or_create(MmapDirectory::open(dir)?, schema.clone())
+        };
+        let index = match open(dir) {
+            Ok(index) => index,
+            Err(_) => {
+                fs::remove_dir_all(dir).map_err(err)?;
+                fs::create_dir_all(dir).map_err(err)?;
+                open(dir).map_err(err)?
+            }
+        };
+        let analyzer = TextAnalyzer::builder(NgramTokenizer::new(3, 3, false).map_err(err)?)
+            .filter(LowerCaser)
+            .build();
+        index.tokenizers().register(TRIGRAM, analyzer);
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into().map_err(err)?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY_BYTES).map_err(err)?;
+        Ok(FulltextIndex {
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            fields,
+        })
+    }
+
+    /// Brings the index in line with `notes`: notes modified since they were indexed (or new)
+    /// are reindexed, indexed notes no longer in the list removed. Returns how many changed.
+    pub fn sync(&self, notes: &[PathBuf]) -> Result<usize, String> {
+        let indexed = self.indexed_mtimes()?;
+        let listed: HashSet<&PathBuf> = notes.iter().collect();
+        let stale: Vec<PathBuf> = notes
+            .iter()
+            .filter(|note| indexed.get(*note) != modified_secs(note).as_ref())
+            .chain(indexed.keys().filter(|path| !listed.contains(path)))
+            .cloned()
+            .collect();
+        self.update(&stale)?;
+        Ok(stale.len())
+    }
+
+    /// Reindexes `paths`: Markdown files that exist are (re)added, everything else removed.
+    pub fn update(&self, paths: &[PathBuf]) -> Result<(), String> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.writer.lock().unwrap();
+        for path in paths {
+            let Some(key) = path.to_str() else {
+                continue;
+            };
+            writer.delete_term(Term::from_field_text(self.fields.path, key));
+            let is_note = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+            let Some(content) = is_note.then(|| fs::read_to_string(path).ok()).flatten() else {
+                continue;
+            };
+            let mut doc = TantivyDocument::default();
+            doc.add_text(self.fields.path, key);
+            doc.add_u64(self.fields.mtime, modified_secs(path).unwrap_or_default());
+            doc.add_text(self.fields.name, path.file_stem().and_then(|s| s.to_str()).unwrap_or(""));
+            doc.add_text(self.fields.body, &content);
+            writer.add_document(doc).map_err(err)?;
+        }
+        writer.commit().map_err(err)?;
+        self.reader.reload().map_err(err)
+    }
+
+    /// Notes whose name or content contains `query` (case-insensitive); `None` when the index
+    /// cannot tell (queries shorter than three characters), meaning every note is a candidate.
+    pub fn candidates(&self, query: &str) -> Result<Option<HashSet<PathBuf>>, String> {
+        let mut analyzer = self.index.tokenizer_for_field(self.fields.body).map_err(err)?;
+        let mut trigrams = Vec::new();
+        let mut stream = analyzer.token_stream(query.trim());
+        while stream.advance() {
+            trigrams.push(stream.token().text.clone());
+        }
+        if trigrams.is_empty() {
+            return Ok(None);
+        }
+        let all_in = |field: Field| -> Box<dyn Query> {
+            let terms = trigrams.iter().map(|trigram| {
+                let term = Term::from_field_text(field, trigram);
+                let query: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+                (Occur::Must, query)
+            });
+            Box::new(BooleanQuery::new(terms.collect()))
+        };
+        let query = BooleanQuery::new(vec![
+            (Occur::Should, all_in(self.fields.name)),
+            (Occur::Should, all_in(self.fields.body)),
+        ]);
+        Ok(Some(self.paths_matching(&query)?.into_keys().collect()))
+    }
+
+    fn indexed_mtimes(&self) -> Result<HashMap<PathBuf, u64>, String> {
+        self.paths_matching(&AllQuery)
+    }
+
+    fn paths_matching(&self, query: &dyn Query) -> Result<HashMap<PathBuf, u64>, String> {
+        let searcher = self.reader.searcher();
+        let docs = searcher.search(query, &DocSetCollector).map_err(err)?;
+        let mut paths = HashMap::with_capacity(docs.len());
+        for address in docs {
+            let doc: TantivyDocument = searcher.doc(address).map_err(err)?;
+            let Some(path) = doc.get_first(self.fields.path).and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let mtime = doc.get_first(self.fields.mtime).and_then(|v| v.as_u64()).unwrap_or_default();
+            paths.insert(PathBuf::from(path), mtime);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_follow_substring_matches_and_updates() {
+        let vault = tempfile::TempDir::new().unwrap();
+        let store = tempfile::TempDir::new().unwrap();
+        let a = vault.path().join("alpha.md");
+        let b = vault.path().join("Rust notes.md");
+        fs::write(&a, "We use Rustacean tooling.").unwrap();
+        fs::write(&b, "# Notes").unwrap();
+        let index = FulltextIndex::open(store.path()).unwrap();
+        assert_eq!(index.sync(&[a.clone(), b.clone()]).unwrap(), 2);
+        assert_eq!(index.sync(&[a.clone(), b.clone()]).unwrap(), 0, "unchanged notes are kept");
+
+        let found = index.candidates("RUST").unwrap().unwrap();
+        assert_eq!(found, HashSet::from([a.clone(), b.clone()]));
+        let found = index.candidates("use rust").unwrap().unwrap();
+        assert_eq!(found, HashSet::from([a.clone()]));
+        assert!(index.candidates("go").unwrap().is_none(), "too short for trigrams");
+
+        fs::remove_file(&a).unwrap();
+        index.update(&[a]).unwrap();
+        assert_eq!(index.candidates("rustacean").unwrap().unwrap(), HashSet::new());
+        drop(index);
+        let reopened = FulltextIndex::open(store.path()).unwrap();
+        assert_eq!(reopened.candidates("notes").unwrap().unwrap(), HashSet::from([b]));
+    }
+}
@@ -0,0 +1,142 @@
+//! Note history from the vault's git repository, read through the `git` CLI.
+
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteCommit {
+    pub hash: String,
+    /// Author date, ISO 8601.
+    pub date: String,
+    pub author: String,
+    pub message: String,
+}
+
+const FIELD_SEP: char = '\u{1f}';
+const RECORD_SEP: char = '\u{1e}';
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("git not available: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn split_note_path(path: &Path) -> Result<(&Path, String), String> {
+    let dir = path.parent().ok_or("No parent dir")?;
+    let name = path.file_name().and_then(|n| n.to_str()).ok_or("Invalid path")?;
+    Ok((dir, format!("./{}", name)))
+}
+
+fn parse_log(output: &str) -> Vec<NoteCommit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, FIELD_SEP);
+            Some(NoteCommit {
+                hash: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                message: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Commits touching `path`, newest first (renames followed). Empty when the note is not in a repo.
+pub fn note_history(path: &Path) -> Result<Vec<NoteCommit>, String> {
+    let (dir, file) = split_note_path(path)?;
+    if git(dir, &["rev-parse", "--is-inside-work-tree"]).is_err() {
+        return Ok(Vec::new());
+    }
+    let format = "--format=%H%x1f%aI%x1f%an%x1f%s";
+    git(dir, &["log", "--follow", format, "--", &file]).map(|out| parse_log(&out))
+}
+
+/// Path of the note (relative to the repository root) in each commit of its history, newest
+/// first, following renames.
+fn names_in_history(dir: &Path, file: &str) -> Result<Vec<(String, String)>, String> {
+    let out = git(dir, &["log", "--follow", "--name-only", "--format=%x1e%H", "--", file])?;
+    Ok(out
+        .split(RECORD_SEP)
+        .filter_map(|record| {
+            let mut lines = record.lines().map(str::trim).filter(|line| !line.is_empty());
+            Some((lines.next()?.to_string(), lines.next()?.to_string()))
+        })
+        .collect())
+}
+
+/// Contents of `path` at revision `rev` (a commit hash or ref), under the name the note had in
+/// that commit when it was renamed since.
+pub fn note_at_revision(path: &Path, rev: &str) -> Result<String, String> {
+    let valid_rev = !rev.is_empty()
+        && !rev.starts_with('-')
+        && rev.chars().all(|c| c.is_ascii_alphanumeric() || "~^/._-".contains(c));
+    if !valid_rev {
+        return Err(format!("Invalid revision: {}", rev));
+    }
+    let (dir, file) = split_note_path(path)?;
+    let hash = git(dir, &["rev-parse", "--verify", &format!("{}^{{commit}}", rev)])?;
+    let renamed = names_in_history(dir, &file)?
+        .into_iter()
+        .find(|(commit, _)| *commit == hash.trim())
+        .map(|(_, name)| name);
+    match renamed {
+        // Paths from `git log` are relative to the repository root.
+        Some(name) => git(dir, &["show", &format!("{}:{}", rev, name)]),
+        None => git(dir, &["show", &format!("{}:{}", rev, file)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_log_keeps_separators_in_message() {
+        let out = "abc\u{1f}2024-01-02T03:04:05+00:00\u{1f}Ana\u{1f}Fix: a\u{1f}b\n";
+        let commits = parse_log(out);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].hash, "abc");
+        assert_eq!(commits[0].author, "Ana");
+        assert_eq!(commits[0].message, "Fix: a\u{1f}b");
+    }
+
+    #[test]
+    fn rejects_option_like_revisions() {
+        let err = note_at_revision(Path::new("/tmp/a.md"), "--output=x").unwrap_err();
+        assert!(err.starts_with("Invalid revision"));
+    }
+
+    #[test]
+    fn revisions_follow_renames() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = crate::paths::canonicalize(dir.path()).unwrap();
+        let run = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=Test", "-c", "user.email=test@example.org"];
+            all.extend_from_slice(args);
+            git(&root, &all).unwrap()
+        };
+        run(&["init", "-q"]);
+        std::fs::create_dir(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/old.md"), "first").unwrap();
+        run(&["add", "-A"]);
+        run(&["commit", "-qm", "add"]);
+        let first = run(&["rev-parse", "HEAD"]);
+        run(&["mv", "notes/old.md", "notes/new.md"]);
+        run(&["commit", "-qm", "rename"]);
+        std::fs::write(root.join("notes/new.md"), "second").unwrap();
+        run(&["commit", "-qam", "edit"]);
+
+        let note = root.join("notes/new.md");
+        assert_eq!(note_history(&note).unwrap().len(), 3);
+        assert_eq!(note_at_revision(&note, first.trim()).unwrap(), "first");
+        assert_eq!(note_at_revision(&note, "HEAD").unwrap(), "second");
+    }
+}
@@ -0,0 +1,164 @@
+//! Pixel dimensions read from image headers (PNG, GIF, JPEG, WebP, BMP), so rendered `<img>`
+//! tags can carry `width`/`height` and the preview doesn't reflow as images load.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::export::local_image_path;
+use crate::obsidian_embed::RenderCache;
+
+/// Enough for every supported header; JPEG frame headers further in are read on demand.
+const HEADER_BYTES: usize = 32;
+
+fn be16(b: &[u8]) -> u32 {
+    u32::from(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le16(b: &[u8]) -> u32 {
+    u32::from(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le24(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], 0])
+}
+
+fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// Width and height of the image at `path`, or `None` for unknown or truncated formats.
+pub fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; HEADER_BYTES];
+    let len = file.read(&mut header).ok()?;
+    let h = &header[..len];
+    if h.starts_with(b"\x89PNG\r\n\x1a\n") && len >= 24 {
+        return Some((be32(&h[16..20]), be32(&h[20..24])));
+    }
+    if (h.starts_with(b"GIF87a") || h.starts_with(b"GIF89a")) && len >= 10 {
+        return Some((le16(&h[6..8]), le16(&h[8..10])));
+    }
+    if h.starts_with(b"BM") && len >= 26 {
+        // Top-down bitmaps store a negative height.
+        let height = i32::from_le_bytes([h[22], h[23], h[24], h[25]]).unsigned_abs();
+        return Some((le32(&h[18..22]), height));
+    }
+    if h.starts_with(b"RIFF") && h.get(8..12) == Some(b"WEBP") && len >= 30 {
+        return match &h[12..16] {
+            b"VP8 " => Some((le16(&h[26..28]) & 0x3fff, le16(&h[28..30]) & 0x3fff)),
+            b"VP8L" => {
+                let bits = le32(&h[21..25]);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(&h[24..27]) + 1, le24(&h[27..30]) + 1)),
+            _ => None,
+        };
+    }
+    if h.starts_with(&[0xff, 0xd8]) {
+        file.seek(SeekFrom::Start(2)).ok()?;
+        return jpeg_dimensions(&mut BufReader::new(file));
+    }
+    None
+}
+
+/// Walks JPEG segments (after SOI) to the first start-of-frame marker, reading only segment
+/// headers and seeking past segment bodies.
+fn jpeg_dimensions<R: Read + Seek>(reader: &mut R) -> Option<(u32, u32)> {
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte).ok()?;
+        if byte[0] != 0xff {
+            return None;
+        }
+        // Fill bytes may pad a marker.
+        while byte[0] == 0xff {
+            reader.read_exact(&mut byte).ok()?;
+        }
+        let marker = byte[0];
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).ok()?;
+        let is_frame = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_frame {
+            // Precision, then height and width.
+            let mut frame = [0u8; 5];
+            reader.read_exact(&mut frame).ok()?;
+            return Some((be16(&frame[3..5]), be16(&frame[1..3])));
+        }
+        let body = i64::from(be16(&length)).checked_sub(2)?;
+        reader.seek(SeekFrom::Current(body)).ok()?;
+    }
+}
+
+/// Adds `width`/`height` to every local `<img>` of `html` (sources relative to `base_dir`) that
+/// has neither, using dimensions memoized in `cache` by file mtime.
+pub fn add_image_dimensions(html: &str, base_dir: &Path, cache: &mut RenderCache) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(tag_start) = rest.find("<img ") {
+        let tag_len = rest[tag_start..].find('>').map(|i| i + 1).unwrap_or(rest.len() - tag_start);
+        let tag = &rest[tag_start..tag_start + tag_len];
+        out.push_str(&rest[..tag_start]);
+        let src = tag
+            .split_once("src=\"")
+            .and_then(|(_, value)| value.split_once('"'))
+            .map(|(src, _)| src);
+        let size = src
+            .filter(|_| !tag.contains(" width=") && !tag.contains(" height="))
+            .and_then(|src| local_image_path(src, base_dir))
+            .and_then(|path| cache.image_size(&path));
+        match size {
+            Some((width, height)) => {
+                let close = if tag.ends_with(" />") { tag.len() - 3 } else { tag.len() - 1 };
+                out.push_str(&tag[..close]);
+                out.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+                out.push_str(&tag[close..]);
+            }
+            None => out.push_str(tag),
+        }
+        rest = &rest[tag_start + tag_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_png_gif_and_jpeg_headers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend([0, 0, 1, 0x2c, 0, 0, 0, 0xc8, 8, 6, 0, 0, 0]);
+        std::fs::write(dir.path().join("a.png"), &png).unwrap();
+        std::fs::write(dir.path().join("b.gif"), b"GIF89a\x0a\x00\x14\x00\x00\x00").unwrap();
+        // SOI, an APP0 segment, then SOF0 with height 480 and width 640.
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xc0, 0, 11, 8, 0x01, 0xe0, 0x02, 0x80, 3,
+        ];
+        std::fs::write(dir.path().join("c.jpg"), jpeg).unwrap();
+        std::fs::write(dir.path().join("d.png"), b"not an image").unwrap();
+        assert_eq!(image_dimensions(&dir.path().join("a.png")), Some((300, 200)));
+        assert_eq!(image_dimensions(&dir.path().join("b.gif")), Some((10, 20)));
+        assert_eq!(image_dimensions(&dir.path().join("c.jpg")), Some((640, 480)));
+        assert_eq!(image_dimensions(&dir.path().join("d.png")), None);
+    }
+
+    #[test]
+    fn local_images_get_width_and_height() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("b.gif"), b"GIF89a\x0a\x00\x14\x00\x00\x00").unwrap();
+        let html = "<p><img src=\"b.gif\" alt=\"x\" /> <img src=\"https://x.org/a.png\" alt=\"\" /> \
+                    <img src=\"b.gif\" width=\"5\"></p>";
+        let mut cache = RenderCache::default();
+        let out = add_image_dimensions(html, dir.path(), &mut cache);
+        assert!(out.contains("<img src=\"b.gif\" alt=\"x\" width=\"10\" height=\"20\" />"), "{}", out);
+        assert!(out.contains("<img src=\"https://x.org/a.png\" alt=\"\" />"), "{}", out);
+        assert!(out.contains("<img src=\"b.gif\" width=\"5\">"), "{}", out);
+    }
+}
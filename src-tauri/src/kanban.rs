@@ -0,0 +1,76 @@
+//! Boards written by the Obsidian Kanban plugin: a `kanban-plugin` frontmatter key, one
+//! `## Lane` heading per column and a list item per card, followed by a `%% kanban:settings`
+//! comment holding the plugin's own settings.
+
+use crate::frontmatter::{parse_frontmatter, split_frontmatter};
+
+const SETTINGS_MARKER: &str = "%% kanban:settings";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct KanbanLane {
+    pub title: String,
+    /// Each card's markdown: its list item line (`- [ ] Task`) and indented continuation lines.
+    pub cards: Vec<String>,
+}
+
+pub fn is_kanban(md: &str) -> bool {
+    parse_frontmatter(md).contains_key("kanban-plugin")
+}
+
+/// The lanes of a board, in order. Text outside lanes and cards (the settings comment, `***`
+/// separators, the plugin's `**Complete**` markers) is dropped.
+pub fn parse_board(md: &str) -> Vec<KanbanLane> {
+    let body = split_frontmatter(md).map_or(md, |(_, body)| body);
+    let mut lanes: Vec<KanbanLane> = Vec::new();
+    for line in body.lines() {
+        if line.trim_start().starts_with(SETTINGS_MARKER) {
+            break;
+        }
+        if let Some(title) = line.strip_prefix("## ") {
+            lanes.push(KanbanLane {
+                title: title.trim().to_string(),
+                cards: Vec::new(),
+            });
+            continue;
+        }
+        let Some(lane) = lanes.last_mut() else {
+            continue;
+        };
+        if line.starts_with("- ") || line.starts_with("* ") {
+            lane.cards.push(line.to_string());
+        } else if line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+            if let Some(card) = lane.cards.last_mut() {
+                card.push('\n');
+                card.push_str(line);
+            }
+        }
+    }
+    lanes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_and_cards_from_plugin_markdown() {
+        let md = "---\nkanban-plugin: basic\n---\n\n## To do\n\n- [ ] Write [[Plan]]\n  due friday\n- [ ] Call\n\n\
+                  ## Done\n\n**Complete**\n- [x] Ship\n\n%% kanban:settings\n```\n{\"kanban-plugin\":\"basic\"}\n```\n%%\n";
+        assert!(is_kanban(md));
+        assert!(!is_kanban("## To do\n\n- [ ] Call"));
+        let lanes = parse_board(md);
+        assert_eq!(
+            lanes,
+            [
+                KanbanLane {
+                    title: "To do".into(),
+                    cards: vec!["- [ ] Write [[Plan]]\n  due friday".into(), "- [ ] Call".into()],
+                },
+                KanbanLane {
+                    title: "Done".into(),
+                    cards: vec!["- [x] Ship".into()],
+                },
+            ]
+        );
+    }
+}
@@ -5,36 +5,154 @@
 // Command implementations: app/commands. Watch service: app/watch.
 
 mod app;
+mod calendar;
+mod citations;
+mod code_meta;
+mod csv_table;
+mod diagrams;
+mod diff;
+mod epub;
+mod export;
+mod frontmatter;
+#[cfg(feature = "fulltext")]
+mod fulltext;
+mod git;
+mod image_size;
+mod kanban;
 mod markdown;
 mod obsidian_embed;
+mod pandoc;
+mod paths;
+mod properties;
+mod remote_images;
+mod search;
+mod tasks;
+mod template_vars;
+mod templates;
+mod text;
+mod thumbnails;
 mod wiki;
 
-pub use app::{InitialFile, InitialPath, TreeNode};
+pub use app::{InitialFile, InitialPath, NodeKind, TreeNode};
 
 use std::path::Path;
 
 use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
 
-use app::{get_initial_file, open_markdown_file, open_wiki_folder, spawn_watch_service, watch_paths, VaultState, WatchService};
+use app::{
+    add_block_id, apply_template, cancel_operation, check_links, complete_wikilink,
+    copy_note_as_html, copy_note_as_markdown, diff_note, export_epub, export_flattened_markdown,
+    export_site, export_via_pandoc, find_duplicate_notes, generate_moc, get_aliases, get_backlinks,
+    get_calendar_data, get_embedding_notes, get_file_metadata, get_initial_file,
+    get_note_at_revision, get_note_history, get_note_panel_data, get_notes_by_mtime,
+    get_outgoing_links, get_settings, get_theme, get_thumbnail, get_tree, handle_deep_link,
+    handle_window_event, initial_path_for, lint_note, list_saved_searches, load_workspace,
+    move_folder, open_in_editor, open_markdown_file, open_wiki_folder, prefetch_notes, preview_link,
+    print_note, query_frontmatter, rebuild_index, render_markdown_string, resolve_app_link,
+    reveal_in_file_manager, save_markdown_file, save_search, save_workspace, search_vault,
+    serve_vault, spawn_watch_service, start_search, stop_serving, suggest_link_targets, toggle_task,
+    update_settings, watch_paths, FulltextState, OperationRegistry, PreviewServer,
+    RenderGenerations, RenderQueue, SettingsState, VaultState, WatchService, WorkspaceState,
+};
 
 fn run_app(initial_file: Option<app::InitialPath>) {
     tauri::Builder::default()
         .manage(InitialFile::new(initial_file))
         .manage(VaultState::new())
         .manage(WatchService::new())
+        .manage(SettingsState::new())
+        .manage(OperationRegistry::new())
+        .manage(RenderGenerations::new())
+        .manage(WorkspaceState::new())
+        .manage(PreviewServer::new())
+        .manage(FulltextState::new())
+        .manage(RenderQueue::new())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .invoke_handler(tauri::generate_handler![
             get_initial_file,
             open_markdown_file,
             open_wiki_folder,
             watch_paths,
+            get_settings,
+            update_settings,
+            get_theme,
+            search_vault,
+            get_backlinks,
+            start_search,
+            cancel_operation,
+            reveal_in_file_manager,
+            open_in_editor,
+            copy_note_as_html,
+            print_note,
+            get_file_metadata,
+            get_note_history,
+            get_note_at_revision,
+            get_tree,
+            apply_template,
+            resolve_app_link,
+            toggle_task,
+            preview_link,
+            get_outgoing_links,
+            rebuild_index,
+            load_workspace,
+            save_workspace,
+            export_flattened_markdown,
+            copy_note_as_markdown,
+            export_via_pandoc,
+            export_epub,
+            serve_vault,
+            stop_serving,
+            move_folder,
+            find_duplicate_notes,
+            check_links,
+            get_thumbnail,
+            save_markdown_file,
+            get_notes_by_mtime,
+            get_calendar_data,
+            query_frontmatter,
+            get_aliases,
+            complete_wikilink,
+            add_block_id,
+            lint_note,
+            get_note_panel_data,
+            save_search,
+            list_saved_searches,
+            get_embedding_notes,
+            prefetch_notes,
+            render_markdown_string,
+            diff_note,
+            export_site,
+            suggest_link_targets,
+            generate_moc,
         ])
+        .on_window_event(handle_window_event)
         .setup(|app| {
             let handle = app.handle().clone();
+            if let Ok(config_dir) = handle.path().app_config_dir() {
+                app.state::<SettingsState>().load_from(config_dir.clone());
+                app.state::<WorkspaceState>().load_from(config_dir);
+            }
+
+            #[cfg(any(windows, target_os = "linux"))]
+            let _ = app.deep_link().register_all();
+            let is_link = |arg: &String| arg.starts_with("mdglasses://") || arg.starts_with("obsidian://");
+            if let Some(uri) = std::env::args().skip(1).find(is_link) {
+                handle_deep_link(&handle, &uri);
+            }
+            let handle_for_links = handle.clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    handle_deep_link(&handle_for_links, url.as_str());
+                }
+            });
             let watch_sender = spawn_watch_service(handle.clone());
             app.state::<WatchService>().set_sender(watch_sender);
+            app.state::<RenderQueue>().spawn_worker(handle.clone());
 
             let handle_for_closure = handle.clone();
             let _ = handle.run_on_main_thread(move || {
@@ -46,19 +164,26 @@ fn run_app(initial_file: Option<app::InitialPath>) {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(handle_run_event);
+}
+
+/// macOS delivers files opened via file associations as `Opened` events instead of argv.
+fn handle_run_event(app_handle: &tauri::AppHandle, event: tauri::RunEvent) {
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    if let tauri::RunEvent::Opened { urls } = event {
+        for path in urls.into_iter().filter_map(|url| url.to_file_path().ok()) {
+            app::handle_opened_path(app_handle, &path);
+        }
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    let _ = (app_handle, event);
 }
 
 fn parse_initial_file_from_args() -> Option<app::InitialPath> {
     let arg = std::env::args().skip(1).find(|argument| !argument.starts_with('-'))?;
-    let canonical_path = Path::new(&arg).canonicalize().ok()?;
-    let path_str = canonical_path.to_str()?.to_string();
-    let is_dir = canonical_path.is_dir();
-    Some(app::InitialPath {
-        path: path_str,
-        is_dir,
-    })
+    initial_path_for(Path::new(&arg))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -115,7 +240,7 @@ mod wiki_tests {
     #[test]
     fn build_tree_includes_md_files_and_subdirs() {
         let (_dir, root) = setup_temp_wiki();
-        let tree = wiki::build_tree(&root).unwrap();
+        let tree = wiki::build_tree_with(&root, &wiki::TreeOptions::default()).unwrap();
         let names: Vec<&str> = tree.iter().map(|n| n.name.as_str()).collect();
         assert!(names.contains(&"a.md"), "expected a.md in {:?}", names);
         assert!(names.contains(&"b.md"), "expected b.md in {:?}", names);
@@ -128,6 +253,181 @@ mod wiki_tests {
         assert!(sub_names.contains(&"c.md"), "expected c.md in sub {:?}", sub_names);
     }
 
+    #[test]
+    fn build_tree_counts_notes_in_folders() {
+        let (dir, root) = setup_temp_wiki();
+        fs::create_dir_all(dir.path().join("sub/deeper")).unwrap();
+        fs::write(dir.path().join("sub/deeper/d.md"), "# D").unwrap();
+        let options = wiki::TreeOptions {
+            show_all_files: true,
+            ..Default::default()
+        };
+        fs::write(dir.path().join("sub/pic.png"), [0u8]).unwrap();
+        let tree = wiki::build_tree_with(&root, &options).unwrap();
+        let sub = tree.iter().find(|n| n.name == "sub").unwrap();
+        assert_eq!(sub.note_count, 2, "c.md and deeper/d.md; attachments are not notes");
+        assert_eq!(sub.children[0].note_count, 1);
+    }
+
+    #[test]
+    fn build_tree_sorts_by_size_descending_with_dirs_first() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_str().unwrap().to_string();
+        fs::write(dir.path().join("small.md"), "a").unwrap();
+        fs::write(dir.path().join("big.md"), "a much longer note").unwrap();
+        fs::create_dir_all(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/tiny.md"), "").unwrap();
+        let options = wiki::TreeOptions {
+            sort_by: wiki::TreeSortKey::Size,
+            descending: true,
+            ..Default::default()
+        };
+        let tree = wiki::build_tree_with(&root, &options).unwrap();
+        let names: Vec<&str> = tree.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["sub", "big.md", "small.md"]);
+        assert_eq!(tree[1].size, 18);
+    }
+
+    #[test]
+    fn build_tree_filters_by_modification_age() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_str().unwrap().to_string();
+        fs::create_dir_all(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("fresh.md"), "a").unwrap();
+        fs::write(dir.path().join("old/stale.md"), "b").unwrap();
+        let ten_days_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(10 * 86_400);
+        fs::File::options()
+            .write(true)
+            .open(dir.path().join("old/stale.md"))
+            .unwrap()
+            .set_modified(ten_days_ago)
+            .unwrap();
+        let options = wiki::TreeOptions {
+            modified_within_days: Some(7),
+            ..Default::default()
+        };
+        let tree = wiki::build_tree_with(&root, &options).unwrap();
+        let names: Vec<&str> = tree.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["fresh.md"]);
+    }
+
+    #[test]
+    fn build_tree_lists_attachments_only_when_enabled() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_str().unwrap().to_string();
+        fs::write(dir.path().join("note.md"), "# Note").unwrap();
+        fs::create_dir_all(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("assets/pic.PNG"), [0u8]).unwrap();
+        fs::write(dir.path().join("paper.pdf"), [0u8]).unwrap();
+
+        let default_tree = wiki::build_tree_with(&root, &wiki::TreeOptions::default()).unwrap();
+        assert_eq!(default_tree.len(), 1, "only the note by default");
+
+        let options = wiki::TreeOptions {
+            show_all_files: true,
+            ..Default::default()
+        };
+        let tree = wiki::build_tree_with(&root, &options).unwrap();
+        let kinds: Vec<(&str, crate::NodeKind)> = tree.iter().map(|n| (n.name.as_str(), n.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("assets", crate::NodeKind::Dir),
+                ("note.md", crate::NodeKind::Note),
+                ("paper.pdf", crate::NodeKind::Pdf),
+            ]
+        );
+        assert_eq!(tree[0].children[0].kind, crate::NodeKind::Image);
+    }
+
+    #[test]
+    fn build_tree_keeps_empty_dirs_when_enabled() {
+        let (dir, root) = setup_temp_wiki();
+        fs::create_dir_all(dir.path().join("empty/nested")).unwrap();
+        let default_tree = wiki::build_tree_with(&root, &wiki::TreeOptions::default()).unwrap();
+        let default_names: Vec<String> = default_tree.into_iter().map(|n| n.name).collect();
+        assert!(!default_names.contains(&"empty".to_string()));
+
+        let options = wiki::TreeOptions {
+            show_empty_dirs: true,
+            ..Default::default()
+        };
+        let tree = wiki::build_tree_with(&root, &options).unwrap();
+        let empty = tree.iter().find(|n| n.name == "empty").expect("empty dir kept");
+        assert_eq!(empty.kind, crate::NodeKind::Dir);
+        assert_eq!(empty.children.len(), 1, "nested empty dir kept too");
+    }
+
+    #[test]
+    fn build_tree_leaves_out_excluded_folders() {
+        let (dir, root) = setup_temp_wiki();
+        fs::create_dir_all(dir.path().join("Templates")).unwrap();
+        fs::write(dir.path().join("Templates/daily.md"), "# Daily").unwrap();
+        let options = wiki::TreeOptions {
+            excluded: vec![dir.path().join("Templates"), dir.path().join("sub")],
+            ..Default::default()
+        };
+        let names: Vec<String> = wiki::build_tree_with(&root, &options)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.name)
+            .collect();
+        assert_eq!(names, ["a.md", "b.md", "index.md"]);
+    }
+
+    #[test]
+    fn build_tree_links_folder_notes() {
+        let (dir, root) = setup_temp_wiki();
+        fs::write(dir.path().join("sub/sub.md"), "# Sub").unwrap();
+        fs::create_dir_all(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs/index.md"), "# Docs").unwrap();
+        let tree = wiki::build_tree_with(&root, &wiki::TreeOptions::default()).unwrap();
+        let folder_note = |name: &str| tree.iter().find(|n| n.name == name).unwrap().folder_note.clone();
+        assert!(folder_note("sub").unwrap().ends_with("sub.md"));
+        assert!(folder_note("docs").unwrap().ends_with("index.md"));
+        assert_eq!(tree.iter().find(|n| n.name == "a.md").unwrap().folder_note, None);
+    }
+
+    #[test]
+    fn initial_note_follows_priority_then_searches_subfolders() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.md"), "# A").unwrap();
+        fs::write(root.join("Readme.md"), "# Readme").unwrap();
+        fs::write(root.join("Home.md"), "# Home").unwrap();
+        let priority = ["home.md".to_string(), "readme.md".to_string()];
+        let path = wiki::initial_note_path(root, &priority, &[]).unwrap().unwrap();
+        assert!(path.ends_with("Home.md"), "{:?}", path);
+        let (path, _) = wiki::initial_note(root.to_str().unwrap()).unwrap();
+        assert!(path.unwrap().ends_with("Readme.md"), "README.md outranks the first note by name");
+
+        let nested = TempDir::new().unwrap();
+        fs::create_dir_all(nested.path().join("b/deep")).unwrap();
+        fs::create_dir_all(nested.path().join("c")).unwrap();
+        fs::create_dir_all(nested.path().join(".hidden")).unwrap();
+        fs::write(nested.path().join(".hidden/index.md"), "").unwrap();
+        fs::write(nested.path().join("b/deep/index.md"), "").unwrap();
+        fs::write(nested.path().join("c/z.md"), "").unwrap();
+        let path = wiki::initial_note_path(nested.path(), &priority, &[]).unwrap().unwrap();
+        assert!(path.ends_with("c/z.md"), "shallowest folder wins: {:?}", path);
+        let excluded = [nested.path().join("c")];
+        let path = wiki::initial_note_path(nested.path(), &priority, &excluded).unwrap().unwrap();
+        assert!(path.ends_with("b/deep/index.md"), "excluded folders are skipped: {:?}", path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn initial_note_search_survives_symlink_loops() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/loop")).unwrap();
+        assert_eq!(wiki::initial_note_path(root, &[], &[]).unwrap(), None);
+        fs::write(root.join("a/note.md"), "").unwrap();
+        let path = wiki::initial_note_path(root, &[], &[]).unwrap().unwrap();
+        assert!(path.ends_with("a/note.md"), "{:?}", path);
+    }
+
     #[test]
     fn initial_note_empty_dir_returns_none() {
         let dir = TempDir::new().unwrap();
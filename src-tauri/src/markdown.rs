@@ -1,16 +1,385 @@
-use comrak::{markdown_to_html, Options};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use comrak::nodes::NodeValue;
+use comrak::{markdown_to_html, parse_document, Arena, Options};
+
+use crate::code_meta::apply_code_meta;
 
 /// Renders markdown to HTML with safe options (no raw HTML / unsafe content).
+/// Headings get GitHub-style anchor ids so `#heading` links can scroll to them.
 pub fn render_markdown_safe(md: &str) -> String {
+    render_markdown(md, &RenderOptions::default())
+}
+
+/// Renderer switches that come from settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Keep raw HTML, run through `sanitize_html`, instead of stripping it.
+    pub allow_html: bool,
+    /// Render every newline as a line break, as Obsidian does by default.
+    pub hardbreaks: bool,
+    /// Embedded notes larger than this many bytes are linked instead of inlined.
+    pub max_embed_bytes: Option<u64>,
+    /// Wikilinks to a basename shared by several notes carry every candidate.
+    pub report_ambiguous: bool,
+    /// BibTeX or CSL JSON file that `[@key]` citations resolve against.
+    pub bibliography: Option<PathBuf>,
+    /// Exports only: embeds of unpublished notes (see `is_published`) become this markdown,
+    /// with `{name}` replaced by the note name. Such renders bypass the render cache.
+    pub unpublished_embed: Option<String>,
+    /// Exports only: embeds of notes outside this set (canonical paths) become links to them.
+    /// Such renders bypass the render cache.
+    pub embed_scope: Option<BTreeSet<PathBuf>>,
+    /// Append a "Linked mentions" section listing the notes that link to the rendered note.
+    pub backlinks_footer: bool,
+    /// Replace remote images with click-to-load placeholders.
+    pub block_remote_images: bool,
+    /// Show the note's frontmatter as a properties table instead of raw YAML; embedded notes
+    /// drop theirs.
+    pub properties_table: bool,
+    /// Texts replacing `EmbedPlaceholder::default_text`; `{name}` is the embed target, `{size}`
+    /// the size of a note too large to embed.
+    pub embed_placeholders: BTreeMap<EmbedPlaceholder, String>,
+}
+
+/// Stand-ins for embeds that cannot be shown. The key (`cycle`, `depth_limit`, ...) names the
+/// placeholder in settings and, with dashes, in its CSS class (`embed-placeholder-depth-limit`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EmbedPlaceholder {
+    /// The note embeds itself, directly or through other embeds.
+    Cycle,
+    /// Embeds nested deeper than the render allows.
+    DepthLimit,
+    NotFound,
+    /// The target's basename matches several notes.
+    Ambiguous,
+    /// A folder embed (`![[Projects/]]`) with no notes directly inside.
+    EmptyFolder,
+    /// A note over `max_embed_bytes`; `{name}` links to it.
+    TooLarge,
+    /// Label before the path and OS error of an embed that could not be read.
+    EmbedFailed,
+    /// The button re-rendering after a failed embed.
+    Retry,
+    /// The rendered path itself could not be resolved.
+    InvalidPath,
+}
+
+impl EmbedPlaceholder {
+    pub const ALL: [EmbedPlaceholder; 9] = [
+        EmbedPlaceholder::Cycle,
+        EmbedPlaceholder::DepthLimit,
+        EmbedPlaceholder::NotFound,
+        EmbedPlaceholder::Ambiguous,
+        EmbedPlaceholder::EmptyFolder,
+        EmbedPlaceholder::TooLarge,
+        EmbedPlaceholder::EmbedFailed,
+        EmbedPlaceholder::Retry,
+        EmbedPlaceholder::InvalidPath,
+    ];
+
+    /// The kind keyed `key`, with dashes or underscores; `None` for unknown keys.
+    pub fn from_key(key: &str) -> Option<EmbedPlaceholder> {
+        let key = key.replace('-', "_");
+        EmbedPlaceholder::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            EmbedPlaceholder::Cycle => "cycle",
+            EmbedPlaceholder::DepthLimit => "depth_limit",
+            EmbedPlaceholder::NotFound => "not_found",
+            EmbedPlaceholder::Ambiguous => "ambiguous",
+            EmbedPlaceholder::EmptyFolder => "empty_folder",
+            EmbedPlaceholder::TooLarge => "too_large",
+            EmbedPlaceholder::EmbedFailed => "embed_failed",
+            EmbedPlaceholder::Retry => "retry",
+            EmbedPlaceholder::InvalidPath => "invalid_path",
+        }
+    }
+
+    pub fn default_text(self) -> &'static str {
+        match self {
+            EmbedPlaceholder::Cycle => "[Embed: {name} (cycle)]",
+            EmbedPlaceholder::DepthLimit => "[Embed: {name} (depth limit)]",
+            EmbedPlaceholder::NotFound => "[Embed: {name} (not found)]",
+            EmbedPlaceholder::Ambiguous => "[Embed: {name} (ambiguous)]",
+            EmbedPlaceholder::EmptyFolder => "[Embed: {name} (empty folder)]",
+            EmbedPlaceholder::TooLarge => "[Embed: {name} (too large, {size} — click to open)]",
+            EmbedPlaceholder::EmbedFailed => "Embed failed:",
+            EmbedPlaceholder::Retry => "Retry",
+            EmbedPlaceholder::InvalidPath => "[Embed: invalid path]",
+        }
+    }
+}
+
+/// Markdown to HTML under `render`; `:smile:`-style shortcodes become emoji (not inside code).
+pub fn render_markdown(md: &str, render: &RenderOptions) -> String {
+    let mut options = Options::default();
+    options.render.unsafe_ = render.allow_html;
+    options.render.hardbreaks = render.hardbreaks;
+    options.extension.header_ids = Some(String::new());
+    options.extension.shortcodes = true;
+    options.extension.tasklist = true;
+    options.extension.footnotes = true;
+    options.render.full_info_string = true;
+    let html = apply_code_meta(&markdown_to_html(md, &options));
+    if render.allow_html {
+        sanitize_html(&html)
+    } else {
+        html
+    }
+}
+
+/// Allowlist sanitizer for notes with raw HTML: ammonia's defaults plus disclosure, keyboard and
+/// media tags; scripts, event handlers and unknown URL schemes are removed. `id` and `class` stay
+/// for heading anchors and code highlighting, `app:` for wikilink hrefs, footnote markup, and task
+/// checkboxes (which `apply_task_offsets` wires up after sanitizing).
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::Builder::default()
+        .add_tags(["details", "summary", "kbd", "mark", "video", "audio", "source", "input", "section"])
+        .add_generic_attributes(["id", "class"])
+        .add_tag_attributes("details", ["open"])
+        .add_tag_attributes("pre", ["data-highlight-lines"])
+        .add_tag_attributes("video", ["src", "controls", "poster", "width", "height", "loop", "muted"])
+        .add_tag_attributes("audio", ["src", "controls", "loop", "muted"])
+        .add_tag_attributes("source", ["src", "type"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("section", ["data-footnotes"])
+        .add_tag_attributes(
+            "a",
+            ["data-footnote-ref", "data-footnote-backref", "data-footnote-backref-idx", "aria-label"],
+        )
+        .add_url_schemes(["app"])
+        .link_rel(None)
+        .clean(html)
+        .to_string()
+}
+
+/// One top-level block: its 1-based inclusive source line range and its index
+/// among the top-level elements of the rendered HTML.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct BlockMapping {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub element_index: usize,
+}
+
+/// Block-level mapping between source lines and rendered elements (via comrak sourcepos).
+/// Raw HTML blocks are skipped because safe rendering emits them as comments, not elements.
+pub fn block_source_map(md: &str) -> Vec<BlockMapping> {
+    let arena = Arena::new();
     let mut options = Options::default();
     options.render.unsafe_ = false;
-    markdown_to_html(md, &options)
+    let root = parse_document(&arena, md, &options);
+    let mut out = Vec::new();
+    for node in root.children() {
+        let data = node.data.borrow();
+        if matches!(data.value, NodeValue::HtmlBlock(_)) {
+            continue;
+        }
+        out.push(BlockMapping {
+            start_line: data.sourcepos.start.line,
+            end_line: data.sourcepos.end.line,
+            element_index: out.len(),
+        });
+    }
+    out
+}
+
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Adds `data-source-line="N"` to each top-level element of `html` that `map` covers, N being
+/// the first source line of its block, so an editor and the preview can scroll each other.
+pub fn annotate_source_lines(html: &str, map: &[BlockMapping]) -> String {
+    let lines: HashMap<usize, usize> = map.iter().map(|b| (b.element_index, b.start_line)).collect();
+    let mut out = String::with_capacity(html.len() + map.len() * 24);
+    let (mut depth, mut element) = (0usize, 0usize);
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(rest.len(), |i| i + 3)
+        } else {
+            tag_end(rest)
+        };
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        let name_len = tag[1..].find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(tag.len() - 1);
+        let name = &tag[1..1 + name_len];
+        if tag.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            out.push_str(tag);
+            continue;
+        }
+        match lines.get(&element) {
+            Some(line) if depth == 0 && !name.is_empty() => {
+                out.push_str(&tag[..1 + name_len]);
+                out.push_str(&format!(" data-source-line=\"{}\"", line));
+                out.push_str(&tag[1 + name_len..]);
+            }
+            _ => out.push_str(tag),
+        }
+        if name.is_empty() {
+            continue;
+        }
+        if depth == 0 {
+            element += 1;
+        }
+        if !VOID_TAGS.contains(&name.to_ascii_lowercase().as_str()) && !tag.ends_with("/>") {
+            depth += 1;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the tag at the start of `html` (through its `>`, skipping quoted attribute values).
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+const READING_WORDS_PER_MINUTE: usize = 200;
+
+/// Counts over the note's prose; code blocks, raw HTML and frontmatter are excluded.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteStats {
+    pub words: usize,
+    /// Non-whitespace characters.
+    pub characters: usize,
+    pub headings: usize,
+    /// At 200 words per minute, rounded up; 0 for an empty note.
+    pub reading_minutes: usize,
+}
+
+pub fn note_stats(md: &str) -> NoteStats {
+    let arena = Arena::new();
+    let mut options = Options::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let root = parse_document(&arena, md, &options);
+    let mut text = String::new();
+    let mut headings = 0;
+    for node in root.descendants() {
+        match &node.data.borrow().value {
+            NodeValue::Text(t) => text.push_str(t),
+            NodeValue::Code(code) => text.push_str(&code.literal),
+            NodeValue::Heading(_) => {
+                headings += 1;
+                text.push(' ');
+            }
+            NodeValue::SoftBreak
+            | NodeValue::LineBreak
+            | NodeValue::Paragraph
+            | NodeValue::Item(_)
+            | NodeValue::TableCell => text.push(' '),
+            _ => {}
+        }
+    }
+    let words = text.split_whitespace().count();
+    NoteStats {
+        words,
+        characters: text.chars().filter(|c| !c.is_whitespace()).count(),
+        headings,
+        reading_minutes: words.div_ceil(READING_WORDS_PER_MINUTE),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn headings_get_anchor_ids() {
+        let html = render_markdown_safe("# Hello World\n\n## Hello World");
+        assert!(html.contains("id=\"hello-world\""), "expected anchor in {}", html);
+        assert!(html.contains("id=\"hello-world-1\""), "expected deduped anchor in {}", html);
+    }
+
+    #[test]
+    fn shortcodes_become_emoji_outside_code() {
+        let html = render_markdown_safe("Nice :smile: `:smile:` :not-an-emoji:");
+        assert!(html.contains("Nice \u{1f604}"), "expected emoji in {}", html);
+        assert!(html.contains("<code>:smile:</code>"), "code untouched in {}", html);
+        assert!(html.contains(":not-an-emoji:"), "unknown shortcodes kept in {}", html);
+    }
+
+    #[test]
+    fn hardbreaks_turn_newlines_into_breaks() {
+        assert!(!render_markdown_safe("one\ntwo").contains("<br"));
+        let render = RenderOptions {
+            hardbreaks: true,
+            ..Default::default()
+        };
+        assert!(render_markdown("one\ntwo", &render).contains("one<br />\ntwo"));
+    }
+
+    #[test]
+    fn allow_html_keeps_allowlisted_tags_only() {
+        let md = "<details><summary>More</summary>\n\nPress <kbd>Ctrl</kbd></details>\n\n<script>alert(1)</script>\n\n<img src=\"x.png\" onerror=\"alert(1)\">";
+        assert!(!render_markdown_safe(md).contains("<details>"), "raw HTML stripped by default");
+        let render = RenderOptions {
+            allow_html: true,
+            ..Default::default()
+        };
+        let html = render_markdown(md, &render);
+        assert!(html.contains("<details>") && html.contains("<kbd>Ctrl</kbd>"), "{}", html);
+        assert!(!html.contains("<script") && !html.contains("onerror"), "{}", html);
+    }
+
+    #[test]
+    fn sanitized_footnotes_keep_their_markup() {
+        let render = RenderOptions {
+            allow_html: true,
+            ..Default::default()
+        };
+        let html = render_markdown("Text[^1]\n\n[^1]: Note\n", &render);
+        assert!(html.contains("<section class=\"footnotes\" data-footnotes=\"\">"), "{}", html);
+        assert!(html.contains("id=\"fnref-1\" data-footnote-ref=\"\">1</a>"), "{}", html);
+        let backref = "data-footnote-backref-idx=\"1\" aria-label=\"Back to reference 1\">";
+        assert!(html.contains(backref), "{}", html);
+    }
+
+    #[test]
+    fn sanitized_tasks_keep_their_checkboxes() {
+        let render = RenderOptions {
+            allow_html: true,
+            ..Default::default()
+        };
+        let md = "- [ ] todo\n- [x] done\n";
+        let html = crate::tasks::apply_task_offsets(&render_markdown(&crate::tasks::mark_tasks(md), &render));
+        assert!(html.contains("<input data-task-offset=\"2\" type=\"checkbox\">"), "{}", html);
+        assert!(html.contains("<input data-task-offset=\"13\" type=\"checkbox\" checked=\"\">"), "{}", html);
+    }
+
+    #[test]
+    fn note_stats_skip_code_blocks_and_frontmatter() {
+        let md = "---\ntitle: x y z\n---\n# Title\n\nOne *two* `three`\n\n```\nnot counted here\n```\n\n- four\n";
+        let stats = note_stats(md);
+        assert_eq!(stats.words, 5, "{:?}", stats);
+        assert_eq!(stats.headings, 1);
+        assert_eq!(stats.characters, "Titleonetwothreefour".len());
+        assert_eq!(stats.reading_minutes, 1);
+    }
+
+    #[test]
+    fn note_stats_empty_note() {
+        assert_eq!(note_stats("").reading_minutes, 0);
+    }
+
     #[test]
     fn heading_becomes_h1() {
         let html = render_markdown_safe("# Hi");
@@ -47,6 +416,34 @@ mod tests {
         assert!(html.contains("<code>"), "expected code in {}", html);
     }
 
+    #[test]
+    fn block_source_map_tracks_lines_and_indices() {
+        let map = block_source_map("# Title\n\nFirst para\nstill first\n\n- a\n- b\n");
+        assert_eq!(map.len(), 3, "{:?}", map);
+        assert_eq!((map[0].start_line, map[0].end_line, map[0].element_index), (1, 1, 0));
+        assert_eq!((map[1].start_line, map[1].end_line, map[1].element_index), (3, 4, 1));
+        assert_eq!((map[2].start_line, map[2].element_index), (6, 2));
+    }
+
+    #[test]
+    fn block_source_map_skips_raw_html_blocks() {
+        let map = block_source_map("<div>x</div>\n\npara");
+        assert_eq!(map.len(), 1, "{:?}", map);
+        assert_eq!(map[0].start_line, 3);
+        assert_eq!(map[0].element_index, 0);
+    }
+
+    #[test]
+    fn source_lines_mark_top_level_elements() {
+        let md = "# Title\n\n---\n\n<div>x</div>\n\n- a\n- b\n\n![i](x.png \"a>b\")";
+        let html = annotate_source_lines(&render_markdown_safe(md), &block_source_map(md));
+        assert!(html.contains("<h1 data-source-line=\"1\""), "{}", html);
+        assert!(html.contains("<hr data-source-line=\"3\" />"), "{}", html);
+        assert!(html.contains("<ul data-source-line=\"7\">\n<li>a</li>"), "{}", html);
+        assert!(html.contains("<p data-source-line=\"10\"><img"), "{}", html);
+        assert_eq!(html.matches("data-source-line").count(), 4);
+    }
+
     #[test]
     fn unsafe_html_escaped() {
         let html = render_markdown_safe("<script>alert(1)</script>");
@@ -0,0 +1,84 @@
+//! Backlinks: notes whose `[[...]]` / `![[...]]` links resolve to a given note.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+use super::index::VaultIndex;
+use super::parse::{compute_skip_ranges, find_obsidian_spans_inner, parse_wikilink_inner};
+use super::resolve::{resolve_target, ResolveResult};
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Backlink {
+    pub source: String,
+    /// 1-based line of the link in `source`.
+    pub line: usize,
+    /// The full source line; commands truncate it before sending.
+    pub snippet: String,
+    pub is_embed: bool,
+}
+
+/// Links into and out of a file, counted as `find_backlinks` counts them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkCounts {
+    pub inbound: usize,
+    pub outbound: usize,
+}
+
+/// Link counts of every file linked to or from a note of `index`, from one pass over the notes.
+/// Links a note makes to itself are left out.
+pub fn link_counts(index: &VaultIndex, vault_root: &Path) -> HashMap<PathBuf, LinkCounts> {
+    let mut counts: HashMap<PathBuf, LinkCounts> = HashMap::new();
+    for note in index.notes() {
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let skip = compute_skip_ranges(&content);
+        for (_, _, _, raw_inner) in find_obsidian_spans_inner(&content, &skip) {
+            let parsed = parse_wikilink_inner(&raw_inner);
+            let ResolveResult::Resolved(target) = resolve_target(&parsed, index, vault_root) else {
+                continue;
+            };
+            if target == note {
+                continue;
+            }
+            counts.entry(target).or_default().inbound += 1;
+            counts.entry(note.clone()).or_default().outbound += 1;
+        }
+    }
+    counts
+}
+
+pub fn find_backlinks(target: &Path, index: &VaultIndex, vault_root: &Path) -> Vec<Backlink> {
+    let Ok(target) = paths::canonicalize(target) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for note in index.notes() {
+        if note == target {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let skip = compute_skip_ranges(&content);
+        for (is_embed, start, _, raw_inner) in find_obsidian_spans_inner(&content, &skip) {
+            let parsed = parse_wikilink_inner(&raw_inner);
+            let resolved = resolve_target(&parsed, index, vault_root);
+            if !matches!(&resolved, ResolveResult::Resolved(p) if *p == target) {
+                continue;
+            }
+            let line_start = content[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = content[start..].find('\n').map(|i| start + i).unwrap_or(content.len());
+            out.push(Backlink {
+                source: note.to_string_lossy().into_owned(),
+                line: content[..start].matches('\n').count() + 1,
+                snippet: content[line_start..line_end].trim().to_string(),
+                is_embed,
+            });
+        }
+    }
+    out
+}
@@ -0,0 +1,69 @@
+//! New `^id` block markers, so a block can be referenced as `[[Note#^id]]` from the viewer.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use super::parse::{compute_skip_ranges, in_skip_range, scan_block_ids, split_block_id};
+
+const ID_LEN: usize = 6;
+
+/// Whether `line` starts a block of its own (list item, heading, quote, fence), rather than
+/// continuing a paragraph.
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let bullet = trimmed.starts_with(['-', '*', '+']) && trimmed[1..].starts_with([' ', '\t']);
+    let numbered = trimmed
+        .split_once(". ")
+        .is_some_and(|(n, _)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    bullet || numbered || trimmed.starts_with(['#', '>', '`', '~'])
+}
+
+/// A random lowercase alphanumeric id not in `taken`.
+fn fresh_id(taken: &[String]) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let state = RandomState::new();
+    (0u64..)
+        .map(|attempt| {
+            let mut n = state.hash_one(attempt);
+            (0..ID_LEN)
+                .map(|_| {
+                    let c = ALPHABET[(n % ALPHABET.len() as u64) as usize] as char;
+                    n /= ALPHABET.len() as u64;
+                    c
+                })
+                .collect::<String>()
+        })
+        .find(|id| !taken.contains(id))
+        .unwrap_or_default()
+}
+
+/// `md` with a `^id` marker ending the block at 1-based `line` (the last line of its paragraph
+/// when `line` is inside one), and the id. A block that already has a marker keeps it.
+pub fn add_block_id(md: &str, line: usize) -> Result<(String, String), String> {
+    let lines: Vec<&str> = md.split('\n').collect();
+    let index = line.checked_sub(1).filter(|&i| i < lines.len()).ok_or("Line out of range")?;
+    let offset_of = |i: usize| lines[..i].iter().map(|l| l.len() + 1).sum::<usize>();
+    let skip = compute_skip_ranges(md);
+    let (start, len) = (offset_of(index), lines[index].len());
+    let in_code = in_skip_range(start, &skip) && in_skip_range(start + len.saturating_sub(1), &skip);
+    if lines[index].trim().is_empty() || in_code {
+        return Err(format!("Line {} is not a text block", line));
+    }
+    let mut end = index;
+    if !lines[index].trim_start().starts_with('#') {
+        while lines.get(end + 1).is_some_and(|next| !next.trim().is_empty() && !starts_block(next)) {
+            end += 1;
+        }
+    }
+    let target = lines[end].trim_end_matches('\r');
+    if let Some((_, id)) = split_block_id(target) {
+        return Ok((md.to_string(), id.to_string()));
+    }
+    let taken: Vec<String> = scan_block_ids(md).into_iter().map(|(id, _)| id).collect();
+    let id = fresh_id(&taken);
+    let line_start = offset_of(end);
+    let insert_at = line_start + target.trim_end().len();
+    let mut out = md.to_string();
+    out.insert_str(insert_at, &format!(" ^{}", id));
+    Ok((out, id))
+}
@@ -1,11 +1,18 @@
-//! Render cache: LRU by entry count and size; mtime-based invalidation.
+//! Render cache: LRU by entry count and size; mtime-based invalidation, plus eviction of the
+//! entries a changed file went into when the watcher reports it.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use crate::citations::Bibliography;
+
 pub(crate) const MAX_CACHE_ENTRIES: usize = 100;
 pub(crate) const MAX_CACHE_SIZE_BYTES: usize = 50 * 1024 * 1024;
+/// Rendered diagrams kept; the whole set is dropped when it fills up.
+const MAX_CACHED_DIAGRAMS: usize = 200;
+/// Image sizes kept; dropped wholesale like diagrams.
+const MAX_CACHED_IMAGE_SIZES: usize = 2000;
 
 #[derive(Clone)]
 pub struct CachedEntry {
@@ -13,26 +20,23 @@ pub struct CachedEntry {
     pub html: String,
     pub size_bytes: usize,
     pub last_accessed: SystemTime,
+    /// Other files rendered into the entry (embedded notes and assets), canonical.
+    pub embeds: HashSet<PathBuf>,
 }
 
+#[derive(Default)]
 pub struct RenderCache {
     entries: HashMap<PathBuf, CachedEntry>,
     access_order: Vec<PathBuf>,
     current_size_bytes: usize,
     hits: usize,
     misses: usize,
-}
-
-impl Default for RenderCache {
-    fn default() -> Self {
-        Self {
-            entries: HashMap::new(),
-            access_order: Vec::new(),
-            current_size_bytes: 0,
-            hits: 0,
-            misses: 0,
-        }
-    }
+    /// Diagram SVG keyed by a hash of the diagram kind and source.
+    diagrams: HashMap<u64, String>,
+    /// Image dimensions by path, valid while the file's mtime matches.
+    image_sizes: HashMap<PathBuf, (SystemTime, Option<(u32, u32)>)>,
+    /// The parsed bibliography, valid while its file's mtime matches.
+    bibliography: Option<(PathBuf, SystemTime, Bibliography)>,
 }
 
 impl RenderCache {
@@ -53,7 +57,19 @@ impl RenderCache {
         None
     }
 
+    #[allow(dead_code)]
     pub fn insert(&mut self, path: PathBuf, mtime: SystemTime, html: String) {
+        self.insert_with_embeds(path, mtime, html, HashSet::new());
+    }
+
+    /// `insert` for HTML that also renders `embeds`, so a change to any of them evicts it.
+    pub fn insert_with_embeds(
+        &mut self,
+        path: PathBuf,
+        mtime: SystemTime,
+        html: String,
+        embeds: HashSet<PathBuf>,
+    ) {
         let size_bytes = html.len();
         if let Some(old_entry) = self.entries.remove(&path) {
             self.current_size_bytes -= old_entry.size_bytes;
@@ -71,12 +87,73 @@ impl RenderCache {
             html: html.clone(),
             size_bytes,
             last_accessed: now,
+            embeds,
         };
         self.current_size_bytes += size_bytes;
         self.entries.insert(path.clone(), entry);
         self.access_order.push(path);
     }
 
+    /// Drops the entries of `changed` files and of every note embedding one of them; returns
+    /// how many were dropped. Paths are compared as given, so pass canonical ones.
+    pub fn evict_changed(&mut self, changed: &[PathBuf]) -> usize {
+        let stale: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|(path, entry)| changed.iter().any(|c| c == *path || entry.embeds.contains(c)))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &stale {
+            if let Some(entry) = self.entries.remove(path) {
+                self.current_size_bytes -= entry.size_bytes;
+                self.remove_from_access_order(path);
+            }
+        }
+        stale.len()
+    }
+
+    pub fn diagram(&self, key: u64) -> Option<String> {
+        self.diagrams.get(&key).cloned()
+    }
+
+    pub fn insert_diagram(&mut self, key: u64, svg: String) {
+        if self.diagrams.len() >= MAX_CACHED_DIAGRAMS {
+            self.diagrams.clear();
+        }
+        self.diagrams.insert(key, svg);
+    }
+
+    /// Width and height of the image at `path`, read from its header unless the file is unchanged
+    /// since the last call.
+    pub fn image_size(&mut self, path: &Path) -> Option<(u32, u32)> {
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        if let Some((cached_mtime, size)) = self.image_sizes.get(path) {
+            if *cached_mtime == mtime {
+                return *size;
+            }
+        }
+        let size = crate::image_size::image_dimensions(path);
+        if self.image_sizes.len() >= MAX_CACHED_IMAGE_SIZES {
+            self.image_sizes.clear();
+        }
+        self.image_sizes.insert(path.to_path_buf(), (mtime, size));
+        size
+    }
+
+    /// The bibliography at `path`, parsed again only when the file changed since the last call.
+    pub fn bibliography(&mut self, path: &Path) -> Result<&Bibliography, String> {
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).map_err(|e| e.to_string())?;
+        let fresh = self
+            .bibliography
+            .as_ref()
+            .is_some_and(|(cached, cached_mtime, _)| cached == path && *cached_mtime == mtime);
+        if !fresh {
+            self.bibliography = Some((path.to_path_buf(), mtime, Bibliography::load(path)?));
+        }
+        let (_, _, bibliography) = self.bibliography.as_ref().ok_or("No bibliography")?;
+        Ok(bibliography)
+    }
+
     fn update_access_order(&mut self, path: &Path) {
         self.access_order.retain(|p| p != path);
         self.access_order.push(path.to_path_buf());
@@ -108,12 +185,14 @@ impl RenderCache {
         )
     }
 
-    #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.entries.clear();
         self.access_order.clear();
         self.current_size_bytes = 0;
         self.hits = 0;
         self.misses = 0;
+        self.diagrams.clear();
+        self.image_sizes.clear();
+        self.bibliography = None;
     }
 }
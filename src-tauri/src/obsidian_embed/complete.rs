@@ -0,0 +1,156 @@
+//! `[[` autocompletion: notes and aliases matching what was typed so far, best match first, and
+//! after `[[Note#` the headings of that note.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::paths::slash_path;
+
+use super::index::{normalize_rel_key, VaultIndex};
+use super::parse::{parse_wikilink_inner, NoteHeading};
+use super::query::note_aliases;
+use super::resolve::{resolve_target, ResolveResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LinkCompletion {
+    /// Text to put between `[[` and `]]`: the basename, or the vault-relative path (without
+    /// `.md`) when several notes share it; `target|alias` for alias matches.
+    pub link: String,
+    pub name: String,
+    /// Vault-relative path with forward slashes.
+    pub rel_path: String,
+    pub path: PathBuf,
+    /// The alias that matched, for alias completions.
+    pub alias: Option<String>,
+    /// The heading, for `Note#Heading` completions.
+    pub heading: Option<NoteHeading>,
+}
+
+/// Match quality, lower is better: exact name, name prefix, alias prefix, path prefix, then
+/// substring of the name or an alias.
+fn rank(name: &str, rel: &str, alias: Option<&str>, typed: &str) -> Option<u8> {
+    let (name, rel) = (name.to_lowercase(), rel.to_lowercase());
+    match alias.map(str::to_lowercase) {
+        Some(alias) if alias.starts_with(typed) => Some(2),
+        Some(alias) if alias.contains(typed) => Some(5),
+        Some(_) => None,
+        None if name == typed => Some(0),
+        None if name.starts_with(typed) => Some(1),
+        None if rel.starts_with(typed) => Some(3),
+        None if name.contains(typed) => Some(4),
+        None => None,
+    }
+}
+
+/// Headings of the note `note` resolves to: those starting with `typed` first, then those
+/// containing it, each in document order.
+fn complete_heading(
+    index: &VaultIndex,
+    vault_root: &Path,
+    note: &str,
+    typed: &str,
+    limit: usize,
+) -> Vec<LinkCompletion> {
+    let resolved = resolve_target(&parse_wikilink_inner(note), index, vault_root);
+    let ResolveResult::Resolved(path) = resolved else {
+        return Vec::new();
+    };
+    let rel = slash_path(path.strip_prefix(vault_root).unwrap_or(&path));
+    let rel = rel.strip_suffix(".md").unwrap_or(&rel).to_string();
+    let name = rel.rsplit('/').next().unwrap_or(&rel).to_string();
+    let typed = typed.trim().to_lowercase();
+    let mut ranked: Vec<(u8, &NoteHeading)> = index
+        .headings(&path)
+        .iter()
+        .filter_map(|heading| {
+            let text = heading.text.to_lowercase();
+            let rank = if text.starts_with(&typed) {
+                0
+            } else if text.contains(&typed) {
+                1
+            } else {
+                return None;
+            };
+            Some((rank, heading))
+        })
+        .collect();
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, heading)| LinkCompletion {
+            link: format!("{}#{}", note.trim(), heading.text),
+            name: name.clone(),
+            rel_path: rel.clone(),
+            path: path.clone(),
+            alias: None,
+            heading: Some(heading.clone()),
+        })
+        .collect()
+}
+
+/// Wikilink target for the note at vault-relative `rel` (without `.md`): its basename, or `rel`
+/// when several notes share the basename.
+pub(super) fn shortest_link(index: &VaultIndex, rel: &str) -> String {
+    let name = rel.rsplit('/').next().unwrap_or(rel);
+    if index.by_basename(&normalize_rel_key(name)).len() > 1 {
+        rel.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Up to `limit` completions for `prefix` (case-insensitive; may include a folder path). After
+/// a `#` (`Note#Go`), the completions are headings of that note.
+pub fn complete_wikilink(
+    index: &VaultIndex,
+    vault_root: &Path,
+    prefix: &str,
+    limit: usize,
+) -> Vec<LinkCompletion> {
+    if let Some((note, heading)) = prefix.split_once('#') {
+        return complete_heading(index, vault_root, note, heading, limit);
+    }
+    let typed = normalize_rel_key(prefix.trim()).to_lowercase();
+    let mut notes: Vec<(&str, &PathBuf)> = index.rel_paths().map(|(rel, id)| (rel, index.path(id))).collect();
+    notes.sort();
+    let rel_of: HashMap<&PathBuf, &str> = notes.iter().map(|(rel, path)| (*path, *rel)).collect();
+    let mut ranked: Vec<(u8, LinkCompletion)> = Vec::new();
+    for (rel, path) in &notes {
+        let name = rel.rsplit('/').next().unwrap_or(rel);
+        if let Some(rank) = rank(name, rel, None, &typed) {
+            let completion = LinkCompletion {
+                link: shortest_link(index, rel),
+                name: name.to_string(),
+                rel_path: rel.to_string(),
+                path: (*path).clone(),
+                alias: None,
+                heading: None,
+            };
+            ranked.push((rank, completion));
+        }
+    }
+    for alias in note_aliases(index) {
+        let path = PathBuf::from(&alias.path);
+        let Some(rel) = rel_of.get(&path).map(|rel| rel.to_string()) else {
+            continue;
+        };
+        let name = rel.rsplit('/').next().unwrap_or(&rel).to_string();
+        if let Some(rank) = rank(&name, &rel, Some(&alias.alias), &typed) {
+            let completion = LinkCompletion {
+                link: format!("{}|{}", shortest_link(index, &rel), alias.alias),
+                name,
+                rel_path: rel,
+                path,
+                alias: Some(alias.alias),
+                heading: None,
+            };
+            ranked.push((rank, completion));
+        }
+    }
+    ranked.sort_by(|(rank_a, a), (rank_b, b)| {
+        let key = |c: &LinkCompletion| (c.alias.as_ref().unwrap_or(&c.name).len(), c.rel_path.clone());
+        rank_a.cmp(rank_b).then_with(|| key(a).cmp(&key(b)))
+    });
+    ranked.into_iter().take(limit).map(|(_, completion)| completion).collect()
+}
@@ -0,0 +1,132 @@
+//! Duplicate notes: basenames shared by several notes (wikilinks to them resolve to the first by
+//! path) and notes with the same or nearly the same content.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use crate::frontmatter::split_frontmatter;
+
+use super::index::VaultIndex;
+
+/// Notes this short are ignored for content similarity (stubs and empty notes all look alike).
+const MIN_WORDS: usize = 8;
+/// Jaccard similarity of word trigrams from which two notes count as near-duplicates.
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+/// MinHash signature length: `BANDS` × `ROWS` values, bucketed per band to find candidates.
+const BANDS: usize = 8;
+const ROWS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SharedBasename {
+    pub name: String,
+    /// Sorted by path; wikilinks `[[name]]` resolve to the first.
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SimilarNotes {
+    pub paths: [String; 2],
+    /// Jaccard similarity of the notes' word trigrams, 1.0 for identical text.
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct DuplicateReport {
+    pub shared_basenames: Vec<SharedBasename>,
+    /// Most similar first.
+    pub similar: Vec<SimilarNotes>,
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes of the lowercase word trigrams of the note body (frontmatter excluded).
+fn shingles(markdown: &str) -> Option<HashSet<u64>> {
+    let body = split_frontmatter(markdown).map_or(markdown, |(_, body)| body);
+    let words: Vec<String> = body
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    Some(words.windows(3).map(hash_of).collect())
+}
+
+fn min_hashes(shingles: &HashSet<u64>) -> Vec<u64> {
+    (0..BANDS * ROWS)
+        .map(|seed| shingles.iter().map(|s| hash_of((seed, s))).min().unwrap_or(0))
+        .collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+pub fn find_duplicates(index: &VaultIndex) -> DuplicateReport {
+    let mut shared_basenames: Vec<SharedBasename> = index
+        .basenames()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(name, ids)| {
+            let mut paths: Vec<String> =
+                ids.iter().map(|id| index.path(*id).to_string_lossy().into_owned()).collect();
+            paths.sort();
+            SharedBasename {
+                name: name.to_string(),
+                paths,
+            }
+        })
+        .collect();
+    shared_basenames.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let notes: Vec<(PathBuf, HashSet<u64>)> = index
+        .notes()
+        .into_iter()
+        .filter_map(|path| {
+            let shingles = shingles(&fs::read_to_string(&path).ok()?)?;
+            Some((path, shingles))
+        })
+        .collect();
+    // Locality-sensitive hashing: notes sharing every MinHash value of some band are candidates.
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (i, (_, shingles)) in notes.iter().enumerate() {
+        let signature = min_hashes(shingles);
+        for (band, rows) in signature.chunks(ROWS).enumerate() {
+            buckets.entry((band, hash_of(rows))).or_default().push(i);
+        }
+    }
+    let mut candidates = HashSet::new();
+    for members in buckets.values() {
+        for (n, &a) in members.iter().enumerate() {
+            for &b in &members[n + 1..] {
+                candidates.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+    let mut similar: Vec<SimilarNotes> = candidates
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let similarity = jaccard(&notes[a].1, &notes[b].1);
+            (similarity >= SIMILARITY_THRESHOLD).then(|| SimilarNotes {
+                paths: [
+                    notes[a].0.to_string_lossy().into_owned(),
+                    notes[b].0.to_string_lossy().into_owned(),
+                ],
+                similarity,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| a.paths.cmp(&b.paths)));
+    DuplicateReport {
+        shared_basenames,
+        similar,
+    }
+}
@@ -1,56 +1,345 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+use unicode_normalization::UnicodeNormalization;
+
+use crate::frontmatter::{parse_frontmatter, FrontmatterValue};
+use crate::paths;
+
+use super::parse::{parse_embed_syntax, scan_block_ids, scan_headings, NoteHeading};
+
+/// Lookup key for a vault-relative path: forward slashes, NFC so that decomposed file names
+/// (as macOS stores them) match links typed in composed form.
 pub(crate) fn normalize_rel_key(rel: &str) -> String {
-    rel.replace('\\', "/").trim_matches('/').to_string()
+    rel.replace('\\', "/").trim_matches('/').nfc().collect()
 }
 
+/// Position of a note in the index's path list; the other tables refer to notes by it.
+pub type NoteId = u32;
+
+#[derive(Default)]
 pub struct VaultIndex {
-    pub by_rel_path: HashMap<String, PathBuf>,
-    pub by_basename: HashMap<String, Vec<PathBuf>>,
+    /// Canonical path of every indexed note, sorted, so ids follow path order.
+    paths: Vec<PathBuf>,
+    /// Vault-relative path key without `.md` → note.
+    by_rel_path: HashMap<Box<str>, NoteId>,
+    /// Basename key → notes, in path order.
+    by_basename: HashMap<Box<str>, Vec<NoteId>>,
+    /// `^block-id` markers per note: id → 1-based line.
+    block_ids: HashMap<NoteId, HashMap<String, usize>>,
+    /// Frontmatter fields per note, for notes that have any.
+    frontmatter: HashMap<NoteId, BTreeMap<String, FrontmatterValue>>,
+    /// ATX headings per note, for notes that have any.
+    headings: HashMap<NoteId, Vec<NoteHeading>>,
+    /// Raw `![[...]]` targets per note, for notes that embed anything.
+    embeds: HashMap<NoteId, Vec<String>>,
+    /// Folders left out of the index (absolute); attachments in them don't resolve either.
+    excluded: Vec<PathBuf>,
 }
 
 impl VaultIndex {
+    /// `build_index_with` under the default options, for tests.
+    #[cfg(test)]
     pub fn build_index(vault_root: &Path) -> Result<VaultIndex, String> {
-        let root_canon = vault_root.canonicalize().map_err(|e| e.to_string())?;
-        let mut by_rel_path = HashMap::new();
-        let mut by_basename: HashMap<String, Vec<PathBuf>> = HashMap::new();
-        walk_index(&root_canon, &root_canon, &mut by_rel_path, &mut by_basename)?;
-        for paths in by_basename.values_mut() {
-            paths.sort();
-        }
-        Ok(VaultIndex { by_rel_path, by_basename })
-    }
-}
-
-fn walk_index(
-    vault_root: &Path,
-    dir: &Path,
-    by_rel_path: &mut HashMap<String, PathBuf>,
-    by_basename: &mut HashMap<String, Vec<PathBuf>>,
-) -> Result<(), String> {
-    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        if path.is_dir() {
-            if path.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with('.')).unwrap_or(false) {
-                continue;
+        Self::build_index_with(vault_root, &IndexOptions::default(), |_, _| Ok(()))
+    }
+
+    /// The index of the notes under `vault_root` with `options`, reporting (notes scanned, total
+    /// notes) after each note; an `Err` from `on_progress` aborts the build.
+    pub fn build_index_with(
+        vault_root: &Path,
+        options: &IndexOptions,
+        mut on_progress: impl FnMut(usize, usize) -> Result<(), String>,
+    ) -> Result<VaultIndex, String> {
+        let root_canon = paths::canonicalize(vault_root)?;
+        let mut walk = IndexWalk {
+            vault_root: &root_canon,
+            options,
+            visited_dirs: HashSet::from([root_canon.clone()]),
+            seen_notes: HashSet::new(),
+            deferred_links: Vec::new(),
+            notes: Vec::new(),
+        };
+        walk.run()?;
+        let mut notes = walk.notes;
+        notes.sort_by(|a, b| a.canonical.cmp(&b.canonical));
+        let mut index = VaultIndex {
+            excluded: options.excluded.clone(),
+            ..VaultIndex::default()
+        };
+        let total = notes.len();
+        for (id, note) in notes.into_iter().enumerate() {
+            let id = id as NoteId;
+            on_progress(id as usize + 1, total)?;
+            index.by_rel_path.insert(note.rel_key, id);
+            index.by_basename.entry(note.basename).or_default().push(id);
+            if let Ok(text) = fs::read_to_string(&note.canonical) {
+                let ids: HashMap<String, usize> = scan_block_ids(&text).into_iter().collect();
+                if !ids.is_empty() {
+                    index.block_ids.insert(id, ids);
+                }
+                index.set_frontmatter(id, &text);
+                index.set_headings(id, &text);
+                index.set_embeds(id, &text);
             }
-            walk_index(vault_root, &path, by_rel_path, by_basename)?;
-        } else if path.extension().map(|e| e == "md").unwrap_or(false) {
-            let canonical = path.canonicalize().map_err(|e| e.to_string())?;
-            let rel = canonical.strip_prefix(vault_root).map_err(|e| e.to_string())?;
-            let rel_key = rel.to_str().unwrap_or("").replace('\\', "/").trim_matches('/').to_string();
-            by_rel_path.insert(rel_key.clone(), canonical.clone());
-            if let Some(without_md) = rel_key.strip_suffix(".md") {
-                if without_md != rel_key {
-                    by_rel_path.insert(without_md.to_string(), canonical.clone());
+            index.paths.push(note.canonical);
+        }
+        Ok(index)
+    }
+
+    /// Canonical path of note `id`.
+    pub fn path(&self, id: NoteId) -> &PathBuf {
+        &self.paths[id as usize]
+    }
+
+    /// Id of the note at canonical `path`, if indexed.
+    pub fn id_of(&self, path: &Path) -> Option<NoteId> {
+        self.paths.binary_search_by(|p| p.as_path().cmp(path)).ok().map(|i| i as NoteId)
+    }
+
+    /// The note at vault-relative key `rel` (see `normalize_rel_key`), with or without `.md`.
+    pub fn by_rel_path(&self, rel: &str) -> Option<NoteId> {
+        self.by_rel_path.get(rel.strip_suffix(".md").unwrap_or(rel)).copied()
+    }
+
+    /// Notes whose basename key is `base`, in path order.
+    pub fn by_basename(&self, base: &str) -> &[NoteId] {
+        self.by_basename.get(base).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every basename key with its notes, in no particular order.
+    pub fn basenames(&self) -> impl Iterator<Item = (&str, &[NoteId])> {
+        self.by_basename.iter().map(|(base, ids)| (&**base, ids.as_slice()))
+    }
+
+    /// Every vault-relative key (without `.md`) with its note, in no particular order.
+    pub fn rel_paths(&self) -> impl Iterator<Item = (&str, NoteId)> {
+        self.by_rel_path.iter().map(|(rel, id)| (&**rel, *id))
+    }
+
+    /// Frontmatter fields of the notes that have any, in no particular order.
+    pub fn frontmatter(&self) -> impl Iterator<Item = (&PathBuf, &BTreeMap<String, FrontmatterValue>)> {
+        self.frontmatter.iter().map(|(id, fields)| (self.path(*id), fields))
+    }
+
+    /// ATX headings of `note`, in document order.
+    pub fn headings(&self, note: &Path) -> &[NoteHeading] {
+        self.id_of(note).and_then(|id| self.headings.get(&id)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `path` (canonical, inside `vault_root`) lies outside hidden and excluded folders,
+    /// like every indexed note.
+    pub fn admits(&self, vault_root: &Path, path: &Path) -> bool {
+        let Ok(inside) = path.strip_prefix(vault_root) else {
+            return false;
+        };
+        let hidden = inside
+            .parent()
+            .is_some_and(|dir| dir.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')));
+        !hidden && !self.excluded.iter().any(|folder| path.starts_with(folder))
+    }
+
+    /// Raw `![[...]]` targets of the notes that embed anything, in no particular order.
+    pub fn embeds(&self) -> impl Iterator<Item = (&PathBuf, &[String])> {
+        self.embeds.iter().map(|(id, targets)| (self.path(*id), targets.as_slice()))
+    }
+
+    fn set_embeds(&mut self, id: NoteId, text: &str) {
+        let targets: Vec<String> = parse_embed_syntax(text).into_iter().map(|span| span.raw_inner).collect();
+        if targets.is_empty() {
+            self.embeds.remove(&id);
+        } else {
+            self.embeds.insert(id, targets);
+        }
+    }
+
+    fn set_frontmatter(&mut self, id: NoteId, text: &str) {
+        let fields = parse_frontmatter(text);
+        if fields.is_empty() {
+            self.frontmatter.remove(&id);
+        } else {
+            self.frontmatter.insert(id, fields);
+        }
+    }
+
+    fn set_headings(&mut self, id: NoteId, text: &str) {
+        let headings = scan_headings(text);
+        if headings.is_empty() {
+            self.headings.remove(&id);
+        } else {
+            self.headings.insert(id, headings);
+        }
+    }
+
+    /// Re-reads what the index keeps of `note` from its current `text`, after it changed on
+    /// disk; notes not in the index are left alone.
+    pub fn refresh_note(&mut self, note: &Path, text: &str) {
+        let Some(id) = self.id_of(note) else {
+            return;
+        };
+        self.refresh_block_ids(note, text);
+        self.set_frontmatter(id, text);
+        self.set_headings(id, text);
+        self.set_embeds(id, text);
+    }
+
+    /// Line of block `id` in `note`, if the note defines it.
+    pub fn block_line(&self, note: &Path, id: &str) -> Option<usize> {
+        self.block_ids.get(&self.id_of(note)?)?.get(id).copied()
+    }
+
+    /// Re-reads the block ids of `note` from its current `text`; notes not in the index are
+    /// left alone.
+    pub fn refresh_block_ids(&mut self, note: &Path, text: &str) {
+        let Some(note) = self.id_of(note) else {
+            return;
+        };
+        let ids: HashMap<String, usize> = scan_block_ids(text).into_iter().collect();
+        if ids.is_empty() {
+            self.block_ids.remove(&note);
+        } else {
+            self.block_ids.insert(note, ids);
+        }
+    }
+
+    /// Every indexed note, sorted by path.
+    pub fn notes(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+
+    /// Notes inside folder `dir` (canonical), at any depth, sorted.
+    pub fn notes_under(&self, dir: &Path) -> Vec<PathBuf> {
+        self.paths.iter().filter(|note| note.starts_with(dir)).cloned().collect()
+    }
+
+    /// The `limit` most recently modified notes, newest first. Mtimes are read now rather than
+    /// at indexing time, so edits made since the index was built count.
+    pub fn notes_by_mtime(&self, limit: usize) -> Vec<(PathBuf, SystemTime)> {
+        let mut notes: Vec<(PathBuf, SystemTime)> = self
+            .paths
+            .iter()
+            .filter_map(|path| {
+                let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+                Some((path.clone(), modified))
+            })
+            .collect();
+        notes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        notes.truncate(limit);
+        notes
+    }
+}
+
+/// How indexing treats symbolic links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Ignore symlinked files and folders.
+    Skip,
+    /// Follow links whose target lies inside the vault.
+    #[default]
+    WithinVault,
+    /// Follow every link, including targets outside the vault.
+    Follow,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions {
+    pub symlinks: SymlinkPolicy,
+    /// Folders (absolute, under the vault root) whose notes are not indexed.
+    pub excluded: Vec<PathBuf>,
+}
+
+/// Recursive walk state. Symlinks are deferred until the real tree is walked, so a note
+/// reachable both directly and through a link is indexed under its real path; directories and
+/// notes are visited once by canonical path, which breaks symlink cycles.
+struct IndexWalk<'a> {
+    vault_root: &'a Path,
+    options: &'a IndexOptions,
+    visited_dirs: HashSet<PathBuf>,
+    seen_notes: HashSet<PathBuf>,
+    deferred_links: Vec<(PathBuf, PathBuf)>,
+    notes: Vec<WalkedNote>,
+}
+
+struct WalkedNote {
+    canonical: PathBuf,
+    /// Vault-relative key without `.md`.
+    rel_key: Box<str>,
+    basename: Box<str>,
+}
+
+impl IndexWalk<'_> {
+    fn run(&mut self) -> Result<(), String> {
+        self.walk(self.vault_root)?;
+        while let Some((path, canonical)) = self.deferred_links.pop() {
+            if canonical.is_dir() {
+                if self.visited_dirs.insert(canonical) {
+                    self.walk(&path)?;
                 }
+            } else {
+                self.add_note(&path, canonical)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn walk(&mut self, dir: &Path) -> Result<(), String> {
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            let hidden = path.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with('.')).unwrap_or(false);
+            let is_note = path.extension().map(|e| e == "md").unwrap_or(false);
+            if (is_dir && hidden) || (!is_dir && !is_note) {
+                continue;
             }
-            let base = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
-            by_basename.entry(base).or_default().push(canonical);
+            if self.options.excluded.iter().any(|folder| path.starts_with(folder)) {
+                continue;
+            }
+            if entry.file_type().map(|t| t.is_symlink()).unwrap_or(false) {
+                self.defer_link(path);
+            } else if is_dir {
+                self.visited_dirs.insert(paths::canonicalize(&path)?);
+                self.walk(&path)?;
+            } else {
+                let canonical = paths::canonicalize(&path)?;
+                self.add_note(&path, canonical)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn defer_link(&mut self, path: PathBuf) {
+        let Ok(canonical) = paths::canonicalize(&path) else {
+            return;
+        };
+        let admitted = match self.options.symlinks {
+            SymlinkPolicy::Skip => false,
+            SymlinkPolicy::WithinVault => canonical.starts_with(self.vault_root),
+            SymlinkPolicy::Follow => true,
+        };
+        if admitted {
+            self.deferred_links.push((path, canonical));
+        }
+    }
+
+    /// Indexes the note at `path` (inside the vault, possibly via links) unless its target
+    /// was already indexed. Keys use `path`, which stays meaningful for followed links.
+    fn add_note(&mut self, path: &Path, canonical: PathBuf) -> Result<(), String> {
+        if !self.seen_notes.insert(canonical.clone()) {
+            return Ok(());
         }
+        let rel = path.strip_prefix(self.vault_root).map_err(|e| e.to_string())?;
+        let rel_key = normalize_rel_key(rel.to_str().unwrap_or(""));
+        let rel_key = rel_key.strip_suffix(".md").unwrap_or(&rel_key).into();
+        let basename = normalize_rel_key(path.file_stem().and_then(|s| s.to_str()).unwrap_or("")).into();
+        self.notes.push(WalkedNote {
+            canonical,
+            rel_key,
+            basename,
+        });
+        Ok(())
     }
-    Ok(())
 }
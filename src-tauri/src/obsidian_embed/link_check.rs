@@ -0,0 +1,123 @@
+//! Vault-wide link check: every note's outgoing links classified, with a markdown rendering
+//! of the problems for export.
+
+use std::path::Path;
+
+use super::index::VaultIndex;
+use super::outgoing::{find_outgoing_links, LinkKind, LinkStatus, OutgoingLink};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkProblem {
+    /// `[[Note]]` or `![[Note]]` naming no note.
+    BrokenWikilink,
+    /// An embedded or linked image/attachment that does not exist.
+    MissingAsset,
+    /// A markdown link to a relative path that does not exist.
+    DeadRelativePath,
+}
+
+impl LinkProblem {
+    fn label(self) -> &'static str {
+        match self {
+            LinkProblem::BrokenWikilink => "broken wikilink",
+            LinkProblem::MissingAsset => "missing asset",
+            LinkProblem::DeadRelativePath => "dead relative path",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct BrokenLink {
+    pub source: String,
+    /// 1-based line of the link in `source`.
+    pub line: usize,
+    /// The link target as written.
+    pub target: String,
+    pub problem: LinkProblem,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct LinkReport {
+    pub notes_checked: usize,
+    pub resolved: usize,
+    /// External URLs, counted but not fetched.
+    pub external: usize,
+    /// Sorted by source note, then line.
+    pub broken: Vec<BrokenLink>,
+}
+
+/// An attachment rather than a note: a target with an extension other than `.md`.
+fn is_asset_target(target: &str) -> bool {
+    let target = target.split(['#', '|', '?']).next().unwrap_or_default();
+    Path::new(target.trim())
+        .extension()
+        .map(|ext| !ext.eq_ignore_ascii_case("md"))
+        .unwrap_or(false)
+}
+
+fn classify(link: &OutgoingLink) -> Option<LinkProblem> {
+    if link.status != LinkStatus::Broken {
+        return None;
+    }
+    Some(match link.kind {
+        LinkKind::Image => LinkProblem::MissingAsset,
+        _ if is_asset_target(&link.target) => LinkProblem::MissingAsset,
+        LinkKind::Wikilink | LinkKind::Embed => LinkProblem::BrokenWikilink,
+        LinkKind::Markdown => LinkProblem::DeadRelativePath,
+    })
+}
+
+/// Checks the links of every indexed note.
+pub fn check_links(index: &VaultIndex, vault_root: &Path) -> LinkReport {
+    let mut report = LinkReport::default();
+    for note in index.notes() {
+        let Ok(links) = find_outgoing_links(&note, index, vault_root) else {
+            continue;
+        };
+        report.notes_checked += 1;
+        let source = note.to_string_lossy().into_owned();
+        for link in links {
+            match (link.status, classify(&link)) {
+                (LinkStatus::External, _) => report.external += 1,
+                (_, Some(problem)) => report.broken.push(BrokenLink {
+                    source: source.clone(),
+                    line: link.line,
+                    target: link.target,
+                    problem,
+                }),
+                (_, None) => report.resolved += 1,
+            }
+        }
+    }
+    report
+}
+
+impl LinkReport {
+    /// The report as a markdown document: a summary, then the broken links grouped by note
+    /// (paths relative to `vault_root`).
+    pub fn to_markdown(&self, vault_root: &Path) -> String {
+        let mut out = format!(
+            "# Link check\n\n{} notes checked: {} links resolved, {} broken, {} external.\n",
+            self.notes_checked,
+            self.resolved,
+            self.broken.len(),
+            self.external
+        );
+        let mut current: Option<&str> = None;
+        for link in &self.broken {
+            if current != Some(link.source.as_str()) {
+                let rel = Path::new(&link.source).strip_prefix(vault_root).unwrap_or(Path::new(&link.source));
+                out.push_str(&format!("\n## {}\n\n", rel.to_string_lossy().replace('\\', "/")));
+                current = Some(&link.source);
+            }
+            out.push_str(&format!(
+                "- Line {}: `{}` — {}\n",
+                link.line,
+                link.target.replace('`', "'"),
+                link.problem.label()
+            ));
+        }
+        out
+    }
+}
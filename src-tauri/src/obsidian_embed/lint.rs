@@ -0,0 +1,124 @@
+//! Per-note lint: structure problems that render fine but break links or navigation (skipped
+//! heading levels, duplicate headings, unclosed fences, wikilinks the parser drops).
+
+use std::collections::HashMap;
+
+use crate::frontmatter::split_frontmatter;
+
+use super::parse::{compute_skip_ranges, fence_marker, heading_anchor, in_skip_range, scan_headings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// A heading more than one level below the previous one (`#` then `###`).
+    SkippedHeadingLevel,
+    /// A heading with the same anchor as an earlier one; `[[Note#Heading]]` only reaches the first.
+    DuplicateHeading,
+    /// A code fence never closed, so the rest of the note renders as code.
+    UnclosedFence,
+    /// `[[` or `![[` without `]]` on the same line, or with nothing inside.
+    MalformedWikilink,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LintIssue {
+    /// 1-based line in the note, frontmatter included.
+    pub line: usize,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+fn heading_issues(md: &str, issues: &mut Vec<LintIssue>) {
+    let mut previous_level: Option<u8> = None;
+    let mut first_line: HashMap<String, usize> = HashMap::new();
+    for heading in scan_headings(md) {
+        if let Some(previous) = previous_level.filter(|&p| heading.level > p + 1) {
+            issues.push(LintIssue {
+                line: heading.line,
+                kind: LintKind::SkippedHeadingLevel,
+                message: format!("Heading level {} follows level {}", heading.level, previous),
+            });
+        }
+        previous_level = Some(heading.level);
+        let anchor = heading_anchor(&heading.text);
+        if let Some(first) = first_line.get(&anchor) {
+            issues.push(LintIssue {
+                line: heading.line,
+                kind: LintKind::DuplicateHeading,
+                message: format!("Duplicate heading \"{}\" (first on line {})", heading.text, first),
+            });
+        } else {
+            first_line.insert(anchor, heading.line);
+        }
+    }
+}
+
+fn fence_issues(md: &str, issues: &mut Vec<LintIssue>) {
+    let body_start = split_frontmatter(md).map_or(0, |(_, body)| md.len() - body.len());
+    let first_line = md[..body_start].matches('\n').count() + 1;
+    let mut open: Option<(u8, usize, usize)> = None;
+    for (i, line) in md[body_start..].lines().enumerate() {
+        let Some((marker, len, rest)) = fence_marker(line) else {
+            continue;
+        };
+        match open {
+            Some((m, l, _)) if m == marker && len >= l && rest.trim().is_empty() => open = None,
+            Some(_) => {}
+            None if marker == b'~' || !rest.contains('`') => open = Some((marker, len, first_line + i)),
+            None => {}
+        }
+    }
+    if let Some((marker, len, line)) = open {
+        let fence = (marker as char).to_string().repeat(len);
+        issues.push(LintIssue {
+            line,
+            kind: LintKind::UnclosedFence,
+            message: format!("Code fence is never closed (add {})", fence),
+        });
+    }
+}
+
+fn wikilink_issues(md: &str, issues: &mut Vec<LintIssue>) {
+    let skip = compute_skip_ranges(md);
+    let mut offset = 0;
+    for (i, line) in md.split('\n').enumerate() {
+        let mut from = 0;
+        while let Some(found) = line[from..].find("[[") {
+            let at = from + found;
+            from = at + 2;
+            if in_skip_range(offset + at, &skip) {
+                continue;
+            }
+            let written = if line[..at].ends_with('!') { "![[" } else { "[[" };
+            let rest = &line[from..];
+            let reopened = rest.find("[[").unwrap_or(rest.len());
+            let message = match rest.find("]]").filter(|&close| close < reopened) {
+                Some(close) if rest[..close].trim().is_empty() => {
+                    from += close + 2;
+                    format!("Empty link `{}]]`", written)
+                }
+                Some(close) => {
+                    from += close + 2;
+                    continue;
+                }
+                None => format!("`{}{}` has no closing `]]`", written, rest[..reopened].trim_end()),
+            };
+            issues.push(LintIssue {
+                line: i + 1,
+                kind: LintKind::MalformedWikilink,
+                message,
+            });
+        }
+        offset += line.len() + 1;
+    }
+}
+
+/// Problems in `md`, sorted by line.
+pub fn lint_note(md: &str) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    heading_issues(md, &mut issues);
+    fence_issues(md, &mut issues);
+    wikilink_issues(md, &mut issues);
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
@@ -0,0 +1,75 @@
+//! Maps of content: an index note with a wikilink to every note in a folder, so index notes
+//! don't have to be kept up to date by hand.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::paths::slash_path;
+
+use super::complete::shortest_link;
+use super::index::VaultIndex;
+
+#[derive(Default)]
+struct Group {
+    notes: Vec<PathBuf>,
+    /// Keyed by lowercased name, then name, so folders sort case-insensitively.
+    folders: BTreeMap<(String, String), Group>,
+}
+
+/// Markdown for the map of content of `folder` (canonical): a `#` title, links to the notes
+/// directly inside, then a section per subfolder, one heading level deeper per level of nesting.
+/// Notes are sorted by name within each folder; `exclude` (the map's own note) is left out.
+pub fn map_of_content(
+    folder: &Path,
+    exclude: Option<&Path>,
+    index: &VaultIndex,
+    vault_root: &Path,
+) -> String {
+    let mut root = Group::default();
+    for note in index.notes_under(folder) {
+        if exclude == Some(note.as_path()) {
+            continue;
+        }
+        let Ok(inside) = note.strip_prefix(folder) else {
+            continue;
+        };
+        let mut group = &mut root;
+        if let Some(parent) = inside.parent() {
+            for component in parent.components() {
+                if let Component::Normal(name) = component {
+                    let name = name.to_string_lossy().into_owned();
+                    group = group.folders.entry((name.to_lowercase(), name)).or_default();
+                }
+            }
+        }
+        group.notes.push(note);
+    }
+    let title = folder.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut out = format!("# {}\n", title);
+    write_group(&root, 2, index, vault_root, &mut out);
+    out
+}
+
+fn write_group(group: &Group, level: usize, index: &VaultIndex, vault_root: &Path, out: &mut String) {
+    let mut links: Vec<(String, String)> = group
+        .notes
+        .iter()
+        .map(|note| {
+            let rel = slash_path(note.strip_prefix(vault_root).unwrap_or(note));
+            let rel = rel.strip_suffix(".md").unwrap_or(&rel).to_string();
+            let name = rel.rsplit('/').next().unwrap_or(&rel).to_string();
+            (name.to_lowercase(), shortest_link(index, &rel))
+        })
+        .collect();
+    links.sort();
+    if !links.is_empty() {
+        out.push('\n');
+    }
+    for (_, link) in links {
+        out.push_str(&format!("- [[{}]]\n", link));
+    }
+    for ((_, name), folder) in &group.folders {
+        out.push_str(&format!("\n{} {}\n", "#".repeat(level.min(6)), name));
+        write_group(folder, level + 1, index, vault_root, out);
+    }
+}
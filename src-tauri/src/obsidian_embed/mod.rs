@@ -1,14 +1,45 @@
 //! Obsidian-style embed resolution and expansion for `![[...]]` and `[[...]]` wikilinks.
 
+mod backlinks;
+mod block_id;
 mod cache;
+mod complete;
+mod duplicates;
 mod index;
+mod link_check;
+mod lint;
+mod moc;
+mod outgoing;
 mod parse;
+mod preview;
+mod query;
+mod refactor;
 mod render;
 mod resolve;
+mod suggest;
+mod tags;
 
+pub use backlinks::{find_backlinks, link_counts, Backlink, LinkCounts};
+pub use block_id::add_block_id;
 pub use cache::RenderCache;
-pub use index::VaultIndex;
-pub use render::{render_markdown_with_embeds, RenderContext};
+pub use complete::{complete_wikilink, LinkCompletion};
+pub use duplicates::{find_duplicates, DuplicateReport};
+pub use index::{IndexOptions, SymlinkPolicy, VaultIndex};
+pub use link_check::check_links as check_vault_links;
+pub use lint::{lint_note, LintIssue};
+pub use moc::map_of_content;
+pub use outgoing::{find_outgoing_links, OutgoingLink};
+pub use parse::portable_href;
+pub use preview::{preview_link, LinkPreview};
+pub use query::{note_aliases, query_frontmatter, FrontmatterMatch, NoteAlias, QueryOp};
+pub use refactor::{folder_move_edits, NoteEdits};
+pub use render::{
+    block_source_map_of_string, block_source_map_with_embeds, embed_dependencies, find_embedding_notes,
+    flatten_markdown_with_embeds, render_markdown_string_with_embeds, render_markdown_with_embeds,
+    RenderContext, RenderGeneration,
+};
+pub use suggest::suggest_targets;
+pub use tags::note_tags;
 
 #[cfg(test)]
 mod tests {
@@ -16,7 +47,10 @@ mod tests {
     use std::path::{Path, PathBuf};
     use std::time::SystemTime;
 
+    use crate::markdown::{EmbedPlaceholder, RenderOptions};
+
     use super::cache::{MAX_CACHE_ENTRIES, MAX_CACHE_SIZE_BYTES};
+    use super::lint::LintKind;
     use super::parse::{
         link_display_text, obs_link_href, parse_embed_syntax, parse_wikilink_inner, HeadingOrBlock,
         ParsedLink,
@@ -81,12 +115,52 @@ mod tests {
         assert_eq!(spans.len(), 0, "![[Link]] inside fenced code block should be skipped");
     }
 
+    #[test]
+    fn parse_embed_syntax_skipped_inside_tilde_fence() {
+        let md = "~~~md\n![[Link]]\n```\n![[Still code]]\n~~~\n![[After]]";
+        let spans = parse_embed_syntax(md);
+        assert_eq!(spans.len(), 1, "only the embed after the fence: {:?}", spans);
+        assert_eq!(spans[0].raw_inner, "After");
+    }
+
+    #[test]
+    fn parse_embed_syntax_skipped_inside_indented_code() {
+        let md = "Intro\n\n    ![[Code]]\n\n    ![[More code]]\nText\n    ![[Continuation]]";
+        let spans = parse_embed_syntax(md);
+        let inners: Vec<&str> = spans.iter().map(|s| s.raw_inner.as_str()).collect();
+        assert_eq!(inners, ["Continuation"], "indented lines after a paragraph are not code");
+    }
+
+    #[test]
+    fn parse_embed_syntax_sees_links_in_list_continuations() {
+        let md = "- Item\n\n    ![[Continued]]\n\n    - Nested\n\n        ![[Nested continued]]\n\n\
+                  1. First\n\n           ![[Code in item]]\n\nAfter\n\n    ![[Code]]";
+        let spans = parse_embed_syntax(md);
+        let inners: Vec<&str> = spans.iter().map(|s| s.raw_inner.as_str()).collect();
+        assert_eq!(inners, ["Continued", "Nested continued"]);
+    }
+
     #[test]
     fn parse_embed_syntax_skipped_inside_inline_code() {
         let spans = parse_embed_syntax("text `![[x]]` more");
         assert_eq!(spans.len(), 0, "![[x]] inside inline code should be skipped");
     }
 
+    #[test]
+    fn parse_embed_syntax_skipped_inside_double_backtick_code() {
+        let spans = parse_embed_syntax("a ``![[x]]` still code`` ![[y]] ``` unmatched ![[z]]");
+        let inners: Vec<&str> = spans.iter().map(|s| s.raw_inner.as_str()).collect();
+        assert_eq!(inners, ["y", "z"]);
+    }
+
+    #[test]
+    fn parse_embed_syntax_skipped_inside_frontmatter() {
+        let md = "---\nrelated: \"![[Other]]\"\nfence: ```\n---\n![[Body]]\n";
+        let spans = parse_embed_syntax(md);
+        let inners: Vec<&str> = spans.iter().map(|s| s.raw_inner.as_str()).collect();
+        assert_eq!(inners, ["Body"]);
+    }
+
     #[test]
     fn link_display_text_alias() {
         let p = ParsedLink {
@@ -120,14 +194,14 @@ mod tests {
     #[test]
     fn obs_link_href_resolved() {
         let p = Path::new("/vault/Note.md");
-        let h = obs_link_href(Some(p));
+        let h = obs_link_href(Some(p), None);
         assert!(h.starts_with("app://open?path="));
         assert!(h.contains("Note"));
     }
 
     #[test]
     fn obs_link_href_empty() {
-        assert_eq!(obs_link_href(None), "app://open?path=");
+        assert_eq!(obs_link_href(None, None), "app://open?path=");
     }
 
     #[test]
@@ -231,8 +305,69 @@ mod tests {
         // Sorted: shortest path first (a.md at root, then foo/a.md, then foo/bar/a.md)
         assert!(path.ends_with("a.md"));
         // Deterministic: we pick first after sort
-        let first = index.by_basename.get("a").unwrap()[0].clone();
-        assert_eq!(path, first);
+        let first = index.path(index.by_basename("a")[0]);
+        assert_eq!(&path, first);
+    }
+
+    #[test]
+    fn ambiguous_links_list_candidates_when_reported() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("work")).unwrap();
+        std::fs::write(root.join("Plan.md"), "x").unwrap();
+        std::fs::write(root.join("work/Plan.md"), "y").unwrap();
+        std::fs::write(root.join("A.md"), "See [[Plan#Goals]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let p = parse_wikilink_inner("Plan");
+        assert!(matches!(resolve_target(&p, &index, &vault), ResolveResult::Resolved(_)));
+        let ambiguous = super::resolve::resolve_target_with(&p, &index, &vault, true);
+        assert!(matches!(&ambiguous, ResolveResult::Ambiguous(c) if c.len() == 2), "{:?}", ambiguous);
+
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault.clone(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions {
+                report_ambiguous: true,
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        let first = crate::paths::slash_path(&vault.join("Plan.md"));
+        let second = crate::paths::slash_path(&vault.join("work/Plan.md"));
+        assert!(html.contains("class=\"obs-link ambiguous\""), "{}", html);
+        assert!(
+            html.contains(&format!("data-obs-candidates=\"[&quot;{}&quot;,&quot;{}&quot;]\"", first, second)),
+            "{}",
+            html
+        );
+        assert!(html.contains("data-obs-anchor=\"goals\""), "{}", html);
+        assert!(!html.contains("candidate="), "candidates moved out of the href: {}", html);
+    }
+
+    #[test]
+    fn folder_links_resolve_to_folder_notes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("Projects/Alpha")).unwrap();
+        std::fs::create_dir_all(root.join("Areas")).unwrap();
+        std::fs::write(root.join("Projects/Alpha/Alpha.md"), "x").unwrap();
+        std::fs::write(root.join("Projects/Alpha/index.md"), "x").unwrap();
+        std::fs::write(root.join("Areas/index.md"), "x").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let resolve = |target: &str| resolve_target(&parse_wikilink_inner(target), &index, &vault);
+        assert!(matches!(resolve("Projects/Alpha"), ResolveResult::Resolved(p) if p.ends_with("Alpha/Alpha.md")));
+        assert!(matches!(resolve("Areas"), ResolveResult::Resolved(p) if p.ends_with("Areas/index.md")));
+        assert!(matches!(resolve("Projects"), ResolveResult::NotFound));
     }
 
     #[test]
@@ -245,6 +380,98 @@ mod tests {
         assert!(matches!(res, ResolveResult::NotFound));
     }
 
+    #[test]
+    fn attachments_resolve_only_for_placeholder_kinds_outside_hidden_and_excluded_folders() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".obsidian")).unwrap();
+        std::fs::create_dir_all(root.join("Archive")).unwrap();
+        let files = ["anim.gif", "data.csv", ".obsidian/app.json", ".obsidian/pic.png", "Archive/old.png", "ok.png"];
+        for file in files {
+            std::fs::write(root.join(file), "x").unwrap();
+        }
+        let options = IndexOptions {
+            excluded: vec![root.join("Archive")],
+            ..Default::default()
+        };
+        let index = VaultIndex::build_index_with(&root, &options, |_, _| Ok(())).unwrap();
+        let resolve = |target: &str| resolve_target(&parse_wikilink_inner(target), &index, &root);
+        assert_eq!(resolve("ok.png"), ResolveResult::Placeholder(root.join("ok.png")));
+        assert_eq!(resolve("data.csv"), ResolveResult::Placeholder(root.join("data.csv")));
+        for target in ["anim.gif", ".obsidian/app.json", ".obsidian/pic.png", "Archive/old.png"] {
+            assert_eq!(resolve(target), ResolveResult::NotFound, "{}", target);
+        }
+    }
+
+    #[test]
+    fn backlinks_list_linking_notes_with_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Target.md"), "# Target").unwrap();
+        std::fs::write(root.join("A.md"), "# A\n\nSee [[Target]] here").unwrap();
+        std::fs::write(root.join("B.md"), "![[Target]]\n`[[Target]]`").unwrap();
+        std::fs::write(root.join("C.md"), "[[Other]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let links = find_backlinks(&root.join("Target.md"), &index, &vault);
+        assert_eq!(links.len(), 2, "{:?}", links);
+        assert!(links[0].source.ends_with("A.md"));
+        assert_eq!(links[0].line, 3);
+        assert_eq!(links[0].snippet, "See [[Target]] here");
+        assert!(!links[0].is_embed);
+        assert!(links[1].source.ends_with("B.md"));
+        assert!(links[1].is_embed);
+    }
+
+    #[test]
+    fn link_counts_match_backlinks_and_outgoing_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Hub.md"), "[[Hub]] [[A]]").unwrap();
+        std::fs::write(root.join("A.md"), "[[Hub]] ![[Hub#Part]] [[Missing]]").unwrap();
+        std::fs::write(root.join("B.md"), "[[Hub]]\n```\n[[A]]\n```").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let counts = link_counts(&index, &vault);
+        let of = |name: &str| counts.get(&vault.join(name)).copied().unwrap_or_default();
+        assert_eq!(of("Hub.md"), LinkCounts { inbound: 3, outbound: 1 });
+        assert_eq!(of("A.md"), LinkCounts { inbound: 1, outbound: 2 });
+        assert_eq!(of("B.md"), LinkCounts { inbound: 0, outbound: 1 });
+        assert_eq!(of("Hub.md").inbound, find_backlinks(&root.join("Hub.md"), &index, &vault).len());
+    }
+
+    #[test]
+    fn map_of_content_groups_notes_by_subfolder() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("Projects/beta/Deep")).unwrap();
+        std::fs::create_dir_all(root.join("Projects/Alpha")).unwrap();
+        std::fs::create_dir_all(root.join("Other")).unwrap();
+        for note in [
+            "Projects/Projects.md",
+            "Projects/zeta.md",
+            "Projects/Brief.md",
+            "Projects/Alpha/Plan.md",
+            "Projects/beta/Notes.md",
+            "Projects/beta/Deep/Plan.md",
+            "Other/Notes.md",
+        ] {
+            std::fs::write(root.join(note), "x").unwrap();
+        }
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let folder = vault.join("Projects");
+        let moc = map_of_content(&folder, Some(&folder.join("Projects.md")), &index, &vault);
+        assert_eq!(
+            moc,
+            "# Projects\n\n- [[Brief]]\n- [[zeta]]\n\n## Alpha\n\n- [[Projects/Alpha/Plan]]\n\n\
+             ## beta\n\n- [[Projects/beta/Notes]]\n\n### Deep\n\n- [[Projects/beta/Deep/Plan]]\n"
+        );
+    }
+
     // ---------- Expansion tests ----------
     #[test]
     fn expand_single_embed() {
@@ -268,6 +495,8 @@ mod tests {
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
         assert!(html.contains("<h1>"), "expected h1 in {}", html);
@@ -294,290 +523,1332 @@ mod tests {
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(html.contains("A "), "{}", html);
-        assert!(html.contains("B "), "{}", html);
+        assert!(html.contains("<p>A</p>"), "{}", html);
+        assert!(html.contains("<p>B</p>"), "{}", html);
         assert!(html.contains("C"), "{}", html);
+        let source = crate::paths::slash_path(&root.canonicalize().unwrap().join("B.md"));
+        assert!(html.contains(&format!("<div class=\"obs-embed\" data-source=\"{}\">", source)), "{}", html);
+        assert!(html.contains("<div class=\"obs-embed-header\"><a class=\"obs-link\""), "{}", html);
+        assert_eq!(html.matches("class=\"obs-embed\"").count(), 2, "{}", html);
+        assert_eq!(html.matches("<div").count(), html.matches("</div>").count(), "{}", html);
     }
 
     #[test]
-    fn expand_cycle_detection() {
+    fn oversized_embed_becomes_link() {
         let dir = tempfile::TempDir::new().unwrap();
         let root = dir.path();
-        std::fs::write(root.join("A.md"), "A ![[B]]").unwrap();
-        std::fs::write(root.join("B.md"), "B ![[A]]").unwrap();
+        std::fs::write(root.join("A.md"), "A ![[Log]]").unwrap();
+        std::fs::write(root.join("Log.md"), "line\n".repeat(2000)).unwrap();
 
         let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.canonicalize().unwrap(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions {
+                max_embed_bytes: Some(4096),
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(html.contains("A "), "{}", html);
-        assert!(html.contains("B "), "{}", html);
-        assert!(html.contains("cycle"), "expected cycle placeholder in {}", html);
+        assert!(html.contains("too large, 9 KB"), "{}", html);
+        assert!(html.contains("Log.md\">Log.md</a>"), "{}", html);
+        assert!(!html.contains("line\nline"), "{}", html);
     }
 
     #[test]
-    fn expand_depth_limit() {
+    fn unreadable_embed_reports_path_and_error() {
         let dir = tempfile::TempDir::new().unwrap();
         let root = dir.path();
-        std::fs::write(root.join("0.md"), "0 ![[1]]").unwrap();
-        std::fs::write(root.join("1.md"), "1 ![[2]]").unwrap();
-        std::fs::write(root.join("2.md"), "2 ![[3]]").unwrap();
-        std::fs::write(root.join("3.md"), "3 ![[4]]").unwrap();
-        std::fs::write(root.join("4.md"), "4 ![[5]]").unwrap();
-        std::fs::write(root.join("5.md"), "# Five").unwrap();
+        std::fs::write(root.join("A.md"), "A ![[Gone_<1>]]").unwrap();
+        std::fs::write(root.join("Gone_<1>.md"), "x").unwrap();
 
         let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        std::fs::remove_file(root.join("Gone_<1>.md")).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.canonicalize().unwrap(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
-            max_depth: 3,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
-        let html = render_markdown_with_embeds(&root.join("0.md"), &mut ctx);
-        assert!(html.contains("depth limit"), "expected depth limit placeholder in {}", html);
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("<span class=\"embed-error\""), "{}", html);
+        assert!(html.contains("Gone_&lt;1&gt;.md</code>"), "path shown verbatim: {}", html);
+        assert!(html.contains("No such file") || html.contains("cannot find"), "{}", html);
+        assert!(html.contains("class=\"embed-retry\""), "{}", html);
+        assert_eq!(ctx.cache.get_stats().0, 0, "failed render not cached");
     }
 
     #[test]
-    fn wikilink_renders_as_link_no_raw_brackets() {
+    fn flattened_markdown_inlines_embeds_with_relative_links() {
         let dir = tempfile::TempDir::new().unwrap();
         let root = dir.path();
-        std::fs::write(root.join("Note.md"), "# Note").unwrap();
-        std::fs::write(root.join("A.md"), "See [[Note]] here").unwrap();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/A.md"), "![[B]]\n\nSee [[My Note#Part]] and [[Missing]].").unwrap();
+        std::fs::write(root.join("B.md"), "Embedded [[sub/A|back]]").unwrap();
+        std::fs::write(root.join("My Note.md"), "# Part").unwrap();
 
         let index = VaultIndex::build_index(root).unwrap();
         let vault = root.canonicalize().unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: vault.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
-        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(!html.contains("[[Note]]"), "wikilink should be replaced, no raw [[Note]] in {}", html);
-        assert!(html.contains("app://open?path="), "expected app link in {}", html);
-        assert!(html.contains("obs-link") || html.contains("href="), "expected link styling or href");
+        let md = flatten_markdown_with_embeds(&vault.join("sub/A.md"), &vault.join("sub"), true, &mut ctx);
+        assert!(md.contains("Embedded [back](A.md)"), "{}", md);
+        assert!(md.contains("](../My%20Note.md#part)"), "{}", md);
+        assert!(md.contains("and Missing."), "unresolved link is plain text: {}", md);
+        assert!(!md.contains("app://") && !md.contains("[["), "{}", md);
+
+        let linked = flatten_markdown_with_embeds(&vault.join("sub/A.md"), &vault.join("sub"), false, &mut ctx);
+        assert!(linked.starts_with("[B](../B.md)"), "embed kept as a link: {}", linked);
     }
 
     #[test]
-    fn wikilink_broken_renders_as_broken_or_empty_path() {
+    fn csv_embed_renders_table_with_row_limit() {
         let dir = tempfile::TempDir::new().unwrap();
-        let root = dir.path();
-        std::fs::write(root.join("A.md"), "See [[Missing]] here").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("data")).unwrap();
+        let rows: String = (0..60).map(|i| format!("{},<b>{}</b>\n", i, i)).collect();
+        std::fs::write(root.join("data/big.csv"), format!("id,label\n{}", rows)).unwrap();
+        std::fs::write(root.join("A.md"), "Before\n\n![[data/big.csv]]\n\nAfter").unwrap();
 
-        let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(!html.contains("[[Missing]]"), "broken wikilink should be replaced");
-        let has_broken = html.contains("obs-link broken") || html.contains("app://open?path=\"\"") || (html.contains("app://open?path=") && html.contains("Missing"));
-        assert!(has_broken, "expected broken link marker in {}", html);
+        assert!(html.contains("<thead><tr><th>id</th><th>label</th></tr></thead>"), "{}", html);
+        assert!(html.contains("<td>&lt;b&gt;3&lt;/b&gt;</td>"), "cells are escaped: {}", html);
+        assert!(html.contains("big.csv — 50 of 61 rows"), "{}", html);
+        assert!(html.contains("class=\"csv-open\""), "{}", html);
+        assert!(!html.contains("<p><figure"), "{}", html);
+
+        let md = flatten_markdown_with_embeds(&root.join("A.md"), &root, true, &mut ctx);
+        assert!(md.contains("[Asset: big.csv](data/big.csv)"), "{}", md);
     }
 
     #[test]
-    fn embed_no_literal_in_html() {
+    fn citations_link_to_generated_references() {
         let dir = tempfile::TempDir::new().unwrap();
-        let root = dir.path();
-        std::fs::write(root.join("B.md"), "# B").unwrap();
-        std::fs::write(root.join("A.md"), "Before ![[B]] After").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("refs.bib"), "@book{doe20, author = {Doe, Jane}, title = {T}, year = 2020}").unwrap();
+        std::fs::write(root.join("A.md"), "Claim [@doe20, p. 2].[^1]\n\n[^1]: A footnote.\n").unwrap();
 
-        let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions {
+                bibliography: Some(root.join("refs.bib")),
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(!html.contains("![["), "embed syntax must not appear in output HTML");
+        assert!(html.contains("(<a href=\"#%5Eref-doe20\">Doe 2020</a>, p. 2)"), "{}", html);
+        assert!(html.contains("<li id=\"^ref-doe20\">Doe, J. (2020). T."), "{}", html);
+        assert!(html.contains("A footnote."), "{}", html);
+        assert!(!html.contains("[^1]"), "footnotes rendered: {}", html);
+        assert_eq!(cache.evict_changed(&[root.join("refs.bib")]), 1, "the bibliography is a dependency");
     }
 
     #[test]
-    fn normal_markdown_link_unchanged() {
+    fn unpublished_embeds_become_placeholder_in_exports() {
         let dir = tempfile::TempDir::new().unwrap();
-        let root = dir.path();
-        std::fs::write(root.join("A.md"), "Link: [text](https://x.com)").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "![[Draft]] ![[Done]]").unwrap();
+        std::fs::write(root.join("Draft.md"), "---\ndraft: true\n---\nSecret").unwrap();
+        std::fs::write(root.join("Done.md"), "Public").unwrap();
 
-        let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(html.contains("https://x.com"), "normal markdown link href should be preserved: {}", html);
+        assert!(html.contains("Secret"), "{}", html);
+
+        ctx.render.unpublished_embed = Some("*[Hidden: {name}]*".to_string());
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("<em>[Hidden: Draft]</em>") && html.contains("Public"), "{}", html);
+        assert!(!html.contains("Secret"), "cached render not reused: {}", html);
     }
 
     #[test]
-    fn wikilink_inside_inline_code_not_replaced() {
+    fn embeds_outside_export_scope_become_links() {
         let dir = tempfile::TempDir::new().unwrap();
-        let root = dir.path();
-        std::fs::write(root.join("Note.md"), "# Note").unwrap();
-        std::fs::write(root.join("A.md"), "Code: `[[Link]]` end").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "![[In]]\n\n![[Out|Other]]").unwrap();
+        std::fs::write(root.join("In.md"), "Inside").unwrap();
+        std::fs::write(root.join("Out.md"), "Outside").unwrap();
 
-        let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
+        ctx.render.embed_scope = Some([root.join("A.md"), root.join("In.md")].into_iter().collect());
         let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
-        assert!(html.contains("[[Link]]"), "[[Link]] inside inline code should remain literal: {}", html);
-    }
-
-    #[test]
-    fn cache_lru_evicts_oldest_when_limit_reached() {
-        let mut cache = RenderCache::default();
-        let mtime = SystemTime::UNIX_EPOCH;
-        
-        // Insert entries up to limit
-        for i in 0..=MAX_CACHE_ENTRIES {
-            let path = PathBuf::from(format!("/file{}.md", i));
-            let html = format!("<h1>File {}</h1>", i);
-            cache.insert(path, mtime, html);
-        }
-        
-        let (count, _, _, _) = cache.get_stats();
-        assert!(count <= MAX_CACHE_ENTRIES, "cache should not exceed max entries");
+        assert!(html.contains("Inside") && !html.contains("Outside"), "{}", html);
+        assert!(html.contains("Out.md\">Other</a>"), "{}", html);
+        assert_eq!(ctx.cache.get_stats().0, 0, "scoped renders not cached");
     }
 
     #[test]
-    fn cache_lru_evicts_when_size_limit_reached() {
-        let mut cache = RenderCache::default();
-        let mtime = SystemTime::UNIX_EPOCH;
-        
-        // Insert large entries
-        let large_html = "x".repeat(1024 * 1024); // 1MB each
-        for i in 0..60 {
-            let path = PathBuf::from(format!("/large{}.md", i));
-            cache.insert(path, mtime, large_html.clone());
-        }
-        
-        let (_, size_bytes, _, _) = cache.get_stats();
-        assert!(size_bytes <= MAX_CACHE_SIZE_BYTES, "cache size should not exceed limit");
-    }
+    fn backlinks_footer_lists_linking_notes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("Plan.md"), "# Plan").unwrap();
+        std::fs::write(root.join("A.md"), "Intro\nSee [[Plan]] <soon>").unwrap();
 
-    #[test]
-    fn cache_tracks_hits_and_misses() {
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
-        let path = PathBuf::from("/test.md");
-        let mtime = SystemTime::UNIX_EPOCH;
-        
-        // Miss
-        let result = cache.get(&path, mtime);
-        assert!(result.is_none());
-        
-        // Insert
-        cache.insert(path.clone(), mtime, "<h1>Test</h1>".to_string());
-        
-        // Hit
-        let result = cache.get(&path, mtime);
-        assert!(result.is_some());
-        
-        let (_, _, hits, misses) = cache.get_stats();
-        assert_eq!(hits, 1);
-        assert_eq!(misses, 1);
+        let mut ctx = RenderContext {
+            vault_root: root.clone(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("Plan.md"), &mut ctx);
+        assert!(!html.contains("backlinks-footer"), "{}", html);
+        ctx.render.backlinks_footer = true;
+        let html = render_markdown_with_embeds(&root.join("Plan.md"), &mut ctx);
+        assert!(html.contains("<h2>Linked mentions</h2>"), "{}", html);
+        let item = ">A</a> <span class=\"backlink-snippet\">See [[Plan]] &lt;soon&gt;</span>";
+        assert!(html.contains(item), "{}", html);
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(!html.contains("backlinks-footer"), "no backlinks: {}", html);
     }
 
     #[test]
-    fn cache_updates_access_order_on_get() {
-        let mut cache = RenderCache::default();
-        let mtime = SystemTime::UNIX_EPOCH;
-        
-        let path1 = PathBuf::from("/file1.md");
-        let path2 = PathBuf::from("/file2.md");
-        
-        cache.insert(path1.clone(), mtime, "<h1>1</h1>".to_string());
-        cache.insert(path2.clone(), mtime, "<h1>2</h1>".to_string());
-        
-        // Access first file
-        cache.get(&path1, mtime);
-        
-        // Insert another to trigger eviction
-        for i in 3..=MAX_CACHE_ENTRIES + 1 {
-            let path = PathBuf::from(format!("/file{}.md", i));
-            cache.insert(path, mtime, format!("<h1>{}</h1>", i));
-        }
-        
-        // path1 should still be in cache (most recently accessed)
-        let result = cache.get(&path1, mtime);
-        assert!(result.is_some(), "most recently accessed entry should remain");
-    }
+    fn properties_table_replaces_frontmatter_but_not_in_embeds() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "---\nstatus: done\n---\n# A\n\n![[B]]").unwrap();
+        std::fs::write(root.join("B.md"), "---\nsecret: b\n---\nB body").unwrap();
 
-    #[test]
-    fn cache_clear_resets_all_stats() {
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
-        let mtime = SystemTime::UNIX_EPOCH;
-        
-        cache.insert(PathBuf::from("/test.md"), mtime, "<h1>Test</h1>".to_string());
-        cache.get(&PathBuf::from("/test.md"), mtime);
-        
-        cache.clear();
-        
-        let (count, size, hits, misses) = cache.get_stats();
-        assert_eq!(count, 0);
-        assert_eq!(size, 0);
-        assert_eq!(hits, 0);
-        assert_eq!(misses, 0);
+        let mut ctx = RenderContext {
+            vault_root: root.clone(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions {
+                properties_table: true,
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        let table = "<table class=\"properties\"><tbody><tr data-type=\"text\"><th>status</th>";
+        assert!(html.starts_with(table), "{}", html);
+        assert!(html.contains("B body") && !html.contains("secret"), "{}", html);
+        assert!(!html.contains("<hr"), "{}", html);
+        let map = block_source_map_with_embeds(&root.join("A.md"), &mut ctx);
+        let lines: Vec<(usize, usize, usize)> =
+            map.iter().map(|b| (b.start_line, b.end_line, b.element_index)).collect();
+        assert_eq!(lines, [(1, 3, 0), (4, 4, 1), (6, 6, 2)]);
     }
 
     #[test]
-    fn cache_hit_when_mtime_unchanged() {
+    fn kanban_notes_render_as_boards() {
         let dir = tempfile::TempDir::new().unwrap();
-        let root = dir.path();
-        std::fs::write(root.join("x.md"), "# X").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let board = "---\nkanban-plugin: basic\n---\n\n## To do\n\n- [ ] Read [[Plan]]\n\n## Done\n\n- [x] Ship\n";
+        std::fs::write(root.join("Board.md"), board).unwrap();
+        std::fs::write(root.join("Plan.md"), "# Plan").unwrap();
 
-        let index = VaultIndex::build_index(root).unwrap();
-        let vault = root.canonicalize().unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
         let mut cache = RenderCache::default();
         let mut ctx = RenderContext {
-            vault_root: vault,
+            vault_root: root.clone(),
             index: &index,
             cache: &mut cache,
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
-        let html1 = render_markdown_with_embeds(&root.join("x.md"), &mut ctx);
-        let html2 = render_markdown_with_embeds(&root.join("x.md"), &mut ctx);
+        let html = render_markdown_with_embeds(&root.join("Board.md"), &mut ctx);
+        assert!(html.starts_with("<div class=\"kanban-board\">"), "{}", html);
+        assert_eq!(html.matches("<section class=\"kanban-lane\">").count(), 2, "{}", html);
+        assert!(html.contains("class=\"obs-link\""), "card links resolve: {}", html);
+        let offset = board.find("[ ] Read").unwrap();
+        assert!(html.contains(&format!("data-task-offset=\"{}\"", offset)), "{}", html);
+        assert!(!html.contains("kanban-plugin"), "{}", html);
+    }
+
+    #[test]
+    fn frontmatter_queries_compare_text_lists_and_dates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "---\nstatus: Done\ntags: [project, urgent]\ndue: 2024-06-10\n---\n").unwrap();
+        std::fs::write(root.join("B.md"), "---\nstatus: open\ndue: 2024-05-01T09:00\n---\n").unwrap();
+        std::fs::write(root.join("C.md"), "No frontmatter").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let names = |op: QueryOp, field: &str, value: &str| -> Vec<String> {
+            query_frontmatter(&index, field, op, value)
+                .into_iter()
+                .map(|m| m.path.rsplit(['/', '\\']).next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names(QueryOp::Equals, "Status", "done"), ["A.md"]);
+        assert_eq!(names(QueryOp::Contains, "tags", "proj"), ["A.md"]);
+        assert_eq!(names(QueryOp::GreaterThan, "due", "2024-06-01"), ["A.md"]);
+        assert_eq!(names(QueryOp::LessThan, "due", "2024-06-01"), ["B.md"]);
+        assert_eq!(names(QueryOp::Exists, "due", ""), ["A.md", "B.md"]);
+
+        let mut index = index;
+        index.refresh_note(&root.join("B.md"), "No frontmatter");
+        index.refresh_note(&root.join("C.md"), "---\nstatus: done\n---\n");
+        let names = |field: &str, value: &str| -> Vec<String> {
+            query_frontmatter(&index, field, QueryOp::Equals, value)
+                .into_iter()
+                .map(|m| m.path.rsplit(['/', '\\']).next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names("status", "done"), ["A.md", "C.md"], "edits count");
+        assert!(names("status", "open").is_empty());
+    }
+
+    #[test]
+    fn wikilink_completion_ranks_names_aliases_and_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("work")).unwrap();
+        std::fs::write(root.join("Plan.md"), "x").unwrap();
+        std::fs::write(root.join("work/Plan.md"), "x").unwrap();
+        std::fs::write(root.join("Planning notes.md"), "x").unwrap();
+        std::fs::write(root.join("Roadmap.md"), "---\naliases: [Plan B]\n---\n").unwrap();
+        std::fs::write(root.join("Other.md"), "x").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let links = |prefix: &str| -> Vec<String> {
+            complete_wikilink(&index, &root, prefix, 10).into_iter().map(|c| c.link).collect()
+        };
+        assert_eq!(links("plan"), ["Plan", "work/Plan", "Planning notes", "Roadmap|Plan B"]);
+        assert_eq!(links("work/"), ["work/Plan"]);
+        assert_eq!(complete_wikilink(&index, &root, "", 2).len(), 2);
+    }
+
+    #[test]
+    fn wikilink_completion_after_hash_lists_headings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let note = "---\n# not: heading\n---\n# Plan\n\n## Goals ##\n\n```\n# code\n```\n\n### Next goals\n#tag\n";
+        std::fs::write(root.join("Plan.md"), note).unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let completions = complete_wikilink(&index, &root, "Plan#go", 10);
+        let links: Vec<&str> = completions.iter().map(|c| c.link.as_str()).collect();
+        assert_eq!(links, ["Plan#Goals", "Plan#Next goals"]);
+        assert_eq!(completions[1].heading.as_ref().map(|h| (h.level, h.line)), Some((3, 12)));
+        assert_eq!(complete_wikilink(&index, &root, "Plan#", 10).len(), 3);
+        assert!(complete_wikilink(&index, &root, "Missing#", 10).is_empty());
+    }
+
+    #[test]
+    fn heading_completions_follow_edits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("Plan.md"), "# Plan\n\n## Goals\n").unwrap();
+        let mut index = VaultIndex::build_index(&root).unwrap();
+        let edited = "# Plan\n\n## Milestones\n";
+        std::fs::write(root.join("Plan.md"), edited).unwrap();
+        index.refresh_note(&root.join("Plan.md"), edited);
+        let links = |prefix: &str| -> Vec<String> {
+            complete_wikilink(&index, &root, prefix, 10).into_iter().map(|c| c.link).collect()
+        };
+        assert_eq!(links("Plan#mile"), ["Plan#Milestones"]);
+        assert!(links("Plan#goals").is_empty());
+    }
+
+    #[test]
+    fn aliases_from_lists_and_single_values() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "---\naliases:\n  - Alpha\n  - first\n---\n").unwrap();
+        std::fs::write(root.join("B.md"), "---\nalias: Beta\n---\n").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let aliases: Vec<(String, bool)> = note_aliases(&index)
+            .into_iter()
+            .map(|a| (a.alias, a.path.ends_with("A.md")))
+            .collect();
+        assert_eq!(
+            aliases,
+            [("Alpha".to_string(), true), ("Beta".to_string(), false), ("first".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn block_ids_end_the_paragraph_and_are_reused() {
+        let md = "# Title\n\nFirst line\nsecond line\n\n- item ^done\n- other\n\n```\ncode\n```\n";
+        let (out, id) = add_block_id(md, 3).unwrap();
+        assert_eq!(id.len(), 6);
+        assert!(out.contains(&format!("First line\nsecond line ^{}\n", id)), "{}", out);
+        assert_eq!(add_block_id(&out, 4).unwrap(), (out.clone(), id));
+        assert_eq!(add_block_id(md, 6).unwrap().1, "done");
+        let (out, id) = add_block_id(md, 7).unwrap();
+        assert!(out.contains(&format!("- other ^{}\n", id)), "{}", out);
+        assert!(add_block_id(md, 2).is_err(), "blank line");
+        assert!(add_block_id(md, 10).is_err(), "code block");
+        assert!(add_block_id(md, 99).is_err());
+    }
+
+    #[test]
+    fn lint_reports_structure_problems_by_line() {
+        let md = "---\ntitle: x\n---\n# Top\n### Deep\n## Top\n\nSee [[Plan and ![[]] `[[code`\n\n```rust\nlet a = 1;\n";
+        let issues: Vec<(usize, LintKind)> = lint_note(md).iter().map(|i| (i.line, i.kind)).collect();
+        assert_eq!(
+            issues,
+            [
+                (5, LintKind::SkippedHeadingLevel),
+                (6, LintKind::DuplicateHeading),
+                (8, LintKind::MalformedWikilink),
+                (8, LintKind::MalformedWikilink),
+                (10, LintKind::UnclosedFence),
+            ]
+        );
+        assert!(lint_note("# A\n## B\n# A2\n[[Note]] ![[img.png]]\n```\n[[x\n```\n").is_empty());
+    }
+
+    #[test]
+    fn tags_from_frontmatter_and_body_outside_code() {
+        let md = "---\ntags: [project, Work]\n---\n# Heading\n\nSee #work and #area/home, not #1 or a#b.\n\
+                  `#code`\n\n```\n#fenced\n```\n#last";
+        assert_eq!(note_tags(md), ["project", "Work", "area/home", "last"]);
+        assert_eq!(note_tags("---\ntags: a, #b\n---\n"), ["a", "b"]);
+    }
+
+    #[test]
+    fn notes_under_filters_by_folder_prefix() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("Projects/sub")).unwrap();
+        std::fs::create_dir_all(root.join("ProjectsOld")).unwrap();
+        for note in ["A.md", "Projects/B.md", "Projects/sub/C.md", "ProjectsOld/D.md"] {
+            std::fs::write(root.join(note), "x").unwrap();
+        }
+        let index = VaultIndex::build_index(&root).unwrap();
+        let names: Vec<PathBuf> = index
+            .notes_under(&root.join("Projects"))
+            .iter()
+            .map(|p| p.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(names, [PathBuf::from("Projects/B.md"), PathBuf::from("Projects/sub/C.md")]);
+    }
+
+    #[test]
+    fn folder_move_rewrites_path_links_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("Projects/sub")).unwrap();
+        std::fs::write(root.join("Projects/sub/Plan.md"), "# Plan").unwrap();
+        std::fs::write(
+            root.join("A.md"),
+            "[[Projects/sub/Plan#Goals|plan]] ![[Projects/]] [[Plan]] [[ProjectsX/Plan]]\n`[[Projects/sub/Plan]]`",
+        )
+        .unwrap();
+        std::fs::write(root.join("B.md"), "[[Plan]]").unwrap();
+        std::fs::write(root.join("C.md"), "- Item\n\n    See [[Projects/sub/Plan]]").unwrap();
+
+        let index = VaultIndex::build_index(&root).unwrap();
+        let notes = folder_move_edits(&index, "Projects", "Archive/Projects 2024");
+        assert_eq!(notes.len(), 2, "{:?}", notes);
+        assert_eq!(notes[1].content, "- Item\n\n    See [[Archive/Projects 2024/sub/Plan]]");
+        assert_eq!(notes[0].path, root.join("A.md"));
+        let afters: Vec<&str> = notes[0].edits.iter().map(|e| e.after.as_str()).collect();
+        assert_eq!(afters, ["[[Archive/Projects 2024/sub/Plan#Goals|plan]]", "![[Archive/Projects 2024/]]"]);
+        assert_eq!(
+            notes[0].content,
+            "[[Archive/Projects 2024/sub/Plan#Goals|plan]] ![[Archive/Projects 2024/]] [[Plan]] [[ProjectsX/Plan]]\n`[[Projects/sub/Plan]]`"
+        );
+    }
+
+    #[test]
+    fn duplicates_report_shared_basenames_and_similar_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("old")).unwrap();
+        let text = "Meeting notes about the quarterly roadmap and the hiring plan for the platform team";
+        std::fs::write(root.join("Plan.md"), text).unwrap();
+        std::fs::write(root.join("old/Plan.md"), format!("---\ntags: [x]\n---\n{}.", text)).unwrap();
+        std::fs::write(root.join("Copy.md"), format!("{} and budget", text)).unwrap();
+        std::fs::write(root.join("Other.md"), "Completely unrelated text about gardening tomatoes in the summer heat").unwrap();
+        std::fs::write(root.join("Stub.md"), "").unwrap();
+        std::fs::write(root.join("Stub2.md"), "").unwrap();
+
+        let index = VaultIndex::build_index(&root).unwrap();
+        let report = find_duplicates(&index);
+        assert_eq!(report.shared_basenames.len(), 1);
+        assert_eq!(report.shared_basenames[0].name, "Plan");
+        assert_eq!(report.shared_basenames[0].paths.len(), 2);
+        let pairs: Vec<(&str, &str)> = report
+            .similar
+            .iter()
+            .map(|s| (s.paths[0].rsplit('/').next().unwrap(), s.paths[1].rsplit('/').next().unwrap()))
+            .collect();
+        assert!(pairs.contains(&("Plan.md", "Plan.md")), "identical bodies: {:?}", pairs);
+        assert!(pairs.contains(&("Copy.md", "Plan.md")), "near-duplicate: {:?}", pairs);
+        assert!(report.similar[0].similarity == 1.0);
+        assert!(!pairs.iter().any(|(a, b)| a.starts_with("Other") || b.starts_with("Stub")), "{:?}", pairs);
+    }
+
+    #[test]
+    fn embed_dependencies_follow_nested_embeds() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "![[B]] [[C]]").unwrap();
+        std::fs::write(root.join("B.md"), "![[D]] ![[A]]").unwrap();
+        std::fs::write(root.join("C.md"), "c").unwrap();
+        std::fs::write(root.join("D.md"), "d").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let files = embed_dependencies(&root.join("A.md"), &index, &root);
+        let expected: HashSet<PathBuf> = ["A.md", "B.md", "D.md"].iter().map(|f| root.join(f)).collect();
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn unsaved_markdown_renders_with_vault_links_and_embeds() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("Note.md"), "saved text").unwrap();
+        std::fs::write(root.join("Part.md"), "embedded part").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.clone(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let note = root.join("Note.md");
+        let markdown = "draft ![[Part]] ![[Note]] [[Part]]";
+        let html = render_markdown_string_with_embeds(markdown, Some(&note), &mut ctx);
+        assert!(html.contains("draft") && !html.contains("saved text"), "{}", html);
+        assert!(html.contains("embedded part"), "{}", html);
+        assert!(html.contains("(cycle)"), "{}", html);
+        assert!(html.contains("data-obs-path"), "{}", html);
+        assert!(ctx.visited.is_empty() && ctx.depth == 0);
+        assert_eq!(cache.get_stats().0, 0, "nothing cached");
+    }
+
+    #[test]
+    fn embedding_notes_include_indirect_embedders() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "![[B]]").unwrap();
+        std::fs::write(root.join("B.md"), "![[C]] ![[pic.png]]").unwrap();
+        std::fs::write(root.join("C.md"), "c ![[A]]").unwrap();
+        std::fs::write(root.join("D.md"), "[[C]]").unwrap();
+        std::fs::write(root.join("pic.png"), "png").unwrap();
+        let index = VaultIndex::build_index(&root).unwrap();
+        let notes = |changed: &str| find_embedding_notes(&[root.join(changed)], &index, &root);
+        assert_eq!(notes("C.md"), [root.join("A.md"), root.join("B.md")], "links don't count, cycles end");
+        assert_eq!(notes("pic.png"), [root.join("A.md"), root.join("B.md"), root.join("C.md")]);
+        assert!(notes("D.md").is_empty());
+
+        let mut index = index;
+        index.refresh_note(&root.join("D.md"), "![[C]]");
+        let notes = find_embedding_notes(&[root.join("C.md")], &index, &root);
+        assert_eq!(notes, [root.join("A.md"), root.join("B.md"), root.join("D.md")], "edits count");
+    }
+
+    #[test]
+    fn superseded_render_stops_and_skips_cache() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "A ![[B]]").unwrap();
+        std::fs::write(root.join("B.md"), "B").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let latest = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let older = RenderGeneration::next(&latest);
+        let newer = RenderGeneration::next(&latest);
+        assert!(older.is_superseded() && !newer.is_superseded());
+
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: older,
+        };
+        assert_eq!(render_markdown_with_embeds(&root.join("A.md"), &mut ctx), "");
+        assert_eq!(ctx.cache.get_stats().0, 0);
+        ctx.generation = newer;
+        assert!(render_markdown_with_embeds(&root.join("A.md"), &mut ctx).contains("B"));
+        assert_eq!(ctx.cache.get_stats().0, 1);
+    }
+
+    #[test]
+    fn expand_cycle_detection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "A ![[B]]").unwrap();
+        std::fs::write(root.join("B.md"), "B ![[A]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("<p>A</p>"), "{}", html);
+        assert!(html.contains("<p>B</p>"), "{}", html);
+        assert!(html.contains("cycle"), "expected cycle placeholder in {}", html);
+    }
+
+    #[test]
+    fn expand_depth_limit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("0.md"), "0 ![[1]]").unwrap();
+        std::fs::write(root.join("1.md"), "1 ![[2]]").unwrap();
+        std::fs::write(root.join("2.md"), "2 ![[3]]").unwrap();
+        std::fs::write(root.join("3.md"), "3 ![[4]]").unwrap();
+        std::fs::write(root.join("4.md"), "4 ![[5]]").unwrap();
+        std::fs::write(root.join("5.md"), "# Five").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 3,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("0.md"), &mut ctx);
+        assert!(html.contains("depth limit"), "expected depth limit placeholder in {}", html);
+    }
+
+    #[test]
+    fn embed_placeholders_use_configured_text_and_classes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Loop.md"), "![[Loop]] ![[Missing]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions {
+                embed_placeholders: [(EmbedPlaceholder::NotFound, "«{name}» não encontrada <b>")]
+                    .into_iter()
+                    .map(|(kind, text)| (kind, text.to_string()))
+                    .collect(),
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("Loop.md"), &mut ctx);
+        assert!(
+            html.contains(
+                "<em class=\"embed-placeholder embed-placeholder-not-found\" data-placeholder=\"not_found\">\
+                 «Missing» não encontrada &lt;b&gt;</em>"
+            ),
+            "{}",
+            html
+        );
+        let cycle = "embed-placeholder-cycle\" data-placeholder=\"cycle\">[Embed: Loop.md (cycle)]";
+        assert!(html.contains(cycle), "{}", html);
+
+        let flat = flatten_markdown_with_embeds(&root.join("Loop.md"), root, true, &mut ctx);
+        assert!(flat.contains("*«Missing» não encontrada <b>*"), "{}", flat);
+    }
+
+    #[test]
+    fn oversized_and_failed_embeds_use_configured_text() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "![[Log]] ![[Gone]]").unwrap();
+        std::fs::write(root.join("Log.md"), "line\n".repeat(2000)).unwrap();
+        std::fs::write(root.join("Gone.md"), "x").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        std::fs::remove_file(root.join("Gone.md")).unwrap();
+        let mut cache = RenderCache::default();
+        let texts = [
+            (EmbedPlaceholder::TooLarge, "{name} é grande demais ({size})"),
+            (EmbedPlaceholder::EmbedFailed, "Falha:"),
+            (EmbedPlaceholder::Retry, "Tentar de novo"),
+        ];
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions {
+                max_embed_bytes: Some(4096),
+                embed_placeholders: texts.into_iter().map(|(kind, text)| (kind, text.to_string())).collect(),
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("embed-placeholder-too-large"), "{}", html);
+        assert!(html.contains("Log.md\">Log.md</a> é grande demais (9 KB)</em>"), "{}", html);
+        assert!(html.contains("<span class=\"embed-error-label\">Falha:</span>"), "{}", html);
+        assert!(html.contains("class=\"embed-retry\">Tentar de novo</button>"), "{}", html);
+
+        let flat = flatten_markdown_with_embeds(&root.join("A.md"), root, true, &mut ctx);
+        assert!(flat.contains("*[Log.md](Log.md) é grande demais (9 KB)*"), "{}", flat);
+        assert!(flat.contains("*[Falha: "), "{}", flat);
+    }
+
+    #[test]
+    fn size_limit_spares_the_opened_note() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Big.md"), format!("# Big\n\n{}", "line\n".repeat(2000))).unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions {
+                max_embed_bytes: Some(4096),
+                ..Default::default()
+            },
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("Big.md"), &mut ctx);
+        assert!(html.contains("Big</h1>"), "{}", html);
+        assert!(!html.contains("embed-placeholder-too-large"), "{}", html);
+    }
+
+    #[test]
+    fn image_sizes_resolve_against_the_note_folder() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/b.gif"), b"GIF89a\x0a\x00\x14\x00\x00\x00").unwrap();
+        std::fs::write(root.join("sub/Note.md"), "![x](b.gif)\n").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("sub/Note.md"), &mut ctx);
+        assert!(html.contains("width=\"10\" height=\"20\""), "{}", html);
+    }
+
+    #[test]
+    fn note_text_cannot_forge_sentinels() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let forged = concat!(
+            "\u{E00D}%22%3E%3Cimg%20src%3Dx%20onerror%3Dalert(1)%3E\u{E00E}x\u{E00F} ",
+            "\u{E007}%2Fetc%2Fpasswd\u{E008}"
+        );
+        std::fs::write(root.join("A.md"), forged).unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(!html.contains("embed-placeholder"), "{}", html);
+        assert!(!html.contains("csv-embed"), "{}", html);
+        let html = render_markdown_string_with_embeds(forged, None, &mut ctx);
+        assert!(!html.contains("<img") && !html.contains("csv-embed"), "{}", html);
+    }
+
+    #[test]
+    fn wikilink_renders_as_link_no_raw_brackets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Note.md"), "# Note").unwrap();
+        std::fs::write(root.join("A.md"), "See [[Note]] here").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(!html.contains("[[Note]]"), "wikilink should be replaced, no raw [[Note]] in {}", html);
+        assert!(html.contains("app://open?path="), "expected app link in {}", html);
+        assert!(html.contains("obs-link") || html.contains("href="), "expected link styling or href");
+    }
+
+    #[test]
+    fn external_links_open_in_new_context() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Note.md"), "# Note").unwrap();
+        std::fs::write(root.join("A.md"), "[site](https://example.com) [[Note]] [mail](mailto:a@b.c)").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        let external = "<a href=\"https://example.com\" class=\"external-link\" target=\"_blank\" \
+                        rel=\"noopener noreferrer\">site</a>";
+        assert!(html.contains(external), "{}", html);
+        assert_eq!(html.matches("external-link").count(), 1, "{}", html);
+    }
+
+    #[test]
+    fn heading_wikilink_carries_slugged_anchor() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Note.md"), "# Note\n\n## Next Steps").unwrap();
+        std::fs::write(root.join("A.md"), "See [[Note#Next Steps]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("Note.md#next-steps\""), "expected anchor in href: {}", html);
+        assert!(html.contains("data-obs-anchor=\"next-steps\""), "expected anchor attr: {}", html);
+        let target = render_markdown_with_embeds(&root.join("Note.md"), &mut ctx);
+        assert!(target.contains("id=\"next-steps\""), "expected heading id: {}", target);
+    }
+
+    #[test]
+    fn block_links_target_indexed_block_ids() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Note.md"), "# Note\n\nA claim ^claim-1\n\n```\nnot ^code\n```").unwrap();
+        std::fs::write(root.join("A.md"), "See [[Note^claim-1]] and [[Note^missing]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let note = root.join("Note.md").canonicalize().unwrap();
+        assert_eq!(index.block_line(&note, "claim-1"), Some(3));
+        assert_eq!(index.block_line(&note, "code"), None);
+
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("Note.md#^claim-1\""), "expected block anchor in href: {}", html);
+        assert!(!html.contains("#^missing"), "unknown block ids get no anchor: {}", html);
+        let target = render_markdown_with_embeds(&note, &mut ctx);
+        assert!(target.contains("<p id=\"^claim-1\">A claim</p>"), "expected block id: {}", target);
+        assert!(target.contains("not ^code"), "code keeps markers: {}", target);
+    }
+
+    #[test]
+    fn same_note_heading_link_is_in_page_anchor() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "Jump to [[#Next Steps]] or [[#^tail]]\n\n## Next Steps\n\nEnd ^tail").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("<a href=\"#next-steps\">Next Steps</a>"), "expected in-page link: {}", html);
+        assert!(html.contains("href=\"#%5Etail\""), "expected block anchor: {}", html);
+        assert!(!html.contains("obs-link broken"), "same-note links are not broken: {}", html);
+    }
+
+    #[test]
+    fn parse_wikilink_inner_hash_caret_is_block() {
+        let p = parse_wikilink_inner("Note#^abc");
+        assert_eq!(p.target, "Note");
+        assert_eq!(p.subtarget, Some(HeadingOrBlock::Block("abc".to_string())));
+    }
+
+    #[test]
+    fn folder_embed_lists_notes_one_level_deep() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("Projects/archive")).unwrap();
+        std::fs::write(root.join("Projects/beta.md"), "# B").unwrap();
+        std::fs::write(root.join("Projects/Alpha.md"), "# A").unwrap();
+        std::fs::write(root.join("Projects/archive/old.md"), "# Old").unwrap();
+        std::fs::write(root.join("MOC.md"), "![[Projects/]]\n\n![[Nope/]]").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("MOC.md"), &mut ctx);
+        let alpha = html.find(">Alpha</a>").expect("Alpha listed");
+        let beta = html.find(">beta</a>").expect("beta listed");
+        assert!(alpha < beta, "sorted by name: {}", html);
+        assert!(!html.contains("archive"), "only one level deep: {}", html);
+        assert!(html.contains("Nope/ (not found)"), "missing folder placeholder: {}", html);
+    }
+
+    #[test]
+    fn heading_anchor_uses_last_nested_heading() {
+        assert_eq!(super::parse::heading_anchor("Intro#What's New?"), "whats-new");
+    }
+
+    #[test]
+    fn unicode_links_resolve_across_normalization_forms() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        // File name in decomposed form (U+0308 combining diaeresis), link in composed form.
+        std::fs::write(root.join("U\u{308}ber.md"), "# \u{dc}berschrift").unwrap();
+        std::fs::write(root.join("A.md"), "[[\u{dc}ber#\u{dc}berschrift]] [[\u{65e5}\u{8a18}]]").unwrap();
+        std::fs::write(root.join("\u{65e5}\u{8a18}.md"), "x").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("data-obs-anchor=\"\u{fc}berschrift\""), "unicode slug: {}", html);
+        assert!(html.contains("\u{65e5}\u{8a18}.md\""), "non-ASCII kept readable in href: {}", html);
+        assert!(!html.contains("%E6"), "no byte-encoded chars: {}", html);
+    }
+
+    #[test]
+    fn wikilink_broken_renders_as_broken_or_empty_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "See [[Missing]] here").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(!html.contains("[[Missing]]"), "broken wikilink should be replaced");
+        let has_broken = html.contains("obs-link broken") || html.contains("app://open?path=\"\"") || (html.contains("app://open?path=") && html.contains("Missing"));
+        assert!(has_broken, "expected broken link marker in {}", html);
+    }
+
+    #[test]
+    fn broken_wikilinks_suggest_near_note_names() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("A.md"), "[[Projcet]] [[Nothing like it]]").unwrap();
+        std::fs::write(root.join("Project.md"), "x").unwrap();
+        std::fs::write(root.join("Projects.md"), "x").unwrap();
+        std::fs::write(root.join("Prose.md"), "x").unwrap();
+
+        let index = VaultIndex::build_index(&root).unwrap();
+        assert_eq!(suggest_targets("sub/projcet", &index), ["Project", "Projects"]);
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.clone(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        let suggestions = "data-suggestions=\"[&quot;Project&quot;,&quot;Projects&quot;]\">Projcet</span>";
+        assert!(html.contains(suggestions), "{}", html);
+        assert!(html.contains("<span class=\"obs-link broken\">Nothing like it</span>"), "{}", html);
+    }
+
+    #[test]
+    fn embed_no_literal_in_html() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("B.md"), "# B").unwrap();
+        std::fs::write(root.join("A.md"), "Before ![[B]] After").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(!html.contains("![["), "embed syntax must not appear in output HTML");
+    }
+
+    #[test]
+    fn normal_markdown_link_unchanged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("A.md"), "Link: [text](https://x.com)").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("https://x.com"), "normal markdown link href should be preserved: {}", html);
+    }
+
+    #[test]
+    fn wikilink_inside_inline_code_not_replaced() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Note.md"), "# Note").unwrap();
+        std::fs::write(root.join("A.md"), "Code: `[[Link]]` end").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html = render_markdown_with_embeds(&root.join("A.md"), &mut ctx);
+        assert!(html.contains("[[Link]]"), "[[Link]] inside inline code should remain literal: {}", html);
+    }
+
+    #[test]
+    fn source_map_with_embeds_points_embedded_blocks_at_embed_line() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("B.md"), "# B\n\nB body").unwrap();
+        std::fs::write(root.join("A.md"), "# A\n\n![[B]]\n\nAfter").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let map = block_source_map_with_embeds(&root.join("A.md"), &mut ctx);
+        let lines: Vec<(usize, usize, usize)> = map
+            .iter()
+            .map(|b| (b.start_line, b.end_line, b.element_index))
+            .collect();
+        assert_eq!(lines, vec![(1, 1, 0), (3, 3, 1), (5, 5, 2)]);
+    }
+
+    #[test]
+    fn cache_lru_evicts_oldest_when_limit_reached() {
+        let mut cache = RenderCache::default();
+        let mtime = SystemTime::UNIX_EPOCH;
+        
+        // Insert entries up to limit
+        for i in 0..=MAX_CACHE_ENTRIES {
+            let path = PathBuf::from(format!("/file{}.md", i));
+            let html = format!("<h1>File {}</h1>", i);
+            cache.insert(path, mtime, html);
+        }
+        
+        let (count, _, _, _) = cache.get_stats();
+        assert!(count <= MAX_CACHE_ENTRIES, "cache should not exceed max entries");
+    }
+
+    #[test]
+    fn cache_lru_evicts_when_size_limit_reached() {
+        let mut cache = RenderCache::default();
+        let mtime = SystemTime::UNIX_EPOCH;
+        
+        // Insert large entries
+        let large_html = "x".repeat(1024 * 1024); // 1MB each
+        for i in 0..60 {
+            let path = PathBuf::from(format!("/large{}.md", i));
+            cache.insert(path, mtime, large_html.clone());
+        }
+        
+        let (_, size_bytes, _, _) = cache.get_stats();
+        assert!(size_bytes <= MAX_CACHE_SIZE_BYTES, "cache size should not exceed limit");
+    }
+
+    #[test]
+    fn cache_tracks_hits_and_misses() {
+        let mut cache = RenderCache::default();
+        let path = PathBuf::from("/test.md");
+        let mtime = SystemTime::UNIX_EPOCH;
+        
+        // Miss
+        let result = cache.get(&path, mtime);
+        assert!(result.is_none());
+        
+        // Insert
+        cache.insert(path.clone(), mtime, "<h1>Test</h1>".to_string());
+        
+        // Hit
+        let result = cache.get(&path, mtime);
+        assert!(result.is_some());
+        
+        let (_, _, hits, misses) = cache.get_stats();
+        assert_eq!(hits, 1);
+        assert_eq!(misses, 1);
+    }
+
+    #[test]
+    fn cache_updates_access_order_on_get() {
+        let mut cache = RenderCache::default();
+        let mtime = SystemTime::UNIX_EPOCH;
+        
+        let path1 = PathBuf::from("/file1.md");
+        let path2 = PathBuf::from("/file2.md");
+        
+        cache.insert(path1.clone(), mtime, "<h1>1</h1>".to_string());
+        cache.insert(path2.clone(), mtime, "<h1>2</h1>".to_string());
+        
+        // Access first file
+        cache.get(&path1, mtime);
+        
+        // Insert another to trigger eviction
+        for i in 3..=MAX_CACHE_ENTRIES + 1 {
+            let path = PathBuf::from(format!("/file{}.md", i));
+            cache.insert(path, mtime, format!("<h1>{}</h1>", i));
+        }
+        
+        // path1 should still be in cache (most recently accessed)
+        let result = cache.get(&path1, mtime);
+        assert!(result.is_some(), "most recently accessed entry should remain");
+    }
+
+    #[test]
+    fn evicting_a_changed_file_drops_notes_embedding_it() {
+        let mut cache = RenderCache::default();
+        let mtime = SystemTime::UNIX_EPOCH;
+        let host = PathBuf::from("/host.md");
+        let embedded = PathBuf::from("/part.md");
+        let other = PathBuf::from("/x.md");
+        let embeds = HashSet::from([embedded.clone()]);
+        cache.insert_with_embeds(host.clone(), mtime, "host".to_string(), embeds);
+        cache.insert(embedded.clone(), mtime, "part".to_string());
+        cache.insert(other.clone(), mtime, "x".to_string());
+
+        assert_eq!(cache.evict_changed(std::slice::from_ref(&embedded)), 2);
+        assert!(cache.get(&host, mtime).is_none(), "embedding note dropped, its own mtime unchanged");
+        assert!(cache.get(&embedded, mtime).is_none());
+        assert!(cache.get(&other, mtime).is_some());
+        assert_eq!(cache.get_stats().1, 1);
+    }
+
+    #[test]
+    fn cache_clear_resets_all_stats() {
+        let mut cache = RenderCache::default();
+        let mtime = SystemTime::UNIX_EPOCH;
+        
+        cache.insert(PathBuf::from("/test.md"), mtime, "<h1>Test</h1>".to_string());
+        cache.get(&PathBuf::from("/test.md"), mtime);
+        
+        cache.clear();
+        
+        let (count, size, hits, misses) = cache.get_stats();
+        assert_eq!(count, 0);
+        assert_eq!(size, 0);
+        assert_eq!(hits, 0);
+        assert_eq!(misses, 0);
+    }
+
+    #[test]
+    fn cache_hit_when_mtime_unchanged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("x.md"), "# X").unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: vault,
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let html1 = render_markdown_with_embeds(&root.join("x.md"), &mut ctx);
+        let html2 = render_markdown_with_embeds(&root.join("x.md"), &mut ctx);
         assert_eq!(html1, html2);
         assert!(html1.contains("X"));
     }
@@ -599,6 +1870,8 @@ mod tests {
             visited: HashSet::new(),
             depth: 0,
             max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
         };
         let html1 = render_markdown_with_embeds(&path, &mut ctx);
         assert!(html1.contains("Y1"));
@@ -609,4 +1882,182 @@ mod tests {
         assert!(html2.contains("Y2"));
         assert!(!html2.contains("Y1"));
     }
+
+    #[test]
+    fn preview_link_excerpts_heading_sections_and_blocks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let paras: Vec<String> = (1..=7).map(|i| format!("Para {}", i)).collect();
+        let note = format!(
+            "---\ntags: [x]\n---\n# Top\n\n{}\n\n## Setup\n\nInstall it\n\n### Detail\n\nDeep\n\n## Usage\n\nRun it ^run\n",
+            paras.join("\n\n")
+        );
+        std::fs::write(root.join("Guide.md"), note).unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let mut cache = RenderCache::default();
+        let mut ctx = RenderContext {
+            vault_root: root.canonicalize().unwrap(),
+            index: &index,
+            cache: &mut cache,
+            visited: HashSet::new(),
+            depth: 0,
+            max_depth: 5,
+            render: RenderOptions::default(),
+            generation: RenderGeneration::default(),
+        };
+        let whole = preview_link("[[Guide]]", &mut ctx).unwrap();
+        assert!(whole.truncated && whole.html.contains("Para 4") && !whole.html.contains("Para 5"), "{}", whole.html);
+        assert!(!whole.html.contains("tags"), "frontmatter skipped: {}", whole.html);
+
+        let section = preview_link("Guide#setup", &mut ctx).unwrap();
+        assert_eq!(section.title, "setup");
+        assert!(section.html.contains("Deep") && !section.html.contains("Run it"), "{}", section.html);
+        assert!(!section.truncated);
+
+        let block = preview_link("Guide#^run", &mut ctx).unwrap();
+        assert!(block.html.contains("Run it") && !block.html.contains("Usage"), "{}", block.html);
+        assert!(preview_link("Missing", &mut ctx).is_err());
+    }
+
+    #[test]
+    fn outgoing_links_report_resolution() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/Other.md"), "x").unwrap();
+        std::fs::write(
+            root.join("A.md"),
+            "[[Other]] and ![[Gone]]\n\n[web](https://example.com) [rel](sub/Other.md#top) [bad](nope.md)\n\n`[[code]]`",
+        )
+        .unwrap();
+
+        let index = VaultIndex::build_index(root).unwrap();
+        let vault = root.canonicalize().unwrap();
+        let links = find_outgoing_links(&root.join("A.md"), &index, &vault).unwrap();
+        let summary: Vec<(&str, outgoing::LinkKind, outgoing::LinkStatus)> =
+            links.iter().map(|l| (l.target.as_str(), l.kind, l.status)).collect();
+        use outgoing::{LinkKind::*, LinkStatus::*};
+        assert_eq!(
+            summary,
+            [
+                ("Other", Wikilink, Resolved),
+                ("Gone", Embed, Broken),
+                ("https://example.com", Markdown, External),
+                ("sub/Other.md#top", Markdown, Resolved),
+                ("nope.md", Markdown, Broken),
+            ]
+        );
+        assert!(links[3].resolved_path.as_deref().unwrap().ends_with("Other.md"));
+    }
+
+    #[test]
+    fn link_check_classifies_broken_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/Other.md"), "[[A]] [[Gone]]").unwrap();
+        std::fs::write(
+            root.join("A.md"),
+            "[[Other]] ![[Missing]] ![[chart.png]]\n\n![img](img/none.png) [bad](nope.md) [web](https://x.org)",
+        )
+        .unwrap();
+
+        let index = VaultIndex::build_index(&root).unwrap();
+        let report = check_vault_links(&index, &root);
+        assert_eq!((report.notes_checked, report.resolved, report.external), (2, 2, 1));
+        let problems: Vec<(&str, &str, link_check::LinkProblem)> = report
+            .broken
+            .iter()
+            .map(|b| (b.source.rsplit('/').next().unwrap(), b.target.as_str(), b.problem))
+            .collect();
+        use link_check::LinkProblem::*;
+        assert_eq!(
+            problems,
+            [
+                ("A.md", "Missing", BrokenWikilink),
+                ("A.md", "chart.png", MissingAsset),
+                ("A.md", "img/none.png", MissingAsset),
+                ("A.md", "nope.md", DeadRelativePath),
+                ("Other.md", "Gone", BrokenWikilink),
+            ]
+        );
+        let markdown = report.to_markdown(&root);
+        assert!(markdown.contains("2 notes checked: 2 links resolved, 5 broken, 1 external."), "{}", markdown);
+        assert!(markdown.contains("## sub/Other.md\n\n- Line 1: `Gone` — broken wikilink\n"), "{}", markdown);
+    }
+
+    #[test]
+    fn build_index_with_reports_progress_and_aborts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            std::fs::write(dir.path().join(name), "x").unwrap();
+        }
+        let mut seen = Vec::new();
+        VaultIndex::build_index_with(dir.path(), &IndexOptions::default(), |done, total| {
+            seen.push((done, total));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(seen, [(1, 3), (2, 3), (3, 3)]);
+
+        let aborted = VaultIndex::build_index_with(dir.path(), &IndexOptions::default(), |_, _| {
+            Err("cancelled".to_string())
+        });
+        assert_eq!(aborted.err().as_deref(), Some("cancelled"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn index_symlinks_follow_policy_without_looping() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let vault = dir.path().join("vault");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(vault.join("sub")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(vault.join("sub/Inner.md"), "x").unwrap();
+        std::fs::write(outside.join("Shared.md"), "x").unwrap();
+        symlink(&vault, vault.join("sub/loop")).unwrap();
+        symlink(&outside, vault.join("linked")).unwrap();
+        symlink(vault.join("sub/Inner.md"), vault.join("Alias.md")).unwrap();
+
+        let build = |symlinks| {
+            let options = IndexOptions {
+                symlinks,
+                ..Default::default()
+            };
+            VaultIndex::build_index_with(&vault, &options, |_, _| Ok(())).unwrap()
+        };
+        let within = build(SymlinkPolicy::WithinVault);
+        assert_eq!(within.notes().len(), 1, "loop and duplicate link visited once: {:?}", within.notes());
+        assert!(within.by_basename("Shared").is_empty(), "outside target not indexed");
+
+        let follow = build(SymlinkPolicy::Follow);
+        assert!(follow.by_rel_path("linked/Shared").is_some(), "{:?}", follow.notes());
+
+        let skip = build(SymlinkPolicy::Skip);
+        assert_eq!(skip.notes().len(), 1);
+        assert!(skip.by_basename("Alias").is_empty());
+    }
+
+    #[test]
+    fn index_skips_excluded_folders() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = crate::paths::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("Archive/2020")).unwrap();
+        std::fs::create_dir_all(root.join("Archived")).unwrap();
+        std::fs::write(root.join("Keep.md"), "x").unwrap();
+        std::fs::write(root.join("Archive/2020/Old.md"), "x").unwrap();
+        std::fs::write(root.join("Archived/Other.md"), "x").unwrap();
+        let options = IndexOptions {
+            excluded: vec![root.join("Archive")],
+            ..Default::default()
+        };
+        let index = VaultIndex::build_index_with(&root, &options, |_, _| Ok(())).unwrap();
+        assert!(!index.by_basename("Keep").is_empty());
+        assert!(index.by_basename("Old").is_empty());
+        assert!(!index.by_basename("Other").is_empty(), "only whole folder names match");
+    }
 }
@@ -0,0 +1,123 @@
+//! Outgoing links: every wikilink, embed and markdown link in a note with its resolution.
+
+use std::fs;
+use std::path::Path;
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, Options};
+
+use crate::paths;
+use crate::text::percent_decode;
+
+use super::index::VaultIndex;
+use super::parse::{compute_skip_ranges, find_obsidian_spans_inner, parse_wikilink_inner};
+use super::resolve::{resolve_target, ResolveResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Wikilink,
+    Embed,
+    Markdown,
+    /// Markdown image, `![alt](src)`.
+    Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStatus {
+    Resolved,
+    Broken,
+    External,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OutgoingLink {
+    /// The link target as written (wikilink body or markdown URL).
+    pub target: String,
+    /// 1-based line of the link.
+    pub line: usize,
+    pub kind: LinkKind,
+    pub status: LinkStatus,
+    pub resolved_path: Option<String>,
+}
+
+/// `scheme:` URLs; a single letter before the colon is a Windows drive, not a scheme.
+fn is_external(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Markdown links and images: (url, line, kind).
+fn markdown_links(content: &str) -> Vec<(String, usize, LinkKind)> {
+    let arena = Arena::new();
+    let mut options = Options::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let root = parse_document(&arena, content, &options);
+    root.descendants()
+        .filter_map(|node| {
+            let line = node.data.borrow().sourcepos.start.line;
+            match &node.data.borrow().value {
+                NodeValue::Link(link) => Some((link.url.clone(), line, LinkKind::Markdown)),
+                NodeValue::Image(image) => Some((image.url.clone(), line, LinkKind::Image)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub fn find_outgoing_links(note: &Path, index: &VaultIndex, vault_root: &Path) -> Result<Vec<OutgoingLink>, String> {
+    let note = paths::canonicalize(note)?;
+    let content = fs::read_to_string(&note).map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+
+    let skip = compute_skip_ranges(&content);
+    for (is_embed, start, _, raw_inner) in find_obsidian_spans_inner(&content, &skip) {
+        let parsed = parse_wikilink_inner(&raw_inner);
+        let resolved = if parsed.target.trim().is_empty() {
+            Some(note.clone())
+        } else {
+            match resolve_target(&parsed, index, vault_root) {
+                ResolveResult::Resolved(p) | ResolveResult::Placeholder(p) => Some(p),
+                _ => None,
+            }
+        };
+        out.push(OutgoingLink {
+            target: raw_inner,
+            line: content[..start].matches('\n').count() + 1,
+            kind: if is_embed { LinkKind::Embed } else { LinkKind::Wikilink },
+            status: if resolved.is_some() { LinkStatus::Resolved } else { LinkStatus::Broken },
+            resolved_path: resolved.map(|p| p.to_string_lossy().into_owned()),
+        });
+    }
+
+    let note_dir = note.parent().unwrap_or(vault_root);
+    for (url, line, kind) in markdown_links(&content) {
+        let (status, resolved) = if is_external(&url) {
+            (LinkStatus::External, None)
+        } else {
+            let path = url.split(['#', '?']).next().unwrap_or_default();
+            let target = if path.is_empty() {
+                Some(note.clone())
+            } else {
+                paths::canonicalize(&note_dir.join(percent_decode(path))).ok()
+            };
+            match target {
+                Some(p) => (LinkStatus::Resolved, Some(p.to_string_lossy().into_owned())),
+                None => (LinkStatus::Broken, None),
+            }
+        };
+        out.push(OutgoingLink {
+            target: url,
+            line,
+            kind,
+            status,
+            resolved_path: resolved,
+        });
+    }
+    out.sort_by_key(|link| link.line);
+    Ok(out)
+}
@@ -1,50 +1,181 @@
-//! Parsing of `[[...]]` and `![[...]]` spans; skip ranges for code blocks and inline code.
+//! Parsing of `[[...]]` and `![[...]]` spans; skip ranges for frontmatter, code blocks and inline code.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::frontmatter::split_frontmatter;
+use crate::paths::slash_path;
 
 /// Inclusive (start, end) byte ranges that must not be scanned for [[ or ![[.
 pub(crate) fn compute_skip_ranges(text: &str) -> Vec<(usize, usize)> {
     let mut ranges = Vec::new();
-    let bytes = text.as_bytes();
-    let mut i = 0;
-    while i < bytes.len() {
-        if i + 3 <= bytes.len() && bytes[i] == b'`' && bytes[i + 1] == b'`' && bytes[i + 2] == b'`' {
-            let start = i;
-            i += 3;
-            while i < bytes.len() && bytes[i] != b'\n' {
-                i += 1;
-            }
-            if i < bytes.len() {
-                i += 1;
-            }
-            while i + 3 <= bytes.len() {
-                if bytes[i] == b'`' && bytes[i + 1] == b'`' && bytes[i + 2] == b'`' {
-                    i += 3;
-                    ranges.push((start, i));
-                    break;
+    let body_start = split_frontmatter(text).map_or(0, |(_, body)| text.len() - body.len());
+    if body_start > 0 {
+        ranges.push((0, body_start - 1));
+    }
+    let mut gap_start = body_start;
+    for (start, end) in code_block_ranges(&text[body_start..]) {
+        let (start, end) = (start + body_start, end + body_start);
+        inline_code_ranges(text, gap_start, start, &mut ranges);
+        ranges.push((start, end));
+        gap_start = end;
+    }
+    inline_code_ranges(text, gap_start, text.len(), &mut ranges);
+    ranges
+}
+
+/// Fenced (backtick or tilde) and indented code blocks, line by line as CommonMark reads them.
+/// Inside a list item, lines count as indented code only 4 columns past the item's content, so
+/// continuation paragraphs and nested items are not code. An unclosed fence runs to the end of
+/// the text.
+fn code_block_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(u8, usize, usize)> = None;
+    let mut indented: Option<(usize, usize)> = None;
+    // Content columns of the open list items, innermost last.
+    let mut list_items: Vec<usize> = Vec::new();
+    let mut prev_blank = true;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+        let end = start + content.len();
+        if let Some((marker, len, fence_start)) = fence {
+            if let Some((m, l, rest)) = fence_marker(content) {
+                if m == marker && l >= len && rest.trim().is_empty() {
+                    ranges.push((fence_start, end));
+                    fence = None;
                 }
-                i += 1;
             }
             continue;
         }
-        if bytes[i] == b'`' {
-            let start = i;
-            i += 1;
-            while i < bytes.len() && bytes[i] != b'`' {
-                i += 1;
-            }
-            if i < bytes.len() {
-                i += 1;
-                ranges.push((start, i));
+        let blank = content.trim().is_empty();
+        let indent = indent_width(content);
+        let item = list_item_content(content);
+        if !blank && prev_blank && item.is_none() {
+            // After a blank line, a line left of an item's content closes that item.
+            while list_items.last().is_some_and(|&column| indent < column) {
+                list_items.pop();
             }
+        }
+        let is_indented = indent >= list_items.last().copied().unwrap_or(0) + 4;
+        if is_indented && !blank && (prev_blank || indented.is_some()) {
+            indented = Some((indented.map_or(start, |(s, _)| s), end));
             continue;
         }
-        i += 1;
+        if !blank {
+            if let Some(block) = indented.take() {
+                ranges.push(block);
+            }
+        }
+        if let Some(column) = item {
+            while list_items.last().is_some_and(|&open| indent < open) {
+                list_items.pop();
+            }
+            list_items.push(column);
+        }
+        if let Some((marker, len, rest)) = fence_marker(content) {
+            if marker == b'~' || !rest.contains('`') {
+                fence = Some((marker, len, start));
+                prev_blank = false;
+                continue;
+            }
+        }
+        prev_blank = blank;
+    }
+    if let Some(block) = indented {
+        ranges.push(block);
+    }
+    if let Some((_, _, fence_start)) = fence {
+        ranges.push((fence_start, text.len()));
     }
     ranges
 }
 
-fn in_skip_range(pos: usize, skip: &[(usize, usize)]) -> bool {
+/// Columns of leading whitespace, tabs advancing to the next multiple of 4.
+fn indent_width(line: &str) -> usize {
+    let mut width = 0;
+    for b in line.bytes() {
+        match b {
+            b' ' => width += 1,
+            b'\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Column where the content of the list item `line` starts, when it starts one (`-`, `*`, `+`,
+/// `1.` or `1)` followed by whitespace or the end of the line).
+fn list_item_content(line: &str) -> Option<usize> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let indent = indent_width(line);
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match rest.as_bytes().get(digits)? {
+        b'-' | b'*' | b'+' if digits == 0 => 1,
+        b'.' | b')' if (1..=9).contains(&digits) => digits + 1,
+        _ => return None,
+    };
+    let after = &rest[marker..];
+    if after.trim().is_empty() {
+        return Some(indent + marker + 1);
+    }
+    let spaces = after.bytes().take_while(|&b| b == b' ').count();
+    match spaces {
+        0 => None,
+        1..=4 => Some(indent + marker + spaces),
+        // Five or more spaces start indented code inside the item, one space past the marker.
+        _ => Some(indent + marker + 1),
+    }
+}
+
+/// Marker byte, run length and the rest of the line when `line` opens or closes a fence.
+pub(crate) fn fence_marker(line: &str) -> Option<(u8, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = *trimmed.as_bytes().first()?;
+    if marker != b'`' && marker != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == marker).count();
+    (len >= 3).then(|| (marker, len, &trimmed[len..]))
+}
+
+/// Code spans: a run of N backticks closes at the next run of exactly N; an unmatched run is literal.
+fn inline_code_ranges(text: &str, from: usize, to: usize, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = &text.as_bytes()[..to];
+    let run_len = |at: usize| bytes[at..].iter().take_while(|&&b| b == b'`').count();
+    let mut i = from;
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let open = run_len(i);
+        i += open;
+        let mut j = i;
+        while j < bytes.len() {
+            if bytes[j] != b'`' {
+                j += 1;
+                continue;
+            }
+            let close = run_len(j);
+            if close == open {
+                ranges.push((start, j + close));
+                i = j + close;
+                break;
+            }
+            j += close;
+        }
+    }
+}
+
+pub(crate) fn in_skip_range(pos: usize, skip: &[(usize, usize)]) -> bool {
     skip.iter().any(|&(s, e)| pos >= s && pos <= e)
 }
 
@@ -145,7 +276,13 @@ pub fn parse_wikilink_inner(inner: &str) -> ParsedLink {
                 Some(HeadingOrBlock::Block(rest[c + 1..].trim().to_string())),
             ),
             (Some(s), Some(c)) => {
-                if s < c {
+                if c == s + 1 {
+                    // `Note#^id` is Obsidian's block link syntax.
+                    (
+                        rest[..s].replace('\\', "/").trim().to_string(),
+                        Some(HeadingOrBlock::Block(rest[c + 1..].trim().to_string())),
+                    )
+                } else if s < c {
                     (
                         rest[..s].replace('\\', "/").trim().to_string(),
                         Some(HeadingOrBlock::Heading(rest[s + 1..].trim().to_string())),
@@ -166,37 +303,160 @@ pub fn parse_wikilink_inner(inner: &str) -> ParsedLink {
     }
 }
 
+/// Encodes URL-significant ASCII, whitespace and control characters; other non-ASCII characters
+/// stay readable and are carried as UTF-8.
 fn percent_encode_path(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
-    for b in s.bytes() {
-        match b {
-            b'%' => out.push_str("%25"),
-            b'?' => out.push_str("%3F"),
-            b'#' => out.push_str("%23"),
-            b'&' => out.push_str("%26"),
-            b'=' => out.push_str("%3D"),
-            b'+' => out.push_str("%2B"),
-            b' ' => out.push_str("%20"),
-            b'"' => out.push_str("%22"),
-            b'<' => out.push_str("%3C"),
-            b'>' => out.push_str("%3E"),
-            _ if b.is_ascii_graphic() || b == b'/' => out.push(b as char),
-            _ => out.push_str(&format!("%{:02X}", b)),
+    for c in s.chars() {
+        match c {
+            '%' | '?' | '#' | '&' | '=' | '+' | '"' | '<' | '>' => push_encoded(&mut out, c),
+            _ if c.is_whitespace() || c.is_control() => push_encoded(&mut out, c),
+            _ => out.push(c),
         }
     }
     out
 }
 
-pub fn obs_link_href(resolved_path: Option<&Path>) -> String {
+fn push_encoded(out: &mut String, c: char) {
+    let mut buf = [0u8; 4];
+    for b in c.encode_utf8(&mut buf).bytes() {
+        out.push_str(&format!("%{:02X}", b));
+    }
+}
+
+/// `app://open` href for a resolved note; `anchor` (a heading or block id) is appended as `#anchor`.
+pub fn obs_link_href(resolved_path: Option<&Path>, anchor: Option<&str>) -> String {
     match resolved_path {
         Some(p) => {
-            let s = p.to_string_lossy().replace('\\', "/");
-            format!("app://open?path={}", percent_encode_path(&s))
+            let s = slash_path(p);
+            let href = format!("app://open?path={}", percent_encode_path(&s));
+            match anchor.filter(|a| !a.is_empty()) {
+                Some(anchor) => format!("{}#{}", href, anchor),
+                None => href,
+            }
         }
         None => "app://open?path=".to_string(),
     }
 }
 
+/// Percent-encoded markdown link destination for a plain (typically relative) `path`.
+pub fn portable_href(path: &Path) -> String {
+    percent_encode_path(&slash_path(path))
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// `obs_link_href` for the first of several `candidates`, each also listed as a `candidate`
+/// query parameter so postprocessing can expose them to the UI.
+pub fn obs_ambiguous_href(candidates: &[PathBuf], anchor: Option<&str>) -> String {
+    let mut out = obs_link_href(candidates.first().map(PathBuf::as_path), None);
+    for candidate in candidates {
+        out.push_str("&candidate=");
+        out.push_str(&percent_encode_path(&slash_path(candidate)));
+    }
+    match anchor.filter(|a| !a.is_empty()) {
+        Some(anchor) => format!("{}#{}", out, anchor),
+        None => out,
+    }
+}
+
+/// `app://open` href of an unresolved link, carrying `suggestions` (note names) as
+/// `&suggestion=` parameters.
+pub fn obs_broken_href(suggestions: &[String]) -> String {
+    let mut out = obs_link_href(None, None);
+    for suggestion in suggestions {
+        out.push_str("&suggestion=");
+        out.push_str(&percent_encode_path(suggestion));
+    }
+    out
+}
+
+/// Anchor id the rendered heading carries (same algorithm as comrak's `header_ids`).
+/// For nested `Note#A#B` links the last heading wins.
+pub fn heading_anchor(heading: &str) -> String {
+    let heading = heading.rsplit('#').next().unwrap_or(heading).trim();
+    comrak::Anchorizer::new().anchorize(heading.nfc().collect())
+}
+
+/// Element id of a `^block-id` block in rendered HTML.
+pub fn block_anchor(id: &str) -> String {
+    format!("^{}", id)
+}
+
+/// Anchor for a link's `#heading` or `^block` subtarget, if any.
+pub fn subtarget_anchor(parsed: &ParsedLink) -> Option<String> {
+    match &parsed.subtarget {
+        Some(HeadingOrBlock::Heading(h)) => Some(heading_anchor(h)),
+        Some(HeadingOrBlock::Block(b)) => Some(block_anchor(b)),
+        None => None,
+    }
+}
+
+/// Splits a trailing Obsidian block marker (`text ^id`, or a line that is only `^id`) off a
+/// line, returning (text before the marker, id).
+pub(crate) fn split_block_id(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_end();
+    let caret = trimmed.rfind('^')?;
+    let before = &trimmed[..caret];
+    if !(before.is_empty() || before.ends_with(' ') || before.ends_with('\t')) {
+        return None;
+    }
+    let id = &trimmed[caret + 1..];
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then_some((before.trim_end(), id))
+}
+
+/// Block ids with their 1-based line numbers, ignoring markers inside code.
+pub(crate) fn scan_block_ids(text: &str) -> Vec<(String, usize)> {
+    let skip = compute_skip_ranges(text);
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        if !in_skip_range(offset, &skip) {
+            if let Some((_, id)) = split_block_id(line) {
+                out.push((id.to_string(), i + 1));
+            }
+        }
+        offset += line.len() + 1;
+    }
+    out
+}
+
+/// An ATX heading (`## Text`) of a note.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteHeading {
+    pub level: u8,
+    pub text: String,
+    /// 1-based.
+    pub line: usize,
+}
+
+/// ATX headings in document order, ignoring `#` lines inside code and frontmatter.
+pub(crate) fn scan_headings(text: &str) -> Vec<NoteHeading> {
+    let skip = compute_skip_ranges(text);
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        let hashes = line.len() - line.trim_start_matches('#').len();
+        let rest = &line[hashes..];
+        if (1..=6).contains(&hashes)
+            && (rest.is_empty() || rest.starts_with([' ', '\t']))
+            && !in_skip_range(offset, &skip)
+        {
+            let text = rest.trim().trim_end_matches('#').trim_end();
+            if !text.is_empty() {
+                out.push(NoteHeading {
+                    level: hashes as u8,
+                    text: text.to_string(),
+                    line: i + 1,
+                });
+            }
+        }
+        offset += line.len() + 1;
+    }
+    out
+}
+
 pub fn link_display_text(parsed: &ParsedLink) -> String {
     if let Some(ref alias) = parsed.alias {
         if !alias.is_empty() {
@@ -210,6 +470,12 @@ pub fn link_display_text(parsed: &ParsedLink) -> String {
         target.to_string()
     };
     let base = base.trim_end_matches(".md").to_string();
+    if base.is_empty() {
+        // Same-note link (`[[#Heading]]`): show just the heading.
+        if let Some(HeadingOrBlock::Heading(h)) = &parsed.subtarget {
+            return h.clone();
+        }
+    }
     if let Some(ref s) = parsed.subtarget {
         let suffix = match s {
             HeadingOrBlock::Heading(h) => format!("#{}", h),
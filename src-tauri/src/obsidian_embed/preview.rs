@@ -0,0 +1,141 @@
+//! Hover previews: a short rendered excerpt of a wikilink target (its first blocks, or the
+//! linked heading section or block).
+
+use std::fs;
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, Options};
+
+use crate::frontmatter::split_frontmatter;
+
+use super::parse::{heading_anchor, parse_wikilink_inner, HeadingOrBlock};
+use super::render::{preprocess_obsidian_links, render_expanded, strip_sentinels, RenderContext};
+use super::resolve::{resolve_target, ResolveResult};
+
+/// Top-level blocks shown in a preview before it is cut off.
+pub const PREVIEW_MAX_BLOCKS: usize = 5;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LinkPreview {
+    pub path: String,
+    /// Note name, or the heading when the link targets one.
+    pub title: String,
+    pub html: String,
+    /// More content follows the excerpt.
+    pub truncated: bool,
+}
+
+struct Block {
+    start_line: usize,
+    end_line: usize,
+    /// (level, anchor) for headings.
+    heading: Option<(u8, String)>,
+}
+
+fn top_level_blocks(md: &str) -> Vec<Block> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &Options::default());
+    root.children()
+        .map(|node| {
+            let data = node.data.borrow();
+            let heading = match &data.value {
+                NodeValue::Heading(h) => {
+                    let mut text = String::new();
+                    for d in node.descendants() {
+                        match &d.data.borrow().value {
+                            NodeValue::Text(t) => text.push_str(t),
+                            NodeValue::Code(c) => text.push_str(&c.literal),
+                            _ => {}
+                        }
+                    }
+                    Some((h.level, heading_anchor(&text)))
+                }
+                _ => None,
+            };
+            Block {
+                start_line: data.sourcepos.start.line,
+                end_line: data.sourcepos.end.line,
+                heading,
+            }
+        })
+        .collect()
+}
+
+/// Lines `from..=to` (1-based) of `md`.
+fn line_range(md: &str, from: usize, to: usize) -> String {
+    md.lines()
+        .skip(from.saturating_sub(1))
+        .take((to + 1).saturating_sub(from))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Markdown of the excerpt and whether it was truncated. `block_line` is the line of the
+/// targeted `^block`, when the link has one.
+fn excerpt(md: &str, subtarget: Option<&HeadingOrBlock>, block_line: Option<usize>) -> Option<(String, bool)> {
+    let blocks = top_level_blocks(md);
+    let selected: &[Block] = match subtarget {
+        Some(HeadingOrBlock::Heading(heading)) => {
+            let anchor = heading_anchor(heading);
+            let start = blocks
+                .iter()
+                .position(|b| matches!(&b.heading, Some((_, a)) if *a == anchor))?;
+            let level = blocks[start].heading.as_ref().map(|(l, _)| *l).unwrap_or(1);
+            let end = blocks[start + 1..]
+                .iter()
+                .position(|b| matches!(&b.heading, Some((l, _)) if *l <= level))
+                .map_or(blocks.len(), |i| start + 1 + i);
+            &blocks[start..end]
+        }
+        Some(HeadingOrBlock::Block(_)) => {
+            let line = block_line?;
+            let i = blocks.iter().position(|b| b.start_line <= line && line <= b.end_line)?;
+            &blocks[i..=i]
+        }
+        None => &blocks,
+    };
+    let shown = &selected[..selected.len().min(PREVIEW_MAX_BLOCKS)];
+    let (first, last) = (shown.first()?, shown.last()?);
+    Some((line_range(md, first.start_line, last.end_line), shown.len() < selected.len()))
+}
+
+/// Resolves `target` (a wikilink body like `Note#Heading`, brackets optional) and renders
+/// its excerpt with links and embeds expanded.
+pub fn preview_link(target: &str, ctx: &mut RenderContext<'_>) -> Result<LinkPreview, String> {
+    let inner = target.trim().trim_start_matches('!');
+    let inner = inner.strip_prefix("[[").and_then(|t| t.strip_suffix("]]")).unwrap_or(inner);
+    let parsed = parse_wikilink_inner(inner);
+    let path = match resolve_target(&parsed, ctx.index, &ctx.vault_root) {
+        ResolveResult::Resolved(path) => path,
+        ResolveResult::Placeholder(_) => return Err("No preview for attachments".to_string()),
+        _ => return Err(format!("Note not found: {}", parsed.target)),
+    };
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let body = split_frontmatter(&content).map_or(content.as_str(), |(_, body)| body);
+    let block_line = match &parsed.subtarget {
+        Some(HeadingOrBlock::Block(id)) => {
+            let body_offset = content[..content.len() - body.len()].matches('\n').count();
+            ctx.index.block_line(&path, id).map(|line| line - body_offset)
+        }
+        _ => None,
+    };
+    let (md, truncated) = excerpt(body, parsed.subtarget.as_ref(), block_line)
+        .ok_or_else(|| format!("Nothing to preview in {}", parsed.target))?;
+
+    ctx.visited.insert(path.clone());
+    ctx.depth += 1;
+    let expanded = preprocess_obsidian_links(&strip_sentinels(&md), ctx);
+    ctx.visited.remove(&path);
+    ctx.depth -= 1;
+
+    let title = match &parsed.subtarget {
+        Some(HeadingOrBlock::Heading(h)) => h.rsplit('#').next().unwrap_or(h).trim().to_string(),
+        _ => path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string(),
+    };
+    Ok(LinkPreview {
+        path: path.to_string_lossy().into_owned(),
+        title,
+        html: render_expanded(&expanded, &ctx.render),
+        truncated,
+    })
+}
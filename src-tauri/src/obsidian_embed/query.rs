@@ -0,0 +1,112 @@
+//! Frontmatter queries over the index's cached fields (`status = done`, `tags contains
+//! project`, `due > 2024-06-01`), the building block for dashboards.
+
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+
+use crate::frontmatter::FrontmatterValue;
+
+use super::index::VaultIndex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOp {
+    /// Case-insensitive equality; lists match when any item is equal.
+    #[default]
+    Equals,
+    /// Case-insensitive substring; lists match when any item contains the value.
+    Contains,
+    /// Compared as dates (`YYYY-MM-DD`, times ignored) or else as numbers.
+    GreaterThan,
+    LessThan,
+    /// The field is present, whatever its value.
+    Exists,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FrontmatterMatch {
+    pub path: String,
+    pub value: FrontmatterValue,
+}
+
+/// Dates first (a leading `YYYY-MM-DD`, so datetimes compare by day), then numbers.
+fn compare(field: &str, value: &str) -> Option<Ordering> {
+    let date = |s: &str| NaiveDate::parse_from_str(s.trim().get(..10)?, "%Y-%m-%d").ok();
+    if let (Some(a), Some(b)) = (date(field), date(value)) {
+        return Some(a.cmp(&b));
+    }
+    let (a, b) = (field.trim().parse::<f64>().ok()?, value.trim().parse::<f64>().ok()?);
+    a.partial_cmp(&b)
+}
+
+fn text_matches(text: &str, op: QueryOp, value: &str) -> bool {
+    match op {
+        QueryOp::Equals => text.trim().eq_ignore_ascii_case(value.trim()),
+        QueryOp::Contains => text.to_lowercase().contains(&value.to_lowercase()),
+        QueryOp::GreaterThan => compare(text, value) == Some(Ordering::Greater),
+        QueryOp::LessThan => compare(text, value) == Some(Ordering::Less),
+        QueryOp::Exists => true,
+    }
+}
+
+/// Notes whose frontmatter `field` (matched case-insensitively) satisfies `op` against
+/// `value`, sorted by path.
+pub fn query_frontmatter(
+    index: &VaultIndex,
+    field: &str,
+    op: QueryOp,
+    value: &str,
+) -> Vec<FrontmatterMatch> {
+    let mut matches: Vec<FrontmatterMatch> = index
+        .frontmatter()
+        .filter_map(|(path, fields)| {
+            let (_, found) = fields.iter().find(|(key, _)| key.eq_ignore_ascii_case(field))?;
+            let hit = match found {
+                FrontmatterValue::Text(text) => text_matches(text, op, value),
+                FrontmatterValue::List(items) => {
+                    op == QueryOp::Exists || items.iter().any(|item| text_matches(item, op, value))
+                }
+            };
+            hit.then(|| FrontmatterMatch {
+                path: path.to_string_lossy().into_owned(),
+                value: found.clone(),
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    matches
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteAlias {
+    pub alias: String,
+    pub path: String,
+}
+
+/// Every `aliases` (or `alias`) entry in the vault's frontmatter with its note, sorted by alias
+/// (case-insensitive) then path. A single text value is one alias.
+pub fn note_aliases(index: &VaultIndex) -> Vec<NoteAlias> {
+    let mut aliases: Vec<NoteAlias> = index
+        .frontmatter()
+        .flat_map(|(path, fields)| {
+            let values = fields
+                .iter()
+                .filter(|(key, _)| matches!(key.to_lowercase().as_str(), "aliases" | "alias"))
+                .flat_map(|(_, value)| match value {
+                    FrontmatterValue::Text(text) => vec![text.clone()],
+                    FrontmatterValue::List(items) => items.clone(),
+                });
+            values
+                .map(|alias| alias.trim().to_string())
+                .filter(|alias| !alias.is_empty())
+                .map(|alias| NoteAlias {
+                    alias,
+                    path: path.to_string_lossy().into_owned(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    aliases.sort_by_key(|a| (a.alias.to_lowercase(), a.path.clone()));
+    aliases
+}
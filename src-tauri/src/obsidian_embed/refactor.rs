@@ -0,0 +1,80 @@
+//! Link refactoring for vault moves: path-style wikilinks into a moved folder are rewritten to
+//! its new location. Basename links (`[[Note]]`) keep resolving after a move and are left alone.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::index::{normalize_rel_key, VaultIndex};
+use super::parse::{compute_skip_ranges, find_obsidian_spans_inner};
+
+/// One rewritten wikilink or embed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LinkEdit {
+    /// 1-based line of the link.
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The link edits for one note, at its current (pre-move) path.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteEdits {
+    pub path: PathBuf,
+    pub edits: Vec<LinkEdit>,
+    /// The note's full text with the edits applied.
+    #[serde(skip)]
+    pub content: String,
+}
+
+/// `raw_inner` with its leading `old` folder (vault-relative) replaced by `new`, when the link
+/// targets something inside `old` (`old/Note`, `old/sub/Note#Heading|alias`, `old/`).
+fn moved_inner(raw_inner: &str, old: &str, new: &str) -> Option<String> {
+    let leading = raw_inner.len() - raw_inner.trim_start().len();
+    let body = &raw_inner[leading..];
+    let prefix = body.get(..old.len())?;
+    let rest = &body[old.len()..];
+    if normalize_rel_key(prefix) != old || !rest.starts_with(['/', '\\']) {
+        return None;
+    }
+    Some(format!("{}{}{}", &raw_inner[..leading], new, rest))
+}
+
+/// Edits that keep every path-style wikilink and embed in `index` pointing into the folder
+/// moved from `old` to `new` (both vault-relative). Notes without such links are omitted.
+pub fn folder_move_edits(index: &VaultIndex, old: &str, new: &str) -> Vec<NoteEdits> {
+    let (old, new) = (normalize_rel_key(old), normalize_rel_key(new));
+    let mut out = Vec::new();
+    for note in index.notes() {
+        let Ok(text) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let skip = compute_skip_ranges(&text);
+        let mut content = String::with_capacity(text.len());
+        let mut edits = Vec::new();
+        let mut last = 0;
+        for (is_embed, start, end, raw_inner) in find_obsidian_spans_inner(&text, &skip) {
+            let Some(inner) = moved_inner(&raw_inner, &old, &new) else {
+                continue;
+            };
+            let after = format!("{}[[{}]]", if is_embed { "!" } else { "" }, inner);
+            content.push_str(&text[last..start]);
+            content.push_str(&after);
+            edits.push(LinkEdit {
+                line: text[..start].matches('\n').count() + 1,
+                before: text[start..end].to_string(),
+                after,
+            });
+            last = end;
+        }
+        if edits.is_empty() {
+            continue;
+        }
+        content.push_str(&text[last..]);
+        out.push(NoteEdits {
+            path: note,
+            edits,
+            content,
+        });
+    }
+    out
+}
@@ -1,18 +1,37 @@
 //! Preprocess/postprocess Obsidian links and render markdown with embeds.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
-use crate::markdown::render_markdown_safe;
+use crate::markdown::{
+    block_source_map, render_markdown, render_markdown_safe, BlockMapping, EmbedPlaceholder, RenderOptions,
+};
+
+use crate::citations::{find_citations, render_citations, Citation};
+use crate::csv_table::parse_csv;
+use crate::frontmatter::{is_published, parse_frontmatter, split_frontmatter};
+use crate::kanban::{is_kanban, parse_board};
+use crate::image_size::add_image_dimensions;
+use crate::paths::{self, relative_to};
+use crate::properties::properties_table;
+use crate::remote_images::block_remote_images;
+use crate::tasks::{apply_task_offsets, mark_tasks};
+use crate::text::{percent_decode, truncate_snippet, MAX_SNIPPET_CHARS};
 
+use super::backlinks::find_backlinks;
 use super::cache::RenderCache;
 use super::index::VaultIndex;
 use super::parse::{
-    compute_skip_ranges, find_obsidian_spans_inner, link_display_text, obs_link_href,
-    parse_embed_syntax, parse_wikilink_inner,
+    block_anchor, compute_skip_ranges, find_obsidian_spans_inner, in_skip_range, link_display_text,
+    obs_ambiguous_href, obs_broken_href, obs_link_href, portable_href,
+    parse_embed_syntax, parse_wikilink_inner, split_block_id, subtarget_anchor, HeadingOrBlock,
+    ParsedLink,
 };
-use super::resolve::{resolve_target, ResolveResult};
+use super::resolve::{resolve_target, resolve_target_with, ResolveResult};
+use super::suggest::suggest_targets;
 
 pub struct RenderContext<'a> {
     pub vault_root: PathBuf,
@@ -21,46 +40,173 @@ pub struct RenderContext<'a> {
     pub visited: HashSet<PathBuf>,
     pub depth: u32,
     pub max_depth: u32,
+    pub render: RenderOptions,
+    pub generation: RenderGeneration,
+}
+
+/// One render among many of the same kind: starting a newer one supersedes it, and a
+/// superseded render stops expanding embeds and skips the cache. The default is never superseded.
+#[derive(Clone, Default)]
+pub struct RenderGeneration {
+    latest: Arc<AtomicU64>,
+    own: u64,
+}
+
+impl RenderGeneration {
+    /// Starts the next generation on `latest`, superseding every earlier one.
+    pub fn next(latest: &Arc<AtomicU64>) -> Self {
+        let own = latest.fetch_add(1, Ordering::Relaxed) + 1;
+        RenderGeneration {
+            latest: latest.clone(),
+            own,
+        }
+    }
+
+    pub fn is_superseded(&self) -> bool {
+        self.latest.load(Ordering::Relaxed) != self.own
+    }
 }
 
 pub fn preprocess_obsidian_links(markdown: &str, ctx: &mut RenderContext<'_>) -> String {
-    let skip = compute_skip_ranges(markdown);
-    let mut spans = find_obsidian_spans_inner(markdown, &skip);
-    if spans.is_empty() {
+    let replacements = obsidian_replacements(markdown, ctx);
+    if replacements.is_empty() {
         return markdown.to_string();
     }
-    spans.sort_by(|a, b| b.1.cmp(&a.1));
     let mut out = markdown.to_string();
+    for (start, end, replacement) in replacements.into_iter().rev() {
+        out.replace_range(start..end, &replacement);
+    }
+    out
+}
+
+/// Returns (start, end, replacement) for every `[[...]]` / `![[...]]` span, in source order.
+fn obsidian_replacements(markdown: &str, ctx: &mut RenderContext<'_>) -> Vec<(usize, usize, String)> {
+    let skip = compute_skip_ranges(markdown);
+    let mut spans = find_obsidian_spans_inner(markdown, &skip);
+    spans.sort_by_key(|span| span.1);
+    let mut out = Vec::with_capacity(spans.len());
     for (is_embed, start, end, raw_inner) in spans {
+        if ctx.generation.is_superseded() {
+            break;
+        }
         let replacement = if is_embed {
             let parsed = parse_wikilink_inner(&raw_inner);
+            if parsed.target.ends_with('/') {
+                out.push((start, end, folder_listing(&parsed.target, ctx)));
+                continue;
+            }
             let resolved = resolve_target(&parsed, ctx.index, &ctx.vault_root);
             match resolved {
-                ResolveResult::Resolved(path) => get_expanded_markdown(&path, ctx),
-                ResolveResult::Placeholder(path) => {
-                    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("asset");
-                    let href = path.to_string_lossy();
-                    format!("[Asset: {}](file:///{})", name, href.replace('\\', "/"))
+                ResolveResult::Resolved(path) => match &ctx.render.unpublished_embed {
+                    Some(placeholder) if !note_is_published(&path) => {
+                        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("?");
+                        placeholder.replace("{name}", name)
+                    }
+                    _ if ctx.render.embed_scope.as_ref().is_some_and(|scope| !scope.contains(&path)) => {
+                        format!("[{}]({})", link_display_text(&parsed), obs_link_href(Some(&path), None))
+                    }
+                    _ => {
+                        let expanded = get_expanded_markdown(&path, ctx);
+                        let expanded = if ctx.render.properties_table {
+                            split_frontmatter(&expanded).map_or(expanded.as_str(), |(_, body)| body)
+                        } else {
+                            &expanded
+                        };
+                        embed_container(&path, &parsed, expanded)
+                    }
+                },
+                ResolveResult::Placeholder(path) if is_csv(&path) => csv_embed(&path, &ctx.vault_root),
+                ResolveResult::Placeholder(path) => asset_link(&path),
+                ResolveResult::NotFound => {
+                    embed_placeholder(EmbedPlaceholder::NotFound, &parsed.target, &ctx.render)
+                }
+                ResolveResult::Ambiguous(_) => {
+                    embed_placeholder(EmbedPlaceholder::Ambiguous, &parsed.target, &ctx.render)
                 }
-                ResolveResult::NotFound => format!("*[Embed: {} (not found)]*", parsed.target),
-                ResolveResult::Ambiguous(_) => format!("*[Embed: {} (ambiguous)]*", parsed.target),
             }
         } else {
             let parsed = parse_wikilink_inner(&raw_inner);
-            let resolved = resolve_target(&parsed, ctx.index, &ctx.vault_root);
+            let same_note_anchor = parsed
+                .target
+                .is_empty()
+                .then(|| subtarget_anchor(&parsed))
+                .flatten();
+            if let Some(anchor) = same_note_anchor {
+                let display = link_display_text(&parsed);
+                out.push((start, end, format!("[{}](#{})", display, anchor)));
+                continue;
+            }
+            let resolved = resolve_target_with(&parsed, ctx.index, &ctx.vault_root, ctx.render.report_ambiguous);
             let path_opt = match &resolved {
                 ResolveResult::Resolved(p) | ResolveResult::Placeholder(p) => Some(p.as_path()),
-                _ => None,
+                ResolveResult::Ambiguous(candidates) => candidates.first().map(PathBuf::as_path),
+                ResolveResult::NotFound => None,
             };
             let display = link_display_text(&parsed);
-            let href = obs_link_href(path_opt);
+            let anchor = match (&parsed.subtarget, path_opt) {
+                (Some(HeadingOrBlock::Block(id)), Some(path))
+                    if ctx.index.block_line(path, id).is_none() =>
+                {
+                    None
+                }
+                _ => subtarget_anchor(&parsed),
+            };
+            let href = match &resolved {
+                ResolveResult::Ambiguous(candidates) => obs_ambiguous_href(candidates, anchor.as_deref()),
+                ResolveResult::NotFound => obs_broken_href(&suggest_targets(&parsed.target, ctx.index)),
+                _ => obs_link_href(path_opt, anchor.as_deref()),
+            };
             format!("[{}]({})", display, href)
         };
-        out.replace_range(start..end, &replacement);
+        out.push((start, end, replacement));
     }
     out
 }
 
+fn note_is_published(path: &Path) -> bool {
+    fs::read_to_string(path).map(|md| is_published(&md)).unwrap_or(true)
+}
+
+fn asset_link(path: &Path) -> String {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("asset");
+    let href = path.to_string_lossy();
+    format!("[Asset: {}](file:///{})", name, href.replace('\\', "/"))
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().map(|e| e.eq_ignore_ascii_case("csv")).unwrap_or(false)
+}
+
+/// Markdown list linking the notes directly inside vault folder `target` (`![[Projects/]]`).
+fn folder_listing(target: &str, ctx: &RenderContext<'_>) -> String {
+    let rel = target.trim_matches('/');
+    let dir = match paths::canonicalize(&ctx.vault_root.join(rel)) {
+        Ok(dir) if dir.is_dir() && dir.starts_with(&ctx.vault_root) => dir,
+        _ => return embed_placeholder(EmbedPlaceholder::NotFound, target, &ctx.render),
+    };
+    let mut notes: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().map(|e| e == "md").unwrap_or(false))
+                .collect()
+        })
+        .unwrap_or_default();
+    if notes.is_empty() {
+        return embed_placeholder(EmbedPlaceholder::EmptyFolder, target, &ctx.render);
+    }
+    notes.sort_by_key(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+    let items: Vec<String> = notes
+        .iter()
+        .map(|note| {
+            let name = note.file_stem().and_then(|n| n.to_str()).unwrap_or("?");
+            format!("- [{}]({})", name, obs_link_href(Some(note), None))
+        })
+        .collect();
+    format!("\n{}\n", items.join("\n"))
+}
+
 #[allow(dead_code)]
 pub fn expand_embeds(markdown: &str, ctx: &mut RenderContext<'_>) -> String {
     let spans = parse_embed_syntax(markdown);
@@ -78,43 +224,665 @@ pub fn expand_embeds(markdown: &str, ctx: &mut RenderContext<'_>) -> String {
                 let href = path.to_string_lossy();
                 format!("[Asset: {}](file:///{})", name, href.replace('\\', "/"))
             }
-            ResolveResult::NotFound => format!("*[Embed: {} (not found)]*", parsed.target),
-            ResolveResult::Ambiguous(_) => format!("*[Embed: {} (ambiguous)]*", parsed.target),
+            ResolveResult::NotFound => {
+                embed_placeholder(EmbedPlaceholder::NotFound, &parsed.target, &ctx.render)
+            }
+            ResolveResult::Ambiguous(_) => {
+                embed_placeholder(EmbedPlaceholder::Ambiguous, &parsed.target, &ctx.render)
+            }
         };
         out.replace_range(span.start..span.end, &replacement);
     }
     out
 }
 
+/// Files a render of the note at `path` reads: the note itself and the notes (or assets) it
+/// embeds, recursively. Canonical paths.
+pub fn embed_dependencies(path: &Path, index: &VaultIndex, vault_root: &Path) -> HashSet<PathBuf> {
+    let mut files = HashSet::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(note) = pending.pop() {
+        let Ok(note) = paths::canonicalize(&note) else {
+            continue;
+        };
+        if !files.insert(note.clone()) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        for span in parse_embed_syntax(&content) {
+            match resolve_target(&parse_wikilink_inner(&span.raw_inner), index, vault_root) {
+                ResolveResult::Resolved(embedded) => pending.push(embedded),
+                ResolveResult::Placeholder(asset) => {
+                    files.insert(paths::canonicalize(&asset).unwrap_or(asset));
+                }
+                ResolveResult::NotFound | ResolveResult::Ambiguous(_) => {}
+            }
+        }
+    }
+    files
+}
+
+/// The inverse of `embed_dependencies`: notes whose render reads one of the `changed` files
+/// (canonical) through embeds, directly or via other embedded notes. Sorted, without the
+/// changed files themselves. Answered from the embeds kept in the index, without reading notes.
+pub fn find_embedding_notes(changed: &[PathBuf], index: &VaultIndex, vault_root: &Path) -> Vec<PathBuf> {
+    let mut embedded_by: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for (note, targets) in index.embeds() {
+        for raw_inner in targets {
+            let target = match resolve_target(&parse_wikilink_inner(raw_inner), index, vault_root) {
+                ResolveResult::Resolved(path) | ResolveResult::Placeholder(path) => path,
+                ResolveResult::NotFound | ResolveResult::Ambiguous(_) => continue,
+            };
+            let target = paths::canonicalize(&target).unwrap_or(target);
+            embedded_by.entry(target).or_default().push(note.clone());
+        }
+    }
+    let mut found = HashSet::new();
+    let mut pending = changed.to_vec();
+    while let Some(path) = pending.pop() {
+        for note in embedded_by.get(&path).into_iter().flatten() {
+            if found.insert(note.clone()) {
+                pending.push(note.clone());
+            }
+        }
+    }
+    let mut notes: Vec<PathBuf> = found.into_iter().filter(|note| !changed.contains(note)).collect();
+    notes.sort();
+    notes
+}
+
 fn get_expanded_markdown(path: &Path, ctx: &mut RenderContext<'_>) -> String {
-    let canonical = match path.canonicalize() {
+    expand_note(path, ctx, str::to_string)
+}
+
+/// Reads the note at `path`, runs `prepare` on its own markdown, then expands links and embeds.
+fn expand_note(path: &Path, ctx: &mut RenderContext<'_>, prepare: fn(&str) -> String) -> String {
+    if ctx.generation.is_superseded() {
+        return String::new();
+    }
+    let canonical = match paths::canonicalize(path) {
         Ok(p) => p,
-        Err(_) => return "*[Embed: invalid path]*".to_string(),
+        Err(error) => return embed_error(path, &error),
     };
     if ctx.visited.contains(&canonical) {
         let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-        return format!("*[Embed: {} (cycle)]*", name);
+        return embed_placeholder(EmbedPlaceholder::Cycle, name, &ctx.render);
     }
     if ctx.depth > ctx.max_depth {
         let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
-        return format!("*[Embed: {} (depth limit)]*", name);
+        return embed_placeholder(EmbedPlaceholder::DepthLimit, name, &ctx.render);
+    }
+    // The size limit is for embeds; the note being opened always renders.
+    if let Some(limit) = ctx.render.max_embed_bytes.filter(|_| ctx.depth > 0) {
+        let size = fs::metadata(&canonical).map(|m| m.len()).unwrap_or(0);
+        if size > limit {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?");
+            let href = obs_link_href(Some(&canonical), None);
+            let template = placeholder_text(EmbedPlaceholder::TooLarge, &ctx.render);
+            let text = template.replace("{size}", &format_size(size));
+            return sentinel_placeholder(EmbedPlaceholder::TooLarge, &text, name, &href);
+        }
     }
     ctx.visited.insert(canonical.clone());
     ctx.depth += 1;
     let content = match fs::read_to_string(&canonical) {
         Ok(c) => c,
-        Err(_) => {
+        Err(error) => {
             ctx.visited.remove(&canonical);
             ctx.depth -= 1;
-            return "*[Embed: read error]*".to_string();
+            return embed_error(&canonical, &error.to_string());
         }
     };
-    let expanded = preprocess_obsidian_links(&content, ctx);
+    let expanded = preprocess_obsidian_links(&prepare(&strip_sentinels(&content)), ctx);
     ctx.visited.remove(&canonical);
     ctx.depth -= 1;
     expanded
 }
 
+/// `markdown` without the private-use characters U+E000–U+E01F that delimit the renderer's
+/// sentinels, so note text can never forge one.
+pub(crate) fn strip_sentinels(markdown: &str) -> String {
+    markdown.chars().filter(|c| !('\u{E000}'..='\u{E01F}').contains(c)).collect()
+}
+
+const EMBED_ERROR_START: char = '\u{E004}';
+const EMBED_ERROR_SEP: char = '\u{E005}';
+const EMBED_ERROR_END: char = '\u{E006}';
+
+/// Percent-encodes `s` down to alphanumerics, so markdown leaves it alone inside sentinels.
+fn sentinel_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| if b.is_ascii_alphanumeric() { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect()
+}
+
+/// Sentinel-wrapped placeholder for an embed that could not be read. Path and error are
+/// percent-encoded down to alphanumerics so markdown leaves them alone; `apply_embed_errors`
+/// turns the placeholder into HTML.
+fn embed_error(path: &Path, error: &str) -> String {
+    format!(
+        "{}{}{}{}{}",
+        EMBED_ERROR_START,
+        sentinel_encode(&path.to_string_lossy()),
+        EMBED_ERROR_SEP,
+        sentinel_encode(error),
+        EMBED_ERROR_END
+    )
+}
+
+/// Replaces `embed_error` placeholders with a styled `span.embed-error` naming the path and
+/// OS error, plus a retry button the frontend wires up.
+fn apply_embed_errors(html: &str, render: &RenderOptions) -> String {
+    let label = escape_html_text(placeholder_text(EmbedPlaceholder::EmbedFailed, render));
+    let retry = escape_html_text(placeholder_text(EmbedPlaceholder::Retry, render));
+    replace_embed_errors(html, |path, error| {
+        format!(
+            "<span class=\"embed-error\" data-embed-path=\"{}\"><span class=\"embed-error-label\">{}</span> <code>{}</code> — {} <button type=\"button\" class=\"embed-retry\">{}</button></span>",
+            escape_attr(path),
+            label,
+            escape_html_text(path),
+            escape_html_text(error),
+            retry
+        )
+    })
+}
+
+/// Calls `replacement` with the decoded path and error of every `embed_error` placeholder.
+fn replace_embed_errors(text: &str, replacement: impl Fn(&str, &str) -> String) -> String {
+    replace_sentinels(text, [EMBED_ERROR_START, EMBED_ERROR_SEP, EMBED_ERROR_END], |[path, error]| {
+        replacement(&path, &error)
+    })
+}
+
+const EMBED_PLACEHOLDER_START: char = '\u{E00D}';
+const EMBED_PLACEHOLDER_SEP: char = '\u{E00E}';
+const EMBED_PLACEHOLDER_END: char = '\u{E00F}';
+
+/// The render options' text for `kind`, or its English default.
+fn placeholder_text(kind: EmbedPlaceholder, render: &RenderOptions) -> &str {
+    render.embed_placeholders.get(&kind).map_or(kind.default_text(), String::as_str)
+}
+
+/// Sentinel-wrapped placeholder of kind `kind` for an embed of `name`, its text from the render
+/// options; `apply_embed_placeholders` turns it into HTML.
+fn embed_placeholder(kind: EmbedPlaceholder, name: &str, render: &RenderOptions) -> String {
+    sentinel_placeholder(kind, placeholder_text(kind, render), name, "")
+}
+
+/// Placeholder of kind `kind` showing `text`, its `{name}` filled with `name` (a link to `href`
+/// unless empty) once the placeholder is turned into HTML or markdown.
+fn sentinel_placeholder(kind: EmbedPlaceholder, text: &str, name: &str, href: &str) -> String {
+    let fields = [kind.key(), text, name, href].map(sentinel_encode).join(&EMBED_PLACEHOLDER_SEP.to_string());
+    format!("{}{}{}", EMBED_PLACEHOLDER_START, fields, EMBED_PLACEHOLDER_END)
+}
+
+/// Replaces `embed_placeholder`s with an `em.embed-placeholder` classed and keyed by its kind;
+/// placeholders of an unknown kind are dropped. Runs before `postprocess_obsidian_html` so
+/// linked names get the usual note-link attributes.
+fn apply_embed_placeholders(html: &str) -> String {
+    replace_embed_placeholders(html, |key, text, name, href| {
+        let Some(kind) = EmbedPlaceholder::from_key(key) else {
+            return String::new();
+        };
+        let name = if href.is_empty() {
+            escape_html_text(name)
+        } else {
+            format!("<a href=\"{}\">{}</a>", escape_attr(href), escape_html_text(name))
+        };
+        let text: Vec<String> = text.split("{name}").map(escape_html_text).collect();
+        format!(
+            "<em class=\"embed-placeholder embed-placeholder-{}\" data-placeholder=\"{}\">{}</em>",
+            kind.key().replace('_', "-"),
+            kind.key(),
+            text.join(&name)
+        )
+    })
+}
+
+/// Calls `replacement` with the decoded key, text, name and link of every `embed_placeholder`.
+fn replace_embed_placeholders(text: &str, replacement: impl Fn(&str, &str, &str, &str) -> String) -> String {
+    let sentinels = [EMBED_PLACEHOLDER_START, EMBED_PLACEHOLDER_SEP, EMBED_PLACEHOLDER_END];
+    replace_sentinels(text, sentinels, |[key, text, name, href]| replacement(&key, &text, &name, &href))
+}
+
+/// Calls `replacement` with the `N` percent-decoded fields (missing ones empty) of every
+/// `start`, `sep`, `end` delimited placeholder.
+fn replace_sentinels<const N: usize>(
+    text: &str,
+    [start_mark, sep, end_mark]: [char; 3],
+    replacement: impl Fn([String; N]) -> String,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(start_mark) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + start_mark.len_utf8()..];
+        let Some(end) = after.find(end_mark) else {
+            rest = after;
+            continue;
+        };
+        let mut fields = after[..end].splitn(N, sep);
+        let fields = std::array::from_fn(|_| fields.next().map(percent_decode).unwrap_or_default());
+        out.push_str(&replacement(fields));
+        rest = &after[end + end_mark.len_utf8()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+const EMBED_OPEN_START: char = '\u{E009}';
+const EMBED_OPEN_SEP: char = '\u{E00A}';
+const EMBED_OPEN_END: char = '\u{E00B}';
+const EMBED_CLOSE: char = '\u{E00C}';
+
+/// `expanded` (an embedded note) between sentinel paragraphs that `apply_embed_chrome` turns
+/// into a `div.obs-embed` headed by a link to the note. Placeholders for cycles, the depth
+/// limit and unreadable notes get the same container.
+fn embed_container(path: &Path, parsed: &ParsedLink, expanded: &str) -> String {
+    format!(
+        "\n\n{}{}{}{}{}{}{}\n\n{}\n\n{}\n\n",
+        EMBED_OPEN_START,
+        sentinel_encode(&path.to_string_lossy()),
+        EMBED_OPEN_SEP,
+        sentinel_encode(&subtarget_anchor(parsed).unwrap_or_default()),
+        EMBED_OPEN_SEP,
+        sentinel_encode(&link_display_text(parsed)),
+        EMBED_OPEN_END,
+        expanded,
+        EMBED_CLOSE
+    )
+}
+
+/// Calls `open` with the decoded (path, anchor, title) of every container opened by
+/// `embed_container` and `close` at each end; a sentinel alone in a `<p>` replaces the
+/// paragraph. Stray ends are dropped and containers left open are closed at the end.
+fn replace_embed_containers(
+    text: &str,
+    open: impl Fn(&str, &str, &str) -> String,
+    close: &str,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut depth = 0;
+    while let Some(start) = rest.find([EMBED_OPEN_START, EMBED_CLOSE]) {
+        out.push_str(&rest[..start]);
+        let sentinel = rest[start..].chars().next().unwrap_or(EMBED_CLOSE);
+        let is_open = sentinel == EMBED_OPEN_START;
+        let after = &rest[start + sentinel.len_utf8()..];
+        let (replacement, next) = if is_open {
+            let Some(end) = after.find(EMBED_OPEN_END) else {
+                rest = after;
+                continue;
+            };
+            let mut fields = after[..end].split(EMBED_OPEN_SEP).map(percent_decode);
+            let (path, anchor, title) = (
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+            );
+            depth += 1;
+            (open(&path, &anchor, &title), &after[end + EMBED_OPEN_END.len_utf8()..])
+        } else if depth > 0 {
+            depth -= 1;
+            (close.to_string(), after)
+        } else {
+            (String::new(), after)
+        };
+        rest = next;
+        if out.ends_with("<p>") && rest.starts_with("</p>") {
+            out.truncate(out.len() - "<p>".len());
+            rest = &rest["</p>".len()..];
+        }
+        out.push_str(&replacement);
+    }
+    out.push_str(rest);
+    for _ in 0..depth {
+        out.push_str(close);
+    }
+    out
+}
+
+/// Turns embed containers into `div.obs-embed[data-source]`: a header linking to the
+/// embedded note, then the note in `div.obs-embed-content`.
+fn apply_embed_chrome(html: &str) -> String {
+    let open = |path: &str, anchor: &str, title: &str| {
+        let anchor = Some(anchor).filter(|a| !a.is_empty());
+        let anchor_attr = anchor
+            .map(|a| format!(" data-obs-anchor=\"{}\"", escape_attr(a)))
+            .unwrap_or_default();
+        format!(
+            "<div class=\"obs-embed\" data-source=\"{0}\"><div class=\"obs-embed-header\">\
+             <a class=\"obs-link\" href=\"{1}\" data-obs-path=\"{0}\"{2}>{3}</a></div>\
+             <div class=\"obs-embed-content\">\n",
+            escape_attr(path),
+            obs_link_href(Some(Path::new(path)), anchor),
+            anchor_attr,
+            escape_html_text(title)
+        )
+    };
+    replace_embed_containers(html, open, "</div></div>\n")
+}
+
+const CSV_EMBED_START: char = '\u{E007}';
+const CSV_EMBED_END: char = '\u{E008}';
+const CSV_EMBED_SEP: char = '\u{E010}';
+/// Rows of an embedded CSV shown inline, header included; the rest is behind "Open full file".
+const MAX_CSV_ROWS: usize = 50;
+
+/// Sentinel-wrapped placeholder carrying the path and contents of an embedded CSV file, on its
+/// own paragraph; `apply_csv_embeds` turns it into a table. Only `.csv` files inside
+/// `vault_root` are read, anything else becomes an embed error.
+fn csv_embed(path: &Path, vault_root: &Path) -> String {
+    let canonical = match paths::canonicalize(path) {
+        Ok(canonical) => canonical,
+        Err(error) => return embed_error(path, &error),
+    };
+    if !is_csv(&canonical) || !canonical.starts_with(vault_root) {
+        return embed_error(path, "not a CSV file in the vault");
+    }
+    let text = match fs::read(&canonical) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(error) => return embed_error(&canonical, &error.to_string()),
+    };
+    format!(
+        "\n\n{}{}{}{}{}\n\n",
+        CSV_EMBED_START,
+        sentinel_encode(&canonical.to_string_lossy()),
+        CSV_EMBED_SEP,
+        sentinel_encode(&text),
+        CSV_EMBED_END
+    )
+}
+
+/// Calls `replacement` with the decoded path and contents of every `csv_embed` placeholder; a
+/// placeholder alone in a `<p>` replaces the paragraph.
+fn replace_csv_embeds(text: &str, replacement: impl Fn(&Path, &str) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(CSV_EMBED_START) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + CSV_EMBED_START.len_utf8()..];
+        let Some(end) = after.find(CSV_EMBED_END) else {
+            rest = after;
+            continue;
+        };
+        rest = &after[end + CSV_EMBED_END.len_utf8()..];
+        if out.ends_with("<p>") && rest.starts_with("</p>") {
+            out.truncate(out.len() - "<p>".len());
+            rest = &rest["</p>".len()..];
+        }
+        let (path, contents) = after[..end].split_once(CSV_EMBED_SEP).unwrap_or((&after[..end], ""));
+        out.push_str(&replacement(Path::new(&percent_decode(path)), &percent_decode(contents)));
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replaces `csv_embed` placeholders with a `table.csv-embed` of the file's first
+/// `MAX_CSV_ROWS` rows (the first row as header) and a link opening the whole file.
+fn apply_csv_embeds(html: &str) -> String {
+    replace_csv_embeds(html, |path, text| {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let open = format!(
+            "<a class=\"csv-open\" href=\"file:///{}\" data-path=\"{}\">Open full file</a>",
+            escape_attr(&path.to_string_lossy().replace('\\', "/")),
+            escape_attr(&path.to_string_lossy())
+        );
+        let preview = parse_csv(text, MAX_CSV_ROWS);
+        let cell_row = |row: &[String], tag: &str| {
+            let cells: String = row
+                .iter()
+                .map(|cell| format!("<{0}>{1}</{0}>", tag, escape_html_text(cell)))
+                .collect();
+            format!("<tr>{}</tr>", cells)
+        };
+        let mut rows = preview.rows.iter();
+        let head = rows.next().map(|row| format!("<thead>{}</thead>", cell_row(row, "th")));
+        let body: String = rows.map(|row| cell_row(row, "td")).collect();
+        let summary = if preview.truncated() {
+            format!("{} — {} of {} rows", escape_html_text(&name), preview.rows.len(), preview.total_rows)
+        } else {
+            escape_html_text(&name)
+        };
+        format!(
+            "<figure class=\"csv-embed\"><table>{}<tbody>{}</tbody></table><figcaption>{} · {}</figcaption></figure>\n",
+            head.unwrap_or_default(),
+            body,
+            summary,
+            open
+        )
+    })
+}
+
+/// The note at `path` as one self-contained markdown document for tools that don't know
+/// Obsidian syntax: embeds are inlined (or linked when `inline_embeds` is false), wikilinks
+/// and asset embeds become standard links relative to `base_dir`, and links to missing notes
+/// become plain text.
+pub fn flatten_markdown_with_embeds(
+    path: &Path,
+    base_dir: &Path,
+    inline_embeds: bool,
+    ctx: &mut RenderContext<'_>,
+) -> String {
+    let prepare: fn(&str) -> String = if inline_embeds { str::to_string } else { embeds_as_links };
+    let expanded = expand_note(path, ctx, prepare);
+    let label = placeholder_text(EmbedPlaceholder::EmbedFailed, &ctx.render);
+    let expanded = replace_embed_errors(&expanded, |path, error| {
+        format!("*[{} {} ({})]*", label, path, error)
+    });
+    let expanded = replace_embed_placeholders(&expanded, |_, text, name, href| {
+        let name = if href.is_empty() { name.to_string() } else { format!("[{}]({})", name, href) };
+        format!("*{}*", text.replace("{name}", &name))
+    });
+    let expanded = replace_csv_embeds(&expanded, |path, _| asset_link(path));
+    let expanded = replace_embed_containers(&expanded, |_, _, _| String::new(), "");
+    portable_links(&expanded, base_dir)
+}
+
+/// `![[...]]` embeds of the note turned into plain `[[...]]` links.
+fn embeds_as_links(markdown: &str) -> String {
+    let mut out = markdown.to_string();
+    for span in parse_embed_syntax(markdown).into_iter().rev() {
+        out.remove(span.start);
+    }
+    out
+}
+
+/// Rewrites the `app://open` and `file:///` link destinations produced by expansion as
+/// relative paths from `base_dir`.
+fn portable_links(markdown: &str, base_dir: &Path) -> String {
+    const NOTE_LINK: &str = "](app://open?path=";
+    const ASSET_LINK: &str = "](file:///";
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some((at, prefix)) = [NOTE_LINK, ASSET_LINK]
+        .into_iter()
+        .filter_map(|prefix| rest.find(prefix).map(|at| (at, prefix)))
+        .min()
+    {
+        out.push_str(&rest[..at]);
+        let after = &rest[at + prefix.len()..];
+        let end = link_destination_end(after);
+        let (target, anchor) = match after[..end].split_once('#') {
+            Some((target, anchor)) => (target, Some(anchor)),
+            None => (&after[..end], None),
+        };
+        let target = if prefix == NOTE_LINK {
+            // Ambiguous links carry `&candidate=` parameters; the first candidate wins.
+            percent_decode(target.split('&').next().unwrap_or(""))
+        } else {
+            // Asset hrefs are `file:///` + the plain absolute path.
+            target.to_string()
+        };
+        if target.is_empty() {
+            // Unresolved link: keep the text, drop the `[` opening it.
+            if let Some(open) = out.rfind('[') {
+                out.remove(open);
+            }
+        } else {
+            out.push_str("](");
+            out.push_str(&portable_href(&relative_to(Path::new(&target), base_dir)));
+            if let Some(anchor) = anchor {
+                out.push('#');
+                out.push_str(anchor);
+            }
+            out.push(')');
+        }
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of a markdown link destination up to its closing `)`, allowing balanced parentheses.
+fn link_destination_end(s: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return i,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{} KB", b / 1024),
+        b => format!("{} B", b),
+    }
+}
+
+/// Expands `content` (of the note at canonical `note`, if any) like `get_expanded_markdown`
+/// and returns, for each line of the expanded markdown, the 1-based line of `content` it came
+/// from.
+fn expand_with_line_origins(
+    content: &str,
+    note: Option<&Path>,
+    ctx: &mut RenderContext<'_>,
+) -> (String, Vec<usize>) {
+    if let Some(note) = note {
+        ctx.visited.insert(note.to_path_buf());
+    }
+    ctx.depth += 1;
+    let replacements = obsidian_replacements(content, ctx);
+    if let Some(note) = note {
+        ctx.visited.remove(note);
+    }
+    ctx.depth -= 1;
+
+    let mut expanded = String::with_capacity(content.len());
+    let mut origins = vec![1];
+    let mut raw_line = 1;
+    let mut last = 0;
+    for (start, end, replacement) in &replacements {
+        for ch in content[last..*start].chars() {
+            expanded.push(ch);
+            if ch == '\n' {
+                raw_line += 1;
+                origins.push(raw_line);
+            }
+        }
+        for ch in replacement.chars() {
+            expanded.push(ch);
+            if ch == '\n' {
+                origins.push(raw_line);
+            }
+        }
+        raw_line += content[*start..*end].matches('\n').count();
+        last = *end;
+    }
+    for ch in content[last..].chars() {
+        expanded.push(ch);
+        if ch == '\n' {
+            raw_line += 1;
+            origins.push(raw_line);
+        }
+    }
+    (expanded, origins)
+}
+
+/// Block-level source map for a note rendered with embeds: line ranges refer to the note's
+/// own raw markdown, so every block produced by an embed maps to the embed's line.
+pub fn block_source_map_with_embeds(path: &Path, ctx: &mut RenderContext<'_>) -> Vec<BlockMapping> {
+    let Ok(canonical) = paths::canonicalize(path) else {
+        return Vec::new();
+    };
+    let Ok(content) = fs::read_to_string(&canonical) else {
+        return Vec::new();
+    };
+    block_source_map_of_string(&content, Some(&canonical), ctx)
+}
+
+/// `block_source_map_with_embeds` for `markdown` not read from disk (see
+/// `render_markdown_string_with_embeds`); `note` is canonical.
+pub fn block_source_map_of_string(
+    markdown: &str,
+    note: Option<&Path>,
+    ctx: &mut RenderContext<'_>,
+) -> Vec<BlockMapping> {
+    let (expanded, origins) = expand_with_line_origins(markdown, note, ctx);
+    let origin = |line: usize| {
+        origins
+            .get(line.saturating_sub(1))
+            .or(origins.last())
+            .copied()
+            .unwrap_or(1)
+    };
+    // With the properties table, the frontmatter's blocks are one element (or none when it has
+    // no fields).
+    let mut out: Vec<BlockMapping> = Vec::new();
+    let mut frontmatter_end = 0;
+    if ctx.render.properties_table {
+        if let Some((yaml, _)) = split_frontmatter(&expanded) {
+            frontmatter_end = yaml.lines().count() + 2;
+            if !parse_frontmatter(&expanded).is_empty() && !is_kanban(&expanded) {
+                out.push(BlockMapping {
+                    start_line: 1,
+                    end_line: frontmatter_end,
+                    element_index: 0,
+                });
+            }
+        }
+    }
+    // An embed container renders as one element, so the blocks from its opening sentinel
+    // paragraph to its closing one are merged.
+    let lines: Vec<&str> = expanded.split('\n').collect();
+    let first_char = |line: usize| lines.get(line - 1).and_then(|l| l.trim_start().chars().next());
+    let mut open: Option<(usize, usize)> = None;
+    for block in block_source_map(&expanded) {
+        if block.start_line <= frontmatter_end {
+            continue;
+        }
+        let (start_line, end_line) = (origin(block.start_line), origin(block.end_line));
+        match (first_char(block.start_line), open) {
+            (Some(EMBED_OPEN_START), Some((start, depth))) => open = Some((start, depth + 1)),
+            (Some(EMBED_OPEN_START), None) => open = Some((start_line, 1)),
+            (Some(EMBED_CLOSE), Some((start, 1))) => {
+                open = None;
+                out.push(BlockMapping {
+                    start_line: start,
+                    end_line,
+                    element_index: out.len(),
+                });
+            }
+            (Some(EMBED_CLOSE), Some((start, depth))) => open = Some((start, depth - 1)),
+            (_, Some(_)) => {}
+            (_, None) => out.push(BlockMapping {
+                start_line,
+                end_line,
+                element_index: out.len(),
+            }),
+        }
+    }
+    out
+}
+
 pub fn postprocess_obsidian_html(html: &str) -> String {
     const PREFIX: &str = "href=\"app://open?path=";
     let mut out = String::with_capacity(html.len());
@@ -133,7 +901,22 @@ pub fn postprocess_obsidian_html(html: &str) -> String {
         while i < bytes.len() && bytes[i] != b'"' {
             i += 1;
         }
-        let path = &html[path_start..i];
+        let (query, anchor) = match html[path_start..i].split_once('#') {
+            Some((query, anchor)) => (query, Some(percent_decode(anchor))),
+            None => (&html[path_start..i], None),
+        };
+        // comrak escapes the `&` between query parameters.
+        let mut params = query.split("&amp;");
+        let path = percent_decode(params.next().unwrap_or(""));
+        let (mut candidates, mut suggestions) = (Vec::new(), Vec::new());
+        for param in params {
+            if let Some(candidate) = param.strip_prefix("candidate=") {
+                candidates.push(percent_decode(candidate));
+            } else if let Some(suggestion) = param.strip_prefix("suggestion=") {
+                suggestions.push(percent_decode(suggestion));
+            }
+        }
+        let href_end = i;
         i += 1;
         let after_open_gt = html[i..].find('>').map(|j| i + j + 1).unwrap_or(i);
         let inner_start = after_open_gt;
@@ -143,23 +926,45 @@ pub fn postprocess_obsidian_html(html: &str) -> String {
             .unwrap_or(inner_start);
         let inner = &html[inner_start..inner_end];
         let after_close = inner_end + 4;
-        if path.is_empty() {
+        if path.is_empty() && !suggestions.is_empty() {
+            let json = serde_json::to_string(&suggestions).unwrap_or_default();
+            let json = escape_attr(&json);
+            out.push_str(&format!("<span class=\"obs-link broken\" data-suggestions=\"{}\">", json));
+            out.push_str(&escape_html_text(inner));
+            out.push_str("</span>");
+        } else if path.is_empty() {
             out.push_str("<span class=\"obs-link broken\">");
             out.push_str(&escape_html_text(inner));
             out.push_str("</span>");
         } else {
-            let a_tag = &html[tag_start..inner_start];
+            // comrak re-encodes link destinations byte-wise; restore the readable UTF-8 href.
+            let href = obs_link_href(Some(Path::new(&path)), anchor.as_deref());
+            let a_tag = format!(
+                "{}{}{}",
+                &html[tag_start..path_start - PREFIX.len() + "href=\"".len()],
+                href,
+                &html[href_end..inner_start]
+            );
             let before_gt = a_tag.rfind('>').unwrap_or(a_tag.len());
             let frag = &a_tag[..before_gt];
+            let class = if candidates.is_empty() { "obs-link" } else { "obs-link ambiguous" };
             if let Some(pos) = frag.find("class=\"") {
                 let insert = pos + 7;
                 out.push_str(&frag[..insert]);
-                out.push_str("obs-link ");
+                out.push_str(class);
+                out.push(' ');
                 out.push_str(&frag[insert..]);
-                out.push_str(&format!(" data-obs-path=\"{}\"", escape_attr(path)));
+                out.push_str(&format!(" data-obs-path=\"{}\"", escape_attr(&path)));
             } else {
                 out.push_str(frag);
-                out.push_str(&format!(" class=\"obs-link\" data-obs-path=\"{}\"", escape_attr(path)));
+                out.push_str(&format!(" class=\"{}\" data-obs-path=\"{}\"", class, escape_attr(&path)));
+            }
+            if !candidates.is_empty() {
+                let json = serde_json::to_string(&candidates).unwrap_or_default();
+                out.push_str(&format!(" data-obs-candidates=\"{}\"", escape_attr(&json)));
+            }
+            if let Some(anchor) = &anchor {
+                out.push_str(&format!(" data-obs-anchor=\"{}\"", escape_attr(anchor)));
             }
             out.push_str(&a_tag[before_gt..]);
             out.push_str(inner);
@@ -172,6 +977,105 @@ pub fn postprocess_obsidian_html(html: &str) -> String {
     out
 }
 
+/// Opens `http(s)` links in a new context without handing it the opener or the referrer:
+/// each such `<a>` gets `target="_blank"`, `rel="noopener noreferrer"` and the `external-link`
+/// class (added to any class it has). Other links are left alone.
+fn harden_external_links(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a ") {
+        let tag_end = rest[start..].find('>').map_or(rest.len(), |i| start + i);
+        let tag = &rest[start..tag_end];
+        out.push_str(&rest[..start]);
+        rest = &rest[tag_end..];
+        let href = tag
+            .split_once(" href=\"")
+            .and_then(|(_, after)| after.split_once('"'))
+            .map(|(href, _)| href.to_ascii_lowercase());
+        let external = href.is_some_and(|h| h.starts_with("http://") || h.starts_with("https://"));
+        if !external || tag.contains(" target=") || tag.contains(" rel=") {
+            out.push_str(tag);
+            continue;
+        }
+        match tag.split_once(" class=\"") {
+            Some((before, after)) => out.push_str(&format!("{} class=\"external-link {}", before, after)),
+            None => out.push_str(&format!("{} class=\"external-link\"", tag)),
+        }
+        out.push_str(" target=\"_blank\" rel=\"noopener noreferrer\"");
+    }
+    out.push_str(rest);
+    out
+}
+
+const BLOCK_MARK_START: char = '\u{E000}';
+const BLOCK_MARK_END: char = '\u{E001}';
+
+/// Replaces `^block-id` markers (outside code) with private-use sentinels that survive
+/// rendering as text; `apply_block_ids` turns them into element ids.
+fn mark_block_ids(markdown: &str) -> String {
+    let skip = compute_skip_ranges(markdown);
+    let mut out = String::with_capacity(markdown.len());
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        let in_code = in_skip_range(offset, &skip);
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+        match split_block_id(body).filter(|_| !in_code) {
+            Some((text, id)) => {
+                out.push_str(text);
+                out.push(BLOCK_MARK_START);
+                out.push_str(id);
+                out.push(BLOCK_MARK_END);
+                out.push_str(newline);
+            }
+            None => out.push_str(line),
+        }
+        offset += line.len();
+    }
+    out
+}
+
+/// Removes block sentinels and puts `id="^block-id"` on the block element that contained each.
+fn apply_block_ids(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(BLOCK_MARK_START) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + BLOCK_MARK_START.len_utf8()..];
+        let Some(end) = after.find(BLOCK_MARK_END) else {
+            rest = after;
+            continue;
+        };
+        let id = &after[..end];
+        if let Some(insert_at) = last_block_open_tag_end(&out) {
+            out.insert_str(insert_at, &format!(" id=\"{}\"", escape_attr(&block_anchor(id))));
+        }
+        rest = &after[end + BLOCK_MARK_END.len_utf8()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset just past the tag name of the last opened block element (`<p`, `<li`, ...).
+fn last_block_open_tag_end(html: &str) -> Option<usize> {
+    const TAGS: [&str; 10] = ["<p", "<li", "<td", "<th", "<h1", "<h2", "<h3", "<h4", "<h5", "<h6"];
+    TAGS.iter()
+        .filter_map(|tag| {
+            let mut search = html;
+            while let Some(pos) = search.rfind(tag) {
+                let next = search[pos + tag.len()..].chars().next();
+                if matches!(next, Some('>') | Some(' ')) {
+                    return Some(pos + tag.len());
+                }
+                search = &search[..pos];
+            }
+            None
+        })
+        .max()
+}
+
 fn escape_html_text(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -187,20 +1091,175 @@ fn escape_attr(s: &str) -> String {
 }
 
 pub fn render_markdown_with_embeds(path: &Path, ctx: &mut RenderContext<'_>) -> String {
-    let canonical = match path.canonicalize() {
+    let canonical = match paths::canonicalize(path) {
         Ok(p) => p,
-        Err(_) => return render_markdown_safe("*[Embed: invalid path]*"),
+        Err(_) => {
+            let name = path.to_string_lossy();
+            let placeholder = embed_placeholder(EmbedPlaceholder::InvalidPath, &name, &ctx.render);
+            return apply_embed_placeholders(&render_markdown_safe(&placeholder));
+        }
     };
     let mtime = match fs::metadata(&canonical) {
         Ok(m) => m.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH),
         Err(_) => std::time::SystemTime::UNIX_EPOCH,
     };
-    if let Some(html) = ctx.cache.get(&canonical, mtime) {
+    let cacheable = ctx.render.unpublished_embed.is_none() && ctx.render.embed_scope.is_none();
+    if let Some(html) = ctx.cache.get(&canonical, mtime).filter(|_| cacheable) {
+        return finish_html(&html, Some(&canonical), ctx);
+    }
+    // Only the note's own tasks are marked; embedded tasks live in other files.
+    let expanded_md = expand_note(&canonical, ctx, mark_tasks);
+    if ctx.generation.is_superseded() {
+        return String::new();
+    }
+    let html = render_expanded_note(&expanded_md, &ctx.render, ctx.cache);
+    // Failed embeds stay uncached so a retry re-reads them.
+    if cacheable && !html.contains("class=\"embed-error\"") {
+        let mut embeds = embed_dependencies(&canonical, ctx.index, &ctx.vault_root);
+        embeds.remove(&canonical);
+        // A changed bibliography changes the citations too.
+        let cites = !citations_in(&expanded_md).is_empty();
+        let bibliography = ctx.render.bibliography.as_deref().filter(|_| cites);
+        if let Some(file) = bibliography.and_then(|file| paths::canonicalize(file).ok()) {
+            embeds.insert(file);
+        }
+        ctx.cache.insert_with_embeds(canonical.clone(), mtime, html.clone(), embeds);
+    }
+    finish_html(&html, Some(&canonical), ctx)
+}
+
+/// Renders `markdown` that is not (yet) on disk, such as an editor buffer, like the note at
+/// `note` when given: links and embeds resolve against the context's vault, and embeds of the
+/// note itself show as cycles. Bypasses the render cache.
+pub fn render_markdown_string_with_embeds(
+    markdown: &str,
+    note: Option<&Path>,
+    ctx: &mut RenderContext<'_>,
+) -> String {
+    let note = note.and_then(|note| paths::canonicalize(note).ok());
+    if let Some(note) = &note {
+        ctx.visited.insert(note.clone());
+    }
+    ctx.depth += 1;
+    let expanded_md = preprocess_obsidian_links(&strip_sentinels(markdown), ctx);
+    ctx.depth -= 1;
+    if let Some(note) = &note {
+        ctx.visited.remove(note);
+    }
+    let html = render_expanded_note(&expanded_md, &ctx.render, ctx.cache);
+    finish_html(&html, note.as_deref(), ctx)
+}
+
+/// HTML of a note whose links and embeds are expanded: citations, then a kanban board, or the
+/// body under a properties table, or plain markdown.
+fn render_expanded_note(expanded_md: &str, render: &RenderOptions, cache: &mut RenderCache) -> String {
+    let expanded_md = apply_citations(expanded_md, render, cache);
+    let html = match split_frontmatter(&expanded_md) {
+        _ if is_kanban(&expanded_md) => render_kanban_board(&expanded_md, render),
+        Some((_, body)) if render.properties_table => {
+            let table = properties_table(&expanded_md, &parse_frontmatter(&expanded_md));
+            format!("{}{}", table, render_expanded(body, render))
+        }
+        _ => render_expanded(&expanded_md, render),
+    };
+    apply_task_offsets(&html)
+}
+
+/// The steps after the render cache: image sizes relative to the note's folder (so a changed
+/// image never serves stale dimensions), remote images in privacy mode, and the backlinks
+/// footer of `note`, if any (backlinks change with other notes).
+fn finish_html(html: &str, note: Option<&Path>, ctx: &mut RenderContext<'_>) -> String {
+    let base_dir = note.and_then(Path::parent).unwrap_or(&ctx.vault_root).to_path_buf();
+    let mut html = add_image_dimensions(html, &base_dir, ctx.cache);
+    if ctx.render.block_remote_images {
+        html = block_remote_images(&html);
+    }
+    match note {
+        Some(note) => with_backlinks_footer(html, note, ctx),
+        None => html,
+    }
+}
+
+/// `html` followed by a `section.backlinks-footer` listing each link to `note` with its line,
+/// when the footer is enabled and the note has backlinks.
+fn with_backlinks_footer(html: String, note: &Path, ctx: &RenderContext<'_>) -> String {
+    if !ctx.render.backlinks_footer {
         return html;
     }
-    let expanded_md = get_expanded_markdown(&canonical, ctx);
-    let raw_html = render_markdown_safe(&expanded_md);
-    let html = postprocess_obsidian_html(&raw_html);
-    ctx.cache.insert(canonical, mtime, html.clone());
-    html
+    let mut backlinks = find_backlinks(note, ctx.index, &ctx.vault_root);
+    if backlinks.is_empty() {
+        return html;
+    }
+    backlinks.sort_by(|a, b| a.source.cmp(&b.source).then(a.line.cmp(&b.line)));
+    let items: String = backlinks
+        .iter()
+        .map(|backlink| {
+            let source = Path::new(&backlink.source);
+            let name = source.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+            format!(
+                "<li><a class=\"obs-link\" href=\"{}\" data-obs-path=\"{}\">{}</a> \
+                 <span class=\"backlink-snippet\">{}</span></li>",
+                obs_link_href(Some(source), None),
+                escape_attr(&backlink.source),
+                escape_html_text(&name),
+                escape_html_text(&truncate_snippet(&backlink.snippet, MAX_SNIPPET_CHARS))
+            )
+        })
+        .collect();
+    format!(
+        "{}<section class=\"backlinks-footer\"><h2>Linked mentions</h2><ul>{}</ul></section>\n",
+        html, items
+    )
+}
+
+/// `[@key]` citations of `markdown` outside code.
+fn citations_in(markdown: &str) -> Vec<Citation> {
+    let skip = compute_skip_ranges(markdown);
+    find_citations(markdown, |pos| in_skip_range(pos, &skip))
+}
+
+/// Resolves `[@key]` citations against the configured bibliography (parsed once per change, in
+/// `cache`), appending the reference list; an unreadable bibliography is reported below the note.
+fn apply_citations(markdown: &str, render: &RenderOptions, cache: &mut RenderCache) -> String {
+    let Some(file) = &render.bibliography else {
+        return markdown.to_string();
+    };
+    let citations = citations_in(markdown);
+    if citations.is_empty() {
+        return markdown.to_string();
+    }
+    match cache.bibliography(file) {
+        Ok(bibliography) => render_citations(markdown, &citations, bibliography),
+        Err(error) => format!("{}\n\n*[Bibliography: {} ({})]*\n", markdown, file.display(), error),
+    }
+}
+
+/// A Kanban plugin board as `div.kanban-board`: one `section.kanban-lane` per `##` lane with
+/// its cards rendered as markdown, so links, embeds and task checkboxes keep working.
+fn render_kanban_board(expanded_md: &str, render: &RenderOptions) -> String {
+    let lanes: String = parse_board(expanded_md)
+        .iter()
+        .map(|lane| {
+            let cards: String = lane
+                .cards
+                .iter()
+                .map(|card| format!("<div class=\"kanban-card\">{}</div>", render_expanded(card, render)))
+                .collect();
+            format!(
+                "<section class=\"kanban-lane\"><h2 class=\"kanban-lane-title\">{} \
+                 <span class=\"kanban-count\">{}</span></h2>{}</section>",
+                escape_html_text(&lane.title),
+                lane.cards.len(),
+                cards
+            )
+        })
+        .collect();
+    format!("<div class=\"kanban-board\">{}</div>\n", lanes)
+}
+
+/// HTML for markdown whose links and embeds were already expanded.
+pub(crate) fn render_expanded(expanded_md: &str, render: &RenderOptions) -> String {
+    let raw_html = apply_embed_placeholders(&render_markdown(&mark_block_ids(expanded_md), render));
+    let html = apply_block_ids(&harden_external_links(&postprocess_obsidian_html(&raw_html)));
+    apply_embed_chrome(&apply_csv_embeds(&apply_embed_errors(&html, render)))
 }
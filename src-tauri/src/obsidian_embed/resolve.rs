@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use crate::paths;
+
 use super::index::{normalize_rel_key, VaultIndex};
 use super::parse::ParsedLink;
 
@@ -8,52 +10,88 @@ pub enum ResolveResult {
     Resolved(PathBuf),
     Placeholder(PathBuf),
     NotFound,
-    #[allow(dead_code)]
     Ambiguous(Vec<PathBuf>),
 }
 
+/// Resolves like Obsidian: a basename shared by several notes picks the first by path.
 pub fn resolve_target(
     parsed: &ParsedLink,
     index: &VaultIndex,
-    _vault_root: &std::path::Path,
+    vault_root: &std::path::Path,
+) -> ResolveResult {
+    resolve_target_with(parsed, index, vault_root, false)
+}
+
+/// `resolve_target`, except that with `report_ambiguous` a basename shared by several notes
+/// yields `Ambiguous` with every candidate.
+pub fn resolve_target_with(
+    parsed: &ParsedLink,
+    index: &VaultIndex,
+    vault_root: &std::path::Path,
+    report_ambiguous: bool,
 ) -> ResolveResult {
     let target = normalize_rel_key(parsed.target.trim());
     if target.is_empty() {
         return ResolveResult::NotFound;
     }
     if target.contains('/') {
-        let with_md = if target.ends_with(".md") {
-            target.clone()
-        } else {
-            format!("{}.md", target)
-        };
-        if let Some(p) = index.by_rel_path.get(&target) {
-            return path_to_result(p.clone());
+        if let Some(id) = index.by_rel_path(&target) {
+            return path_to_result(index.path(id).clone());
         }
-        if let Some(p) = index.by_rel_path.get(&with_md) {
-            return path_to_result(p.clone());
+        if let Some(p) = folder_note(&target, index) {
+            return path_to_result(p);
         }
-        return ResolveResult::NotFound;
+        return vault_attachment(&target, index, vault_root).map_or(ResolveResult::NotFound, path_to_result);
     }
     let base = if target.ends_with(".md") {
         target.strip_suffix(".md").unwrap_or(&target).to_string()
     } else {
         target
     };
-    if let Some(paths) = index.by_basename.get(&base) {
-        if paths.is_empty() {
-            return ResolveResult::NotFound;
-        }
-        return path_to_result(paths[0].clone());
+    match index.by_basename(&base) {
+        [] => {}
+        [first, ..] if !report_ambiguous => return path_to_result(index.path(*first).clone()),
+        [only] => return path_to_result(index.path(*only).clone()),
+        ids => return ResolveResult::Ambiguous(ids.iter().map(|id| index.path(*id).clone()).collect()),
+    }
+    if let Some(p) = folder_note(&base, index) {
+        return path_to_result(p);
     }
-    ResolveResult::NotFound
+    vault_attachment(&base, index, vault_root).map_or(ResolveResult::NotFound, path_to_result)
+}
+
+/// Extensions of the attachments embeds render as placeholders (images, PDFs, CSV tables).
+const ATTACHMENT_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "svg", "pdf", "csv"];
+
+fn is_attachment(path: &std::path::Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    ATTACHMENT_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known))
+}
+
+/// Attachments are not indexed: `target` names one when it has an attachment extension and
+/// the file exists at that path under the vault root, outside hidden and excluded folders.
+fn vault_attachment(target: &str, index: &VaultIndex, vault_root: &std::path::Path) -> Option<PathBuf> {
+    if !is_attachment(std::path::Path::new(target)) {
+        return None;
+    }
+    let root = paths::canonicalize(vault_root).ok()?;
+    let path = paths::canonicalize(&root.join(target)).ok()?;
+    (path.is_file() && is_attachment(&path) && index.admits(&root, &path)).then_some(path)
+}
+
+/// The folder note of vault folder `folder`: `Folder/Folder.md`, else `Folder/index.md`.
+fn folder_note(folder: &str, index: &VaultIndex) -> Option<PathBuf> {
+    let name = folder.rsplit('/').next()?;
+    [format!("{}/{}.md", folder, name), format!("{}/index.md", folder)]
+        .iter()
+        .find_map(|key| index.by_rel_path(key))
+        .map(|id| index.path(id).clone())
 }
 
 fn path_to_result(p: PathBuf) -> ResolveResult {
-    let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
-    match ext.to_lowercase().as_str() {
-        "md" => ResolveResult::Resolved(p),
-        "png" | "jpg" | "jpeg" | "svg" | "pdf" => ResolveResult::Placeholder(p),
-        _ => ResolveResult::Resolved(p),
+    if is_attachment(&p) {
+        ResolveResult::Placeholder(p)
+    } else {
+        ResolveResult::Resolved(p)
     }
 }
@@ -0,0 +1,65 @@
+//! Near misses for wikilinks that resolve to nothing: existing note names a few typos away
+//! from the target, so links like `[[Projcet]]` are easy to fix.
+
+use super::index::VaultIndex;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Note basenames closest to the last segment of `target`, nearest first then by name. Case is
+/// ignored; a swap of neighbouring letters counts as one edit, and at most a third of the
+/// target's letters (at least one) may differ.
+pub fn suggest_targets(target: &str, index: &VaultIndex) -> Vec<String> {
+    let name = target.rsplit('/').next().unwrap_or(target).trim();
+    let typed: Vec<char> = name.strip_suffix(".md").unwrap_or(name).to_lowercase().chars().collect();
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let max = (typed.len() / 3).max(1);
+    let mut scored: Vec<(usize, &str)> = index
+        .basenames()
+        .filter_map(|(base, _)| {
+            let candidate: Vec<char> = base.to_lowercase().chars().collect();
+            edit_distance(&typed, &candidate, max).map(|distance| (distance, base))
+        })
+        .collect();
+    scored.sort_unstable();
+    scored.into_iter().take(MAX_SUGGESTIONS).map(|(_, base)| base.to_string()).collect()
+}
+
+/// Optimal string alignment distance between `a` and `b`, when it is at most `max`.
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        if current.iter().min().is_some_and(|&least| least > max) {
+            return None;
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    Some(previous[b.len()]).filter(|&distance| distance <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("projcet"), &chars("project"), 2), Some(1));
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting"), 3), Some(3));
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting"), 2), None);
+        assert_eq!(edit_distance(&chars(""), &chars("a"), 1), Some(1));
+    }
+}
@@ -0,0 +1,51 @@
+//! Tags of a note: its frontmatter `tags` (or `tag`) and inline `#tags` outside code, as
+//! Obsidian collects them.
+
+use crate::frontmatter::{parse_frontmatter, FrontmatterValue};
+
+use super::parse::{compute_skip_ranges, in_skip_range};
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Inline `#tag`s: after whitespace or at a line start, made of letters, digits, `_`, `-` and
+/// `/` (nested tags), with at least one non-digit. Headings (`# Title`) and `#1` are not tags.
+fn inline_tags(md: &str) -> Vec<String> {
+    let skip = compute_skip_ranges(md);
+    let mut tags = Vec::new();
+    for (at, _) in md.match_indices('#') {
+        let boundary = at == 0 || md[..at].ends_with(char::is_whitespace);
+        if !boundary || in_skip_range(at, &skip) {
+            continue;
+        }
+        let rest = &md[at + 1..];
+        let len = rest.find(|c: char| !is_tag_char(c)).unwrap_or(rest.len());
+        let tag = rest[..len].trim_end_matches('/');
+        if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit() || c == '/') {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Frontmatter tags first, then inline ones in order; without `#` and without duplicates
+/// (case-insensitive, first spelling wins).
+pub fn note_tags(md: &str) -> Vec<String> {
+    let fields = parse_frontmatter(md);
+    let listed = fields
+        .iter()
+        .filter(|(key, _)| matches!(key.to_lowercase().as_str(), "tags" | "tag"))
+        .flat_map(|(_, value)| match value {
+            FrontmatterValue::List(items) => items.clone(),
+            FrontmatterValue::Text(text) => text.split([',', ' ']).map(str::to_string).collect(),
+        })
+        .map(|tag| tag.trim().trim_start_matches('#').to_string());
+    let mut tags: Vec<String> = Vec::new();
+    for tag in listed.chain(inline_tags(md)) {
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
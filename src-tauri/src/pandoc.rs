@@ -0,0 +1,80 @@
+//! DOCX/ODT/LaTeX export through a user-installed `pandoc`, fed flattened markdown on stdin.
+
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Pandoc writer and file extension for an export `format`.
+pub fn output_format(format: &str) -> Result<(&'static str, &'static str), String> {
+    match format.to_ascii_lowercase().as_str() {
+        "docx" => Ok(("docx", "docx")),
+        "odt" => Ok(("odt", "odt")),
+        "latex" | "tex" => Ok(("latex", "tex")),
+        _ => Err(format!("Unsupported export format: {} (expected docx, odt or latex)", format)),
+    }
+}
+
+/// Converts `markdown` to `format` at `output`, running the `pandoc` binary. Relative image
+/// and link paths resolve against `resource_dir`; with a `bibliography`, citeproc formats
+/// `[@key]` citations.
+pub fn convert(
+    pandoc: &str,
+    markdown: &str,
+    format: &str,
+    resource_dir: &Path,
+    bibliography: Option<&Path>,
+    output: &Path,
+) -> Result<(), String> {
+    let (writer, _) = output_format(format)?;
+    let mut command = Command::new(pandoc);
+    command
+        .args(["--from", "markdown", "--to", writer, "--standalone", "--resource-path"])
+        .arg(resource_dir);
+    if let Some(bibliography) = bibliography {
+        command.arg("--citeproc").arg("--bibliography").arg(bibliography);
+    }
+    let mut child = command
+        .arg("--output")
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!(
+                "pandoc not found ({}); install it from pandoc.org or set its path in settings",
+                pandoc
+            ),
+            _ => format!("Could not run pandoc ({}): {}", pandoc, e),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(markdown.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let result = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("pandoc failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_map_to_writer_and_extension() {
+        assert_eq!(output_format("DOCX").unwrap(), ("docx", "docx"));
+        assert_eq!(output_format("latex").unwrap(), ("latex", "tex"));
+        assert!(output_format("pdf").is_err());
+    }
+
+    #[test]
+    fn missing_pandoc_is_reported() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let missing = dir.path().join("no-pandoc");
+        let output = dir.path().join("a.docx");
+        let err = convert(&missing.to_string_lossy(), "# A", "docx", dir.path(), None, &output).unwrap_err();
+        assert!(err.starts_with("pandoc not found"), "{}", err);
+    }
+}
@@ -0,0 +1,172 @@
+//! Path normalization shared by commands, the vault index and link rendering. Canonical paths
+//! drop the Windows verbatim prefix (`\\?\C:\...`, `\\?\UNC\...`) whenever the plain form is
+//! equivalent, so they compare equal to user-supplied roots and stay readable in hrefs.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tempfile::NamedTempFile;
+
+pub fn canonicalize(path: &Path) -> Result<PathBuf, String> {
+    dunce::canonicalize(path).map_err(|e| e.to_string())
+}
+
+/// `path` with forward slashes and no verbatim prefix, for hrefs and lookup keys.
+pub fn slash_path(path: &Path) -> String {
+    dunce::simplified(path).to_string_lossy().replace('\\', "/")
+}
+
+/// `target` relative to directory `base` (`../other/Note.md`); `target` itself when the two
+/// share no prefix (different drives).
+pub fn relative_to(target: &Path, base: &Path) -> PathBuf {
+    let target: Vec<_> = target.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return target.iter().collect();
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&target[common..]);
+    relative
+}
+
+/// Replaces `path` with `contents` via a temporary sibling file and a rename, so readers never
+/// see a half-written note.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    write_atomic_with(path, false, |file| file.write_all(contents.as_ref()))
+}
+
+/// A uniquely named hidden file next to `path`, removed again unless persisted.
+fn temp_sibling(path: &Path, name: &str) -> io::Result<NamedTempFile> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    tempfile::Builder::new().prefix(&format!(".{}.", name)).suffix(".tmp").tempfile_in(dir)
+}
+
+/// Replaces `path` with what `write` puts in a temporary sibling file (uniquely named, so
+/// concurrent writes never share one), keeping the permissions of the file it replaces. The
+/// file is flushed to disk before it is renamed over `path` (and the folder after), so a crash
+/// leaves either the old or the new contents, never a truncated file. With `backup`, the
+/// previous contents are kept as `<name>.bak`, replaced on every write.
+pub fn write_atomic_with(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> Result<(), String> {
+    let name = path.file_name().and_then(|n| n.to_str()).ok_or("Invalid path")?;
+    let result = temp_sibling(path, name).and_then(|mut tmp| {
+        write(tmp.as_file_mut())?;
+        if let Ok(metadata) = fs::metadata(path) {
+            tmp.as_file().set_permissions(metadata.permissions())?;
+        }
+        tmp.as_file().sync_all()?;
+        if backup && path.is_file() {
+            let bak_tmp = temp_sibling(path, &format!("{}.bak", name))?;
+            fs::copy(path, bak_tmp.path())?;
+            bak_tmp.persist(path.with_file_name(format!("{}.bak", name))).map_err(|e| e.error)?;
+        }
+        tmp.persist(path).map_err(|e| e.error)
+    });
+    result.map_err(|e| e.to_string())?;
+    sync_parent(path);
+    Ok(())
+}
+
+/// Flushes the rename itself; only possible (and needed) on Unix.
+#[cfg(unix)]
+fn sync_parent(path: &Path) {
+    if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) {}
+
+/// Fingerprint of note text, handed to the frontend on load and checked again on save.
+pub fn content_hash(text: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// The text at `path` when it no longer hashes to `expected_hash` (edited elsewhere since it was
+/// loaded); `None` when unchanged or deleted, so a save can go ahead.
+pub fn changed_on_disk(path: &Path, expected_hash: &str) -> Result<Option<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok((content_hash(&text) != expected_hash).then_some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slash_path_uses_forward_slashes() {
+        assert_eq!(slash_path(Path::new("vault\\sub\\Note.md")), "vault/sub/Note.md");
+    }
+
+    #[test]
+    fn relative_to_walks_up_to_common_ancestor() {
+        let rel = |target: &str, base: &str| slash_path(&relative_to(Path::new(target), Path::new(base)));
+        assert_eq!(rel("/v/a/Note.md", "/v/a"), "Note.md");
+        assert_eq!(rel("/v/b/c/Note.md", "/v/a"), "../b/c/Note.md");
+    }
+
+    #[test]
+    fn canonicalize_has_no_verbatim_prefix() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let canonical = canonicalize(dir.path()).unwrap();
+        assert!(!canonical.to_string_lossy().starts_with(r"\\?\"), "{:?}", canonical);
+        assert_eq!(canonical, canonicalize(&canonical).unwrap());
+    }
+
+    #[test]
+    fn changed_on_disk_reports_external_edits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let note = dir.path().join("Note.md");
+        fs::write(&note, "loaded").unwrap();
+        let loaded = content_hash("loaded");
+        assert_eq!(changed_on_disk(&note, &loaded).unwrap(), None);
+        fs::write(&note, "edited in Obsidian").unwrap();
+        assert_eq!(changed_on_disk(&note, &loaded).unwrap().as_deref(), Some("edited in Obsidian"));
+        assert_eq!(changed_on_disk(&dir.path().join("Gone.md"), &loaded).unwrap(), None);
+    }
+
+    #[test]
+    fn write_atomic_keeps_a_rolling_backup() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let note = dir.path().join("Note.md");
+        let write = |text: &'static str| write_atomic_with(&note, true, |f| f.write_all(text.as_bytes()));
+        write("v1").unwrap();
+        assert!(!dir.path().join("Note.md.bak").exists());
+        write("v2").unwrap();
+        write("v3").unwrap();
+        assert_eq!(fs::read_to_string(&note).unwrap(), "v3");
+        assert_eq!(fs::read_to_string(dir.path().join("Note.md.bak")).unwrap(), "v2");
+        let mut names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["Note.md", "Note.md.bak"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let note = dir.path().join("Note.md");
+        fs::write(&note, "v1").unwrap();
+        fs::set_permissions(&note, fs::Permissions::from_mode(0o640)).unwrap();
+        write_atomic(&note, "v2").unwrap();
+        assert_eq!(fs::metadata(&note).unwrap().permissions().mode() & 0o777, 0o640);
+        assert_eq!(fs::read_to_string(&note).unwrap(), "v2");
+    }
+}
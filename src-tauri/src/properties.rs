@@ -0,0 +1,110 @@
+//! Frontmatter shown as a properties table at the top of a note, as Obsidian 1.4+ does: each
+//! value typed from its text (checkbox, date, number, link, list) rather than shown as YAML.
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::frontmatter::{split_frontmatter, FrontmatterValue};
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// (`data-type`, cell HTML) for a scalar value.
+fn typed_value(text: &str) -> (&'static str, String) {
+    let trimmed = text.trim();
+    let lower = trimmed.to_ascii_lowercase();
+    if lower == "true" || lower == "false" {
+        let checked = if lower == "true" { " checked" } else { "" };
+        return ("checkbox", format!("<input type=\"checkbox\" disabled{}>", checked));
+    }
+    let datetime = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .any(|format| NaiveDateTime::parse_from_str(trimmed, format).is_ok());
+    if datetime || NaiveDate::parse_from_str(trimmed, "%Y-%m-%d").is_ok() {
+        let kind = if datetime { "datetime" } else { "date" };
+        let escaped = escape_html(trimmed);
+        return (kind, format!("<time datetime=\"{}\">{}</time>", escaped, escaped));
+    }
+    if !trimmed.is_empty() && trimmed.parse::<f64>().is_ok_and(f64::is_finite) {
+        return ("number", escape_html(trimmed));
+    }
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        let escaped = escape_html(trimmed);
+        return (
+            "link",
+            format!(
+                "<a class=\"external-link\" href=\"{0}\" target=\"_blank\" \
+                 rel=\"noopener noreferrer\">{0}</a>",
+                escaped
+            ),
+        );
+    }
+    ("text", escape_html(trimmed))
+}
+
+/// Keys in the order they appear in `yaml` (`parse_frontmatter` sorts them).
+fn key_order(yaml: &str) -> Vec<&str> {
+    yaml.lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#', '-']))
+        .filter_map(|line| line.split_once(':').map(|(key, _)| key.trim()))
+        .collect()
+}
+
+/// `table.properties` for the frontmatter of `md`, one row per field in file order; empty when
+/// the note has no fields. Lists become `ul.property-list`.
+pub fn properties_table(md: &str, fields: &BTreeMap<String, FrontmatterValue>) -> String {
+    if fields.is_empty() {
+        return String::new();
+    }
+    let yaml = split_frontmatter(md).map_or("", |(yaml, _)| yaml);
+    let mut keys: Vec<&String> = fields.keys().collect();
+    let order = key_order(yaml);
+    keys.sort_by_key(|key| order.iter().position(|k| k == key).unwrap_or(usize::MAX));
+    let rows: String = keys
+        .into_iter()
+        .map(|key| {
+            let (kind, cell) = match &fields[key] {
+                FrontmatterValue::Text(text) => typed_value(text),
+                FrontmatterValue::List(items) => {
+                    let items: String = items
+                        .iter()
+                        .map(|item| format!("<li>{}</li>", typed_value(item).1))
+                        .collect();
+                    ("list", format!("<ul class=\"property-list\">{}</ul>", items))
+                }
+            };
+            format!(
+                "<tr data-type=\"{}\"><th>{}</th><td>{}</td></tr>",
+                kind,
+                escape_html(key),
+                cell
+            )
+        })
+        .collect();
+    format!("<table class=\"properties\"><tbody>{}</tbody></table>\n", rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::parse_frontmatter;
+
+    #[test]
+    fn values_are_typed_and_keep_file_order() {
+        let md = "---\ntitle: A <b>\ndone: true\ndue: 2024-06-01\ntags: [x, y]\nsize: 12.5\n---\nBody";
+        let html = properties_table(md, &parse_frontmatter(md));
+        let rows: Vec<&str> = html.split("<tr data-type=\"").skip(1).collect();
+        let kinds: Vec<&str> = rows.iter().map(|row| row.split('"').next().unwrap()).collect();
+        assert_eq!(kinds, ["text", "checkbox", "date", "list", "number"]);
+        assert!(html.contains("<th>title</th><td>A &lt;b&gt;</td>"), "{}", html);
+        assert!(html.contains("<input type=\"checkbox\" disabled checked>"), "{}", html);
+        assert!(html.contains("<time datetime=\"2024-06-01\">2024-06-01</time>"), "{}", html);
+        assert!(html.contains("<ul class=\"property-list\"><li>x</li><li>y</li></ul>"), "{}", html);
+        assert_eq!(properties_table("Body", &BTreeMap::new()), "");
+    }
+}
@@ -0,0 +1,72 @@
+//! Privacy mode: remote `<img>` sources are swapped for click-to-load placeholders at render
+//! time, so opening a note doesn't contact third-party servers.
+
+fn is_remote(src: &str) -> bool {
+    let src = src.trim().to_ascii_lowercase();
+    src.starts_with("http://") || src.starts_with("https://") || src.starts_with("//")
+}
+
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let (_, after) = tag.split_once(&format!(" {}=\"", name))?;
+    after.split_once('"').map(|(value, _)| value)
+}
+
+/// Host of a remote `src`, shown on its placeholder.
+fn host(src: &str) -> &str {
+    let after_scheme = src.split_once("//").map_or(src, |(_, rest)| rest);
+    after_scheme.split(['/', '?', '#']).next().unwrap_or(after_scheme)
+}
+
+/// Replaces every `<img>` with a remote `src` by an `a.remote-image` placeholder carrying the
+/// source (`data-src`, `data-alt`) for the viewer to load on click; in exports it stays a plain
+/// link to the image. Attribute values are kept as already escaped in `html`.
+pub fn block_remote_images(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(tag_start) = rest.find("<img ") {
+        let tag_len = rest[tag_start..].find('>').map(|i| i + 1).unwrap_or(rest.len() - tag_start);
+        let tag = &rest[tag_start..tag_start + tag_len];
+        out.push_str(&rest[..tag_start]);
+        rest = &rest[tag_start + tag_len..];
+        let Some(src) = attr(tag, "src").filter(|src| is_remote(src)) else {
+            out.push_str(tag);
+            continue;
+        };
+        let alt = attr(tag, "alt").unwrap_or("");
+        let label = if alt.is_empty() { host(src) } else { alt };
+        out.push_str(&format!(
+            "<a class=\"remote-image\" href=\"{0}\" data-src=\"{0}\" data-alt=\"{1}\" \
+             target=\"_blank\" rel=\"noopener noreferrer\" title=\"Load remote image from {2}\">\
+             Remote image: {3}</a>",
+            src,
+            alt,
+            host(src),
+            label
+        ));
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_images_become_placeholders() {
+        let html = "<p><img src=\"https://cdn.example.com/a.png?x=1&amp;y=2\" alt=\"Chart\" />\
+                    <img src=\"pics/local.png\" alt=\"\" /><img src=\"//t.example.org/p.gif\"></p>";
+        let blocked = block_remote_images(html);
+        assert!(blocked.contains("<img src=\"pics/local.png\" alt=\"\" />"), "{}", blocked);
+        assert!(!blocked.contains("<img src=\"http") && !blocked.contains("<img src=\"//"), "{}", blocked);
+        assert!(
+            blocked.contains(
+                "data-src=\"https://cdn.example.com/a.png?x=1&amp;y=2\" data-alt=\"Chart\" target"
+            ),
+            "{}",
+            blocked
+        );
+        assert!(blocked.contains(">Remote image: Chart</a>"), "{}", blocked);
+        assert!(blocked.contains(">Remote image: t.example.org</a>"), "{}", blocked);
+    }
+}
@@ -0,0 +1,138 @@
+//! Plain-text search over vault notes (case-insensitive, one hit per note).
+
+use std::fs;
+use std::path::PathBuf;
+
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    /// 1-based line of the first match (1 when only the note name matched).
+    pub line: usize,
+    pub snippet: String,
+    pub match_count: usize,
+}
+
+/// Searches note names and contents for `query`; results are sorted by match count, then path.
+pub fn search_notes(notes: &[PathBuf], query: &str) -> Vec<SearchHit> {
+    search_notes_with(notes, query, |_, _| Ok(())).unwrap_or_default()
+}
+
+/// Like `search_notes`, calling `on_progress(done, total)` after each note; an Err aborts the search.
+pub fn search_notes_with<F>(notes: &[PathBuf], query: &str, mut on_progress: F) -> Result<Vec<SearchHit>, String>
+where
+    F: FnMut(usize, usize) -> Result<(), String>,
+{
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut hits = Vec::new();
+    for (i, path) in notes.iter().enumerate() {
+        hits.extend(search_note(path, &needle));
+        on_progress(i + 1, notes.len())?;
+    }
+    hits.sort_by(|a, b| b.match_count.cmp(&a.match_count).then_with(|| a.path.cmp(&b.path)));
+    Ok(hits)
+}
+
+fn search_note(path: &PathBuf, needle: &str) -> Option<SearchHit> {
+    let content = fs::read_to_string(path).ok()?;
+    let name_matches = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase().contains(needle))
+        .unwrap_or(false);
+    let mut match_count = usize::from(name_matches);
+    let mut first: Option<(usize, String)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let lower = line.to_lowercase();
+        let count = lower.matches(needle).count();
+        if count == 0 {
+            continue;
+        }
+        match_count += count;
+        if first.is_none() {
+            let pos = lower.find(needle).unwrap_or(0);
+            first = Some((i + 1, snippet_around(line, &lower, pos)));
+        }
+    }
+    if match_count == 0 {
+        return None;
+    }
+    let (line, snippet) = first.unwrap_or_else(|| (1, content.lines().next().unwrap_or("").trim().to_string()));
+    Some(SearchHit {
+        path: path.to_str()?.to_string(),
+        line,
+        snippet,
+        match_count,
+    })
+}
+
+/// Line text starting a little before the match (`pos` is a byte offset into `lower`).
+fn snippet_around(line: &str, lower: &str, pos: usize) -> String {
+    let chars_before = lower[..pos].chars().count();
+    let skip = chars_before.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let snippet: String = line.chars().skip(skip).collect();
+    if skip > 0 {
+        format!("…{}", snippet.trim())
+    } else {
+        snippet.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_content_and_name_matches() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = dir.path().join("alpha.md");
+        let b = dir.path().join("Rust notes.md");
+        let c = dir.path().join("other.md");
+        fs::write(&a, "# Alpha\n\nWe use rust and RUST.").unwrap();
+        fs::write(&b, "# Notes").unwrap();
+        fs::write(&c, "nothing here").unwrap();
+
+        let hits = search_notes(&[a, b, c], "rust");
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].path.ends_with("alpha.md"));
+        assert_eq!(hits[0].match_count, 2);
+        assert_eq!(hits[0].line, 3);
+        assert!(hits[1].path.ends_with("Rust notes.md"));
+        assert_eq!(hits[1].line, 1);
+    }
+
+    #[test]
+    fn progress_error_aborts_search() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        fs::write(&a, "term").unwrap();
+        fs::write(&b, "term").unwrap();
+        let mut seen = Vec::new();
+        let result = search_notes_with(&[a, b], "term", |done, total| {
+            seen.push((done, total));
+            Err("cancelled".to_string())
+        });
+        assert_eq!(result.unwrap_err(), "cancelled");
+        assert_eq!(seen, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn blank_query_returns_nothing() {
+        assert!(search_notes(&[PathBuf::from("/x.md")], "  ").is_empty());
+    }
+
+    #[test]
+    fn snippet_starts_near_match() {
+        let line = format!("{}needle end", "x".repeat(100));
+        let lower = line.to_lowercase();
+        let snippet = snippet_around(&line, &lower, lower.find("needle").unwrap());
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.contains("needle"));
+        assert!(snippet.chars().count() < 80);
+    }
+}
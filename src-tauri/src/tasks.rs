@@ -0,0 +1,126 @@
+//! Clickable task list checkboxes: each task's `[ ]` marker offset travels from the source to
+//! a `data-task-offset` attribute on the rendered checkbox, and `toggle_task_at` flips it.
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, Options};
+
+const TASK_MARK_START: char = '\u{E002}';
+const TASK_MARK_END: char = '\u{E003}';
+
+/// Byte offsets of the `[` of every task item marker in `md`, in document order.
+pub fn task_offsets(md: &str) -> Vec<usize> {
+    let arena = Arena::new();
+    let mut options = Options::default();
+    options.extension.tasklist = true;
+    let root = parse_document(&arena, md, &options);
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(md.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            if !matches!(data.value, NodeValue::TaskItem(_)) {
+                return None;
+            }
+            let pos = data.sourcepos.start;
+            let start = line_starts.get(pos.line.checked_sub(1)?)? + pos.column.saturating_sub(1);
+            let line_end = md[start..].find('\n').map_or(md.len(), |i| start + i);
+            md[start..line_end].find('[').map(|i| start + i)
+        })
+        .collect()
+}
+
+/// Puts a private-use sentinel carrying the offset at the start of each task's text;
+/// `apply_task_offsets` moves it onto the checkbox after rendering.
+pub fn mark_tasks(md: &str) -> String {
+    let mut out = md.to_string();
+    for offset in task_offsets(md).into_iter().rev() {
+        let after = offset + 3;
+        let mark = format!("{}{}{}", TASK_MARK_START, offset, TASK_MARK_END);
+        match md.as_bytes().get(after) {
+            Some(b' ') | Some(b'\t') => out.insert_str(after + 1, &mark),
+            _ => out.insert_str(after, &format!(" {}", mark)),
+        }
+    }
+    out
+}
+
+/// Moves task sentinels onto the preceding checkbox as `data-task-offset` and enables it.
+pub fn apply_task_offsets(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(TASK_MARK_START) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + TASK_MARK_START.len_utf8()..];
+        let Some(end) = after.find(TASK_MARK_END) else {
+            rest = after;
+            continue;
+        };
+        let offset = &after[..end];
+        if let Some(input) = out.rfind("<input type=\"checkbox\"") {
+            if !out[input..].contains("<li") {
+                let tag_end = out[input..].find('>').map_or(out.len(), |i| input + i);
+                let tag = out[input..tag_end]
+                    .replace(" disabled=\"\"", "")
+                    .replacen("<input", &format!("<input data-task-offset=\"{}\"", offset), 1);
+                out.replace_range(input..tag_end, &tag);
+            }
+        }
+        rest = &after[end + TASK_MARK_END.len_utf8()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `md` with the task at byte `offset` checked or unchecked.
+pub fn toggle_task_at(md: &str, offset: usize) -> Result<String, String> {
+    if !task_offsets(md).contains(&offset) {
+        return Err("No task at this position; the note may have changed".to_string());
+    }
+    let state = md[offset + 1..].chars().next().ok_or("Invalid task marker")?;
+    let flipped = if state == ' ' { "x" } else { " " };
+    let mut out = String::with_capacity(md.len());
+    out.push_str(&md[..offset + 1]);
+    out.push_str(flipped);
+    out.push_str(&md[offset + 1 + state.len_utf8()..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::render_markdown_safe;
+
+    use super::*;
+
+    const NOTE: &str = "# Tasks\n\n- [ ] first\n- [x] done\n  - [ ] nested\n\n> - [ ]\n\n`- [ ] code`\n";
+
+    #[test]
+    fn finds_task_markers() {
+        let offsets = task_offsets(NOTE);
+        assert_eq!(offsets.len(), 4, "{:?}", offsets);
+        for offset in offsets {
+            assert_eq!(&NOTE[offset..offset + 1], "[");
+        }
+    }
+
+    #[test]
+    fn rendered_checkboxes_carry_offsets() {
+        let html = apply_task_offsets(&render_markdown_safe(&mark_tasks(NOTE)));
+        let offsets = task_offsets(NOTE);
+        for offset in &offsets {
+            assert!(html.contains(&format!("data-task-offset=\"{}\"", offset)), "{}", html);
+        }
+        assert!(!html.contains(TASK_MARK_START) && !html.contains("disabled"), "{}", html);
+        assert!(html.contains("first</li>"), "{}", html);
+    }
+
+    #[test]
+    fn toggles_task_state() {
+        let offsets = task_offsets(NOTE);
+        let checked = toggle_task_at(NOTE, offsets[0]).unwrap();
+        assert!(checked.contains("- [x] first"));
+        let unchecked = toggle_task_at(NOTE, offsets[1]).unwrap();
+        assert!(unchecked.contains("- [ ] done"));
+        assert!(toggle_task_at(NOTE, 0).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Template variables, as in Obsidian's core templates: `{{date}}` and `{{time}}` with an
+//! optional moment.js-style format (`{{date:YYYY-MM-DD}}`, `{{time:HH:mm}}`), `{{title}}`, and
+//! `{{cursor}}` markers for where an editor should put the cursor.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+
+const DEFAULT_DATE_FORMAT: &str = "YYYY-MM-DD";
+const DEFAULT_TIME_FORMAT: &str = "HH:mm";
+
+/// moment.js format tokens and their chrono equivalents, longest first.
+const MOMENT_TOKENS: &[(&str, &str)] = &[
+    ("YYYY", "%Y"),
+    ("YY", "%y"),
+    ("MMMM", "%B"),
+    ("MMM", "%b"),
+    ("MM", "%m"),
+    ("M", "%-m"),
+    ("DDDD", "%j"),
+    ("DD", "%d"),
+    ("D", "%-d"),
+    ("dddd", "%A"),
+    ("ddd", "%a"),
+    ("HH", "%H"),
+    ("H", "%-H"),
+    ("hh", "%I"),
+    ("h", "%-I"),
+    ("mm", "%M"),
+    ("m", "%-M"),
+    ("ss", "%S"),
+    ("s", "%-S"),
+    ("A", "%p"),
+    ("a", "%P"),
+    ("WW", "%V"),
+    ("W", "%-V"),
+    ("ZZ", "%z"),
+    ("Z", "%:z"),
+    ("X", "%s"),
+];
+
+pub struct TemplateVars {
+    pub now: DateTime<Local>,
+    pub title: String,
+}
+
+impl TemplateVars {
+    /// Values for a note created at `target` at time `now`; the title is the file stem.
+    pub fn for_note(target: &Path, now: DateTime<Local>) -> Self {
+        TemplateVars {
+            now,
+            title: target
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RenderedTemplate {
+    pub markdown: String,
+    /// Where the `{{cursor}}` markers were, in UTF-16 code units of `markdown` (as the webview
+    /// counts), in order.
+    pub cursors: Vec<usize>,
+}
+
+/// Substitutes the variables (whitespace inside the braces allowed) and drops the cursor
+/// markers; unknown placeholders are left as written.
+pub fn render_template(template: &str, vars: &TemplateVars) -> RenderedTemplate {
+    let mut out = String::with_capacity(template.len());
+    let mut cursors = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let inner = after[..end].trim();
+        let (name, format) = match inner.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format)),
+            None => (inner, None),
+        };
+        match (name, format) {
+            ("date" | "time", format) => {
+                let default = if name == "date" { DEFAULT_DATE_FORMAT } else { DEFAULT_TIME_FORMAT };
+                out.push_str(&format_moment(&vars.now, format.unwrap_or(default)));
+            }
+            ("title", None) => out.push_str(&vars.title),
+            ("cursor", None) => cursors.push(out.encode_utf16().count()),
+            _ => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    RenderedTemplate { markdown: out, cursors }
+}
+
+/// `now` under moment.js format `format`; text in `[brackets]` is kept literally.
+pub fn format_moment(now: &DateTime<Local>, format: &str) -> String {
+    now.format(&moment_to_chrono(format)).to_string()
+}
+
+/// The chrono format equivalent to moment.js `format` (`YYYY-MM-DD` → `%Y-%m-%d`).
+pub(crate) fn moment_to_chrono(format: &str) -> String {
+    let mut out = String::with_capacity(format.len() * 2);
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            let literal = &rest[1..];
+            let end = literal.find(']').unwrap_or(literal.len());
+            out.push_str(&literal[..end].replace('%', "%%"));
+            rest = literal.get(end + 1..).unwrap_or("");
+            continue;
+        }
+        if let Some((token, spec)) = MOMENT_TOKENS.iter().find(|(token, _)| rest.starts_with(token)) {
+            out.push_str(spec);
+            rest = &rest[token.len()..];
+            continue;
+        }
+        if c == '%' {
+            out.push('%');
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 7, 5, 0).unwrap();
+        TemplateVars::for_note(Path::new("/v/Daily/Standup.md"), now)
+    }
+
+    #[test]
+    fn substitutes_known_variables() {
+        let out = render_template("# {{title}}\n{{ date }} {{time}} {{unknown}} {{", &vars());
+        assert_eq!(out.markdown, "# Standup\n2024-03-09 07:05 {{unknown}} {{");
+        assert!(out.cursors.is_empty());
+    }
+
+    #[test]
+    fn dates_follow_moment_formats() {
+        let template = "{{date:dddd, D MMMM YYYY}} {{time:h:mm A}} {{date:[Week] WW, 100%}}";
+        let out = render_template(template, &vars());
+        assert_eq!(out.markdown, "Saturday, 9 March 2024 7:05 AM Week 10, 100%");
+    }
+
+    #[test]
+    fn cursor_markers_are_dropped_and_located() {
+        let out = render_template("# {{title}}\n\n- {{cursor}}\n— {{ cursor }}", &vars());
+        assert_eq!(out.markdown, "# Standup\n\n- \n— ");
+        assert_eq!(out.cursors, [13, 16]);
+    }
+}
@@ -0,0 +1,53 @@
+//! Note templates: markdown files in the configured templates folder, filled in by
+//! `template_vars`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::template_vars::{render_template, RenderedTemplate, TemplateVars};
+
+/// Template file `name` (with or without `.md`) directly inside `folder`.
+pub fn find_template(folder: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid template name: {}", name));
+    }
+    let file = if name.ends_with(".md") {
+        name.to_string()
+    } else {
+        format!("{}.md", name)
+    };
+    let path = folder.join(file);
+    if !path.is_file() {
+        return Err(format!("Template not found: {}", name));
+    }
+    Ok(path)
+}
+
+pub fn apply_template_file(
+    template: &Path,
+    target: &Path,
+    now: DateTime<Local>,
+) -> Result<RenderedTemplate, String> {
+    let raw = fs::read_to_string(template).map_err(|e| e.to_string())?;
+    Ok(render_template(&raw, &TemplateVars::for_note(target, now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn find_template_stays_in_folder() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Meeting.md"), "x").unwrap();
+        assert!(find_template(dir.path(), "Meeting").is_ok());
+        assert!(find_template(dir.path(), "Meeting.md").is_ok());
+        assert!(find_template(dir.path(), "../Meeting").is_err());
+        assert!(find_template(dir.path(), "Missing").is_err());
+    }
+}
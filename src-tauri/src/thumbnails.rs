@@ -0,0 +1,148 @@
+//! Downscaled image previews for asset listings, cached on disk as PNG files keyed by the
+//! source path, its mtime and size and the requested size, so a changed image gets a fresh
+//! thumbnail. The least recently used thumbnails are evicted past `MAX_CACHE_BYTES`.
+
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::ImageFormat;
+use sha2::{Digest, Sha256};
+
+use crate::export::image_mime;
+use crate::paths::write_atomic;
+
+/// Requested sizes are clamped to this range (pixels, longest side).
+const MIN_THUMBNAIL_SIZE: u32 = 16;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Total size of the thumbnail cache before the least recently used ones are removed.
+const MAX_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Cache file name for `source` as of `metadata`, at `size`: a hash stable across runs and
+/// Rust versions.
+fn cache_key(source: &Path, metadata: &fs::Metadata, size: u32) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(source.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(modified.as_nanos().to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(size.to_le_bytes());
+    let digest = hasher.finalize();
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Marks `cached` as just used, for eviction order.
+fn touch(cached: &Path) {
+    if let Ok(file) = File::options().write(true).open(cached) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Removes the least recently used thumbnails of `cache_dir` until they fit in `max_bytes`.
+fn prune_cache(cache_dir: &Path, max_bytes: u64) {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return;
+    };
+    let mut thumbnails: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = thumbnails.iter().map(|(_, len, _)| len).sum();
+    thumbnails.sort();
+    for (_, len, path) in thumbnails {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+/// Path of a thumbnail of `source` fitting in `size`×`size` (aspect ratio kept), generated into
+/// `cache_dir` on first use. SVGs and images already that small are returned as they are.
+pub fn thumbnail(source: &Path, size: u32, cache_dir: &Path) -> Result<PathBuf, String> {
+    thumbnail_within(source, size, cache_dir, MAX_CACHE_BYTES)
+}
+
+fn thumbnail_within(
+    source: &Path,
+    size: u32,
+    cache_dir: &Path,
+    max_cache_bytes: u64,
+) -> Result<PathBuf, String> {
+    let mime = image_mime(source).ok_or("Not an image")?;
+    let metadata = fs::metadata(source).map_err(|e| e.to_string())?;
+    if mime == "image/svg+xml" {
+        return Ok(source.to_path_buf());
+    }
+    let size = size.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+    let cached = cache_dir.join(format!("{}.png", cache_key(source, &metadata, size)));
+    if cached.is_file() {
+        touch(&cached);
+        return Ok(cached);
+    }
+    let image = image::open(source).map_err(|e| e.to_string())?;
+    if image.width() <= size && image.height() <= size {
+        return Ok(source.to_path_buf());
+    }
+    let mut png = Vec::new();
+    image
+        .thumbnail(size, size)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    fs::create_dir_all(cache_dir).map_err(|e| e.to_string())?;
+    write_atomic(&cached, png)?;
+    prune_cache(cache_dir, max_cache_bytes);
+    Ok(cached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_images_are_downscaled_once_and_cached() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (source, cache) = (dir.path().join("wide.png"), dir.path().join("thumbs"));
+        image::RgbImage::new(400, 100).save(&source).unwrap();
+        let first = thumbnail(&source, 64, &cache).unwrap();
+        assert!(first.starts_with(&cache));
+        assert_eq!(image::image_dimensions(&first).unwrap(), (64, 16));
+        assert_eq!(thumbnail(&source, 64, &cache).unwrap(), first);
+        assert_eq!(thumbnail(&source, 800, &cache).unwrap(), source);
+        assert!(thumbnail(&dir.path().join("notes.md"), 64, &cache).is_err());
+    }
+
+    #[test]
+    fn least_recently_used_thumbnails_are_evicted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = dir.path().join("thumbs");
+        let sources: Vec<PathBuf> = (0..3).map(|i| dir.path().join(format!("{}.png", i))).collect();
+        for source in &sources {
+            image::RgbImage::new(400, 100).save(source).unwrap();
+        }
+        let first = thumbnail_within(&sources[0], 64, &cache, u64::MAX).unwrap();
+        let one_thumbnail = fs::metadata(&first).unwrap().len();
+        let second = thumbnail_within(&sources[1], 64, &cache, u64::MAX).unwrap();
+        let old = SystemTime::now() - std::time::Duration::from_secs(60);
+        File::options().write(true).open(&first).unwrap().set_modified(old).unwrap();
+        File::options().write(true).open(&second).unwrap().set_modified(old).unwrap();
+        // Using the first again makes the second the least recently used.
+        assert_eq!(thumbnail_within(&sources[0], 64, &cache, u64::MAX).unwrap(), first);
+        let third = thumbnail_within(&sources[2], 64, &cache, 2 * one_thumbnail).unwrap();
+        assert!(first.is_file() && third.is_file());
+        assert!(!second.exists());
+    }
+}
@@ -1,119 +1,254 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::obsidian_embed::{RenderCache, RenderContext, VaultIndex};
-use crate::TreeNode;
-use crate::markdown::render_markdown_safe;
+use crate::obsidian_embed::{RenderCache, RenderContext, RenderGeneration, VaultIndex};
+use crate::{NodeKind, TreeNode};
+use crate::markdown::{render_markdown_safe, RenderOptions};
 
-pub fn build_tree(root: &str) -> Result<Vec<TreeNode>, String> {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreeSortKey {
+    /// Alphabetical, README first.
+    #[default]
+    Name,
+    Modified,
+    Created,
+    Size,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TreeOptions {
+    pub sort_by: TreeSortKey,
+    pub descending: bool,
+    /// List images, PDFs and other attachments next to notes.
+    pub show_all_files: bool,
+    /// Keep folders that have nothing to list, so notes can be created in them.
+    pub show_empty_dirs: bool,
+    /// Only list files modified within this many days (folders are kept while they hold one).
+    pub modified_within_days: Option<u32>,
+    /// Folders left out of the tree, as absolute paths; filled from the vault's settings.
+    #[serde(skip)]
+    pub excluded: Vec<PathBuf>,
+}
+
+/// Folders come first either way; `options` orders the entries within folders and files.
+pub fn build_tree_with(root: &str, options: &TreeOptions) -> Result<Vec<TreeNode>, String> {
     let mut children = Vec::new();
-    walk_dir(Path::new(root), root, &mut children)?;
+    walk_dir(Path::new(root), options, &mut children)?;
     Ok(children)
 }
 
-fn walk_dir(dir: &Path, root: &str, out: &mut Vec<TreeNode>) -> Result<(), String> {
-    let entries = fs::read_dir(dir).map_err(|e| e.to_string())?;
-    let mut nodes: Vec<_> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| (e.path(), e.file_name().into_string().ok()))
-        .filter_map(|(path, name)| name.map(|n| (path, n)))
-        .collect();
+pub(crate) fn epoch_ms(time: std::io::Result<SystemTime>) -> Option<u64> {
+    time.ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+fn file_kind(path: &Path) -> NodeKind {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if ext == "md" {
+        return NodeKind::Note;
+    }
+    match ext.to_ascii_lowercase().as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" => NodeKind::Image,
+        "pdf" => NodeKind::Pdf,
+        _ => NodeKind::Other,
+    }
+}
+
+fn sort_nodes(nodes: &mut [TreeNode], options: &TreeOptions) {
+    let by_name = |a: &TreeNode, b: &TreeNode| a.name.to_lowercase().cmp(&b.name.to_lowercase());
     nodes.sort_by(|a, b| {
-        let a_is_dir = a.0.is_dir();
-        let b_is_dir = b.0.is_dir();
-        let a_is_readme = a.1.eq_ignore_ascii_case("readme.md");
-        let b_is_readme = b.1.eq_ignore_ascii_case("readme.md");
-        
-        match (a_is_dir, b_is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            (false, false) => {
-                match (a_is_readme, b_is_readme) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.1.to_lowercase().cmp(&b.1.to_lowercase()),
-                }
+        let ordering = match options.sort_by {
+            TreeSortKey::Name => {
+                let a_is_readme = a.name.eq_ignore_ascii_case("readme.md");
+                let b_is_readme = b.name.eq_ignore_ascii_case("readme.md");
+                b_is_readme.cmp(&a_is_readme).then_with(|| by_name(a, b))
             }
-            (true, true) => a.1.to_lowercase().cmp(&b.1.to_lowercase()),
-        }
+            TreeSortKey::Modified => a.modified_ms.cmp(&b.modified_ms),
+            TreeSortKey::Created => a.created_ms.cmp(&b.created_ms),
+            TreeSortKey::Size => a.size.cmp(&b.size),
+        };
+        let ordering = if options.descending { ordering.reverse() } else { ordering };
+        ordering.then_with(|| by_name(a, b))
     });
-    for (path, name) in nodes {
-        if path.is_dir() {
-            if name.starts_with('.') {
-                continue;
-            }
-            let mut children = Vec::new();
-            walk_dir(&path, root, &mut children)?;
-            if !children.is_empty() {
-                out.push(TreeNode {
-                    name,
-                    path: path.to_str().unwrap_or("").to_string(),
-                    children,
-                });
-            }
-        } else if path.extension().map(|e| e == "md").unwrap_or(false) {
-            out.push(TreeNode {
-                name,
-                path: path.to_str().unwrap_or("").to_string(),
-                children: Vec::new(),
-            });
+}
+
+/// The tree node for one filesystem entry, or `None` when the tree would not list it.
+pub fn tree_node_for(path: &Path, options: &TreeOptions) -> Result<Option<TreeNode>, String> {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()).map(String::from) else {
+        return Ok(None);
+    };
+    if options.excluded.iter().any(|folder| path.starts_with(folder)) {
+        return Ok(None);
+    }
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(None);
+    };
+    let created_ms = epoch_ms(metadata.created());
+    let modified_ms = epoch_ms(metadata.modified());
+    if metadata.is_dir() {
+        if name.starts_with('.') {
+            return Ok(None);
         }
+        let mut children = Vec::new();
+        walk_dir(path, options, &mut children)?;
+        if children.is_empty() && !options.show_empty_dirs {
+            return Ok(None);
+        }
+        let folder_note = [format!("{}.md", name), "index.md".to_string()].iter().find_map(|note_name| {
+            children
+                .iter()
+                .find(|c| c.kind == NodeKind::Note && c.name == *note_name)
+                .map(|c| c.path.clone())
+        });
+        return Ok(Some(TreeNode {
+            folder_note,
+            name,
+            path: path.to_str().unwrap_or("").to_string(),
+            kind: NodeKind::Dir,
+            size: children.iter().map(|c| c.size).sum(),
+            modified_ms: children.iter().map(|c| c.modified_ms).chain([modified_ms]).max().flatten(),
+            created_ms,
+            note_count: children
+                .iter()
+                .map(|c| if c.kind == NodeKind::Note { 1 } else { c.note_count })
+                .sum(),
+            children,
+            inbound_links: 0,
+            outbound_links: 0,
+        }));
+    }
+    let kind = file_kind(path);
+    if kind != NodeKind::Note && (!options.show_all_files || name.starts_with('.')) {
+        return Ok(None);
     }
+    if let (Some(days), Ok(modified)) = (options.modified_within_days, metadata.modified()) {
+        let window = Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        if SystemTime::now().duration_since(modified).is_ok_and(|age| age > window) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(TreeNode {
+        name,
+        path: path.to_str().unwrap_or("").to_string(),
+        kind,
+        children: Vec::new(),
+        size: metadata.len(),
+        modified_ms,
+        created_ms,
+        note_count: 0,
+        folder_note: None,
+        inbound_links: 0,
+        outbound_links: 0,
+    }))
+}
+
+fn walk_dir(dir: &Path, options: &TreeOptions, out: &mut Vec<TreeNode>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| e.to_string())?;
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        match tree_node_for(&entry.path(), options)? {
+            Some(node) if node.kind == NodeKind::Dir => dirs.push(node),
+            Some(node) => files.push(node),
+            None => {}
+        }
+    }
+    sort_nodes(&mut dirs, options);
+    sort_nodes(&mut files, options);
+    out.extend(dirs);
+    out.extend(files);
     Ok(())
 }
 
-/// Returns (initial_note_path, initial_html) - prefers index.md, else first .md by name.
+/// Notes tried, in order, when a vault opens; `Settings::initial_notes` overrides it.
+pub const DEFAULT_INITIAL_NOTES: [&str; 3] = ["index.md", "README.md", "Home.md"];
+
+/// The note shown when a vault opens: the first of `priority` (names matched
+/// case-insensitively) in the root, else the first note by name. A root without notes is
+/// searched breadth-first, folder by folder, under the same rules, skipping hidden and
+/// `excluded` folders and visiting each folder once however many symlinks lead to it.
+pub fn initial_note_path(
+    root: &Path,
+    priority: &[String],
+    excluded: &[PathBuf],
+) -> Result<Option<PathBuf>, String> {
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    let mut visited = HashSet::new();
+    while let Some(dir) = queue.pop_front() {
+        let canonical = match crate::paths::canonicalize(&dir) {
+            Ok(canonical) => canonical,
+            Err(e) if dir == root => return Err(e),
+            Err(_) => continue,
+        };
+        if !visited.insert(canonical) {
+            continue;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(e.to_string()),
+            Err(_) => continue,
+        };
+        let mut notes = Vec::new();
+        let mut subdirs = Vec::new();
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let hidden = path.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with('.')).unwrap_or(true);
+            let excluded = excluded.iter().any(|folder| path.starts_with(folder));
+            if path.is_dir() && !hidden && !excluded {
+                subdirs.push(path);
+            } else if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
+                notes.push(path);
+            }
+        }
+        notes.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        let preferred = priority.iter().find_map(|name| {
+            notes.iter().find(|note| {
+                note.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.eq_ignore_ascii_case(name))
+                    .unwrap_or(false)
+            })
+        });
+        if let Some(note) = preferred.or(notes.first()) {
+            return Ok(Some(note.clone()));
+        }
+        subdirs.sort();
+        queue.extend(subdirs);
+    }
+    Ok(None)
+}
+
+/// Returns (initial_note_path, initial_html) for `initial_note_path` with the default priority.
 #[allow(dead_code)]
 pub fn initial_note(root: &str) -> Result<(Option<String>, Option<String>), String> {
-    let root_path = Path::new(root);
-    let index = root_path.join("index.md");
-    if index.exists() {
-        let path_str = index.to_str().unwrap().to_string();
-        let raw = fs::read_to_string(&index).map_err(|e| e.to_string())?;
-        return Ok((Some(path_str), Some(render_markdown_safe(&raw))));
-    }
-    let mut md_files: Vec<_> = fs::read_dir(root_path)
-        .map_err(|e| e.to_string())?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_file() && p.extension().map(|e| e == "md").unwrap_or(false))
-        .collect();
-    md_files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
-    if let Some(path) = md_files.into_iter().next() {
-        let path_str = path.to_str().unwrap().to_string();
-        let raw = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        return Ok((Some(path_str), Some(render_markdown_safe(&raw))));
-    }
-    Ok((None, None))
+    let priority: Vec<String> = DEFAULT_INITIAL_NOTES.iter().map(|n| n.to_string()).collect();
+    let Some(path) = initial_note_path(Path::new(root), &priority, &[])? else {
+        return Ok((None, None));
+    };
+    let raw = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    Ok((Some(path.to_str().unwrap_or("").to_string()), Some(render_markdown_safe(&raw))))
 }
 
-/// Returns (initial_note_path, initial_html) with Obsidian embeds expanded.
-/// Uses the same initial path logic as initial_note (index.md or first .md by name).
+/// Returns (initial_note_path, initial_html) with Obsidian embeds expanded; the note is
+/// picked by `initial_note_path` under `priority` and `excluded`.
 pub fn initial_note_with_embeds(
     root: &str,
+    priority: &[String],
+    excluded: &[PathBuf],
     index: &VaultIndex,
     cache: &mut RenderCache,
+    render: &RenderOptions,
 ) -> Result<(Option<String>, Option<String>), String> {
     let root_path = Path::new(root);
-    let index_md = root_path.join("index.md");
-    let path = if index_md.exists() {
-        index_md
-    } else {
-        let mut md_files: Vec<_> = fs::read_dir(root_path)
-            .map_err(|e| e.to_string())?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|p| p.is_file() && p.extension().map(|e| e == "md").unwrap_or(false))
-            .collect();
-        md_files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
-        match md_files.into_iter().next() {
-            Some(p) => p,
-            None => return Ok((None, None)),
-        }
+    let Some(path) = initial_note_path(root_path, priority, excluded)? else {
+        return Ok((None, None));
     };
     let path_str = path.to_str().unwrap().to_string();
-    let vault_root = root_path.canonicalize().map_err(|e| e.to_string())?;
+    let vault_root = crate::paths::canonicalize(root_path)?;
     let mut ctx = RenderContext {
         vault_root,
         index,
@@ -121,6 +256,8 @@ pub fn initial_note_with_embeds(
         visited: HashSet::new(),
         depth: 0,
         max_depth: 5,
+        render: render.clone(),
+        generation: RenderGeneration::default(),
     };
     let html = crate::obsidian_embed::render_markdown_with_embeds(&path, &mut ctx);
     Ok((Some(path_str), Some(html)))
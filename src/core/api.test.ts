@@ -1,11 +1,17 @@
 import { describe, it, expect, vi, beforeEach } from "vitest";
-import { getInitialFile, openMarkdownFile, openWikiFolder, watchPaths } from "./api";
+import { checkLinks, getInitialFile, openMarkdownFile, openWikiFolder, watchPaths } from "./api";
+import type { OperationFinished } from "../types";
 
 vi.mock("@tauri-apps/api/core", () => ({
   invoke: vi.fn(),
 }));
 
+vi.mock("@tauri-apps/api/event", () => ({
+  listen: vi.fn(),
+}));
+
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 describe("tauriApi", () => {
   beforeEach(() => {
@@ -163,4 +169,45 @@ describe("tauriApi", () => {
       expect(result).toBeUndefined();
     });
   });
+
+  describe("operations", () => {
+    let emit: (payload: OperationFinished) => void;
+    const unlisten = vi.fn();
+    const finished = (id: number, status: OperationFinished["status"], result: unknown = null) => ({
+      id,
+      kind: "link_check",
+      status,
+      result,
+      error: status === "error" ? "boom" : null,
+    });
+
+    beforeEach(() => {
+      vi.mocked(listen).mockImplementation(async (_event, handler) => {
+        emit = (payload) => handler({ event: "operation-finished", id: 0, payload } as never);
+        return unlisten;
+      });
+    });
+
+    it("resolves with the result of the started operation", async () => {
+      vi.mocked(invoke).mockResolvedValue(7);
+
+      const report = checkLinks("/tmp/report.md");
+      await vi.waitFor(() => expect(invoke).toHaveBeenCalled());
+      emit(finished(6, "ok", "other"));
+      emit(finished(7, "ok", { broken: [] }));
+
+      await expect(report).resolves.toEqual({ broken: [] });
+      expect(invoke).toHaveBeenCalledWith("check_links", { destination: "/tmp/report.md" });
+      expect(unlisten).toHaveBeenCalled();
+    });
+
+    it("handles operations finishing before their id arrives", async () => {
+      vi.mocked(invoke).mockImplementation(async () => {
+        emit(finished(3, "error"));
+        return 3;
+      });
+
+      await expect(checkLinks()).rejects.toBe("boom");
+    });
+  });
 });
@@ -1,15 +1,51 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { OpenMarkdownFileResult, OpenWikiFolderResult } from "../types";
+import { listen } from "@tauri-apps/api/event";
+import type {
+  AppLink,
+  CalendarDay,
+  DiffBase,
+  DuplicateReport,
+  FileMetadata,
+  FolderMove,
+  FrontmatterMatch,
+  LinkCompletion,
+  LinkPreview,
+  LinkReport,
+  LintIssue,
+  NoteAlias,
+  NoteCommit,
+  NoteDiff,
+  NotePanelData,
+  OperationFinished,
+  OpenMarkdownFileResult,
+  OpenWikiFolderResult,
+  OutgoingLink,
+  QueryOp,
+  RecentNote,
+  RenderedMarkdown,
+  RenderedTemplate,
+  SaveResult,
+  SavedSearch,
+  Settings,
+  ThemeChanged,
+  TreeNode,
+  TreeOptions,
+  Workspace,
+} from "../types";
 
 export interface InitialPath {
   path: string;
   is_dir: boolean;
+  vault_root: string | null;
 }
 
 export function getInitialFile(): Promise<InitialPath | null> {
   return invoke<InitialPath | null>("get_initial_file");
 }
 
+/** Error from `openMarkdownFile` when a newer navigation superseded the render. */
+export const RENDER_SUPERSEDED = "superseded";
+
 export function openMarkdownFile(
   path: string,
   options?: { vaultRoot?: string | null }
@@ -28,3 +64,306 @@ export function openWikiFolder(path: string): Promise<OpenWikiFolderResult> {
 export function watchPaths(paths: string[]): Promise<void> {
   return invoke<void>("watch_paths", { paths });
 }
+
+export function getSettings(): Promise<Settings> {
+  return invoke<Settings>("get_settings");
+}
+
+export function updateSettings(settings: Settings): Promise<void> {
+  return invoke<void>("update_settings", { settings });
+}
+
+export function loadWorkspace(vaultRoot: string): Promise<Workspace> {
+  return invoke<Workspace>("load_workspace", { vaultRoot });
+}
+
+export function saveWorkspace(vaultRoot: string, workspace: Workspace): Promise<void> {
+  return invoke<void>("save_workspace", { vaultRoot, workspace });
+}
+
+export function getTheme(): Promise<ThemeChanged> {
+  return invoke<ThemeChanged>("get_theme");
+}
+
+export function revealInFileManager(path: string): Promise<void> {
+  return invoke<void>("reveal_in_file_manager", { path });
+}
+
+export function openInEditor(path: string, line?: number): Promise<void> {
+  return invoke<void>("open_in_editor", { path, line: line ?? null });
+}
+
+export function copyNoteAsHtml(path: string, vaultRoot?: string | null): Promise<void> {
+  return invoke<void>("copy_note_as_html", { path, vaultRoot: vaultRoot ?? null });
+}
+
+/**
+ * Starts `command`, which returns an operation id, and resolves with the result of its
+ * `operation-finished` event; errors, cancellation and timeouts reject.
+ */
+function runOperation<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  return new Promise<T>((resolve, reject) => {
+    let id: number | null = null;
+    // The operation may finish before `invoke` returns its id.
+    const early: OperationFinished[] = [];
+    const settle = (finished: OperationFinished) => {
+      void stopListening.then((stop) => stop());
+      if (finished.status === "ok") resolve(finished.result as T);
+      else reject(finished.error ?? finished.status);
+    };
+    const stopListening = listen<OperationFinished>("operation-finished", ({ payload }) => {
+      if (id === null) early.push(payload);
+      else if (payload.id === id) settle(payload);
+    });
+    stopListening
+      .then(() => invoke<number>(command, args))
+      .then((started) => {
+        id = started;
+        const finished = early.find((event) => event.id === started);
+        if (finished) settle(finished);
+      })
+      .catch((error) => {
+        void stopListening.then((stop) => stop());
+        reject(error);
+      });
+  });
+}
+
+/**
+ * The note as one standard markdown document (embeds inlined, wikilinks as relative links);
+ * also written to `destination` when given.
+ */
+export function exportFlattenedMarkdown(path: string, destination?: string | null): Promise<string> {
+  return runOperation<string>("export_flattened_markdown", { path, destination: destination ?? null });
+}
+
+/** Copies the note as markdown with relative links; embeds are inlined unless `inlineEmbeds` is false. */
+export function copyNoteAsMarkdown(path: string, inlineEmbeds = true): Promise<void> {
+  return invoke<void>("copy_note_as_markdown", { path, inlineEmbeds });
+}
+
+/** Exports the note with pandoc; resolves to the written file's path. */
+export function exportViaPandoc(
+  path: string,
+  format: "docx" | "odt" | "latex",
+  destination?: string | null
+): Promise<string> {
+  return runOperation<string>("export_via_pandoc", { path, format, destination: destination ?? null });
+}
+
+/**
+ * Writes an EPUB of the published notes under `folder` (one chapter per note, in tree order), or
+ * of just `notes` in their order.
+ */
+export function exportEpub(
+  folder: string,
+  destination: string,
+  notes: string[] | null = null
+): Promise<string> {
+  return runOperation<string>("export_epub", { folder, notes, destination });
+}
+
+/**
+ * Writes the published notes under `folder` to `destination` as a static HTML site with an
+ * `index.html` landing page and a `sitemap.xml` whose URLs start with `baseUrl`.
+ */
+export function exportSite(
+  folder: string,
+  destination: string,
+  baseUrl: string | null = null
+): Promise<string> {
+  return runOperation<string>("export_site", { folder, destination, baseUrl });
+}
+
+/**
+ * Serves the active vault read-only over HTTP (port 0 picks a free one) on localhost, or with
+ * `lan` to the LAN behind an access token; resolves to its URL, token included.
+ */
+export function serveVault(port: number, lan = false): Promise<string> {
+  return invoke<string>("serve_vault", { port, lan });
+}
+
+export function stopServing(): Promise<void> {
+  return invoke<void>("stop_serving");
+}
+
+export function printNote(): Promise<void> {
+  return invoke<void>("print_note");
+}
+
+export function getFileMetadata(path: string): Promise<FileMetadata> {
+  return invoke<FileMetadata>("get_file_metadata", { path });
+}
+
+/** Frontmatter, tags, link counts, word counts and file stats of a note in one call. */
+export function getNotePanelData(path: string): Promise<NotePanelData> {
+  return invoke<NotePanelData>("get_note_panel_data", { path });
+}
+
+/** Path of a cached thumbnail (at most `size` px per side); load it with `convertFileSrc`. */
+export function getThumbnail(path: string, size: number): Promise<string> {
+  return invoke<string>("get_thumbnail", { path, size });
+}
+
+export function getNoteHistory(path: string): Promise<NoteCommit[]> {
+  return invoke<NoteCommit[]>("get_note_history", { path });
+}
+
+export function getNoteAtRevision(path: string, rev: string): Promise<OpenMarkdownFileResult> {
+  return invoke<OpenMarkdownFileResult>("get_note_at_revision", { path, rev });
+}
+
+/** Line diff from `base` (a git revision or earlier contents) to the note on disk. */
+export function diffNote(path: string, base: DiffBase): Promise<NoteDiff> {
+  return invoke<NoteDiff>("diff_note", { path, base });
+}
+
+export function getTree(path: string, options?: TreeOptions): Promise<TreeNode[]> {
+  return invoke<TreeNode[]>("get_tree", { path, options: options ?? null });
+}
+
+/**
+ * Fills in template `templateName` for a note at `targetPath` (`{{date:YYYY-MM-DD}}`, `{{time}}`,
+ * `{{title}}`, `{{cursor}}`); with `write`, also creates the note.
+ */
+export function applyTemplate(
+  templateName: string,
+  targetPath: string,
+  write = false
+): Promise<RenderedTemplate> {
+  return invoke<RenderedTemplate>("apply_template", { templateName, targetPath, write });
+}
+
+/**
+ * Map of content of `folder`: wikilinks to its notes, grouped by subfolder; with `write`, also
+ * saves it as the folder note (`<folder>/<folder name>.md`). An existing folder note is only
+ * replaced with `force`; otherwise the call rejects with "... already exists".
+ */
+export function generateMoc(folder: string, write = false, force = false): Promise<string> {
+  return invoke<string>("generate_moc", { folder, write, force });
+}
+
+/**
+ * Moves vault folder `oldPath` to `newPath`, rewriting path-style wikilinks into it. Without
+ * `write` nothing changes and the planned link edits are returned as a preview.
+ */
+export function moveFolder(oldPath: string, newPath: string, write = false): Promise<FolderMove> {
+  return invoke<FolderMove>("move_folder", { old: oldPath, new: newPath, write });
+}
+
+export function resolveAppLink(href: string): Promise<AppLink> {
+  return invoke<AppLink>("resolve_app_link", { href });
+}
+
+/** Saves `content` unless the file changed since the version hashing to `expectedHash` was loaded. */
+export function saveMarkdownFile(
+  path: string,
+  content: string,
+  expectedHash: string,
+  force = false
+): Promise<SaveResult> {
+  return invoke<SaveResult>("save_markdown_file", { path, content, expectedHash, force });
+}
+
+/** Adds a `^id` marker to the block at 1-based `line` (or reuses its marker) and returns the id. */
+export function addBlockId(path: string, line: number): Promise<string> {
+  return invoke<string>("add_block_id", { path, line });
+}
+
+/** Fails when the note changed on disk since the version hashing to `expectedHash` was loaded. */
+export function toggleTask(
+  path: string,
+  offset: number,
+  expectedHash: string,
+  vaultRoot: string | null
+): Promise<OpenMarkdownFileResult> {
+  return invoke<OpenMarkdownFileResult>("toggle_task", { path, offset, expectedHash, vaultRoot });
+}
+
+export function previewLink(target: string): Promise<LinkPreview> {
+  return invoke<LinkPreview>("preview_link", { target });
+}
+
+/** Structure problems in one note (heading levels, duplicate headings, fences, wikilinks). */
+export function lintNote(path: string): Promise<LintIssue[]> {
+  return invoke<LintIssue[]>("lint_note", { path });
+}
+
+/** Checks every link in the active vault; the report is also saved as markdown to `destination`. */
+export function checkLinks(destination?: string | null): Promise<LinkReport> {
+  return runOperation<LinkReport>("check_links", { destination: destination ?? null });
+}
+
+/** Days of `month` (1–12) with notes in the active vault; days without notes are omitted. */
+export function getCalendarData(year: number, month: number): Promise<CalendarDay[]> {
+  return invoke<CalendarDay[]>("get_calendar_data", { year, month });
+}
+
+/** Notes and aliases matching `prefix`, best first, for `[[` autocompletion; after `Note#`, its headings. */
+export function completeWikilink(prefix: string, limit?: number): Promise<LinkCompletion[]> {
+  return invoke<LinkCompletion[]>("complete_wikilink", { prefix, limit: limit ?? null });
+}
+
+/** Note names near an unresolved wikilink target (a typo like `Projcet`), nearest first. */
+export function suggestLinkTargets(target: string): Promise<string[]> {
+  return invoke<string[]>("suggest_link_targets", { target });
+}
+
+/** Frontmatter aliases of every note in the active vault, sorted by alias. */
+export function getAliases(): Promise<NoteAlias[]> {
+  return invoke<NoteAlias[]>("get_aliases");
+}
+
+/** Notes of the active vault whose frontmatter `field` matches `value` under `op`. */
+export function queryFrontmatter(
+  field: string,
+  value: string | null = null,
+  op: QueryOp = "equals"
+): Promise<FrontmatterMatch[]> {
+  return invoke<FrontmatterMatch[]>("query_frontmatter", { field, value, op });
+}
+
+/** The `limit` most recently modified notes of the active vault, newest first. */
+export function getNotesByMtime(limit: number): Promise<RecentNote[]> {
+  return invoke<RecentNote[]>("get_notes_by_mtime", { limit });
+}
+
+/** Duplicate basenames and (near-)identical notes in the active vault. */
+export function findDuplicateNotes(): Promise<DuplicateReport> {
+  return invoke<DuplicateReport>("find_duplicate_notes");
+}
+
+/** Notes that embed one of the changed `paths`, directly or through other embeds. */
+export function getEmbeddingNotes(paths: string[]): Promise<string[]> {
+  return invoke<string[]>("get_embedding_notes", { paths });
+}
+
+/**
+ * Renders unsaved `markdown` (an editor buffer) with the vault of `basePath`, or the active
+ * vault, for a live preview.
+ */
+export function renderMarkdownString(
+  markdown: string,
+  basePath: string | null = null
+): Promise<RenderedMarkdown> {
+  return invoke<RenderedMarkdown>("render_markdown_string", { markdown, basePath });
+}
+
+/** Renders `paths` into the backend cache in the background, behind the note being opened. */
+export function prefetchNotes(paths: string[]): Promise<void> {
+  return invoke<void>("prefetch_notes", { paths });
+}
+
+export function getOutgoingLinks(path: string): Promise<OutgoingLink[]> {
+  return invoke<OutgoingLink[]>("get_outgoing_links", { path });
+}
+
+/** Saves `query` as `name` in the active vault (replacing a search of that name). */
+export function saveSearch(name: string, query: string): Promise<SavedSearch[]> {
+  return invoke<SavedSearch[]>("save_search", { name, query });
+}
+
+/** The active vault's saved searches, in the order they were saved. */
+export function listSavedSearches(): Promise<SavedSearch[]> {
+  return invoke<SavedSearch[]>("list_saved_searches");
+}
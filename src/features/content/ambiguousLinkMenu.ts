@@ -0,0 +1,61 @@
+import { normalizeBaseDir } from "../../core/pathUtils";
+
+let openMenu: HTMLElement | null = null;
+
+function closeMenu(): void {
+  openMenu?.remove();
+  openMenu = null;
+}
+
+/** Candidates listed on an ambiguous wikilink (`data-obs-candidates`), or null. */
+export function linkCandidates(anchor: Element): string[] | null {
+  const raw = anchor.getAttribute("data-obs-candidates");
+  if (!raw) return null;
+  try {
+    const candidates: unknown = JSON.parse(raw);
+    return Array.isArray(candidates) && candidates.length > 1 ? candidates.map(String) : null;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Shows a menu under `anchor` listing the notes an ambiguous wikilink may point to,
+ * relative to `wikiRoot` when given. `onPick` receives the chosen absolute path.
+ */
+export function showCandidateMenu(
+  anchor: HTMLElement,
+  candidates: string[],
+  wikiRoot: string | null,
+  onPick: (path: string) => void
+): void {
+  closeMenu();
+  const root = wikiRoot ? normalizeBaseDir(wikiRoot) + "/" : null;
+  const menu = document.createElement("div");
+  menu.className = "link-candidates";
+  menu.setAttribute("role", "menu");
+
+  const title = document.createElement("div");
+  title.className = "link-candidates-title";
+  title.textContent = "Várias notas com este nome:";
+  menu.appendChild(title);
+
+  for (const path of candidates) {
+    const item = document.createElement("button");
+    item.type = "button";
+    item.setAttribute("role", "menuitem");
+    item.textContent = root && path.startsWith(root) ? path.slice(root.length) : path;
+    item.addEventListener("click", () => {
+      closeMenu();
+      onPick(path);
+    });
+    menu.appendChild(item);
+  }
+
+  const rect = anchor.getBoundingClientRect();
+  menu.style.left = `${rect.left + window.scrollX}px`;
+  menu.style.top = `${rect.bottom + window.scrollY}px`;
+  document.body.appendChild(menu);
+  openMenu = menu;
+  setTimeout(() => document.addEventListener("click", closeMenu, { once: true }));
+}
@@ -1,6 +1,6 @@
 import { convertFileSrc } from "@tauri-apps/api/core";
 import { injectCodeBlockCopyButtons } from "./codeBlockCopy";
-import { applyHighlighting } from "./highlight";
+import { applyHighlighting, applyLineHighlights } from "./highlight";
 import { resolvePath } from "../../core/pathUtils";
 
 export function getDisplayName(path: string): string {
@@ -41,6 +41,7 @@ export async function renderMarkdownContent(
   showContent(contentEl, html);
   await rewriteImages(contentEl, baseDir);
   applyHighlighting(contentEl);
+  applyLineHighlights(contentEl);
   injectCodeBlockCopyButtons(contentEl);
 }
 
@@ -18,7 +18,12 @@ vi.mock("highlight.js/lib/languages/bash", () => ({ default: {} }));
 vi.mock("highlight.js/lib/languages/css", () => ({ default: {} }));
 vi.mock("highlight.js/lib/languages/xml", () => ({ default: {} }));
 
-import { configureHighlighting, applyHighlighting } from "./highlight";
+import {
+  configureHighlighting,
+  applyHighlighting,
+  applyLineHighlights,
+  parseHighlightLines,
+} from "./highlight";
 import hljs from "highlight.js/lib/core";
 
 describe("highlight", () => {
@@ -150,4 +155,31 @@ describe("highlight", () => {
       expect(mockHighlightElement).toHaveBeenCalledWith(code);
     });
   });
+
+  describe("line highlights", () => {
+    it("expands line lists and ranges", () => {
+      expect(parseHighlightLines("1,3-5", 10)).toEqual([1, 3, 4, 5]);
+      expect(parseHighlightLines("x,4-2,7", 10)).toEqual([7]);
+    });
+
+    it("cuts ranges short at the end of the block", () => {
+      expect(parseHighlightLines("2-999999999,7", 3)).toEqual([2, 3]);
+    });
+
+    it("adds one band per highlighted line that exists", () => {
+      const pre = document.createElement("pre");
+      pre.setAttribute("data-highlight-lines", "1,3-5");
+      const code = document.createElement("code");
+      code.textContent = "a\nb\nc\nd\n";
+      pre.appendChild(code);
+      const root = document.createElement("div");
+      root.appendChild(pre);
+
+      applyLineHighlights(root);
+      applyLineHighlights(root);
+
+      expect(pre.querySelectorAll(".code-line-highlight")).toHaveLength(3);
+      expect(code.textContent).toBe("a\nb\nc\nd\n");
+    });
+  });
 });
@@ -28,3 +28,42 @@ export function applyHighlighting(root: ParentNode): void {
     hljs.highlightElement(element as HTMLElement);
   });
 }
+
+/**
+ * 1-based line numbers listed by a `data-highlight-lines` value such as `1,3-5`, up to
+ * `lineCount` (ranges past the end of the block are cut short).
+ */
+export function parseHighlightLines(spec: string, lineCount: number): number[] {
+  const lines: number[] = [];
+  for (const part of spec.split(",")) {
+    const [from, to = from] = part.split("-").map((n) => Number.parseInt(n, 10));
+    if (!(from >= 1 && to >= from)) continue;
+    const last = Math.min(to, lineCount);
+    for (let line = from; line <= last; line++) lines.push(line);
+  }
+  return lines;
+}
+
+/**
+ * Puts a `.code-line-highlight` band behind each line listed in `pre[data-highlight-lines]`.
+ * Bands are positioned from the code's line height, so highlighted markup is left untouched.
+ */
+export function applyLineHighlights(root: ParentNode): void {
+  root.querySelectorAll<HTMLElement>("pre[data-highlight-lines]").forEach((pre) => {
+    pre.querySelectorAll(".code-line-highlight").forEach((band) => band.remove());
+    const code = pre.querySelector("code") ?? pre;
+    const style = getComputedStyle(code);
+    const fontSize = Number.parseFloat(style.fontSize) || 13.6;
+    const lineHeight = Number.parseFloat(style.lineHeight) || fontSize * 1.45;
+    const top = Number.parseFloat(getComputedStyle(pre).paddingTop) || 0;
+    const lineCount = (code.textContent ?? "").replace(/\n$/, "").split("\n").length;
+    const spec = pre.getAttribute("data-highlight-lines") ?? "";
+    for (const line of parseHighlightLines(spec, lineCount)) {
+      const band = document.createElement("div");
+      band.className = "code-line-highlight";
+      band.style.top = `${top + (line - 1) * lineHeight}px`;
+      band.style.height = `${lineHeight}px`;
+      pre.appendChild(band);
+    }
+  });
+}
@@ -1,5 +1,7 @@
-import { openUrl } from "@tauri-apps/plugin-opener";
+import { openPath, openUrl } from "@tauri-apps/plugin-opener";
+import { prefetchNotes, resolveAppLink } from "../../core/api";
 import { isExternalHref } from "./contentRendering";
+import { linkCandidates, showCandidateMenu } from "./ambiguousLinkMenu";
 
 export interface LinkHandlerState {
   mode: "file" | "wiki";
@@ -11,6 +13,8 @@ export interface LinkHandlerActions {
   openWikiNote: (path: string, options?: { addToHistory?: boolean }) => Promise<void>;
   loadFile: (path: string, options?: { watch?: boolean; addToHistory?: boolean }) => Promise<void>;
   openRelativeLink: (href: string) => Promise<void>;
+  /** Re-renders the current note (used by the retry button on failed embeds). */
+  reload: () => Promise<void>;
 }
 
 export function setupLinkHandler(
@@ -19,7 +23,29 @@ export function setupLinkHandler(
   actions: LinkHandlerActions,
   onError: (message: string) => void
 ): void {
+  // Hovering a note link renders it ahead of the click.
+  contentEl.addEventListener("mouseover", (event) => {
+    const link = (event.target as HTMLElement).closest<HTMLElement>("a[data-obs-path]");
+    const path = link?.dataset.obsPath;
+    if (path && getState().mode === "wiki") void prefetchNotes([path]).catch(() => {});
+  });
+
   contentEl.addEventListener("click", (event) => {
+    if ((event.target as HTMLElement).closest(".embed-retry")) {
+      void actions.reload().catch((error) => onError(`Não foi possível recarregar a nota: ${String(error)}`));
+      return;
+    }
+
+    const remoteImage = (event.target as HTMLElement).closest<HTMLElement>("a.remote-image");
+    if (remoteImage) {
+      event.preventDefault();
+      const image = document.createElement("img");
+      image.src = remoteImage.dataset.src ?? "";
+      image.alt = remoteImage.dataset.alt ?? "";
+      remoteImage.replaceWith(image);
+      return;
+    }
+
     const anchor = (event.target as HTMLElement).closest("a[href]");
     if (!anchor) return;
 
@@ -28,20 +54,32 @@ export function setupLinkHandler(
 
     event.preventDefault();
 
+    const csvPath = anchor.classList.contains("csv-open") ? anchor.getAttribute("data-path") : null;
+    if (csvPath) {
+      void openPath(csvPath).catch((error) => onError(`Não foi possível abrir o arquivo: ${String(error)}`));
+      return;
+    }
+
     if (href.startsWith("app://open")) {
-      try {
-        const url = new URL(href);
-        const path = url.searchParams.get("path");
-        const decoded = path ? decodeURIComponent(path) : "";
-        const state = getState();
-        if (decoded && state.mode === "wiki") {
-          void actions.openWikiNote(decoded).catch(() => {});
-        } else if (decoded && state.mode === "file") {
-          void actions.loadFile(decoded).catch(() => {});
-        }
-      } catch {
-        // Broken or invalid app://open link
+      const openAt = (path: string, anchor: string | null) => {
+        const scrollToAnchor = () => {
+          if (anchor) document.getElementById(anchor)?.scrollIntoView();
+        };
+        const open = getState().mode === "wiki" ? actions.openWikiNote(path) : actions.loadFile(path);
+        return open.then(scrollToAnchor);
+      };
+      const candidates = linkCandidates(anchor);
+      if (candidates) {
+        showCandidateMenu(anchor as HTMLElement, candidates, getState().wikiRoot, (path) => {
+          void openAt(path, anchor.getAttribute("data-obs-anchor")).catch((error) =>
+            onError(`Não foi possível abrir o link: ${String(error)}`)
+          );
+        });
+        return;
       }
+      void resolveAppLink(href)
+        .then(({ path, anchor }) => openAt(path, anchor))
+        .catch((error) => onError(`Não foi possível abrir o link: ${String(error)}`));
       return;
     }
 
@@ -0,0 +1,30 @@
+import { describe, it, expect } from "vitest";
+import { elementForLine, lineAtScrollTop } from "./sourceLines";
+
+function preview(html: string): HTMLElement {
+  const el = document.createElement("div");
+  el.innerHTML = html;
+  return el;
+}
+
+describe("sourceLines", () => {
+  const html =
+    '<h1 data-source-line="1">Title</h1><p data-source-line="3">a</p><ul data-source-line="6"><li>b</li></ul>';
+
+  it("maps a line to the block containing it", () => {
+    const el = preview(html);
+    expect(elementForLine(el, 4)?.tagName).toBe("P");
+    expect(elementForLine(el, 6)?.tagName).toBe("UL");
+    expect(elementForLine(el, 99)?.tagName).toBe("UL");
+  });
+
+  it("falls back to the first block before any mapped line", () => {
+    const el = preview('<p data-source-line="5">a</p>');
+    expect(elementForLine(el, 1)?.tagName).toBe("P");
+  });
+
+  it("has no line without mapped elements", () => {
+    expect(elementForLine(preview("<p>a</p>"), 1)).toBeNull();
+    expect(lineAtScrollTop(preview("<p>a</p>"))).toBeNull();
+  });
+});
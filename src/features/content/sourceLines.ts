@@ -0,0 +1,27 @@
+/**
+ * Scroll sync between an editor and the preview, through the `data-source-line` attribute the
+ * backend puts on each top-level rendered element (the first source line of its block).
+ */
+
+function sourceLineElements(contentEl: HTMLElement): HTMLElement[] {
+  return Array.from(contentEl.querySelectorAll<HTMLElement>(":scope > [data-source-line]"));
+}
+
+/** The last top-level element whose block starts at or before 1-based `line`. */
+export function elementForLine(contentEl: HTMLElement, line: number): HTMLElement | null {
+  let found: HTMLElement | null = null;
+  for (const el of sourceLineElements(contentEl)) {
+    if (Number(el.dataset.sourceLine) > line) break;
+    found = el;
+  }
+  return found ?? sourceLineElements(contentEl)[0] ?? null;
+}
+
+/** Source line of the first top-level element not scrolled above the top of `contentEl`. */
+export function lineAtScrollTop(contentEl: HTMLElement): number | null {
+  const top = contentEl.getBoundingClientRect().top;
+  const elements = sourceLineElements(contentEl);
+  const visible =
+    elements.find((el) => el.getBoundingClientRect().bottom > top) ?? elements[elements.length - 1];
+  return visible ? Number(visible.dataset.sourceLine) : null;
+}
@@ -0,0 +1,36 @@
+import { toggleTask } from "../../core/api";
+import type { OpenMarkdownFileResult } from "../../types";
+
+export interface TaskToggleState {
+  currentPath: string | null;
+  /** Fingerprint of the shown note's source. */
+  currentHash: string | null;
+  wikiRoot: string | null;
+}
+
+/**
+ * Makes rendered task checkboxes (`input[data-task-offset]`) toggle the task in the file.
+ * `onToggled` receives the re-rendered note.
+ */
+export function setupTaskToggle(
+  contentEl: HTMLElement,
+  getState: () => TaskToggleState,
+  onToggled: (path: string, result: OpenMarkdownFileResult) => Promise<void>,
+  onError: (message: string) => void
+): void {
+  contentEl.addEventListener("change", (event) => {
+    const input = event.target;
+    if (!(input instanceof HTMLInputElement) || input.dataset.taskOffset === undefined) return;
+    const { currentPath, currentHash, wikiRoot } = getState();
+    if (!currentPath || !currentHash) return;
+
+    input.disabled = true;
+    void toggleTask(currentPath, Number(input.dataset.taskOffset), currentHash, wikiRoot)
+      .then((result) => onToggled(currentPath, result))
+      .catch((error) => {
+        input.checked = !input.checked;
+        input.disabled = false;
+        onError(`Não foi possível atualizar a tarefa: ${error instanceof Error ? error.message : String(error)}`);
+      });
+  });
+}
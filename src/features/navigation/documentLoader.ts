@@ -1,15 +1,18 @@
-import type { Mode } from "../../types";
-import { openMarkdownFile, openWikiFolder, watchPaths } from "../../core/api";
+import type { Mode, OpenMarkdownFileResult, OpenWikiFolderResult } from "../../types";
+import { openMarkdownFile, openWikiFolder, RENDER_SUPERSEDED, watchPaths } from "../../core/api";
 import { normalizeBaseDir, resolvePath } from "../../core/pathUtils";
 import { type BreadcrumbCallbacks } from "../content/contentRendering";
 import { applySavedTreeWidth, renderTree, renderTreeSelection, getLastSelectedPath } from "../tree/treePanel";
 import { navigationHistory } from "./navigationHistory";
+import { rememberNote, restoreWorkspace, trackScrollOffsets } from "./workspace";
 import { showLoading, hideLoading } from "../../ui/loading";
 import { showError } from "../../ui/notifications";
 
 export interface AppState {
   mode: Mode;
   currentPath: string | null;
+  /** Fingerprint of the shown note's source, checked by edits made from the preview. */
+  currentHash: string | null;
   currentBaseDir: string | null;
   wikiRoot: string | null;
 }
@@ -52,17 +55,19 @@ function breadcrumbCallbacks(
 }
 
 export function createDocumentLoader(ctx: NavigationContext) {
+  trackScrollOffsets(ctx.contentEl, () => (ctx.state.mode === "wiki" ? ctx.state.currentPath : null));
   const loader = {
     async loadFile(
       path: string,
-      options: { watch?: boolean; addToHistory?: boolean } = {}
+      options: { watch?: boolean; addToHistory?: boolean; result?: OpenMarkdownFileResult } = {}
     ): Promise<void> {
       const loadingId = `load-file-${Date.now()}`;
       showLoading(loadingId, "Carregando arquivo...");
       try {
-        const result = await openMarkdownFile(path);
+        const result = options.result ?? (await openMarkdownFile(path));
         ctx.state.mode = "file";
         ctx.state.currentPath = path;
+        ctx.state.currentHash = result.content_hash;
         ctx.state.currentBaseDir = normalizeBaseDir(result.base_dir);
         ctx.state.wikiRoot = null;
 
@@ -81,6 +86,7 @@ export function createDocumentLoader(ctx: NavigationContext) {
         hideLoading(loadingId);
       } catch (error) {
         hideLoading(loadingId);
+        if (error === RENDER_SUPERSEDED) return;
         const message = error instanceof Error ? error.message : "Erro ao carregar arquivo";
         showError(`Não foi possível carregar o arquivo: ${message}`);
         throw error;
@@ -89,17 +95,20 @@ export function createDocumentLoader(ctx: NavigationContext) {
 
     async openWikiNote(
       path: string,
-      options: { addToHistory?: boolean } = {}
+      options: { addToHistory?: boolean; result?: OpenMarkdownFileResult } = {}
     ): Promise<void> {
       if (!ctx.state.wikiRoot) return;
       const loadingId = `open-wiki-${Date.now()}`;
       showLoading(loadingId, "Abrindo nota...");
       try {
-        const result = await openMarkdownFile(path, { vaultRoot: ctx.state.wikiRoot });
+        const result =
+          options.result ?? (await openMarkdownFile(path, { vaultRoot: ctx.state.wikiRoot }));
         ctx.state.currentPath = path;
+        ctx.state.currentHash = result.content_hash;
         ctx.state.currentBaseDir = ctx.state.wikiRoot;
 
         await ctx.renderMarkdownContent(ctx.contentEl, result.html, ctx.state.wikiRoot);
+        rememberNote(ctx.contentEl, path);
         renderTreeSelection(ctx.treePanel, path);
         ctx.updateBreadcrumb(ctx.breadcrumb, path, ctx.state.wikiRoot, breadcrumbCallbacks(loader, ctx.state));
 
@@ -110,20 +119,23 @@ export function createDocumentLoader(ctx: NavigationContext) {
         hideLoading(loadingId);
       } catch (error) {
         hideLoading(loadingId);
+        // A newer navigation owns the content now.
+        if (error === RENDER_SUPERSEDED) return;
         const message = error instanceof Error ? error.message : "Erro ao abrir nota";
         showError(`Não foi possível abrir a nota: ${message}`);
         throw error;
       }
     },
 
-    async loadWiki(path: string): Promise<void> {
+    async loadWiki(path: string, options: { result?: OpenWikiFolderResult } = {}): Promise<void> {
       const loadingId = `load-wiki-${Date.now()}`;
       showLoading(loadingId, "Carregando pasta...");
       try {
-        const result = await openWikiFolder(path);
+        const result = options.result ?? (await openWikiFolder(path));
         ctx.state.mode = "wiki";
         ctx.state.wikiRoot = normalizeBaseDir(path);
         ctx.state.currentBaseDir = ctx.state.wikiRoot;
+        const workspace = await restoreWorkspace(path);
 
         ctx.treePanel.classList.remove("hidden");
         applySavedTreeWidth(ctx.treePanel);
@@ -131,7 +143,9 @@ export function createDocumentLoader(ctx: NavigationContext) {
 
         if (result.initial_html && result.initial_note_path && ctx.state.wikiRoot) {
           ctx.state.currentPath = result.initial_note_path;
+          ctx.state.currentHash = result.initial_content_hash;
           await ctx.renderMarkdownContent(ctx.contentEl, result.initial_html, ctx.state.wikiRoot);
+          rememberNote(ctx.contentEl, result.initial_note_path);
           renderTreeSelection(ctx.treePanel, result.initial_note_path);
           ctx.updateBreadcrumb(
             ctx.breadcrumb,
@@ -140,17 +154,19 @@ export function createDocumentLoader(ctx: NavigationContext) {
             breadcrumbCallbacks(loader, ctx.state)
           );
         } else {
-          const lastSelected = getLastSelectedPath();
+          const lastSelected = workspace.last_note ?? getLastSelectedPath();
           if (lastSelected && ctx.state.wikiRoot) {
             try {
               await loader.openWikiNote(lastSelected);
             } catch {
               ctx.state.currentPath = null;
+              ctx.state.currentHash = null;
               ctx.showContent(ctx.contentEl, "");
               ctx.breadcrumb.innerHTML = "";
             }
           } else {
             ctx.state.currentPath = null;
+            ctx.state.currentHash = null;
             ctx.showContent(ctx.contentEl, "");
             ctx.breadcrumb.innerHTML = "";
           }
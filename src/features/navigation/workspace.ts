@@ -0,0 +1,72 @@
+import type { Workspace } from "../../types";
+import { loadWorkspace, saveWorkspace } from "../../core/api";
+import { onExpandedPathsChanged, setExpandedPaths } from "../tree/treePanel";
+
+const SAVE_DELAY_MS = 500;
+
+let vaultRoot: string | null = null;
+let workspace: Workspace = emptyWorkspace();
+let saveTimer: ReturnType<typeof setTimeout> | null = null;
+
+function emptyWorkspace(): Workspace {
+  return { expanded_folders: [], last_note: null, scroll_offsets: {} };
+}
+
+function scheduleSave(): void {
+  if (!vaultRoot) return;
+  const root = vaultRoot;
+  const snapshot = workspace;
+  if (saveTimer) clearTimeout(saveTimer);
+  saveTimer = setTimeout(() => {
+    saveTimer = null;
+    saveWorkspace(root, snapshot).catch(() => {
+      // Losing UI state is not worth an error toast.
+    });
+  }, SAVE_DELAY_MS);
+}
+
+onExpandedPathsChanged((paths) => {
+  workspace = { ...workspace, expanded_folders: paths };
+  scheduleSave();
+});
+
+/**
+ * Loads the saved workspace of `root` and applies its expanded folders to the tree;
+ * call before `renderTree`. Falls back to an empty workspace when loading fails.
+ */
+export async function restoreWorkspace(root: string): Promise<Workspace> {
+  vaultRoot = root;
+  try {
+    workspace = await loadWorkspace(root);
+  } catch {
+    workspace = emptyWorkspace();
+  }
+  if (workspace.expanded_folders.length > 0) setExpandedPaths(workspace.expanded_folders);
+  return workspace;
+}
+
+/** Records `path` as the vault's last note and scrolls `contentEl` to its saved offset. */
+export function rememberNote(contentEl: HTMLElement, path: string): void {
+  if (!vaultRoot) return;
+  contentEl.scrollTop = workspace.scroll_offsets[path] ?? 0;
+  if (workspace.last_note === path) return;
+  workspace = { ...workspace, last_note: path };
+  scheduleSave();
+}
+
+/** Keeps the scroll offset of the note shown in `contentEl` in the workspace. */
+export function trackScrollOffsets(contentEl: HTMLElement, currentPath: () => string | null): void {
+  contentEl.addEventListener(
+    "scroll",
+    () => {
+      const path = currentPath();
+      if (!vaultRoot || !path) return;
+      workspace = {
+        ...workspace,
+        scroll_offsets: { ...workspace.scroll_offsets, [path]: Math.round(contentEl.scrollTop) },
+      };
+      scheduleSave();
+    },
+    { passive: true }
+  );
+}
@@ -8,6 +8,7 @@ import {
   applySavedTreeWidth,
   getLastSelectedPath,
   renderTreeSelection,
+  applyTreeChanges,
 } from "./treePanel";
 
 describe("treePanel", () => {
@@ -644,4 +645,62 @@ describe("treePanel", () => {
       expect(item.classList.contains("active")).toBe(false);
     });
   });
+
+  describe("applyTreeChanges", () => {
+    const createTree = (): TreeNode[] => [
+      {
+        name: "sub",
+        path: "/v/sub",
+        kind: "dir",
+        children: [{ name: "a.md", path: "/v/sub/a.md", kind: "note", children: [] }],
+      },
+      { name: "b.md", path: "/v/b.md", kind: "note", children: [] },
+    ];
+
+    const byName = { sort_by: "name", descending: false } as const;
+
+    it("adds nodes under their parent in sort order, folders before files", () => {
+      const result = applyTreeChanges(createTree(), [
+        { op: "added", parent: "/v/sub", node: { name: "c.md", path: "/v/sub/c.md", children: [] }, ...byName },
+        { op: "added", parent: null, node: { name: "new", path: "/v/new", kind: "dir", children: [] }, ...byName },
+        { op: "added", parent: null, node: { name: "a.md", path: "/v/a.md", children: [] }, ...byName },
+      ]);
+      expect(result?.[1].children.map((n) => n.name)).toEqual(["a.md", "c.md"]);
+      expect(result?.map((n) => n.name)).toEqual(["new", "sub", "a.md", "b.md"]);
+    });
+
+    it("follows the tree's sort key and direction", () => {
+      const tree: TreeNode[] = [
+        { name: "old.md", path: "/v/old.md", kind: "note", children: [], modified_ms: 1 },
+        { name: "older.md", path: "/v/older.md", kind: "note", children: [], modified_ms: 0 },
+      ];
+      const node = { name: "new.md", path: "/v/new.md", kind: "note" as const, children: [], modified_ms: 5 };
+      const result = applyTreeChanges(tree, [
+        { op: "added", parent: null, node, sort_by: "modified", descending: true },
+      ]);
+      expect(result?.map((n) => n.name)).toEqual(["new.md", "old.md", "older.md"]);
+    });
+
+    it("removes and renames nodes", () => {
+      const result = applyTreeChanges(createTree(), [
+        { op: "removed", path: "/v/sub/a.md" },
+        {
+          op: "renamed",
+          from: "/v/b.md",
+          parent: null,
+          node: { name: "z.md", path: "/v/z.md", children: [] },
+          ...byName,
+        },
+      ]);
+      expect(result?.[0].children).toEqual([]);
+      expect(result?.map((n) => n.path)).toEqual(["/v/sub", "/v/z.md"]);
+    });
+
+    it("asks for a reload when the parent folder is not in the tree", () => {
+      const result = applyTreeChanges(createTree(), [
+        { op: "added", parent: "/v/hidden", node: { name: "x.md", path: "/v/hidden/x.md", children: [] }, ...byName },
+      ]);
+      expect(result).toBeNull();
+    });
+  });
 });
@@ -1,15 +1,19 @@
-import type { TreeNode } from "../../types";
+import { openPath } from "@tauri-apps/plugin-opener";
+import type { TreeChange, TreeNode, TreeOrder, TreeSortKey } from "../../types";
 
 const TREE_MIN_WIDTH = 180;
 const TREE_MAX_WIDTH = 480;
 const TREE_WIDTH_KEY = "mdglasses-tree-width";
 const TREE_EXPANDED_PATHS_KEY = "mdglasses-tree-expanded";
 const TREE_LAST_SELECTED_KEY = "mdglasses-tree-last-selected";
+/** Notes with at least this many inbound links are highlighted in the tree as hubs. */
+const HUB_INBOUND_LINKS = 10;
 
 type NoteSelectHandler = (path: string) => void | Promise<void>;
 
 let expandedPaths: Set<string> = new Set();
 let currentTreePanel: HTMLElement | null = null;
+let expandedPathsListener: ((paths: string[]) => void) | null = null;
 
 function saveExpandedPaths(paths: Set<string>): void {
   try {
@@ -49,7 +53,7 @@ function loadLastSelected(): string | null {
 }
 
 function isDirectory(node: TreeNode): boolean {
-  return node.children.length > 0;
+  return node.kind === "dir" || node.children.length > 0;
 }
 
 function toggleExpanded(path: string): void {
@@ -59,6 +63,18 @@ function toggleExpanded(path: string): void {
     expandedPaths.add(path);
   }
   saveExpandedPaths(expandedPaths);
+  expandedPathsListener?.(Array.from(expandedPaths));
+}
+
+/** Replaces the expanded folders used by the next `renderTree` (e.g. from a saved workspace). */
+export function setExpandedPaths(paths: string[]): void {
+  expandedPaths = new Set(paths);
+  saveExpandedPaths(expandedPaths);
+}
+
+/** Calls `listener` with every expanded folder whenever the user expands or collapses one. */
+export function onExpandedPathsChanged(listener: (paths: string[]) => void): void {
+  expandedPathsListener = listener;
 }
 
 function isExpanded(path: string): boolean {
@@ -157,10 +173,24 @@ function createTreeItem(node: TreeNode, activePath: string | null, onNoteSelecte
   item.appendChild(chevron);
   item.appendChild(icon);
   item.appendChild(label);
+  if (directory && node.note_count) {
+    const count = document.createElement("span");
+    count.className = "tree-item-count";
+    count.textContent = String(node.note_count);
+    item.appendChild(count);
+  }
+  if (!directory && node.inbound_links) {
+    const links = document.createElement("span");
+    links.className = "tree-item-count tree-item-links";
+    links.textContent = String(node.inbound_links);
+    links.title = `${node.inbound_links} links de entrada · ${node.outbound_links ?? 0} de saída`;
+    item.appendChild(links);
+    item.classList.toggle("hub", node.inbound_links >= HUB_INBOUND_LINKS);
+  }
 
   wrapper.appendChild(item);
 
-  if (node.children.length > 0) {
+  if (directory) {
     const childrenContainer = document.createElement("div");
     childrenContainer.className = "tree-children";
     childrenContainer.style.display = expanded ? "" : "none";
@@ -178,6 +208,14 @@ function createTreeItem(node: TreeNode, activePath: string | null, onNoteSelecte
       childrenContainer.style.display = wasExpanded ? "" : "none";
     });
 
+    const folderNote = node.folder_note;
+    if (folderNote) {
+      item.addEventListener("click", () => {
+        if (!expandedPaths.has(node.path)) chevron.click();
+        void Promise.resolve(onNoteSelected(folderNote)).catch(console.error);
+      });
+    }
+
     item.addEventListener("keydown", (e) => {
       if (e.key === "Enter" || e.key === " ") {
         e.preventDefault();
@@ -190,14 +228,18 @@ function createTreeItem(node: TreeNode, activePath: string | null, onNoteSelecte
       }
     });
   } else {
+    // Attachments open in their default app; only notes render in the viewer.
+    const select = () =>
+      node.kind && node.kind !== "note" ? openPath(node.path) : onNoteSelected(node.path);
+
     item.addEventListener("click", () => {
-      void Promise.resolve(onNoteSelected(node.path)).catch(console.error);
+      void Promise.resolve(select()).catch(console.error);
     });
 
     item.addEventListener("keydown", (e) => {
       if (e.key === "Enter" || e.key === " ") {
         e.preventDefault();
-        void Promise.resolve(onNoteSelected(node.path)).catch(console.error);
+        void Promise.resolve(select()).catch(console.error);
       }
     });
   }
@@ -344,6 +386,98 @@ export function renderTree(
   renderFilteredTree(query, showHidden);
 }
 
+function removeNode(tree: TreeNode[], path: string): TreeNode[] {
+  return tree
+    .filter((node) => node.path !== path)
+    .map((node) => ({ ...node, children: removeNode(node.children, path) }));
+}
+
+function containsPath(tree: TreeNode[], path: string): boolean {
+  return tree.some((node) => node.path === path || containsPath(node.children, path));
+}
+
+/** Mirrors the backend's `sort_nodes`: README first by name, ties broken by name. */
+function compareNodes(a: TreeNode, b: TreeNode, order: TreeOrder): number {
+  const byName = (x: TreeNode, y: TreeNode) => {
+    const [nx, ny] = [x.name.toLowerCase(), y.name.toLowerCase()];
+    return nx < ny ? -1 : nx > ny ? 1 : 0;
+  };
+  const readme = (n: TreeNode) => (n.name.toLowerCase() === "readme.md" ? 0 : 1);
+  const keys: Record<Exclude<TreeSortKey, "name">, (n: TreeNode) => number | null | undefined> = {
+    modified: (n) => n.modified_ms,
+    created: (n) => n.created_ms,
+    size: (n) => n.size,
+  };
+  // Missing timestamps sort first, as `None` does in Rust.
+  const ordering =
+    order.sort_by === "name"
+      ? readme(a) - readme(b) || byName(a, b)
+      : (keys[order.sort_by](a) ?? -1) - (keys[order.sort_by](b) ?? -1);
+  return (order.descending ? -ordering : ordering) || byName(a, b);
+}
+
+function insertSibling(siblings: TreeNode[], node: TreeNode, order: TreeOrder): TreeNode[] {
+  const rest = siblings.filter((n) => n.path !== node.path);
+  // Folders stay ahead of files; within its group the node goes before the first entry it sorts before.
+  const at = rest.findIndex((n) =>
+    isDirectory(node)
+      ? !isDirectory(n) || compareNodes(node, n, order) < 0
+      : !isDirectory(n) && compareNodes(node, n, order) < 0,
+  );
+  rest.splice(at === -1 ? rest.length : at, 0, node);
+  return rest;
+}
+
+function insertNode(tree: TreeNode[], parent: string | null, node: TreeNode, order: TreeOrder): TreeNode[] {
+  if (parent === null) return insertSibling(tree, node, order);
+  return tree.map((n) => ({
+    ...n,
+    children:
+      n.path === parent ? insertSibling(n.children, node, order) : insertNode(n.children, parent, node, order),
+  }));
+}
+
+/**
+ * @internal
+ * Exported for testing purposes only.
+ * Returns null when a node's parent folder is not in the tree (e.g. a folder hidden while
+ * empty); the tree must then be reloaded.
+ */
+export function applyTreeChanges(tree: TreeNode[], changes: TreeChange[]): TreeNode[] | null {
+  let result = tree;
+  for (const change of changes) {
+    if (change.op === "removed") {
+      result = removeNode(result, change.path);
+      continue;
+    }
+    if (change.op === "renamed") result = removeNode(result, change.from);
+    if (change.parent !== null && !containsPath(result, change.parent)) return null;
+    result = insertNode(result, change.parent, change.node, change);
+  }
+  return result;
+}
+
+/**
+ * Applies watcher `tree-diff` changes to the rendered tree without rebuilding it. Returns
+ * false when they cannot be applied and the caller should reload the tree.
+ */
+export function applyTreeDiff(changes: TreeChange[]): boolean {
+  if (!currentTreePanel) return true;
+  const tree = applyTreeChanges(currentTree, changes);
+  if (!tree) return false;
+  replaceTree(tree);
+  return true;
+}
+
+/** Swaps in a rebuilt tree (e.g. with link counts once the vault is indexed), keeping the filters. */
+export function replaceTree(tree: TreeNode[]): void {
+  if (!currentTreePanel) return;
+  currentTree = tree;
+  const searchInput = currentTreePanel.querySelector<HTMLInputElement>("#tree-search");
+  const hideToggle = currentTreePanel.querySelector<HTMLInputElement>("#tree-hide-patterns");
+  renderFilteredTree(searchInput?.value || "", hideToggle?.checked ?? false);
+}
+
 export function getLastSelectedPath(): string | null {
   return loadLastSelected();
 }
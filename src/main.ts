@@ -6,9 +6,17 @@
  */
 import { listen } from "@tauri-apps/api/event";
 import { configureHighlighting } from "./features/content/highlight";
-import { getInitialFile } from "./core/api";
+import {
+  getEmbeddingNotes,
+  getInitialFile,
+  getSettings,
+  getTheme,
+  getTree,
+  updateSettings,
+  type InitialPath,
+} from "./core/api";
 import { applyTheme, isThemeId, loadThemePreference } from "./features/theme/theme";
-import { initTreeResizer, setupTreeSearch } from "./features/tree/treePanel";
+import { applyTreeDiff, initTreeResizer, replaceTree, setupTreeSearch } from "./features/tree/treePanel";
 import { renderAppShell } from "./ui/shell";
 import { showError } from "./ui/notifications";
 import { navigationHistory } from "./features/navigation/navigationHistory";
@@ -26,9 +34,13 @@ import {
 } from "./features/navigation/documentLoader";
 import { setupOpenModal } from "./features/open/openModal";
 import { setupLinkHandler } from "./features/content/linkHandler";
+import { setupTaskToggle } from "./features/content/taskToggle";
+import { hideLoading, showLoading, updateLoadingMessage } from "./ui/loading";
+import type { DroppedFile, DroppedFolder, ThemeChanged, TreeChange, VaultOpenProgress } from "./types";
 import "./styles.css";
 import "./notifications.css";
 import "./loading.css";
+import "./print.css";
 
 const appRoot = document.querySelector<HTMLDivElement>("#app");
 if (!appRoot) throw new Error("Missing #app root element");
@@ -56,6 +68,7 @@ contentEl.classList.add("loading");
 const state: AppState = {
   mode: "file",
   currentPath: null,
+  currentHash: null,
   currentBaseDir: null,
   wikiRoot: null,
 };
@@ -80,12 +93,31 @@ function normalizeWatchedPath(path: string): string {
   return path.replace(/\\/g, "/");
 }
 
+/** Theme select value for following the OS theme (no `theme_override`). */
+const SYSTEM_THEME = "system";
+
 function setupTheme(): void {
   configureHighlighting();
   applyTheme(loadThemePreference(), themeSelect);
+  // While following the OS the select keeps showing "system", whatever theme is applied.
+  const selectToSync = () => (themeSelect.value === SYSTEM_THEME ? null : themeSelect);
   themeSelect.addEventListener("change", () => {
     const selected = themeSelect.value;
-    applyTheme(isThemeId(selected) ? selected : "light", themeSelect);
+    const override = isThemeId(selected) ? selected : null;
+    if (override) applyTheme(override, themeSelect);
+    // The backend answers with `theme-changed`, which applies the OS theme for "system".
+    void getSettings()
+      .then((settings) => updateSettings({ ...settings, theme_override: override }))
+      .catch(() => {});
+  });
+  void Promise.all([getTheme(), getSettings()])
+    .then(([current, settings]) => {
+      if (settings.theme_override === null) themeSelect.value = SYSTEM_THEME;
+      applyTheme(current.theme, selectToSync());
+    })
+    .catch(() => {});
+  void listen<ThemeChanged>("theme-changed", (event) => {
+    applyTheme(event.payload.theme, selectToSync());
   });
 }
 
@@ -116,6 +148,11 @@ function setupKeyboardShortcuts(): void {
 }
 
 function setupWatchListener(): void {
+  void listen<TreeChange[]>("tree-diff", (event) => {
+    if (state.mode !== "wiki" || applyTreeDiff(event.payload) || !state.wikiRoot) return;
+    void getTree(state.wikiRoot).then(replaceTree).catch(() => {});
+  });
+
   void listen<string[]>("watch-change", (event) => {
     if (!state.currentPath || event.payload.length === 0) return;
     const currentPath = normalizeWatchedPath(state.currentPath);
@@ -123,12 +160,74 @@ function setupWatchListener(): void {
       const n = normalizeWatchedPath(path);
       return currentPath === n || currentPath.startsWith(`${n}/`);
     });
-    if (!changed) return;
-    if (state.mode === "file") void loader.loadFile(state.currentPath, { watch: false }).catch(() => {});
-    else void loader.openWikiNote(state.currentPath).catch(() => {});
+    const notePath = state.currentPath;
+    if (state.mode === "file") {
+      if (changed) void loader.loadFile(notePath, { watch: false }).catch(() => {});
+      return;
+    }
+    // A change to an embedded note shows in this one too.
+    const embeds = changed
+      ? Promise.resolve(true)
+      : getEmbeddingNotes(event.payload).then((notes) =>
+          notes.some((note) => normalizeWatchedPath(note) === currentPath)
+        );
+    void embeds
+      .then((stale) => (stale && state.currentPath === notePath ? loader.openWikiNote(notePath) : undefined))
+      .catch(() => {});
+  });
+}
+
+function setupVaultOpenProgressListener(): void {
+  void listen<VaultOpenProgress>("vault-open-progress", (event) => {
+    const { phase, indexed, total, error } = event.payload;
+    if (phase === "index") {
+      const message = total ? `Indexando notas… ${indexed}/${total}` : "Indexando notas…";
+      showLoading("vault-index", message);
+      updateLoadingMessage("vault-index", message);
+      return;
+    }
+    hideLoading("vault-index");
+    if (phase === "error") showError(`Não foi possível indexar o cofre: ${error ?? ""}`);
+    // Links and embeds in the note already shown resolve only once the index is in place.
+    if (phase === "ready" && state.mode === "wiki" && state.currentPath) {
+      void loader.openWikiNote(state.currentPath, { addToHistory: false }).catch(() => {});
+    }
+    // Link counts in the sidebar also come from the index.
+    if (phase === "ready" && state.mode === "wiki" && state.wikiRoot) {
+      void getTree(state.wikiRoot).then(replaceTree).catch(() => {});
+    }
   });
 }
 
+function openInitialPath(initialPath: InitialPath): Promise<void> {
+  if (initialPath.vault_root) {
+    const noteLoad = () => loader.openWikiNote(initialPath.path);
+    return loader.loadWiki(initialPath.vault_root).then(noteLoad);
+  }
+  return initialPath.is_dir ? loader.loadWiki(initialPath.path) : loader.loadFile(initialPath.path);
+}
+
+function setupOpenFileListener(): void {
+  void listen<InitialPath>("open-file", (event) => {
+    void openInitialPath(event.payload).catch(() => {});
+  });
+}
+
+function setupDropListeners(): void {
+  void listen<DroppedFolder>("folder-dropped", (event) => {
+    void loader.loadWiki(event.payload.path, { result: event.payload.result }).catch(() => {});
+  });
+  void listen<DroppedFile>("file-dropped", (event) => {
+    const { path, vault_root, result } = event.payload;
+    const load =
+      vault_root && state.mode === "wiki"
+        ? loader.openWikiNote(path, { result })
+        : loader.loadFile(path, { result });
+    void load.catch(() => {});
+  });
+  void listen<string>("drop-error", (event) => showError(event.payload));
+}
+
 setupTheme();
 initTreeResizer(treePanel, treeResizeHandle);
 setupTreeSearch(treeSearch, treeHideToggle, treePanel);
@@ -145,17 +244,33 @@ setupLinkHandler(
     openWikiNote: (path, opts) => loader.openWikiNote(path, opts),
     loadFile: (path, opts) => loader.loadFile(path, opts),
     openRelativeLink: (href) => loader.openRelativeLink(href),
+    reload: async () => {
+      if (!state.currentPath) return;
+      if (state.mode === "wiki") await loader.openWikiNote(state.currentPath, { addToHistory: false });
+      else await loader.loadFile(state.currentPath, { addToHistory: false, watch: false });
+    },
   },
   showError
 );
+setupTaskToggle(
+  contentEl,
+  () => ({ currentPath: state.currentPath, currentHash: state.currentHash, wikiRoot: state.wikiRoot }),
+  (path, result) =>
+    state.mode === "wiki"
+      ? loader.openWikiNote(path, { result, addToHistory: false })
+      : loader.loadFile(path, { result, addToHistory: false, watch: false }),
+  showError
+);
 setupWatchListener();
+setupVaultOpenProgressListener();
+setupOpenFileListener();
+setupDropListeners();
 loader.updateNavigationButtons();
 
 void getInitialFile()
   .then((initialPath) => {
     if (initialPath) {
-      const loadPromise = initialPath.is_dir ? loader.loadWiki(initialPath.path) : loader.loadFile(initialPath.path);
-      void loadPromise.finally(() => contentEl.classList.remove("loading"));
+      void openInitialPath(initialPath).finally(() => contentEl.classList.remove("loading"));
     } else {
       contentEl.classList.remove("loading");
     }
@@ -1,20 +1,426 @@
 export type ThemeId = "light" | "sepia" | "dark";
 export type Mode = "file" | "wiki";
 
+export interface BlockMapping {
+  start_line: number;
+  end_line: number;
+  element_index: number;
+}
+
+/** Top-level elements of `html` carry `data-source-line` (a block's first line). */
+export interface RenderedMarkdown {
+  html: string;
+  source_map: BlockMapping[];
+}
+
 export interface OpenMarkdownFileResult {
   raw_md: string;
   html: string;
   base_dir: string;
+  source_map: BlockMapping[];
+  /** Path components below the vault root; empty outside a vault. */
+  breadcrumbs: Breadcrumb[];
+  stats: NoteStats;
+  /** Fingerprint of `raw_md`; pass it back to `saveMarkdownFile`. */
+  content_hash: string;
+}
+
+/** `conflict`: the file changed on disk since it was loaded and nothing was written. */
+export type SaveResult =
+  | { status: "saved"; content_hash: string; modified_ms: number | null }
+  | { status: "conflict"; ours: string; theirs: string; theirs_modified_ms: number | null };
+
+export interface NoteStats {
+  words: number;
+  /** Non-whitespace characters. */
+  characters: number;
+  headings: number;
+  reading_minutes: number;
+}
+
+/** Target of an `app://open` link, decoded and confined to the open vault. */
+export interface AppLink {
+  path: string;
+  anchor: string | null;
+}
+
+/** Hover-preview excerpt of a wikilink target. */
+export interface LinkPreview {
+  path: string;
+  title: string;
+  html: string;
+  truncated: boolean;
+}
+
+export type LinkKind = "wikilink" | "embed" | "markdown" | "image";
+export type LinkStatus = "resolved" | "broken" | "external";
+
+/** A link found in a note, with where it points. */
+export interface OutgoingLink {
+  target: string;
+  line: number;
+  kind: LinkKind;
+  status: LinkStatus;
+  resolved_path: string | null;
+}
+
+/** A wikilink rewritten by a folder move. */
+export interface LinkEdit {
+  line: number;
+  before: string;
+  after: string;
+}
+
+/** Link edits planned (or made) in one note, at its pre-move path. */
+export interface NoteEdits {
+  path: string;
+  edits: LinkEdit[];
+}
+
+export interface FolderMove {
+  from: string;
+  to: string;
+  notes: NoteEdits[];
+  applied: boolean;
+}
+
+/** Notes sharing a basename; `[[name]]` resolves to the first path. */
+export interface SharedBasename {
+  name: string;
+  paths: string[];
 }
 
+/** Two notes with (nearly) the same content; similarity is 1 for identical text. */
+export interface SimilarNotes {
+  paths: [string, string];
+  similarity: number;
+}
+
+export interface DuplicateReport {
+  shared_basenames: SharedBasename[];
+  similar: SimilarNotes[];
+}
+
+export type LinkProblem = "broken_wikilink" | "missing_asset" | "dead_relative_path";
+
+export interface BrokenLink {
+  source: string;
+  line: number;
+  target: string;
+  problem: LinkProblem;
+}
+
+/** `operation-finished` payload of a long-running command (exports, link check, ...). */
+export interface OperationFinished {
+  id: number;
+  kind: string;
+  status: "ok" | "error" | "cancelled" | "timeout";
+  result: unknown;
+  error: string | null;
+}
+
+/** Result of `check_links` over the active vault. */
+export interface LinkReport {
+  notes_checked: number;
+  resolved: number;
+  external: number;
+  broken: BrokenLink[];
+}
+
+export type LintKind =
+  | "skipped_heading_level"
+  | "duplicate_heading"
+  | "unclosed_fence"
+  | "malformed_wikilink";
+
+export interface LintIssue {
+  /** 1-based, frontmatter included. */
+  line: number;
+  kind: LintKind;
+  message: string;
+}
+
+export interface Breadcrumb {
+  name: string;
+  path: string;
+}
+
+export type NodeKind = "dir" | "note" | "image" | "pdf" | "other";
+
 export interface TreeNode {
   name: string;
   path: string;
+  kind?: NodeKind;
   children: TreeNode[];
+  /** Bytes; for folders, the total of the listed children. */
+  size?: number;
+  /** Unix epoch milliseconds; for folders, the newest of the folder and its children. */
+  modified_ms?: number | null;
+  created_ms?: number | null;
+  /** Notes anywhere below a folder; 0 for files. */
+  note_count?: number;
+  /** For folders, the note named after the folder (or its index.md), opened on click. */
+  folder_note?: string | null;
+  /** Wikilinks and embeds from other notes resolving here; 0 for folders and until indexed. */
+  inbound_links?: number;
+  /** Wikilinks and embeds in this note resolving to other files. */
+  outbound_links?: number;
+}
+
+/** How a folder's entries are ordered; inserted nodes carry it to be placed in order. */
+export interface TreeOrder {
+  sort_by: TreeSortKey;
+  descending: boolean;
+}
+
+/** `tree-diff` payload entry; `parent` is null for the vault root. */
+export type TreeChange =
+  | ({ op: "added"; parent: string | null; node: TreeNode } & TreeOrder)
+  | { op: "removed"; path: string }
+  | ({ op: "renamed"; from: string; parent: string | null; node: TreeNode } & TreeOrder);
+
+export type TreeSortKey = "name" | "modified" | "created" | "size";
+
+export interface TreeOptions {
+  sort_by: TreeSortKey;
+  descending: boolean;
+  /** List images, PDFs and other attachments next to notes. */
+  show_all_files: boolean;
+  /** Keep folders that have nothing to list. */
+  show_empty_dirs: boolean;
+  /** Only list files modified within this many days; null lists everything. */
+  modified_within_days: number | null;
+}
+
+export interface RecentNote {
+  path: string;
+  relative_path: string;
+  modified_ms: number;
 }
 
 export interface OpenWikiFolderResult {
   tree: TreeNode[];
   initial_note_path: string | null;
+  /** Rendered before indexing finishes; links resolve after the `ready` progress event. */
   initial_html: string | null;
+  /** Fingerprint of the initial note's source, like `OpenMarkdownFileResult.content_hash`. */
+  initial_content_hash: string | null;
+}
+
+/** `vault-open-progress` payload; indexing runs in the background after the tree is returned. */
+export interface VaultOpenProgress {
+  vault_root: string;
+  phase: "tree" | "index" | "ready" | "error";
+  files_walked: number;
+  indexed: number;
+  total: number;
+  error: string | null;
+}
+
+export interface Settings {
+  theme_override: ThemeId | null;
+  recent_vaults: string[];
+  /** Seconds per operation kind ("search", "index", "export", "link_check"); missing or 0 means no timeout. */
+  operation_timeouts_secs: Record<string, number>;
+  /** "vscode" | "neovim" | "obsidian" or a command template using {path} / {line}. */
+  editor: string | null;
+  tree: TreeOptions;
+  templates_folder: string | null;
+  allow_html: boolean;
+  hardbreaks: boolean;
+  /** Embeds above this size (bytes) render as a link; null means 1 MB, 0 disables. */
+  max_embed_bytes: number | null;
+  /** Mark wikilinks whose name matches several notes and let the user pick one. */
+  report_ambiguous_links: boolean;
+  /** Notes tried, in order, when a vault opens; null means index.md, README.md, Home.md. */
+  initial_notes: string[] | null;
+  symlinks: SymlinkPolicy;
+  /** Pandoc binary for DOCX/ODT/LaTeX export; null looks up `pandoc` on the PATH. */
+  pandoc_path: string | null;
+  /** mermaid-cli (`mmdc`) binary for diagrams in exports; null looks it up on the PATH. */
+  mermaid_cli_path: string | null;
+  /** Graphviz `dot` binary for diagrams in exports; null looks it up on the PATH. */
+  graphviz_dot_path: string | null;
+  /**
+   * Markdown replacing embeds of unpublished notes (`publish: false` / `draft: true`) in exports;
+   * `{name}` is the note name. null means `*[Unpublished: {name}]*`.
+   */
+  unpublished_embed_placeholder: string | null;
+  /** In exports of part of a vault, whether embeds of notes left out are inlined or linked. */
+  export_outside_embeds: "inline" | "link";
+  /**
+   * Texts of the placeholders for embeds that cannot be shown; `{name}` is the embed target
+   * (`{size}` too for `too_large`). Missing keys keep the English defaults, unknown keys are
+   * ignored. Rendered as `em.embed-placeholder-<key>` (dashed).
+   */
+  embed_placeholders: Partial<Record<EmbedPlaceholder, string>>;
+  /** Keep the previous version of a saved note as `<name>.bak`. */
+  backup_on_save: boolean;
+  /** End rendered notes and exports with a "Linked mentions" list of their backlinks. */
+  backlinks_footer: boolean;
+  /** Privacy mode: remote images render as placeholders that load them on click. */
+  block_remote_images: boolean;
+  /** Show frontmatter as a properties table at the top of notes (not in embeds). */
+  properties_table: boolean;
+  /** Keep a full-text index of each vault to speed up search (builds with `fulltext`). */
+  fulltext_index: boolean;
+  /** Per-vault overrides keyed by canonical vault root. */
+  vaults: Record<string, VaultSettings>;
+}
+
+/** Kinds of embed placeholder; `embed_failed` and `retry` label the read-error box. */
+export type EmbedPlaceholder =
+  | "cycle"
+  | "depth_limit"
+  | "not_found"
+  | "ambiguous"
+  | "empty_folder"
+  | "too_large"
+  | "embed_failed"
+  | "retry"
+  | "invalid_path";
+
+/** How vault indexing treats symbolic links. */
+export type SymlinkPolicy = "skip" | "within_vault" | "follow";
+
+export interface VaultSettings {
+  hardbreaks: boolean | null;
+  /** Vault-relative folders hidden from the tree, index and search. */
+  excluded_folders: string[];
+  /** BibTeX (`.bib`) or CSL JSON file for `[@key]` citations, relative to the vault root. */
+  bibliography: string | null;
+  /** moment.js format of daily-note names, as in Obsidian (`DD.MM.YYYY`, `YYYY/MM/DD`); null means `YYYY-MM-DD`. */
+  daily_note_format: string | null;
+  /** Named search queries, in the order they were first saved. */
+  saved_searches: SavedSearch[];
+}
+
+export interface SavedSearch {
+  name: string;
+  query: string;
+}
+
+export interface CalendarNote {
+  path: string;
+  name: string;
+  /** `daily_note`: dated by its name; `created`: by the file's creation time. */
+  source: "daily_note" | "created";
+}
+
+export interface CalendarDay {
+  /** Day of the month, from 1. */
+  day: number;
+  notes: CalendarNote[];
+}
+
+/** Per-vault UI state restored when the vault is opened again. */
+export interface Workspace {
+  expanded_folders: string[];
+  last_note: string | null;
+  /** Preview scroll position in pixels, keyed by note path. */
+  scroll_offsets: Record<string, number>;
+}
+
+export interface ThemeChanged {
+  system_theme: "light" | "dark";
+  theme: ThemeId;
+}
+
+export interface DroppedFile {
+  path: string;
+  vault_root: string | null;
+  result: OpenMarkdownFileResult;
+}
+
+export interface DroppedFolder {
+  path: string;
+  result: OpenWikiFolderResult;
+}
+
+export type FrontmatterValue = string | string[];
+
+/** `greater_than` / `less_than` compare dates (`YYYY-MM-DD`) or numbers. */
+export type QueryOp = "equals" | "contains" | "greater_than" | "less_than" | "exists";
+
+export interface LinkCompletion {
+  /** Text between `[[` and `]]`: basename, vault path when ambiguous, `target|alias` for aliases. */
+  link: string;
+  name: string;
+  rel_path: string;
+  path: string;
+  alias: string | null;
+  /** Set for `Note#Heading` completions. */
+  heading: NoteHeading | null;
+}
+
+export interface NoteHeading {
+  level: number;
+  text: string;
+  /** 1-based. */
+  line: number;
+}
+
+export interface NoteAlias {
+  alias: string;
+  path: string;
+}
+
+export interface FrontmatterMatch {
+  path: string;
+  value: FrontmatterValue;
+}
+
+export interface FileMetadata {
+  path: string;
+  /** Relative to the open vault root; null outside the vault. */
+  relative_path: string | null;
+  size: number;
+  /** Unix epoch milliseconds. */
+  created_ms: number | null;
+  modified_ms: number | null;
+  frontmatter: Record<string, FrontmatterValue>;
+}
+
+/** Result of `get_note_panel_data`: everything the info panel shows. */
+export interface NotePanelData {
+  metadata: FileMetadata;
+  /** Frontmatter and inline tags, without `#`. */
+  tags: string[];
+  /** null outside an open vault. */
+  backlink_count: number | null;
+  outgoing_link_count: number | null;
+  stats: NoteStats;
+}
+
+export interface NoteCommit {
+  hash: string;
+  /** Author date, ISO 8601. */
+  date: string;
+  author: string;
+  message: string;
+}
+
+export interface RenderedTemplate {
+  markdown: string;
+  /** Where the `{{cursor}}` markers were, as string offsets into `markdown`. */
+  cursors: number[];
+}
+
+/** `content`: earlier contents, e.g. the `raw_md` shown before a watch event. */
+export type DiffBase = { revision: string } | { content: string };
+
+export interface DiffLine {
+  kind: "equal" | "insert" | "delete";
+  /** 1-based; null for inserted lines. */
+  old_line: number | null;
+  /** 1-based; null for deleted lines. */
+  new_line: number | null;
+  text: string;
+}
+
+export interface NoteDiff {
+  /** Both texts in order, deletions before the insertions replacing them. */
+  lines: DiffLine[];
+  added: number;
+  removed: number;
 }
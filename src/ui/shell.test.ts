@@ -73,7 +73,7 @@ describe("ui", () => {
       const result = renderAppShell(root);
 
       const options = Array.from(result.themeSelect.options).map((opt) => opt.value);
-      expect(options).toEqual(["light", "sepia", "dark"]);
+      expect(options).toEqual(["system", "light", "sepia", "dark"]);
     });
 
     it("toolbar has open button and no file/folder dropdown", () => {
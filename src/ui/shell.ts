@@ -37,6 +37,7 @@ export function renderAppShell(root: HTMLDivElement): AppElements {
         <button type="button" id="btn-open" class="btn-open">Abrir</button>
       </div>
       <select id="theme-select" aria-label="Tema">
+        <option value="system">Sistema</option>
         <option value="light">Claro</option>
         <option value="sepia">Sepia</option>
         <option value="dark">Escuro</option>